@@ -1,5 +1,6 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use pqc_kyber::*;
+use std::convert::TryFrom;
 
 // First set in KAT_2400
 const PK_HEX: &str = "A0B71F67C6CEC0D35686D513423432E512AC4044557E868A624800109A3355F98F151444E2852E27EA6EDB1992CAD3973C3A6FF79A5A049A259EB5415AA2A262456EC9495BBB5200D8D3163A5B10226292ECA01021389DA37881E276306550C6EFB6440EC51A2F7348349B851CD4AA0175A0550213C4791D91011220824B2B61650813ADFD2CB10538BFAB0A726F81129ED2C0F06A16B701090BF048C5A40126D572FCD47AA1218FB01547D150792D2316CB320D5144BA3508A1EBBB5AC1C22913E8295FAB59BF5837A778CF28227E07E1032DAB7D0E09A15F134148C12009DA536B22CC62474E69CC1554C0814D6CA0B722594383A9D0A2C77FD365A5544295FBB973F91EA56490D6CA6876497B98B3CB12417C257B6D0F7183DBB69E33075BEB0117B6914C69BA38349422F2F43364822A2570952DD5077B90755F1574115B8E221427585961913A9BFA0502B5D79AB7811744E6563C5B62C5CC4E93239A0A8CC60FE848F84A95F5902599B54A066293A2021DA196766C17C7E863AF790C270B216A25138DDA0C8126E09377879859DB358F9B82B7C8A6792ACEE92A4CBDE3CEDD4500ACBC555C288EFF9795265B9005351C52E2653554ABAAF872DF95CA7F795903F0B0A182B18AEB0475B29F6E3ABF4C2250FE7B842A73655016A8FC729F390507ACA936825A98B3A32E6B2554CE9528941A3BB8C90996008D74FBCD020A02E706A6DE7B02AF404C10DB00FAEC02D3EAA6D9561A1565A7B05C6366D09DA7A537F20C7B2859A83E029E13A9BD289157C5B74C84EAA307753D431202A3D9B6162218BEC5346945BFEF55B624C5C6E373359BB1C479952BBABA4D6555C276573E5152B553901999F69402D150BEF79D74FB2953018FF48666746ACE607814A1FA33195720F83878D3B575C725744A72070DD044018042DA25714D173090323A51E6C063D203881380912761FC3410839095F26C0E687A00705495E171B57151ACE0498E30F14CA9B02F6E40831854C2E0AB1ECD0C21D8E4C7E669CD728230B9D11F72C266E34466F9C0159EF424F8F31D95A57BA0E210543C10C6503FB5C63ED23AA36CD6A6F378261B0B1E79509D8BEB36AA263DC91545E53369DF26837F394C56777C95B648BD1A72921ABF49563F99CB9D98EAB5C66666F6B16F74022481FA214E617698D3BBD13CB308713FDCC7CFD397B9CA39AFF4C744D5715D58966F2CF9707015C8F3543ED286A3D8D5CBF64ACEDFC02971A91072C69D2EF49829F1037F050C5B92229856CB12B456CC095282A62687EA38C9778AEA491DFF069711FBBE05E8CD9BF44A8E712619573E12EAA7B23829DC6726BFE33DA136B81E153251508F6285BA15B2C1237677FE5B14B4E33F98C326BC58B9D8E075A25B94C8A23233029DCC786B135C56164BA3D160CBCEA854B7971F9CD73A383AAC050A302AD83B3E3AB90246AD160A321D330ACDEC7CA6643D7EC01F91691F16325BDF396950B88DAFE369C654B852055C970362C61380460757C65890F4E59222E4A4060B26C0EBC10197590DE3C8F0955D654B371CCB90ACA371B294476C16A4596A1DE8309E2A3612C69B7125310501E0C049B87440D9A6D0ECB999C9A0942AA340F60365EAFD465FC64A0C5F8F3F9003489415899D59A543D8208C54A3166529B53922";
@@ -22,7 +23,7 @@ fn keypair_bench(c: &mut Criterion) {
 
 // Encapsulating a single public key
 fn encap_bench(c: &mut Criterion) {
-  let pk = crate::decode_hex(PK_HEX);
+  let pk = PublicKey::try_from(&decode_hex(PK_HEX)[..]).unwrap();
   let mut rng = rand::thread_rng();
   c.bench_function(
     "Encapsulate", 
@@ -34,10 +35,25 @@ fn encap_bench(c: &mut Criterion) {
   );
 }
 
+// Encapsulating with a public key prepared once
+fn encap_prepared_bench(c: &mut Criterion) {
+  let pk: PublicKey = PublicKey::try_from(&decode_hex(PK_HEX)[..]).unwrap();
+  let prepared = EncapsulationKey::new(&pk);
+  let mut rng = rand::thread_rng();
+  c.bench_function(
+    "Encapsulate Prepared Key", 
+    |b| b.iter(
+      || {
+        let _enc = prepared.encapsulate(&mut rng);
+      }
+    )
+  );
+}
+
 // Decapsulating a single correct ciphertext
 fn decap_bench(c: &mut Criterion) {
-  let sk = decode_hex(SK_HEX);
-  let ct = decode_hex(CT_HEX);
+  let sk = SecretKey::try_from(&decode_hex(SK_HEX)[..]).unwrap();
+  let ct = Ciphertext::try_from(&decode_hex(CT_HEX)[..]).unwrap();
   c.bench_function(
     "Decapsulate", 
     |b| b.iter(
@@ -48,10 +64,24 @@ fn decap_bench(c: &mut Criterion) {
   );
 }
 
+fn decap_prepared_bench(c: &mut Criterion) {
+  let sk: SecretKey = SecretKey::try_from(&decode_hex(SK_HEX)[..]).unwrap();
+  let ct = Ciphertext::try_from(&decode_hex(CT_HEX)[..]).unwrap();
+  let prepared = DecapsulationKey::new(&sk);
+  c.bench_function(
+    "Decapsulate Prepared Key", 
+    |b| b.iter(
+      || {
+        let _dec = prepared.decapsulate(&ct);
+      }
+    )
+  );
+}
+
 // Decapsulating a single incorrect ciphertext
 fn decap_fail_bench(c: &mut Criterion) {
-  let sk = decode_hex(BAD_SK);
-  let ct = decode_hex(CT_HEX);
+  let sk = SecretKey::try_from(&decode_hex(BAD_SK)[..]).unwrap();
+  let ct = Ciphertext::try_from(&decode_hex(CT_HEX)[..]).unwrap();
   c.bench_function(
     "Decapsulate Failure", 
     |b| b.iter(
@@ -62,7 +92,23 @@ fn decap_fail_bench(c: &mut Criterion) {
   );
 }
 
-criterion_group!(benches, keypair_bench, encap_bench, decap_bench, decap_fail_bench);
+// Encapsulating a batch of 64 public keys
+fn encap_batch_bench(c: &mut Criterion) {
+  let pks = vec![PublicKey::try_from(&decode_hex(PK_HEX)[..]).unwrap(); 64];
+  let mut cts = vec![0u8; pks.len() * KYBER_CIPHERTEXTBYTES];
+  let mut sss = vec![0u8; pks.len() * KYBER_SSBYTES];
+  let mut rng = rand::thread_rng();
+  c.bench_function(
+    "Encapsulate Batch of 64", 
+    |b| b.iter(
+      || {
+        let _enc = encapsulate_batch(&pks, &mut cts, &mut sss, &mut rng);
+      }
+    )
+  );
+}
+
+criterion_group!(benches, keypair_bench, encap_bench, encap_prepared_bench, decap_bench, decap_prepared_bench, decap_fail_bench, encap_batch_bench);
 criterion_main!(benches);
 
 // Decodes a hex string into a vector of bytes
@@ -1,9 +1,10 @@
 fn main() {
   #[cfg(not(feature = "wasm"))]
   {
+    // The avx2 backend is only compiled for x86_64 targets
     #[cfg(feature = "avx2")]
+    if std::env::var("CARGO_CFG_TARGET_ARCH").as_deref() == Ok("x86_64")
     {
-      
       const FILES: [&str; 5] = ["basemul", "fq", "invntt", "ntt", "shuffle"];
 
       #[cfg(feature = "nasm")]
@@ -1,13 +1,17 @@
 use crate::{
-  params::*, 
+  params::*,
   error::KyberError,
   RngCore, CryptoRng,
   kem::*,
-  kex::{PublicKey, SecretKey, Encapsulated, Decapsulated}
+  types::*,
+  batch,
+  rng::{randombytes, NoRng},
 };
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
 
 /// Keypair generation with a provided RNG.
-/// 
+///
 /// ### Example
 /// ```
 /// # use pqc_kyber::*;
@@ -16,13 +20,33 @@ use crate::{
 /// let keys = keypair(&mut rng);
 /// # Ok(())}
 /// ```
-pub fn keypair<R>(rng: &mut R) -> Keypair 
+pub fn keypair<R>(rng: &mut R) -> Keypair
   where R: RngCore + CryptoRng
 {
-  let mut public = [0u8; KYBER_PUBLICKEYBYTES];
-  let mut secret = [0u8; KYBER_SECRETKEYBYTES];
-  crypto_kem_keypair(&mut public, &mut secret, rng, None);
-  Keypair { public, secret }
+  KyberLevel::keypair(rng)
+}
+
+/// Deterministically generates a keypair from a 64 byte seed, the
+/// d||z pair of FIPS 203 key generation
+///
+/// The same seed always gives the same keypair, on every backend, so the
+/// seed can be stored in place of the much larger secret key and expanded
+/// when needed. The seed must be kept as secret as the key itself and come
+/// from a cryptographically secure RNG.
+///
+/// ### Example
+/// ```
+/// # use pqc_kyber::*;
+/// # use rand::RngCore;
+/// let mut seed = [0u8; KYBER_SEEDBYTES];
+/// rand::thread_rng().fill_bytes(&mut seed);
+/// let keys = keypair_from_seed(&seed);
+/// assert_eq!(keys, keypair_from_seed(&seed));
+/// assert_eq!(keys.secret.to_seed(), Some(seed));
+/// ```
+pub fn keypair_from_seed(seed: &[u8; KYBER_SEEDBYTES]) -> Keypair
+{
+  KyberLevel::keypair_from_seed(seed)
 }
 
 /// Encapsulates a public key returning the ciphertext to send
@@ -30,27 +54,50 @@ pub fn keypair<R>(rng: &mut R) -> Keypair
 ///
 /// ### Example
 /// ```
-/// # use pqc_kyber::*; 
+/// # use pqc_kyber::*;
 /// # fn main() -> Result<(), KyberError> {
 /// let mut rng = rand::thread_rng();
 /// let keys = keypair(&mut rng);
 /// let (ciphertext, shared_secret) = encapsulate(&keys.public, &mut rng)?;
 /// # Ok(())}
 /// ```
-pub fn encapsulate<R>(pk: &[u8], rng: &mut R) -> Encapsulated 
+pub fn encapsulate<R>(pk: &PublicKey, rng: &mut R) -> Encapsulated
   where R: CryptoRng + RngCore
 {
-  if pk.len() != KYBER_PUBLICKEYBYTES {
-    return Err(KyberError::InvalidInput)
-  }
-  let mut ct = [0u8; KYBER_CIPHERTEXTBYTES];
-  let mut ss = [0u8; KYBER_SSBYTES];
-  crypto_kem_enc(&mut ct, &mut ss, pk, rng, None);
-  Ok((ct, ss))
+  KyberLevel::encapsulate(pk, rng)
+}
+
+/// Deterministically encapsulates a public key with caller supplied coins
+///
+/// **WARNING**: the coins must be fresh secret randomness for every call,
+/// reusing them or letting a peer know them reveals the shared secret.
+/// Round 3 Kyber hashes the coins before use as the reference does while
+/// ML-KEM uses them as the FIPS 203 message `m` directly.
+///
+/// ### Example
+/// ```
+/// # use pqc_kyber::*;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng);
+/// let coins = [7u8; KYBER_SYMBYTES];
+/// let (ct1, ss1) = encapsulate_derand(&keys.public, &coins)?;
+/// let (ct2, ss2) = encapsulate_derand(&keys.public, &coins)?;
+/// assert_eq!((ct1, ss1), (ct2, ss2));
+/// # Ok(())}
+/// ```
+#[cfg(feature = "hazmat")]
+pub fn encapsulate_derand(pk: &PublicKey, coins: &[u8; KYBER_SYMBYTES]) -> Encapsulated
+{
+  KyberLevel::encapsulate_derand(pk, coins)
 }
 
-/// Decapsulates ciphertext with a secret key, the result will contain
-/// a KyberError if decapsulation fails
+/// Decapsulates ciphertext with a secret key
+///
+/// Uses implicit rejection, an invalid ciphertext results in a pseudorandom
+/// shared secret rather than an error so failures only become apparent once
+/// the secrets are used. Sizes are enforced by the [`Ciphertext`] and
+/// [`SecretKey`] types so this currently never returns an error.
 ///
 /// ### Example
 /// ```
@@ -63,25 +110,369 @@ pub fn encapsulate<R>(pk: &[u8], rng: &mut R) -> Encapsulated
 /// assert_eq!(ss1, ss2);
 /// #  Ok(())}
 /// ```
-pub fn decapsulate(ct: &[u8], sk: &[u8]) -> Decapsulated 
+pub fn decapsulate(ct: &Ciphertext, sk: &SecretKey) -> Decapsulated
+{
+  KyberLevel::decapsulate(ct, sk)
+}
+
+/// Decapsulates ciphertext with a secret key, returning 
+/// `KyberError::Decapsulation` if the ciphertext fails re-encryption
+///
+/// **WARNING**: revealing decapsulation failures to a peer turns the KEM into
+/// a chosen ciphertext oracle. Use [`decapsulate`] unless you know you need this.
+///
+/// ### Example
+/// ```
+/// # use pqc_kyber::*;
+/// # use core::convert::TryFrom;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng);
+/// let (ct, ss1) = encapsulate(&keys.public, &mut rng)?;
+/// assert_eq!(decapsulate_explicit(&ct, &keys.secret)?, ss1);
+/// let mut bytes = ct.as_ref().to_vec();
+/// bytes[0] ^= 1;
+/// let ct = Ciphertext::try_from(&bytes[..])?;
+/// assert_eq!(decapsulate_explicit(&ct, &keys.secret), Err(KyberError::Decapsulation));
+/// #  Ok(())}
+/// ```
+#[cfg(feature = "hazmat")]
+pub fn decapsulate_explicit(ct: &Ciphertext, sk: &SecretKey) -> Decapsulated
+{
+  KyberLevel::decapsulate_explicit(ct, sk)
+}
+
+/// Checks a public key passes the FIPS 203 modulus check, every 
+/// coefficient must be reduced mod q
+///
+/// ### Example
+/// ```
+/// # use pqc_kyber::*;
+/// # use core::convert::TryFrom;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng);
+/// validate_public_key(&keys.public)?;
+/// let invalid = PublicKey::try_from(&[0xff; KYBER_PUBLICKEYBYTES][..])?;
+/// assert_eq!(validate_public_key(&invalid), Err(KyberError::InvalidPublicKey));
+/// # Ok(())}
+/// ```
+pub fn validate_public_key(pk: &PublicKey) -> Result<(), KyberError>
+{
+  KyberLevel::validate_public_key(pk)
+}
+
+/// Checks a secret key passes the FIPS 203 hash check, the stored 
+/// hash must match the embedded public key
+///
+/// ### Example
+/// ```
+/// # use pqc_kyber::*;
+/// # use core::convert::TryFrom;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng);
+/// validate_secret_key(&keys.secret)?;
+/// let mut bytes = keys.secret.as_ref().to_vec();
+/// bytes[KYBER_SECRETKEYBYTES - 1 - KYBER_SYMBYTES] ^= 1;
+/// let secret = SecretKey::try_from(&bytes[..])?;
+/// assert_eq!(validate_secret_key(&secret), Err(KyberError::InvalidSecretKey));
+/// # Ok(())}
+/// ```
+pub fn validate_secret_key(sk: &SecretKey) -> Result<(), KyberError>
+{
+  KyberLevel::validate_secret_key(sk)
+}
+
+/// Encapsulates a public key after checking it with [`validate_public_key`]
+///
+/// ### Example
+/// ```
+/// # use pqc_kyber::*;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng);
+/// let (ciphertext, shared_secret) = encapsulate_strict(&keys.public, &mut rng)?;
+/// # Ok(())}
+/// ```
+pub fn encapsulate_strict<R>(pk: &PublicKey, rng: &mut R) -> Encapsulated
+  where R: CryptoRng + RngCore
+{
+  KyberLevel::encapsulate_strict(pk, rng)
+}
+
+/// Decapsulates ciphertext after checking the secret key with
+/// [`validate_secret_key`]
+///
+/// ### Example
+/// ```
+/// # use pqc_kyber::*;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng);
+/// let (ct, ss1) = encapsulate_strict(&keys.public, &mut rng)?;
+/// let ss2 = decapsulate_strict(&ct, &keys.secret)?;
+/// assert_eq!(ss1, ss2);
+/// # Ok(())}
+/// ```
+pub fn decapsulate_strict(ct: &Ciphertext, sk: &SecretKey) -> Decapsulated
+{
+  KyberLevel::decapsulate_strict(ct, sk)
+}
+
+/// Generates a batch of keypairs into concatenated key buffers
+///
+/// The number of keypairs is `pks.len() / KYBER_PUBLICKEYBYTES` and `sks`
+/// must hold as many secret keys, other lengths return
+/// `KyberError::InvalidInput`. Randomness is drawn exactly as one
+/// [`keypair`] call per key would, so the keys are the same as the
+/// equivalent loop. Keys are generated four at a time, on the avx2 backend
+/// they share the 4-way Keccak, and with the `rayon` feature large batches
+/// are spread over threads.
+///
+/// ### Example
+/// ```
+/// # use pqc_kyber::*;
+/// # use core::convert::TryFrom;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let mut pks = [0u8; 8 * KYBER_PUBLICKEYBYTES];
+/// let mut sks = [0u8; 8 * KYBER_SECRETKEYBYTES];
+/// keypair_batch(&mut pks, &mut sks, &mut rng)?;
+/// let secret = SecretKey::try_from(&sks[..KYBER_SECRETKEYBYTES])?;
+/// validate_secret_key(&secret)?;
+/// # Ok(())}
+/// ```
+pub fn keypair_batch<R>(pks: &mut [u8], sks: &mut [u8], rng: &mut R) -> Result<(), KyberError>
+  where R: RngCore + CryptoRng
+{
+  KyberLevel::keypair_batch(pks, sks, rng)
+}
+
+/// Encapsulates to each public key in turn, writing the ciphertexts and
+/// shared secrets to concatenated buffers in the same order
+///
+/// `cts` and `sss` must hold exactly one ciphertext and one shared secret
+/// per key, otherwise `KyberError::InvalidInput` is returned. The output is
+/// the same as calling [`encapsulate`] for each key with the same RNG, the
+/// keys aren't validated. Groups of four share their hashing and noise
+/// sampling on the avx2 backend and the `rayon` feature spreads large
+/// batches over threads.
+///
+/// ### Example
+/// ```
+/// # use pqc_kyber::*;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys: Vec<_> = (0..6).map(|_| keypair(&mut rng)).collect();
+/// let pks: Vec<_> = keys.iter().map(|k| k.public).collect();
+/// let mut cts = vec![0u8; pks.len() * KYBER_CIPHERTEXTBYTES];
+/// let mut sss = vec![0u8; pks.len() * KYBER_SSBYTES];
+/// encapsulate_batch(&pks, &mut cts, &mut sss, &mut rng)?;
+/// # Ok(())}
+/// ```
+pub fn encapsulate_batch<R>(pks: &[PublicKey], cts: &mut [u8], sss: &mut [u8], rng: &mut R) -> Result<(), KyberError>
+  where R: CryptoRng + RngCore
+{
+  KyberLevel::encapsulate_batch(pks, cts, sss, rng)
+}
+
+/// Decapsulates a batch of ciphertexts with one secret key, writing the
+/// shared secrets to a concatenated buffer in the same order
+///
+/// `sss` must hold exactly one shared secret per ciphertext, otherwise
+/// `KyberError::InvalidInput` is returned. Each ciphertext uses implicit
+/// rejection as in [`decapsulate`]. Groups of four share their
+/// re-encryption hashing and noise sampling on the avx2 backend and the
+/// `rayon` feature spreads large batches over threads.
+///
+/// ### Example
+/// ```
+/// # use pqc_kyber::*;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng);
+/// let mut cts = Vec::new();
+/// let mut expected = Vec::new();
+/// for _ in 0..5 {
+///   let (ct, ss) = encapsulate(&keys.public, &mut rng)?;
+///   cts.push(ct);
+///   expected.extend_from_slice(ss.as_ref());
+/// }
+/// let mut sss = vec![0u8; cts.len() * KYBER_SSBYTES];
+/// decapsulate_batch(&keys.secret, &cts, &mut sss)?;
+/// assert_eq!(sss, expected);
+/// # Ok(())}
+/// ```
+pub fn decapsulate_batch(sk: &SecretKey, cts: &[Ciphertext], sss: &mut [u8]) -> Result<(), KyberError>
 {
-  if ct.len() != KYBER_CIPHERTEXTBYTES || sk.len() != KYBER_SECRETKEYBYTES {
-    return Err(KyberError::InvalidInput)
+  KyberLevel::decapsulate_batch(sk, cts, sss)
+}
+
+/// Key encapsulation for any Kyber parameter set.
+///
+/// Implemented for every [`KyberParams`] type, so all security levels are
+/// available in the same binary regardless of the default level chosen
+/// with feature flags. Keys and ciphertexts are typed by their level so
+/// they can't be mixed up between levels.
+///
+/// ### Example
+/// ```
+/// # use pqc_kyber::*;
+/// # use core::convert::TryFrom;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = Kyber1024::keypair(&mut rng);
+/// let (ct, ss1) = Kyber1024::encapsulate(&keys.public, &mut rng)?;
+/// let ss2 = Kyber1024::decapsulate(&ct, &keys.secret)?;
+/// assert_eq!(ss1, ss2);
+///
+/// // A Kyber1024 ciphertext can't be parsed at another level
+/// assert!(Ciphertext::<Kyber512>::try_from(ct.as_ref()).is_err());
+/// # Ok(())}
+/// ```
+pub trait KyberKem: KyberParams {
+  /// Keypair generation with a provided RNG.
+  fn keypair<R>(rng: &mut R) -> Keypair<Self>
+    where R: RngCore + CryptoRng
+  {
+    // Drawn in the same order as the unseeded keygen, d then z
+    let mut seed = [0u8; KYBER_SEEDBYTES];
+    let (d, z) = seed.split_at_mut(KYBER_SYMBYTES);
+    randombytes(d, KYBER_SYMBYTES, rng);
+    randombytes(z, KYBER_SYMBYTES, rng);
+    let keys = Self::keypair_from_seed(&seed);
+    wipe(&mut seed);
+    keys
+  }
+
+  /// Deterministically generates a keypair from a 64 byte d||z seed,
+  /// the secret key remembers the seed
+  fn keypair_from_seed(seed: &[u8; KYBER_SEEDBYTES]) -> Keypair<Self>
+  {
+    let mut public = PublicKey::<Self>::zeroed();
+    let mut secret = SecretKey::<Self>::zeroed();
+    let (d, z) = seed.split_at(KYBER_SYMBYTES);
+    crypto_kem_keypair::<Self, _>(public.0.as_mut(), secret.0.as_mut(), &mut NoRng, Some((d, z)));
+    secret.1 = Some(*seed);
+    Keypair { public, secret }
+  }
+
+  /// Encapsulates a public key returning the ciphertext to send
+  /// and the shared secret
+  fn encapsulate<R>(pk: &PublicKey<Self>, rng: &mut R) -> Encapsulated<Self>
+    where R: CryptoRng + RngCore
+  {
+    let mut ct = Ciphertext::<Self>::zeroed();
+    let mut ss = SharedSecret::zeroed();
+    crypto_kem_enc::<Self, R>(ct.0.as_mut(), &mut ss.0, pk.as_ref(), rng, None);
+    Ok((ct, ss))
+  }
+
+  /// Deterministically encapsulates a public key with caller supplied coins
+  ///
+  /// **WARNING**: the coins must be fresh secret randomness for every call.
+  #[cfg(feature = "hazmat")]
+  fn encapsulate_derand(pk: &PublicKey<Self>, coins: &[u8; KYBER_SYMBYTES]) -> Encapsulated<Self>
+  {
+    let mut ct = Ciphertext::<Self>::zeroed();
+    let mut ss = SharedSecret::zeroed();
+    crypto_kem_enc::<Self, _>(ct.0.as_mut(), &mut ss.0, pk.as_ref(), &mut NoRng, Some(coins));
+    Ok((ct, ss))
+  }
+
+  /// Decapsulates ciphertext with a secret key, an invalid ciphertext
+  /// results in a pseudorandom shared secret (implicit rejection)
+  fn decapsulate(ct: &Ciphertext<Self>, sk: &SecretKey<Self>) -> Decapsulated
+  {
+    let mut ss = SharedSecret::zeroed();
+    crypto_kem_dec::<Self>(&mut ss.0, ct.as_ref(), sk.as_ref());
+    Ok(ss)
   }
-  let mut ss = [0u8; KYBER_SSBYTES];
-  match crypto_kem_dec(&mut ss, ct, sk) {
-    Ok(_) => Ok(ss),
-    Err(e) => Err(e)
+
+  /// Decapsulates ciphertext with a secret key, returning 
+  /// `KyberError::Decapsulation` if the ciphertext fails re-encryption
+  ///
+  /// **WARNING**: revealing decapsulation failures to a peer turns the KEM 
+  /// into a chosen ciphertext oracle.
+  #[cfg(feature = "hazmat")]
+  fn decapsulate_explicit(ct: &Ciphertext<Self>, sk: &SecretKey<Self>) -> Decapsulated
+  {
+    let mut ss = SharedSecret::zeroed();
+    crypto_kem_dec_explicit::<Self>(&mut ss.0, ct.as_ref(), sk.as_ref())?;
+    Ok(ss)
+  }
+
+  /// Checks a public key passes the FIPS 203 modulus check
+  fn validate_public_key(pk: &PublicKey<Self>) -> Result<(), KyberError>
+  {
+    match crypto_kem_check_pk::<Self>(pk.as_ref()) {
+      0 => Ok(()),
+      _ => Err(KyberError::InvalidPublicKey)
+    }
+  }
+
+  /// Checks a secret key passes the FIPS 203 hash check
+  fn validate_secret_key(sk: &SecretKey<Self>) -> Result<(), KyberError>
+  {
+    match crypto_kem_check_sk::<Self>(sk.as_ref()) {
+      0 => Ok(()),
+      _ => Err(KyberError::InvalidSecretKey)
+    }
+  }
+
+  /// Encapsulates a public key after validating it
+  fn encapsulate_strict<R>(pk: &PublicKey<Self>, rng: &mut R) -> Encapsulated<Self>
+    where R: CryptoRng + RngCore
+  {
+    Self::validate_public_key(pk)?;
+    Self::encapsulate(pk, rng)
+  }
+
+  /// Decapsulates ciphertext after validating the secret key
+  fn decapsulate_strict(ct: &Ciphertext<Self>, sk: &SecretKey<Self>) -> Decapsulated
+  {
+    Self::validate_secret_key(sk)?;
+    Self::decapsulate(ct, sk)
+  }
+
+  /// Generates a batch of keypairs into concatenated key buffers,
+  /// see [`keypair_batch`](crate::keypair_batch)
+  fn keypair_batch<R>(pks: &mut [u8], sks: &mut [u8], rng: &mut R) -> Result<(), KyberError>
+    where R: RngCore + CryptoRng
+  {
+    batch::keypair_batch::<Self, R>(pks, sks, rng)
+  }
+
+  /// Encapsulates to each public key in turn,
+  /// see [`encapsulate_batch`](crate::encapsulate_batch)
+  fn encapsulate_batch<R>(
+    pks: &[PublicKey<Self>], cts: &mut [u8], sss: &mut [u8], rng: &mut R
+  ) -> Result<(), KyberError>
+    where R: CryptoRng + RngCore
+  {
+    batch::encapsulate_batch::<Self, R>(pks, cts, sss, rng)
+  }
+
+  /// Decapsulates a batch of ciphertexts with one secret key,
+  /// see [`decapsulate_batch`](crate::decapsulate_batch)
+  fn decapsulate_batch(sk: &SecretKey<Self>, cts: &[Ciphertext<Self>], sss: &mut [u8]) -> Result<(), KyberError>
+  {
+    batch::decapsulate_batch::<Self>(sk, cts, sss)
   }
 }
 
-/// A public/secret keypair for use with Kyber. 
-/// 
-/// Byte lengths of the keys are determined by the security level chosen.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub struct Keypair {
-    pub public: PublicKey,
-    pub secret: SecretKey
+impl<P: KyberParams> KyberKem for P {}
+
+/// A public/secret keypair for use with Kyber.
+///
+/// Byte lengths of the keys are determined by the security level chosen,
+/// which is the feature selected default unless specified.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(bound = ""))]
+pub struct Keypair<P: KyberParams = KyberLevel> {
+    pub public: PublicKey<P>,
+    pub secret: SecretKey<P>
 }
 
 impl Keypair {
@@ -91,13 +482,10 @@ impl Keypair {
   /// # fn main() -> Result<(), KyberError> {
   /// let mut rng = rand::thread_rng();
   /// let keys = Keypair::generate(&mut rng);
-  /// # let empty_keys = Keypair{
-  ///   public: [0u8; KYBER_PUBLICKEYBYTES], secret: [0u8; KYBER_SECRETKEYBYTES]
-  /// };
-  /// # assert!(empty_keys != keys); 
+  /// # assert!(keys != Keypair::generate(&mut rng));
   /// # Ok(()) }
   /// ```
   pub fn generate<R: CryptoRng + RngCore>(rng: &mut R) -> Keypair {
     keypair(rng)
   }
-}
\ No newline at end of file
+}
@@ -14,6 +14,7 @@ pub(crate) struct Aes256CtrCtx {
 }
 
 impl Aes256CtrCtx {
+  #[inline(always)]
   pub fn new() -> Self {
     unsafe {
       Self {
@@ -24,6 +25,7 @@ impl Aes256CtrCtx {
   }
 }
 
+#[inline(always)]
 unsafe fn aesni_encrypt4(out: &mut[u8], n :&mut __m128i, rkeys: &[__m128i; 16]) 
 {
   let idx: __m128i = _mm_set_epi8(8,9,10,11,12,13,14,15,7,6,5,4,3,2,1,0);
@@ -69,16 +71,19 @@ unsafe fn aesni_encrypt4(out: &mut[u8], n :&mut __m128i, rkeys: &[__m128i; 16])
 }
 
 // Casting aliases
+#[inline(always)]
 unsafe fn cast_128i(x: __m128) -> __m128i
 {
   _mm_castps_si128(x)
 }
 
+#[inline(always)]
 unsafe fn cast_128(x: __m128i) -> __m128
 {
   _mm_castsi128_ps(x)
 }
 
+#[inline(always)]
 pub(crate) fn aes256ctr_init(state: &mut Aes256CtrCtx, key: &[u8], nonce: [u8; 12])
 {
   unsafe {
@@ -142,6 +147,7 @@ pub(crate) fn aes256ctr_init(state: &mut Aes256CtrCtx, key: &[u8], nonce: [u8; 1
   }
 }
 
+#[inline(always)]
 pub(crate) fn aes256ctr_squeezeblocks(out: &mut[u8], nblocks: usize, state: &mut Aes256CtrCtx)
 {
   let mut idx = 0;
@@ -151,7 +157,9 @@ pub(crate) fn aes256ctr_squeezeblocks(out: &mut[u8], nblocks: usize, state: &mut
   }
 }
 
+// The shared prf in symmetric.rs uses the portable AES-CTR
 #[cfg(feature="90s")]
+#[allow(dead_code)]
 pub(crate) fn aes256ctr_prf(out: &mut[u8], mut outlen: usize, seed: &[u8], nonce: u8)
 {
   let mut buf = [0u8; 64];
@@ -2,10 +2,10 @@
 
 use core::arch::x86_64::*;
 use crate::params::*;
-use crate::poly::NOISE_NBLOCKS;
-use crate::fips202::{SHAKE128_RATE, SHAKE256_RATE};
+use super::poly::NOISE_NBLOCKS;
+use super::fips202::{SHAKE128_RATE, SHAKE256_RATE};
 use crate::symmetric::*;
-use crate::avx2::rejsample::REJ_UNIFORM_AVX_NBLOCKS;
+use super::rejsample::REJ_UNIFORM_AVX_NBLOCKS;
 
 // Buffer unions
 // #[derive(Copy, Clone)]
@@ -31,6 +31,7 @@ pub union GenMatrixBuf {
 }
 
 impl GenMatrixBuf {
+  #[inline(always)]
   pub fn new() -> Self {
     Self { coeffs: [0u8; REJ_UNIFORM_AVX_NBLOCKS*SHAKE128_RATE]}
   }
@@ -47,6 +48,7 @@ pub union GenMatrixBuf90s {
 
 #[cfg(feature="90s")]
 impl GenMatrixBuf90s {
+  #[inline(always)]
   pub fn new() -> Self {
     Self { 
       coeffs: [0u8; REJ_UNIFORM_AVX_NBLOCKS*XOF_BLOCKBYTES]
@@ -54,6 +56,7 @@ impl GenMatrixBuf90s {
   }
 
   #[cfg(debug_assertions)]
+  #[inline(always)]
   pub fn checksum(&self) -> i16 {
     let mut out = 0;
     for i in 0..REJ_UNIFORM_AVX_NBLOCKS*XOF_BLOCKBYTES {
@@ -63,21 +66,23 @@ impl GenMatrixBuf90s {
   }
 }
 
+// Sized for the largest ETA1 across parameter sets
 #[repr(C)]
 pub union IndcpaBuf {
   pub coeffs: [u8; 
-  (KYBER_ETA1*KYBER_N/4)
+  (KYBER_MAX_ETA1*KYBER_N/4)
   /XOF_BLOCKBYTES*XOF_BLOCKBYTES+32],
   pub vec: [__m256i; 
-  ((KYBER_ETA1*KYBER_N/4)
+  ((KYBER_MAX_ETA1*KYBER_N/4)
   /XOF_BLOCKBYTES*XOF_BLOCKBYTES+32+31)/32]
 }
 
 impl IndcpaBuf {
+  #[inline(always)]
   pub fn new() -> Self {
     Self { 
       coeffs: [0u8; 
-      (KYBER_ETA1*KYBER_N/4)
+      (KYBER_MAX_ETA1*KYBER_N/4)
       /XOF_BLOCKBYTES*XOF_BLOCKBYTES+32]
     }
   }
@@ -90,6 +95,7 @@ pub union Eta2Buf {
 }
 
 impl Eta2Buf {
+  #[inline(always)]
   pub fn new() -> Self {
     Self { coeffs: [0u8; KYBER_ETA2*KYBER_N/4] }
   }
@@ -103,6 +109,7 @@ pub union Eta4xBuf {
 }
 
 impl Eta4xBuf {
+  #[inline(always)]
   pub fn new() -> Self {
     Self { coeffs: [0u8; NOISE_NBLOCKS*SHAKE256_RATE] }
   }
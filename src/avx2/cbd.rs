@@ -1,12 +1,13 @@
 
 #![allow(non_snake_case, dead_code)]
 use core::arch::x86_64::*;
-use crate::params::KYBER_N;
-use crate::poly::*;
-use crate::align::Eta4xBuf;
+use crate::params::{KyberParams, KYBER_N};
+use super::poly::*;
+use super::align::Eta4xBuf;
 #[cfg(feature="90s")]
-use crate::align::IndcpaBuf;
+use super::align::IndcpaBuf;
 
+#[inline(always)]
 fn cbd2(r: &mut Poly, buf: &[__m256i]) {
   unsafe {
     let mask55: __m256i = _mm256_set1_epi32(0x55555555);
@@ -50,6 +51,7 @@ fn cbd2(r: &mut Poly, buf: &[__m256i]) {
   }
 }
 
+#[inline(always)]
 fn cbd3(r: &mut Poly, buf: &[u8]) {
   unsafe {
     let (mut f0, mut f1, mut f2, mut f3);
@@ -104,33 +106,32 @@ fn cbd3(r: &mut Poly, buf: &[u8]) {
   }
 }
 
-pub fn poly_cbd_eta1(r: &mut Poly, buf: &Eta4xBuf) 
+#[inline(always)]
+pub fn poly_cbd_eta1<P: KyberParams>(r: &mut Poly, buf: &Eta4xBuf) 
 {
   unsafe {
-    if cfg!(feature="kyber512") {
-      cbd3(r, &buf.coeffs)
-    } 
-    else {
-      cbd2(r, &buf.vec)
+    match P::ETA1 {
+      3 => cbd3(r, &buf.coeffs),
+      _ => cbd2(r, &buf.vec)
     }
   }
 }
 
 #[cfg(feature="90s")]
-pub fn poly_cbd_eta1_90s(r: &mut Poly, buf: &IndcpaBuf) 
+#[inline(always)]
+pub fn poly_cbd_eta1_90s<P: KyberParams>(r: &mut Poly, buf: &IndcpaBuf) 
 {
   unsafe {
-    if cfg!(feature="kyber512") {
-      cbd3(r, &buf.coeffs)
-    } 
-    else {
-      cbd2(r, &buf.vec)
+    match P::ETA1 {
+      3 => cbd3(r, &buf.coeffs),
+      _ => cbd2(r, &buf.vec)
     }
   }
 }
 
  
+#[inline(always)]
 pub fn poly_cbd_eta2(r: &mut Poly, buf: &[__m256i]) 
 {
-  cbd2(r, &buf)
+  cbd2(r, buf)
 }
\ No newline at end of file
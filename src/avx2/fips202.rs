@@ -3,10 +3,11 @@ use crate::symmetric::KeccakState;
 
 pub(crate) const SHAKE128_RATE: usize = 168;
 pub(crate) const SHAKE256_RATE: usize = 136;
-const SHA3_256_RATE: usize = 136;
-const SHA3_512_RATE: usize =  72;
+pub(crate) const SHA3_256_RATE: usize = 136;
+pub(crate) const SHA3_512_RATE: usize =  72;
 const NROUNDS: usize = 24;
 
+#[inline(always)]
 fn rol(a: u64, offset: u64) -> u64 
 {
   (a << offset) ^ (a >> (64-offset))
@@ -19,6 +20,7 @@ fn rol(a: u64, offset: u64) -> u64
 // Arguments:   - const [u8] x: input byte array
 //
 // Returns the loaded 64-bit unsigned integer
+#[inline(always)]
 pub fn load64(x: &[u8]) -> u64
 {
   let mut r = 0u64;
@@ -34,6 +36,7 @@ pub fn load64(x: &[u8]) -> u64
 //
 // Arguments:   - [u8] x: the output byte array
 //              - u64 u: input 64-bit unsigned integer
+#[inline(always)]
 pub fn store64(x: &mut[u8], mut u: u64)
 {
   for i in x.iter_mut().take(8) {
@@ -75,6 +78,7 @@ const KECCAKF_ROUNDCONSTANTS: [u64; NROUNDS] = [
 // Description: The Keccak F1600 Permutation
 //
 // Arguments:   - u64 * state: in/output Keccak state
+#[inline(always)]
 pub(crate) fn keccakf1600_statepermute(state: &mut[u64])
 {
   //copyFromState(A, state)
@@ -333,6 +337,7 @@ pub(crate) fn keccakf1600_statepermute(state: &mut[u64])
 //              - usize r:          rate in bytes (e.g., 168 for SHAKE128)
 //              - const [u8] input: pointer to input to be absorbed into s
 //              - u64 inlen: length of input in bytes
+#[inline(always)]
 pub(crate) fn keccak_absorb(
   s: &mut[u64], 
   mut pos: usize, 
@@ -354,6 +359,7 @@ pub(crate) fn keccak_absorb(
   let new_pos = pos+inlen;
   for i in pos..new_pos {
     s[i/8] ^= (input[idx] as u64) << 8 * (i%8);
+    idx += 1;
   }
   new_pos
 }
@@ -368,6 +374,7 @@ pub(crate) fn keccak_absorb(
 //              - u64 nblocks:        number of blocks to be squeezed (written to h)
 //              - u64 *s:             in/output Keccak state
 //              - usize r:            rate in bytes (e.g., 168 for SHAKE128)
+#[inline(always)]
 pub(crate) fn keccak_squeezeblocks(h: &mut[u8], mut nblocks: usize, s: &mut [u64], r: usize)
 {
   let mut idx = 0usize;
@@ -389,6 +396,7 @@ pub(crate) fn keccak_squeezeblocks(h: &mut[u8], mut nblocks: usize, s: &mut [u64
 // Arguments:   - u64 *s:                     (uninitialized) output Keccak state
 //              - const [u8] input:      input to be absorbed into s
 //              - u64 inputByteLen: length of input in bytes
+#[inline(always)]
 pub(crate) fn shake128_absorb(state: &mut KeccakState, input: &[u8], inlen: usize)
 {
   let pos = state.pos;
@@ -405,6 +413,7 @@ pub(crate) fn shake128_absorb(state: &mut KeccakState, input: &[u8], inlen: usiz
 // Arguments:   - [u8] out: pointer to output blocks
 //              - u64 nblocks: number of blocks to be squeezed (written to output)
 //              - KeccakState state: pointer to input/output Keccak state
+#[inline(always)]
 pub(crate) fn shake128_squeezeblocks(out: &mut[u8], nblocks: usize, state: &mut KeccakState)
 {
   keccak_squeezeblocks(out, nblocks, &mut state.s, SHAKE128_RATE);
@@ -418,6 +427,7 @@ pub(crate) fn shake128_squeezeblocks(out: &mut[u8], nblocks: usize, state: &mut
 //              - usize outlen:  requested output length in bytes
 //              - [u8] input: input
 //              - usize inlen:   length of input in bytes
+#[inline(always)]
 pub(crate) fn shake256(out: &mut[u8], mut outlen: usize, input: &[u8], inlen: usize)
 {
   let mut state = KeccakState::new();
@@ -437,6 +447,7 @@ pub(crate) fn shake256(out: &mut[u8], mut outlen: usize, input: &[u8], inlen: us
 // Arguments:   - [u8] h:      output (32 bytes)
 //              - const [u8] input: input
 //              - usize inlen:   length of input in bytes
+#[inline(always)]
 pub(crate) fn sha3_256(h: &mut[u8], input: &[u8], inlen: usize)
 {
   let mut s = [0u64; 25]; 
@@ -454,6 +465,7 @@ pub(crate) fn sha3_256(h: &mut[u8], input: &[u8], inlen: usize)
 // Arguments:   - [u8] h:      output (64 bytes)
 //              - const [u8] input: input
 //              - usize inlen:   length of input in bytes
+#[inline(always)]
 pub(crate) fn sha3_512(h: &mut[u8], input: &[u8], inlen: usize)
 {
   let mut s = [0u64; 25]; 
@@ -474,6 +486,7 @@ pub(crate) fn sha3_512(h: &mut[u8], input: &[u8], inlen: usize)
 //              - usize pos: position in current block to be absorbed
 //              - usize r: rate in bytes (e.g., 168 for SHAKE128)
 //              - u8 p: domain separation byte
+#[inline(always)]
 fn keccak_finalize(s: &mut[u64], pos: usize, r: usize, p: u8)
 {
   s[pos/8] ^= (p as u64) << 8*(pos%8);
@@ -490,6 +503,7 @@ fn keccak_finalize(s: &mut[u64], pos: usize, r: usize, p: u8)
 //              - const [u8] input:  input to be absorbed into s
 //              - u64 mlen: length of input in bytes
 //              - [u8]  p:         domain-separation byte for different Keccak-derived functions
+#[inline(always)]
 pub(crate) fn keccak_absorb_once(
   s: &mut[u64], 
   r: usize, 
@@ -532,6 +546,7 @@ pub(crate) fn keccak_absorb_once(
 //                usize pos: number of bytes in current block already squeezed
 //              - usize r:            rate in bytes (e.g., 168 for SHAKE128)
 // Returns new position pos in current block
+#[inline(always)]
 pub(crate) fn keccak_squeeze(
   out: &mut[u8], 
   mut outlen: usize, 
@@ -563,6 +578,7 @@ pub(crate) fn keccak_squeeze(
 // Description: Initializes Keccak state for use as SHAKE128 XOF
 //
 // Arguments:   - keccak_state state: (uninitialized) Keccak state
+#[inline(always)]
 fn shake128_init(state: &mut KeccakState)
 {
   state.reset()
@@ -574,6 +590,7 @@ fn shake128_init(state: &mut KeccakState)
 // Description: Finalize absorb step of the SHAKE128 XOF.
 //
 // Arguments:   - keccak_state state: pointer to Keccak state
+#[inline(always)]
 fn shake128_finalize(state: &mut KeccakState)
 {
   keccak_finalize(&mut state.s, state.pos, SHAKE128_RATE, 0x1F);
@@ -588,6 +605,7 @@ fn shake128_finalize(state: &mut KeccakState)
 // Arguments:   - [u8] out: pointer to output blocks
 //              - usize outlen : number of bytes to be squeezed (written to output)
 //              - keccak_state s: pointer to input/output Keccak state
+#[inline(always)]
 fn shake128_squeeze(out: &mut[u8], outlen: usize, state: &mut KeccakState)
 {
   state.pos = keccak_squeeze(out, outlen, &mut state.s, state.pos, SHAKE128_RATE);
@@ -600,43 +618,51 @@ fn shake128_squeeze(out: &mut[u8], outlen: usize, state: &mut KeccakState)
 // Arguments:   - keccak_state state: pointer to (uninitialized) output Keccak state
 //              - const [u8] in: input to be absorbed into s
 //              - usize inlen: length of input in bytes
+#[inline(always)]
 pub(crate) fn shake128_absorb_once(state: &mut KeccakState, input: &[u8], inlen: usize)
 {
   keccak_absorb_once(&mut state.s, SHAKE128_RATE, input, inlen, 0x1F);
   state.pos = SHAKE128_RATE;
 }
 
-fn shake256_init(state: &mut KeccakState) {
+#[inline(always)]
+pub(crate) fn shake256_init(state: &mut KeccakState) {
   state.reset();
 }
 
-fn shake256_absorb(state: &mut KeccakState,  input: &[u8], inlen: usize)
+#[inline(always)]
+pub(crate) fn shake256_absorb(state: &mut KeccakState,  input: &[u8], inlen: usize)
 {
   state.pos = keccak_absorb(&mut state.s, state.pos, SHAKE256_RATE, input, inlen);
 }
 
-fn shake256_finalize(state: &mut KeccakState)
+#[inline(always)]
+pub(crate) fn shake256_finalize(state: &mut KeccakState)
 {
   keccak_finalize(&mut state.s, state.pos, SHAKE256_RATE, 0x1F);
   state.pos = SHAKE256_RATE;
 }
 
-fn shake256_squeeze(out: &mut[u8], outlen: usize, state: &mut KeccakState)
+#[inline(always)]
+pub(crate) fn shake256_squeeze(out: &mut[u8], outlen: usize, state: &mut KeccakState)
 {
   state.pos = keccak_squeeze(out, outlen, &mut state.s, state.pos, SHAKE256_RATE);
 }
 
+#[inline(always)]
 pub(crate) fn shake256_absorb_once(state: &mut KeccakState, input: &[u8], inlen: usize)
 {
   keccak_absorb_once(&mut state.s, SHAKE256_RATE, input, inlen, 0x1F);
   state.pos = SHAKE256_RATE;
 }
 
+#[inline(always)]
 fn shake256_squeezeblocks(out: &mut[u8], nblocks: usize, state: &mut KeccakState)
 {
   keccak_squeezeblocks(out, nblocks, &mut state.s, SHAKE256_RATE);
 }
 
+#[inline(always)]
 fn shake128(out: &mut[u8], mut outlen: usize, input: &[u8], inlen: usize)
 {
   let mut state = KeccakState::new();
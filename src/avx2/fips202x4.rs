@@ -1,9 +1,9 @@
 #![allow(dead_code)]
 
 use core::arch::x86_64::*;
-use crate::fips202::*;
-use crate::keccak4x::f1600_x4;
-use crate::align::{GenMatrixBuf, Eta4xBuf};
+use super::fips202::*;
+use super::keccak4x::f1600_x4;
+use super::align::{GenMatrixBuf, Eta4xBuf};
 
 #[repr(C)]
 pub struct Keccakx4State {
@@ -11,11 +11,13 @@ pub struct Keccakx4State {
 }
 
 impl Keccakx4State {
+  #[inline(always)]
   pub fn new() -> Self {
     unsafe {Keccakx4State { s: [_mm256_setzero_si256(); 25]}}
   }
 }
 
+#[inline(always)]
 pub unsafe fn keccakx4_absorb_once(
   s: &mut[__m256i; 25],
   r: usize,
@@ -68,6 +70,7 @@ pub unsafe fn keccakx4_absorb_once(
   s[r/8 - 1] = _mm256_xor_si256(s[r/8 - 1], t);
 }
 
+#[inline(always)]
 pub unsafe fn keccakx4_squeezeblocks128(
   out: &mut [GenMatrixBuf; 4],
   mut nblocks: usize,
@@ -97,6 +100,7 @@ pub unsafe fn keccakx4_squeezeblocks128(
   }
 }
 
+#[inline(always)]
 pub unsafe fn keccakx4_squeezeblocks256(
   out: &mut [Eta4xBuf; 4],
   mut nblocks: usize,
@@ -156,6 +160,7 @@ pub unsafe fn keccakx4_squeezeblocks256(
 //   }
 // }
 
+#[inline(always)]
 pub unsafe fn shake128x4_absorb_once(
   state: &mut Keccakx4State,
   in0: &[u8],
@@ -173,6 +178,7 @@ pub unsafe fn shake128x4_absorb_once(
   )
 }
 
+#[inline(always)]
 pub unsafe fn shake128x4_squeezeblocks(
   out: &mut[GenMatrixBuf; 4], 
   nblocks: usize,
@@ -187,6 +193,7 @@ pub unsafe fn shake128x4_squeezeblocks(
   );
 }
 
+#[inline(always)]
 pub unsafe fn shake256x4_absorb_once(
   state: &mut Keccakx4State,
   in0: &[u8],
@@ -204,6 +211,7 @@ pub unsafe fn shake256x4_absorb_once(
   )
 }
 
+#[inline(always)]
 pub unsafe fn shake256x4_squeezeblocks(
   out: &mut[Eta4xBuf; 4],
   nblocks: usize,
@@ -218,6 +226,60 @@ pub unsafe fn shake256x4_squeezeblocks(
   );
 }
 
+// Name:        keccakx4_hash
+//
+// Description: Hashes four inputs of the same length with one 4-way
+//              permutation per block, squeezing a single block.
+//              Inputs must be a multiple of 8 bytes long as the absorb
+//              gathers whole lanes.
+//
+// Arguments:   - [&mut [u8]; 4] out: output hashes
+//              - usize outlen:       output bytes per hash, at most r
+//              - usize r:            rate in bytes
+//              - [&[u8]; 4] input:   inputs (inlen bytes each)
+//              - u8 p:               domain separation byte
+#[inline(always)]
+pub unsafe fn keccakx4_hash(
+  mut out: [&mut [u8]; 4],
+  outlen: usize,
+  r: usize,
+  input: [&[u8]; 4],
+  inlen: usize,
+  p: u8
+)
+{
+  debug_assert!(inlen & 7 == 0 && outlen & 7 == 0 && outlen <= r);
+  let mut state = Keccakx4State::new();
+  keccakx4_absorb_once(&mut state.s, r, input[0], input[1], input[2], input[3], inlen, p);
+  f1600_x4(&mut state.s);
+  let mut lanes = [0u64; 4];
+  for i in 0..outlen/8 {
+    _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, state.s[i]);
+    for (o, lane) in out.iter_mut().zip(lanes.iter()) {
+      o[8*i..8*i+8].copy_from_slice(&lane.to_le_bytes());
+    }
+  }
+}
+
+#[inline(always)]
+pub unsafe fn sha3_256x4(out: [&mut [u8]; 4], input: [&[u8]; 4], inlen: usize)
+{
+  keccakx4_hash(out, 32, SHA3_256_RATE, input, inlen, 0x06)
+}
+
+#[inline(always)]
+pub unsafe fn sha3_512x4(out: [&mut [u8]; 4], input: [&[u8]; 4], inlen: usize)
+{
+  keccakx4_hash(out, 64, SHA3_512_RATE, input, inlen, 0x06)
+}
+
+// SHAKE256 with 32 bytes of output
+#[inline(always)]
+pub unsafe fn shake256x4_32(out: [&mut [u8]; 4], input: [&[u8]; 4], inlen: usize)
+{
+  keccakx4_hash(out, 32, SHAKE256_RATE, input, inlen, 0x1F)
+}
+
 // pub unsafe fn shake128x4(
 //   out: &mut [GenMatrixBuf; 4],
 //   mut outlen: usize,
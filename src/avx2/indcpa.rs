@@ -1,18 +1,22 @@
 use core::arch::x86_64::*;
 #[cfg(not(feature = "90s"))] 
-use crate::{fips202::*, fips202x4::*};
+use super::{fips202::*, fips202x4::*};
 #[cfg(feature = "90s")] 
-use crate::{aes256ctr::*, cbd::*};
+use super::{aes256ctr::*, cbd::*};
 use crate::rng::randombytes;
-use crate::{
+use super::{
   align::*,
-  CryptoRng,
-  params::*,
   poly::*,
   polyvec::*,
   rejsample::*,
+  verify::verify,
+};
+use crate::{
+  CryptoRng,
+  params::*,
   RngCore,
   symmetric::*,
+  types::wipe_coeffs,
 };
 
 // Name:        pack_pk
@@ -24,10 +28,11 @@ use crate::{
 // Arguments:   [u8] r:          the output serialized public key
 //              const poly *pk:            the input public-key polynomial
 //              const [u8] seed: the input public seed
-fn pack_pk(r: &mut[u8], pk: &Polyvec, seed: &[u8])
+#[inline(always)]
+fn pack_pk<P: KyberParams>(r: &mut[u8], pk: &Polyvec, seed: &[u8])
 {
-  polyvec_tobytes(r, pk);
-  r[KYBER_POLYVECBYTES..][..KYBER_SYMBYTES]
+  polyvec_tobytes::<P>(r, pk);
+  r[P::POLYVECBYTES..][..KYBER_SYMBYTES]
     .copy_from_slice(&seed[..KYBER_SYMBYTES]);
 }
 
@@ -39,11 +44,12 @@ fn pack_pk(r: &mut[u8], pk: &Polyvec, seed: &[u8])
 // Arguments:   - Polyvec pk:                   output public-key vector of polynomials
 //              - [u8] seed:           output seed to generate matrix A
 //              - const [u8] packedpk: input serialized public key
-fn unpack_pk(pk: &mut Polyvec, seed: &mut[u8], packedpk: &[u8])
+#[inline(always)]
+fn unpack_pk<P: KyberParams>(pk: &mut Polyvec, seed: &mut[u8], packedpk: &[u8])
 {
-  unsafe {polyvec_frombytes(pk, packedpk);}
+  unsafe {polyvec_frombytes::<P>(pk, packedpk);}
   seed[..KYBER_SYMBYTES]
-    .copy_from_slice(&packedpk[KYBER_POLYVECBYTES..][..KYBER_SYMBYTES]);
+    .copy_from_slice(&packedpk[P::POLYVECBYTES..][..KYBER_SYMBYTES]);
 }
 
 // Name:        pack_sk
@@ -52,9 +58,10 @@ fn unpack_pk(pk: &mut Polyvec, seed: &mut[u8], packedpk: &[u8])
 //
 // Arguments:   - [u8] r:  output serialized secret key
 //              - const Polyvec sk: input vector of polynomials (secret key)
-fn pack_sk(r: &mut[u8], sk: &Polyvec)
+#[inline(always)]
+fn pack_sk<P: KyberParams>(r: &mut[u8], sk: &Polyvec)
 {
-  polyvec_tobytes(r, sk);
+  polyvec_tobytes::<P>(r, sk);
 }
 
 // Name:        unpack_sk
@@ -64,9 +71,10 @@ fn pack_sk(r: &mut[u8], sk: &Polyvec)
 //
 // Arguments:   - Polyvec sk:                   output vector of polynomials (secret key)
 //              - const [u8] packedsk: input serialized secret key
-fn unpack_sk(sk: &mut Polyvec, packedsk: &[u8])
+#[inline(always)]
+fn unpack_sk<P: KyberParams>(sk: &mut Polyvec, packedsk: &[u8])
 {
-  unsafe {polyvec_frombytes(sk, packedsk);}
+  unsafe {polyvec_frombytes::<P>(sk, packedsk);}
 }
 
 // Name:        pack_ciphertext
@@ -78,11 +86,12 @@ fn unpack_sk(sk: &mut Polyvec, packedsk: &[u8])
 // Arguments:   [u8] r:          the output serialized ciphertext
 //              const poly *pk:            the input vector of polynomials b
 //              const [u8] seed: the input polynomial v
-fn pack_ciphertext(r: &mut[u8], b: &Polyvec, v: Poly)
+#[inline(always)]
+fn pack_ciphertext<P: KyberParams>(r: &mut[u8], b: &Polyvec, v: Poly)
 {
   unsafe {
-    polyvec_compress(r, b);
-    poly_compress(&mut r[KYBER_POLYVECCOMPRESSEDBYTES..], v);
+    polyvec_compress::<P>(r, b);
+    poly_compress::<P>(&mut r[P::POLYVECCOMPRESSEDBYTES..], v);
   }
 }
 
@@ -94,11 +103,12 @@ fn pack_ciphertext(r: &mut[u8], b: &Polyvec, v: Poly)
 // Arguments:   - Polyvec b:             output vector of polynomials b
 //              - Poly *v:                output polynomial v
 //              - const [u8] c:           input serialized ciphertext
-fn unpack_ciphertext(b: &mut Polyvec, v: &mut Poly, c: &[u8])
+#[inline(always)]
+fn unpack_ciphertext<P: KyberParams>(b: &mut Polyvec, v: &mut Poly, c: &[u8])
 {
   unsafe {
-    polyvec_decompress(b, c);
-    poly_decompress(v, &c[KYBER_POLYVECCOMPRESSEDBYTES..]);
+    polyvec_decompress::<P>(b, c);
+    poly_decompress::<P>(v, &c[P::POLYVECCOMPRESSEDBYTES..]);
   }
 }
 
@@ -113,6 +123,7 @@ fn unpack_ciphertext(b: &mut Polyvec, v: &mut Poly, c: &[u8])
 //              - usize buflen:      length of input buffer in bytes
 //
 // Returns number of sampled 16-bit integers (at most len)
+#[inline(always)]
 fn rej_uniform(r: &mut[i16], len: usize, buf: &[u8], buflen: usize) -> usize
 {
   let (mut ctr, mut pos) = (0usize, 0usize);
@@ -135,26 +146,29 @@ fn rej_uniform(r: &mut[i16], len: usize, buf: &[u8], buflen: usize) -> usize
   ctr
 }
 
-pub fn gen_a(a: &mut[Polyvec], b: &[u8]) 
+#[inline(always)]
+pub fn gen_a<P: KyberParams>(a: &mut[Polyvec], b: &[u8]) 
 {
-  unsafe { gen_matrix(a, b, false); }
+  unsafe { gen_matrix::<P>(a, b, false); }
 }
 
-pub fn gen_at(a: &mut[Polyvec], b: &[u8]) 
+#[inline(always)]
+pub fn gen_at<P: KyberParams>(a: &mut[Polyvec], b: &[u8]) 
 {
-  unsafe { gen_matrix(a, b, true); }
+  unsafe { gen_matrix::<P>(a, b, true); }
 }
 
 #[cfg(feature="90s")]
-unsafe fn gen_matrix(a: &mut[Polyvec], seed: &[u8], transposed: bool)
+#[inline(always)]
+unsafe fn gen_matrix<P: KyberParams>(a: &mut[Polyvec], seed: &[u8], transposed: bool)
 {
   let (mut ctr, mut off, mut buflen);
   let mut nonce: u64;
   let mut state = Aes256CtrCtx::new();
   let mut buf = GenMatrixBuf90s::new();
   aes256ctr_init(&mut state, seed, [0u8; 12]);
-  for i in 0..KYBER_K {
-    for j in  0..KYBER_K {
+  for i in 0..P::K {
+    for j in  0..P::K {
       if transposed {
         nonce = ((j << 8) | i) as u64;
       } else {
@@ -178,8 +192,27 @@ unsafe fn gen_matrix(a: &mut[Polyvec], seed: &[u8], transposed: bool)
   }
 }
 
-#[cfg(all(feature="kyber512", not(feature="90s")))]
-unsafe fn gen_matrix(a: &mut[Polyvec], seed: &[u8], transposed: bool)
+// Name:        gen_matrix
+//
+// Description: Dispatches to the 4-way sampler matching the module rank
+//
+// Arguments:   - Polyvec a:        output matrix A
+//              - const [u8] seed:  input seed
+//              - bool transposed:  boolean deciding whether A or A^T is generated
+#[cfg(not(feature="90s"))]
+#[inline(always)]
+unsafe fn gen_matrix<P: KyberParams>(a: &mut[Polyvec], seed: &[u8], transposed: bool)
+{
+  match P::K {
+    2 => gen_matrix2(a, seed, transposed),
+    3 => gen_matrix3(a, seed, transposed),
+    _ => gen_matrix4(a, seed, transposed)
+  }
+}
+
+#[cfg(not(feature="90s"))]
+#[inline(always)]
+unsafe fn gen_matrix2(a: &mut[Polyvec], seed: &[u8], transposed: bool)
 {
   let mut state =  Keccakx4State::new();
   let mut buf = [GenMatrixBuf::new(); 4];
@@ -244,8 +277,9 @@ unsafe fn gen_matrix(a: &mut[Polyvec], seed: &[u8], transposed: bool)
   poly_nttunpack(&mut a[1].vec[1]);
 }
 
-#[cfg(all(not(feature="kyber512"), not(feature="kyber1024"), not(feature="90s")))]
-unsafe fn gen_matrix(a: &mut[Polyvec], seed: &[u8], transposed: bool)
+#[cfg(not(feature="90s"))]
+#[inline(always)]
+unsafe fn gen_matrix3(a: &mut[Polyvec], seed: &[u8], transposed: bool)
 {
   let mut state =  Keccakx4State::new();
   let mut state1x =  KeccakState::new();
@@ -395,8 +429,9 @@ unsafe fn gen_matrix(a: &mut[Polyvec], seed: &[u8], transposed: bool)
   poly_nttunpack(&mut a[2].vec[2]);
 }
 
-#[cfg(all(feature="kyber1024", not(feature="90s")))]
-unsafe fn gen_matrix(a: &mut[Polyvec], seed: &[u8], transposed: bool)
+#[cfg(not(feature="90s"))]
+#[inline(always)]
+unsafe fn gen_matrix4(a: &mut[Polyvec], seed: &[u8], transposed: bool)
 {
   let mut f;
   let mut state = Keccakx4State::new();
@@ -456,223 +491,372 @@ unsafe fn gen_matrix(a: &mut[Polyvec], seed: &[u8], transposed: bool)
   } 
 }
  
-pub fn indcpa_keypair<R>(
+#[inline(always)]
+pub fn indcpa_keypair<P, R>(
   pk: &mut[u8], 
   sk: &mut[u8], 
   _seed: Option<(&[u8], &[u8])>, 
   _rng: &mut R
 )
-  where R: CryptoRng + RngCore
+  where P: KyberParams, R: CryptoRng + RngCore
 {
 
-  let mut a = [Polyvec::new(); KYBER_K];
+  let mut a = [Polyvec::new(); KYBER_MAX_K];
   let (mut e, mut pkpv, mut skpv) = (Polyvec::new(), Polyvec::new(), Polyvec::new());
   let mut buf = [0u8; 2*KYBER_SYMBYTES];
   let mut randbuf = [0u8; 2*KYBER_SYMBYTES];
 
   if let Some(s) = _seed {
-    randbuf[..KYBER_SYMBYTES].copy_from_slice(&s.0);
+    randbuf[..KYBER_SYMBYTES].copy_from_slice(s.0);
   } else {
     randombytes(&mut randbuf, KYBER_SYMBYTES, _rng);
   }
   
-  hash_g(&mut buf, &randbuf, KYBER_SYMBYTES);
+  if P::ML_KEM {
+    // FIPS 203 domain separates key generation by the module rank
+    randbuf[KYBER_SYMBYTES] = P::K as u8;
+    hash_g(&mut buf, &randbuf, KYBER_SYMBYTES + 1);
+  } else {
+    hash_g(&mut buf, &randbuf, KYBER_SYMBYTES);
+  }
 
   let (publicseed, noiseseed) = buf.split_at(KYBER_SYMBYTES);
-  gen_a(&mut a, publicseed);
+  gen_a::<P>(&mut a, publicseed);
 
   #[cfg(feature="90s")]
   {
     // Assumes divisibility
-    const NOISE_NBLOCKS: usize = (KYBER_ETA1*KYBER_N/4)/XOF_BLOCKBYTES;
+    let noise_nblocks = (P::ETA1*KYBER_N/4)/XOF_BLOCKBYTES;
     let mut nonce = 0u64;
     let mut state = Aes256CtrCtx::new();
     let mut coins = IndcpaBuf::new();
     aes256ctr_init(&mut state, noiseseed, [0u8; 12]);
     nonce += 1;
     unsafe {
-      for i in 0..KYBER_K {
-        aes256ctr_squeezeblocks(&mut coins.coeffs, NOISE_NBLOCKS, &mut state);
+      for i in 0..P::K {
+        aes256ctr_squeezeblocks(&mut coins.coeffs, noise_nblocks, &mut state);
         state.n = _mm_loadl_epi64([nonce].as_ptr() as *const __m128i);
         nonce += 1;
-        poly_cbd_eta1_90s(&mut skpv.vec[i], &coins);
+        poly_cbd_eta1_90s::<P>(&mut skpv.vec[i], &coins);
       }
-      for i in 0..KYBER_K {
-        aes256ctr_squeezeblocks(&mut coins.coeffs, NOISE_NBLOCKS, &mut state);
+      for i in 0..P::K {
+        aes256ctr_squeezeblocks(&mut coins.coeffs, noise_nblocks, &mut state);
         state.n = _mm_loadl_epi64([nonce].as_ptr() as *const __m128i);
         nonce += 1;
-        poly_cbd_eta1_90s(&mut e.vec[i], &coins);
+        poly_cbd_eta1_90s::<P>(&mut e.vec[i], &coins);
       }
     }
   } 
   
-  #[cfg(all(feature="kyber512", not(feature="90s")))]
-  {
-    let (skpv0, skpv1) =skpv.vec.split_at_mut(1);
-    let (e0, e1) = e.vec.split_at_mut(1);
-    poly_getnoise_eta1_4x(
-      &mut skpv0[0], &mut skpv1[0], &mut e0[0], &mut e1[0], noiseseed, 0, 1, 2, 3
-    );
-  } 
-
-  #[cfg(all(feature="kyber1024", not(feature="90s")))]
-  {
-    let (skpv0, skpv1) = skpv.vec.split_at_mut(1);
-    let (skpv1, skpv2) = skpv1.split_at_mut(1);
-    let (skpv2, skpv3) = skpv2.split_at_mut(1);
-    poly_getnoise_eta1_4x(
-      &mut skpv0[0], &mut skpv1[0], &mut skpv2[0], &mut skpv3[0], noiseseed,  0, 1, 2, 3
-    );
-    let (e0, e1) = e.vec.split_at_mut(1);
-    let (e1, e2) = e1.split_at_mut(1);
-    let (e2, e3) = e2.split_at_mut(1);
-    poly_getnoise_eta1_4x(
-      &mut e0[0], &mut e1[0], &mut e2[0], &mut e3[0], noiseseed, 4, 5, 6, 7
-    );
+  #[cfg(not(feature="90s"))]
+  match P::K {
+    2 => {
+      let (skpv0, skpv1) =skpv.vec.split_at_mut(1);
+      let (e0, e1) = e.vec.split_at_mut(1);
+      poly_getnoise_eta1_4x::<P>(
+        &mut skpv0[0], &mut skpv1[0], &mut e0[0], &mut e1[0], noiseseed, 0, 1, 2, 3
+      );
+    },
+    3 => {
+      let (skpv0, skpv1) = skpv.vec.split_at_mut(1);
+      let (skpv1, skpv2) = skpv1.split_at_mut(1);
+      poly_getnoise_eta1_4x::<P>(
+        &mut skpv0[0], &mut skpv1[0], &mut skpv2[0], &mut e.vec[0], noiseseed, 0, 1, 2, 3
+      );
+      let (e1, e2) = e.vec.split_at_mut(2);
+      let (pkpv0, pkpv1) = pkpv.vec.split_at_mut(1);
+      poly_getnoise_eta1_4x::<P>(
+        &mut e1[1], &mut e2[0], &mut pkpv0[0], &mut pkpv1[0], noiseseed, 4, 5, 6, 7
+      );
+    },
+    _ => {
+      let (skpv0, skpv1) = skpv.vec.split_at_mut(1);
+      let (skpv1, skpv2) = skpv1.split_at_mut(1);
+      let (skpv2, skpv3) = skpv2.split_at_mut(1);
+      poly_getnoise_eta1_4x::<P>(
+        &mut skpv0[0], &mut skpv1[0], &mut skpv2[0], &mut skpv3[0], noiseseed,  0, 1, 2, 3
+      );
+      let (e0, e1) = e.vec.split_at_mut(1);
+      let (e1, e2) = e1.split_at_mut(1);
+      let (e2, e3) = e2.split_at_mut(1);
+      poly_getnoise_eta1_4x::<P>(
+        &mut e0[0], &mut e1[0], &mut e2[0], &mut e3[0], noiseseed, 4, 5, 6, 7
+      );
+    }
   }
-  
-  #[cfg(not(any(feature="kyber1024", feature="kyber512", feature="90s")))] // kyber768
-  {
-    let (skpv0, skpv1) = skpv.vec.split_at_mut(1);
-    let (skpv1, skpv2) = skpv1.split_at_mut(1);
-    poly_getnoise_eta1_4x(
-      &mut skpv0[0], &mut skpv1[0], &mut skpv2[0], &mut e.vec[0], noiseseed, 0, 1, 2, 3
-    );
-    let (e1, e2) = e.vec.split_at_mut(2);
-    let (pkpv0, pkpv1) = pkpv.vec.split_at_mut(1);
-    poly_getnoise_eta1_4x(
-      &mut e1[1], &mut e2[0], &mut pkpv0[0], &mut pkpv1[0], noiseseed, 4, 5, 6, 7
-    );
-  } 
 
-  polyvec_ntt(&mut skpv);
-  polyvec_reduce(&mut skpv);
-  polyvec_ntt(&mut e);
+  polyvec_ntt::<P>(&mut skpv);
+  polyvec_reduce::<P>(&mut skpv);
+  polyvec_ntt::<P>(&mut e);
 
-  for i in 0..KYBER_K {
-    polyvec_basemul_acc_montgomery(&mut pkpv.vec[i], &a[i], &skpv);
+  for i in 0..P::K {
+    polyvec_basemul_acc_montgomery::<P>(&mut pkpv.vec[i], &a[i], &skpv);
     poly_tomont(&mut pkpv.vec[i]);
   }
 
-  polyvec_add(&mut pkpv, &e);
-  polyvec_reduce(&mut pkpv);
+  polyvec_add::<P>(&mut pkpv, &e);
+  polyvec_reduce::<P>(&mut pkpv);
+
+  pack_sk::<P>(sk, &skpv);
+  pack_pk::<P>(pk, &pkpv, publicseed);
+}
 
-  pack_sk(sk, &skpv);
-  pack_pk(pk, &pkpv, publicseed);
+// Public key unpacked with its matrix expanded, the costly
+// parts of encryption that only depend on the public key
+#[derive(Clone)]
+pub struct ExpandedPk {
+  at: [Polyvec; KYBER_MAX_K],
+  pkpv: Polyvec,
 }
 
-pub fn indcpa_enc(c: &mut[u8], m: &[u8], pk: &[u8], coins: &[u8]) 
+// Name:        indcpa_expand_pk
+//
+// Description: Unpacks the public key and generates the transposed
+//              matrix A from its seed
+//
+// Arguments: - const [u8] pk:   input public key (length INDCPA_PUBLICKEYBYTES)
+#[inline(always)]
+pub fn indcpa_expand_pk<P: KyberParams>(pk: &[u8]) -> ExpandedPk
 {
-  unsafe {
-    let mut at = [Polyvec::new(); KYBER_K];
-    let (mut sp, mut pkpv, mut ep, mut b) = (Polyvec::new(),Polyvec::new(), Polyvec::new(), Polyvec::new());
-    let (mut v, mut k, mut epp) = (Poly::new(), Poly::new(), Poly::new());
-    let mut seed = [0u8; KYBER_SYMBYTES];
+  let mut epk = ExpandedPk { at: [Polyvec::new(); KYBER_MAX_K], pkpv: Polyvec::new() };
+  let mut seed = [0u8; KYBER_SYMBYTES];
+  unpack_pk::<P>(&mut epk.pkpv, &mut seed, pk);
+  gen_at::<P>(&mut epk.at, &seed);
+  epk
+}
 
-    unpack_pk(&mut pkpv, &mut seed, pk);
-    poly_frommsg(&mut k, m);
-    gen_at(&mut at, &seed);
-
-    #[cfg(feature="90s")] 
-    {
-      const NOISE_NBLOCKS: usize  = (KYBER_ETA1*KYBER_N/4)/XOF_BLOCKBYTES;
-      const CIPHERTEXTNOISE_NBLOCKS: usize  = (KYBER_ETA2*KYBER_N/4)/XOF_BLOCKBYTES;
-       let mut buf = IndcpaBuf::new();
-      let mut state = Aes256CtrCtx::new();
-      let mut nonce = 0u64;
-      aes256ctr_init(&mut state, coins, [0u8; 12]);
+#[inline(always)]
+pub fn indcpa_enc<P: KyberParams>(c: &mut[u8], m: &[u8], pk: &[u8], coins: &[u8]) 
+{
+  indcpa_enc_expanded::<P>(c, m, &indcpa_expand_pk::<P>(pk), coins);
+}
+
+// Name:        indcpa_enc_expanded
+//
+// Description: Encryption with a public key from indcpa_expand_pk
+#[inline(always)]
+pub fn indcpa_enc_expanded<P: KyberParams>(c: &mut[u8], m: &[u8], epk: &ExpandedPk, coins: &[u8]) 
+{
+  let (mut sp, mut ep, mut epp) = (Polyvec::new(), Polyvec::new(), Poly::new());
+  enc_noise::<P>(&mut sp, &mut ep, Some(&mut epp), coins);
+  enc_finish::<P>(c, m, epk, &mut sp, &ep, &epp);
+}
+
+// Name:        indcpa_enc_x4
+//
+// Description: Four independent encryptions. Samples the last noise
+//              polynomial of all four with the 4-way PRF where a single
+//              encryption would leave it to a scalar call.
+//
+// Arguments:   - [&mut [u8]; 4] c:     output ciphertexts (length INDCPA_BYTES)
+//              - [&[u8]; 4] m:         input messages (length INDCPA_MSGBYTES)
+//              - [&[u8]; 4] pk:        input public keys (length INDCPA_PUBLICKEYBYTES)
+//              - [&[u8]; 4] coins:     input random coins (length KYBER_SYMBYTES)
+#[cfg(not(feature="90s"))]
+#[inline(always)]
+pub fn indcpa_enc_x4<P: KyberParams>(
+  mut c: [&mut[u8]; 4], m: [&[u8]; 4], pk: [&[u8]; 4], coins: [&[u8]; 4]
+)
+{
+  let mut sp = [Polyvec::new(); 4];
+  let mut ep = [Polyvec::new(); 4];
+  let mut epp = [Poly::new(), Poly::new(), Poly::new(), Poly::new()];
+  // Rank 3 fills every lane of its own noise sampling, epp included
+  let shared = P::K != 3;
+  for i in 0..4 {
+    let own = if shared { None } else { Some(&mut epp[i]) };
+    enc_noise::<P>(&mut sp[i], &mut ep[i], own, coins[i]);
+  }
+  if shared {
+    let [e0, e1, e2, e3] = &mut epp;
+    poly_getnoise_eta2_4x([e0, e1, e2, e3], coins, (2*P::K) as u8);
+  }
+  for (i, c) in c.iter_mut().enumerate() {
+    let epk = indcpa_expand_pk::<P>(pk[i]);
+    enc_finish::<P>(c, m[i], &epk, &mut sp[i], &ep[i], &epp[i]);
+  }
+}
+
+// Name:        enc_noise
+//
+// Description: Samples the noise of an encryption from the coins, epp is
+//              skipped when None for ranks 2 and 4 so it can be batched
+#[inline(always)]
+fn enc_noise<P: KyberParams>(sp: &mut Polyvec, ep: &mut Polyvec, epp: Option<&mut Poly>, coins: &[u8])
+{
+  #[cfg(feature="90s")] 
+  unsafe {
+    let noise_nblocks = (P::ETA1*KYBER_N/4)/XOF_BLOCKBYTES;
+    const CIPHERTEXTNOISE_NBLOCKS: usize  = (KYBER_ETA2*KYBER_N/4)/XOF_BLOCKBYTES;
+    let mut buf = IndcpaBuf::new();
+    let mut state = Aes256CtrCtx::new();
+    let mut nonce = 0u64;
+    aes256ctr_init(&mut state, coins, [0u8; 12]);
+    nonce += 1;
+    for i in 0..P::K {
+      aes256ctr_squeezeblocks(&mut buf.coeffs, noise_nblocks, &mut state);
+      state.n = _mm_loadl_epi64([nonce, 0].as_ptr() as *const __m128i);
       nonce += 1;
-      for i in 0..KYBER_K {
-        aes256ctr_squeezeblocks(&mut buf.coeffs, NOISE_NBLOCKS, &mut state);
-        state.n = _mm_loadl_epi64([nonce, 0].as_ptr() as *const __m128i);
-        nonce += 1;
-        poly_cbd_eta1_90s(&mut sp.vec[i], &buf);
-      }
-      for i in 0..KYBER_K {
-        aes256ctr_squeezeblocks(&mut buf.coeffs, CIPHERTEXTNOISE_NBLOCKS, &mut state);
-        state.n = _mm_loadl_epi64([nonce, 0].as_ptr() as *const __m128i);
-        nonce += 1;
-        poly_cbd_eta2(&mut ep.vec[i], &buf.vec);
-      }
+      poly_cbd_eta1_90s::<P>(&mut sp.vec[i], &buf);
+    }
+    for i in 0..P::K {
       aes256ctr_squeezeblocks(&mut buf.coeffs, CIPHERTEXTNOISE_NBLOCKS, &mut state);
-      poly_cbd_eta2(&mut epp, &buf.vec);
+      state.n = _mm_loadl_epi64([nonce, 0].as_ptr() as *const __m128i);
+      nonce += 1;
+      poly_cbd_eta2(&mut ep.vec[i], &buf.vec);
     }
+    if let Some(epp) = epp {
+      aes256ctr_squeezeblocks(&mut buf.coeffs, CIPHERTEXTNOISE_NBLOCKS, &mut state);
+      poly_cbd_eta2(epp, &buf.vec);
+    }
+  }
 
-    #[cfg(all(feature="kyber512", not(feature="90s")))] 
-    {
+  #[cfg(not(feature="90s"))]
+  match P::K {
+    2 => {
       let (sp0, sp1) = sp.vec.split_at_mut(1);
       let (ep0, ep1) = ep.vec.split_at_mut(1);
-      poly_getnoise_eta1122_4x(
+      poly_getnoise_eta1122_4x::<P>(
         &mut sp0[0], &mut sp1[0], &mut ep0[0], &mut ep1[0], coins, 0, 1, 2, 3
       );
-      poly_getnoise_eta2(&mut epp, coins, 4); 
-    } 
-
-    #[cfg(not(any(feature="kyber1024", feature="kyber512", feature="90s")))] // kyber768)
-    {
+      if let Some(epp) = epp {
+        poly_getnoise_eta2(epp, coins, 4);
+      }
+    },
+    3 => {
+      let mut unused = (Poly::new(), Poly::new());
+      let epp = match epp { Some(epp) => epp, None => &mut unused.0 };
       let (sp0, sp1) = sp.vec.split_at_mut(1);
       let (sp1, sp2) = sp1.split_at_mut(1);
-      poly_getnoise_eta1_4x(
+      poly_getnoise_eta1_4x::<P>(
         &mut sp0[0], &mut sp1[0], &mut sp2[0], &mut ep.vec[0], coins, 0, 1, 2 ,3
       );
       let (ep1, ep2) = ep.vec.split_at_mut(2);
-      poly_getnoise_eta1_4x(
-        &mut ep1[1], &mut ep2[0], &mut epp, &mut b.vec[0], coins,  4, 5, 6, 7
+      poly_getnoise_eta1_4x::<P>(
+        &mut ep1[1], &mut ep2[0], epp, &mut unused.1, coins,  4, 5, 6, 7
       );
-    }
-
-    #[cfg(all(feature="kyber1024", not(feature="90s")))]
-    {
+    },
+    _ => {
       let (sp0, sp1) = sp.vec.split_at_mut(1);
       let (sp1, sp2) = sp1.split_at_mut(1);
       let (sp2, sp3) = sp2.split_at_mut(1);
-      poly_getnoise_eta1_4x(
+      poly_getnoise_eta1_4x::<P>(
         &mut sp0[0], &mut sp1[0], &mut sp2[0],&mut sp3[0], coins, 0, 1, 2, 3
       );
       let (ep0, ep1) = ep.vec.split_at_mut(1);
       let (ep1, ep2) = ep1.split_at_mut(1);
       let (ep2, ep3) = ep2.split_at_mut(1);
-      poly_getnoise_eta1_4x(
+      poly_getnoise_eta1_4x::<P>(
         &mut ep0[0], &mut ep1[0], &mut ep2[0],&mut ep3[0], coins, 4, 5, 6, 7
       );
-      poly_getnoise_eta2(&mut epp, coins, 8);
+      if let Some(epp) = epp {
+        poly_getnoise_eta2(epp, coins, 8);
+      }
     }
-  
-    polyvec_ntt(&mut sp);
+  }
+}
+
+// Name:        enc_finish
+//
+// Description: Completes an encryption from its sampled noise
+#[inline(always)]
+fn enc_finish<P: KyberParams>(c: &mut[u8], m: &[u8], epk: &ExpandedPk, sp: &mut Polyvec, ep: &Polyvec, epp: &Poly)
+{
+  let mut b = Polyvec::new();
+  let (mut v, mut k) = (Poly::new(), Poly::new());
+
+  unsafe {
+    poly_frommsg(&mut k, m);
+
+    polyvec_ntt::<P>(sp);
     
-    for i in 0..KYBER_K {
-      polyvec_basemul_acc_montgomery(&mut b.vec[i], &at[i], &sp);
+    for i in 0..P::K {
+      polyvec_basemul_acc_montgomery::<P>(&mut b.vec[i], &epk.at[i], sp);
     }
-    polyvec_basemul_acc_montgomery(&mut v, &pkpv, &sp);
+    polyvec_basemul_acc_montgomery::<P>(&mut v, &epk.pkpv, sp);
 
-    polyvec_invntt_tomont(&mut b);
+    polyvec_invntt_tomont::<P>(&mut b);
     poly_invntt_tomont(&mut v);
 
-    polyvec_add(&mut b, &ep);
-    poly_add(&mut v, &epp);
+    polyvec_add::<P>(&mut b, ep);
+    poly_add(&mut v, epp);
     poly_add(&mut v, &k);
 
-    polyvec_reduce(&mut b);
+    polyvec_reduce::<P>(&mut b);
     poly_reduce(&mut v);
     
-    pack_ciphertext(c, &b, v);
+    pack_ciphertext::<P>(c, &b, v);
+  }
+}
+
+#[inline(always)]
+pub fn indcpa_dec<P: KyberParams>(m: &mut[u8], c: &[u8], sk: &[u8])
+{
+  indcpa_dec_expanded::<P>(m, c, &indcpa_expand_sk::<P>(sk));
+}
+
+// Secret key vector unpacked for repeated decryption, wiped on drop
+#[derive(Clone)]
+pub struct ExpandedSk {
+  skpv: Polyvec,
+}
+
+impl Drop for ExpandedSk {
+  fn drop(&mut self) {
+    for p in self.skpv.vec.iter_mut() {
+      wipe_coeffs(unsafe { &mut p.coeffs });
+    }
   }
 }
 
-pub fn indcpa_dec(m: &mut[u8], c: &[u8], sk: &[u8])
+// Name:        indcpa_expand_sk
+//
+// Description: Unpacks the secret key vector
+#[inline(always)]
+pub fn indcpa_expand_sk<P: KyberParams>(sk: &[u8]) -> ExpandedSk
 {
-  let (mut b, mut skpv) = (Polyvec::new(),Polyvec::new());
+  let mut esk = ExpandedSk { skpv: Polyvec::new() };
+  unpack_sk::<P>(&mut esk.skpv, sk);
+  esk
+}
+
+// Name:        indcpa_dec_expanded
+//
+// Description: Decryption with a secret key from indcpa_expand_sk
+#[inline(always)]
+pub fn indcpa_dec_expanded<P: KyberParams>(m: &mut[u8], c: &[u8], esk: &ExpandedSk)
+{
+  let mut b = Polyvec::new();
   let (mut v, mut mp) = (Poly::new(),Poly::new());
  
-  unpack_ciphertext(&mut b, &mut v, c);
-  unpack_sk(&mut skpv, sk);
+  unpack_ciphertext::<P>(&mut b, &mut v, c);
 
-  polyvec_ntt(&mut b);
-  polyvec_basemul_acc_montgomery(&mut mp, &skpv, &b);
+  polyvec_ntt::<P>(&mut b);
+  polyvec_basemul_acc_montgomery::<P>(&mut mp, &esk.skpv, &b);
   
   poly_invntt_tomont(&mut mp);
   poly_sub(&mut mp, &v);
   poly_reduce(&mut mp);
 
   poly_tomsg(m, mp);
-}
\ No newline at end of file
+}
+
+// Name:        indcpa_check_pk
+//
+// Description: FIPS 203 modulus check, decodes the vector of polynomials
+//              from the public key, reduces it mod q and compares its
+//              encoding with the input in constant time
+//
+// Arguments:   - const [u8] pk: input serialized public key
+//
+// Returns 0 if every coefficient is below q, 1 otherwise
+#[inline(always)]
+pub fn indcpa_check_pk<P: KyberParams>(pk: &[u8]) -> u8
+{
+  let mut pkpv = Polyvec::new();
+  let mut buf = [0u8; KYBER_MAX_K*KYBER_POLYBYTES];
+  unsafe { polyvec_frombytes::<P>(&mut pkpv, pk); }
+  polyvec_reduce::<P>(&mut pkpv);
+  polyvec_tobytes::<P>(&mut buf, &pkpv);
+  verify(&buf, pk, P::POLYVECBYTES)
+}
@@ -106,6 +106,7 @@ macro_rules! unroll24 {
 }
 
 #[allow(unused_assignments, non_upper_case_globals)]
+#[inline(always)]
 pub fn f1600_x4(a: &mut [__m256i]) {
   unsafe {
     
@@ -1,3 +1,7 @@
+// Only entered through the dispatch wrappers in indcpa.rs which enable
+// the required target features. Every function here is #[inline(always)]
+// so the intrinsics are compiled with those features rather than as
+// out of line calls when the crate is built for a generic x86_64 target.
 
 pub mod aes256ctr;
 pub mod align;
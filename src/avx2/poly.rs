@@ -1,17 +1,27 @@
 #![allow(unused_imports)]
 use core::arch::x86_64::*;
-use crate::{
+use super::{
   align::*,
   cbd::*,
   consts::*,
   fips202::*,
   fips202x4::*,
+};
+use crate::{
   params::*,
   symmetric::*,
 };
 
+// Sized for the largest ETA1 across parameter sets
 pub(crate) const NOISE_NBLOCKS: usize = 
-  (KYBER_ETA1*KYBER_N/4+SHAKE256_RATE-1)/SHAKE256_RATE;
+  (KYBER_MAX_ETA1*KYBER_N/4+SHAKE256_RATE-1)/SHAKE256_RATE;
+
+// Number of SHAKE256 blocks needed to sample a polynomial with ETA1
+#[cfg(not(feature="90s"))]
+#[inline(always)]
+fn noise_nblocks<P: KyberParams>() -> usize {
+  (P::ETA1*KYBER_N/4+SHAKE256_RATE-1)/SHAKE256_RATE
+}
 
 #[derive(Clone)]
 #[repr(C)]
@@ -23,6 +33,7 @@ pub union Poly {
 impl Copy for Poly {}
 
 impl Poly {
+  #[inline(always)]
   pub fn new() -> Self {
     Poly {
       coeffs: [0i16; KYBER_N]
@@ -41,7 +52,7 @@ impl Poly {
   // }
 }
 
-extern {
+extern "C" {
   fn ntt_avx(r: &mut [i16; KYBER_N],  q_data: &[i16; 640]);
   fn invntt_avx(r: &mut [i16; KYBER_N],  q_data: &[i16; 640]);
   fn nttunpack_avx(r: &mut [i16; KYBER_N],  q_data: &[i16; 640]);
@@ -57,8 +68,28 @@ extern {
   fn nttfrombytes_avx(r: *mut i16, a: *const u8, q_data: &[i16; 640]);
 }
 
-#[cfg(any(feature="kyber512", not(feature="kyber1024")))]
-pub unsafe fn poly_compress(r: &mut[u8], a: Poly)
+#[inline(always)]
+pub unsafe fn poly_compress<P: KyberParams>(r: &mut[u8], a: Poly)
+{
+  match P::POLYCOMPRESSEDBYTES {
+    128 => poly_compress4(r, a),
+    160 => poly_compress5(r, a),
+    _ => panic!("POLYCOMPRESSEDBYTES needs to be one of (128, 160)")
+  }
+}
+
+#[inline(always)]
+pub unsafe fn poly_decompress<P: KyberParams>(r: &mut Poly, a: &[u8])
+{
+  match P::POLYCOMPRESSEDBYTES {
+    128 => poly_decompress4(r, a),
+    160 => poly_decompress5(r, a),
+    _ => panic!("POLYCOMPRESSEDBYTES needs to be one of (128, 160)")
+  }
+}
+
+#[inline(always)]
+unsafe fn poly_compress4(r: &mut[u8], a: Poly)
 {
   let (mut f0, mut f1, mut f2, mut f3);
   let v: __m256i = _mm256_load_si256(QDATA.vec[_16XV/16..].as_ptr());
@@ -93,8 +124,8 @@ pub unsafe fn poly_compress(r: &mut[u8], a: Poly)
     _mm256_storeu_si256(r[32*i..].as_mut_ptr() as *mut __m256i,f0);
   }
 }
-#[cfg(any(feature="kyber512", not(feature="kyber1024")))]
-pub unsafe fn poly_decompress(r: &mut Poly, a: &[u8]) 
+#[inline(always)]
+unsafe fn poly_decompress4(r: &mut Poly, a: &[u8]) 
 {
   let (mut t, mut f);
   let q: __m256i = _mm256_load_si256(QDATA.vec[_16XQ/16..].as_ptr());
@@ -116,8 +147,8 @@ pub unsafe fn poly_decompress(r: &mut Poly, a: &[u8])
   }
 }
 
-#[cfg(feature="kyber1024")]
-pub unsafe fn poly_compress(r: &mut[u8], a: Poly) 
+#[inline(always)]
+unsafe fn poly_compress5(r: &mut[u8], a: Poly) 
 {
   let (mut f0, mut f1);
   let (mut t0, mut t1);
@@ -157,8 +188,8 @@ pub unsafe fn poly_compress(r: &mut[u8], a: Poly)
   }
 }
 
-#[cfg(feature="kyber1024")]
-pub unsafe fn poly_decompress(r: &mut Poly, a: &[u8])
+#[inline(always)]
+unsafe fn poly_decompress5(r: &mut Poly, a: &[u8])
 {
   let (mut t, mut f, mut ti);
 
@@ -185,10 +216,11 @@ pub unsafe fn poly_decompress(r: &mut Poly, a: &[u8])
     f = _mm256_and_si256(f,mask);
     f = _mm256_mullo_epi16(f,shift);
     f = _mm256_mulhrs_epi16(f,q);
-    _mm256_store_si256(r.vec[i..].as_mut_ptr() as *mut __m256i,f);
+    _mm256_store_si256(r.vec[i..].as_mut_ptr(),f);
   }
 }
 
+#[inline(always)]
 pub fn poly_frombytes(r: &mut Poly, a: &[u8])
 {
   unsafe { 
@@ -196,6 +228,7 @@ pub fn poly_frombytes(r: &mut Poly, a: &[u8])
   }
 }
 
+#[inline(always)]
 pub fn poly_tobytes(r: &mut[u8], a: Poly)
 {
   let mut buf = [0u8; KYBER_POLYBYTES];
@@ -203,6 +236,7 @@ pub fn poly_tobytes(r: &mut[u8], a: Poly)
   r[..KYBER_POLYBYTES].copy_from_slice(&buf[..]);
 }
 
+#[inline(always)]
 pub unsafe fn poly_frommsg(r: &mut Poly, msg: &[u8])
 {
   let shift = _mm256_broadcastsi128_si256(_mm_set_epi32(0,1,2,3));
@@ -247,6 +281,7 @@ pub unsafe fn poly_frommsg(r: &mut Poly, msg: &[u8])
   frommsg64(3, _mm256_shuffle_epi32(f, 255));
 }
 
+#[inline(always)]
 pub fn poly_tomsg(msg: &mut[u8], a: Poly)
 {
   unsafe {
@@ -273,7 +308,8 @@ pub fn poly_tomsg(msg: &mut[u8], a: Poly)
   }
 }
 
-#[cfg(all(any(feature="kyber1024", feature="kyber512"), not(feature="90s")))]
+#[cfg(not(feature="90s"))]
+#[inline(always)]
 pub fn poly_getnoise_eta2(r: &mut Poly, seed: &[u8], nonce: u8)
 {
   let mut buf = Eta2Buf::new();
@@ -283,8 +319,42 @@ pub fn poly_getnoise_eta2(r: &mut Poly, seed: &[u8], nonce: u8)
   }
 }
 
+// Name:        poly_getnoise_eta2_4x
+//
+// Description: Samples four polynomials from centered binomial distributions
+//              with parameter eta2, each from its own seed. Lets independent
+//              operations share the 4-way PRF for their last noise polynomial.
+//
+// Arguments:   - [&mut Poly; 4] r:    output polynomials
+//              - [&[u8]; 4] seed:     input seeds (KYBER_SYMBYTES bytes each)
+//              - u8 nonce:            one-byte input nonce shared by all four
 #[cfg(not(feature="90s"))]
-pub fn poly_getnoise_eta1_4x(
+#[inline(always)]
+pub fn poly_getnoise_eta2_4x(mut r: [&mut Poly; 4], seed: [&[u8]; 4], nonce: u8)
+{
+  let mut buf = [Eta4xBuf::new(); 4];
+  let mut state = Keccakx4State::new();
+  unsafe {
+    for (b, s) in buf.iter_mut().zip(seed.iter()) {
+      b.coeffs[..KYBER_SYMBYTES].copy_from_slice(&s[..KYBER_SYMBYTES]);
+      b.coeffs[KYBER_SYMBYTES] = nonce;
+    }
+    shake256x4_absorb_once(
+      &mut state,
+      &buf[0].coeffs, &buf[1].coeffs,
+      &buf[2].coeffs, &buf[3].coeffs,
+      KYBER_SYMBYTES + 1
+    );
+    shake256x4_squeezeblocks(&mut buf, 1, &mut state);
+    for (p, b) in r.iter_mut().zip(buf.iter()) {
+      poly_cbd_eta2(p, &b.vec);
+    }
+  }
+}
+
+#[cfg(not(feature="90s"))]
+#[inline(always)]
+pub fn poly_getnoise_eta1_4x<P: KyberParams>(
   r0: &mut Poly, r1: &mut Poly, r2: &mut Poly, r3: &mut Poly, seed: &[u8],
   nonce0: u8, nonce1: u8, nonce2: u8, nonce3: u8
 )
@@ -309,17 +379,18 @@ pub fn poly_getnoise_eta1_4x(
       &buf[2].coeffs, &buf[3].coeffs,
       33
     );
-    shake256x4_squeezeblocks(&mut buf, NOISE_NBLOCKS, &mut state);
+    shake256x4_squeezeblocks(&mut buf, noise_nblocks::<P>(), &mut state);
 
-    poly_cbd_eta1(r0, &buf[0]);
-    poly_cbd_eta1(r1, &buf[1]);
-    poly_cbd_eta1(r2, &buf[2]);
-    poly_cbd_eta1(r3, &buf[3]);
+    poly_cbd_eta1::<P>(r0, &buf[0]);
+    poly_cbd_eta1::<P>(r1, &buf[1]);
+    poly_cbd_eta1::<P>(r2, &buf[2]);
+    poly_cbd_eta1::<P>(r3, &buf[3]);
   }
 }
 
-#[cfg(all(feature="kyber512", not(feature="90s")))]
-pub fn poly_getnoise_eta1122_4x(
+#[cfg(not(feature="90s"))]
+#[inline(always)]
+pub fn poly_getnoise_eta1122_4x<P: KyberParams>(
   r0: &mut Poly, r1: &mut Poly, r2: &mut Poly, r3: &mut Poly, seed: &[u8],
   nonce0: u8, nonce1: u8, nonce2: u8, nonce3: u8,
 )
@@ -344,45 +415,52 @@ pub fn poly_getnoise_eta1122_4x(
       &buf[2].coeffs, &buf[3].coeffs, 
       33
     );
-    shake256x4_squeezeblocks(&mut buf, NOISE_NBLOCKS, &mut state);
+    shake256x4_squeezeblocks(&mut buf, noise_nblocks::<P>(), &mut state);
 
-    poly_cbd_eta1(r0, &buf[0]);
-    poly_cbd_eta1(r1, &buf[1]);
+    poly_cbd_eta1::<P>(r0, &buf[0]);
+    poly_cbd_eta1::<P>(r1, &buf[1]);
     poly_cbd_eta2(r2, &buf[2].vec);
     poly_cbd_eta2(r3, &buf[3].vec);
   }
 }
 
+#[inline(always)]
 pub fn poly_ntt(r: &mut Poly) 
 {
   unsafe { ntt_avx(&mut r.coeffs, &QDATA.coeffs); }
 }
 
+#[inline(always)]
 pub fn poly_invntt_tomont(r: &mut Poly)
 {
   unsafe { invntt_avx(&mut r.coeffs, &QDATA.coeffs); }
 }
 
+#[inline(always)]
 pub fn poly_nttunpack(r: &mut Poly)
 {
   unsafe { nttunpack_avx(&mut r.coeffs, &QDATA.coeffs); }
 }
 
+#[inline(always)]
 pub fn poly_basemul(r: &mut Poly, a: &Poly, b: &Poly)
 {
   unsafe { basemul_avx(&mut r.coeffs, &a.coeffs, &b.coeffs, &QDATA.coeffs); }
 }
 
+#[inline(always)]
 pub fn poly_tomont(r: &mut Poly)
 {
   unsafe { tomont_avx(&mut r.coeffs, &QDATA.coeffs); }
 }
 
+#[inline(always)]
 pub fn poly_reduce(r: &mut Poly)
 {
   unsafe { reduce_avx(&mut r.coeffs, &QDATA.coeffs); }
 }
 
+#[inline(always)]
 pub fn poly_add(r: &mut Poly, b: &Poly)
 {
   let (mut f0, mut f1);
@@ -396,6 +474,7 @@ pub fn poly_add(r: &mut Poly, b: &Poly)
   }
 }
 
+#[inline(always)]
 pub fn poly_sub(r: &mut Poly, a: &Poly)
 {
   let (mut f0, mut f1);
@@ -1,21 +1,23 @@
 use core::arch::x86_64::*;
-use crate::{
+use super::{
   poly::*,
-  params::*,
   consts::*
 };
+use crate::params::*;
 
+// Sized for the largest parameter set, only the first K polynomials are used
 #[derive(Clone)]
 pub struct Polyvec {
-  pub vec: [Poly; KYBER_K]
+  pub vec: [Poly; KYBER_MAX_K]
 }
 
 impl Copy for Polyvec {}
 
 impl Polyvec {
+  #[inline(always)]
   pub fn new() -> Self {
     Polyvec {
-      vec: [Poly::new(); KYBER_K]
+      vec: [Poly::new(); KYBER_MAX_K]
     }
   }
   // Basic polynomial value check for development
@@ -23,7 +25,7 @@ impl Polyvec {
   // pub fn checksum(&self) -> i16 {
   //   unsafe {
   //     let mut out = 0i16;
-  //     for i in 0..KYBER_K {
+  //     for i in 0..P::K {
   //       for j in 0..KYBER_N {
   //         out ^= &self.vec[i].coeffs[j];
   //       }
@@ -34,6 +36,7 @@ impl Polyvec {
 }
 
 // #[target_feature(enable = "avx")]
+#[inline(always)]
 pub unsafe fn poly_compress10(r: &mut[u8], a: &Poly)
 {
   let (mut f0, mut f1, mut f2);
@@ -78,6 +81,7 @@ pub unsafe fn poly_compress10(r: &mut[u8], a: &Poly)
 }
 
 // #[target_feature(enable = "avx")]
+#[inline(always)]
 pub unsafe fn poly_decompress10(r: &mut Poly, a: &[u8])
 {
   let mut f;
@@ -101,6 +105,7 @@ pub unsafe fn poly_decompress10(r: &mut Poly, a: &[u8])
 }
 
 // #[target_feature(enable = "avx")]
+#[inline(always)]
 pub unsafe fn poly_compress11(r: &mut[u8], a: &Poly)
 {
   let (mut f0, mut f1, mut f2);
@@ -148,6 +153,7 @@ pub unsafe fn poly_compress11(r: &mut[u8], a: &Poly)
 }
 
 // #[target_feature(enable = "avx")]
+#[inline(always)]
 pub unsafe fn poly_decompress11(r: &mut Poly, a: &[u8])
 {
   let mut f;
@@ -179,42 +185,46 @@ pub unsafe fn poly_decompress11(r: &mut Poly, a: &[u8])
 }
 
 
-pub unsafe fn polyvec_compress(r: &mut[u8], a: &Polyvec)
+#[inline(always)]
+pub unsafe fn polyvec_compress<P: KyberParams>(r: &mut[u8], a: &Polyvec)
 {
-  if cfg!(feature="kyber1024") {
-    for i in 0..KYBER_K {
+  if P::POLYVECCOMPRESSEDBYTES == P::K * 352 {
+    for i in 0..P::K {
       poly_compress11(&mut r[352*i..], &a.vec[i]);
     } 
   } else {
-    for i in 0..KYBER_K {
+    for i in 0..P::K {
       poly_compress10(&mut r[320*i..], &a.vec[i]);
     }
   }
 }
 
-pub unsafe fn polyvec_decompress(r: &mut Polyvec, a: &[u8])
+#[inline(always)]
+pub unsafe fn polyvec_decompress<P: KyberParams>(r: &mut Polyvec, a: &[u8])
 {
-  if cfg!(feature="kyber1024") {
-    for i in 0..KYBER_K {
+  if P::POLYVECCOMPRESSEDBYTES == P::K * 352 {
+    for i in 0..P::K {
       poly_decompress11(&mut r.vec[i], &a[352*i..]);
     } 
   } else {
-    for i in 0..KYBER_K {
+    for i in 0..P::K {
       poly_decompress10(&mut r.vec[i], &a[320*i..]);
     }
   }
 }
 
-pub fn polyvec_tobytes(r: &mut[u8], a: &Polyvec)
+#[inline(always)]
+pub fn polyvec_tobytes<P: KyberParams>(r: &mut[u8], a: &Polyvec)
 {
-  for i in 0..KYBER_K {
+  for i in 0..P::K {
     poly_tobytes(&mut r[i*KYBER_POLYBYTES..], a.vec[i]);
   }
 }
 
-pub unsafe fn polyvec_frombytes(r: &mut Polyvec, a: &[u8])
+#[inline(always)]
+pub unsafe fn polyvec_frombytes<P: KyberParams>(r: &mut Polyvec, a: &[u8])
 {
-  for i in 0..KYBER_K {
+  for i in 0..P::K {
     poly_frombytes(&mut r.vec[i], &a[i*KYBER_POLYBYTES..]);
   }
 }
@@ -224,9 +234,10 @@ pub unsafe fn polyvec_frombytes(r: &mut Polyvec, a: &[u8])
 // Description: Apply forward NTT to all elements of a vector of polynomials
 //
 // Arguments:   - Polyvec r: in/output vector of polynomials
-pub fn polyvec_ntt(r: &mut Polyvec)
+#[inline(always)]
+pub fn polyvec_ntt<P: KyberParams>(r: &mut Polyvec)
 {
-  for i in 0..KYBER_K {
+  for i in 0..P::K {
     poly_ntt(&mut r.vec[i]);
   }
 }
@@ -236,9 +247,10 @@ pub fn polyvec_ntt(r: &mut Polyvec)
 // Description: Apply inverse NTT to all elements of a vector of polynomials
 //
 // Arguments:   - Polyvec r: in/output vector of polynomials
-pub fn polyvec_invntt_tomont(r: &mut Polyvec)
+#[inline(always)]
+pub fn polyvec_invntt_tomont<P: KyberParams>(r: &mut Polyvec)
 {
-  for i in 0..KYBER_K {
+  for i in 0..P::K {
     poly_invntt_tomont(&mut r.vec[i]);
   }
 }
@@ -250,11 +262,12 @@ pub fn polyvec_invntt_tomont(r: &mut Polyvec)
 // Arguments: - poly *r:          output polynomial
 //            - const Polyvec a: first input vector of polynomials
 //            - const Polyvec b: second input vector of polynomials
-pub fn polyvec_basemul_acc_montgomery(r: &mut Poly, a: &Polyvec, b: &Polyvec)
+#[inline(always)]
+pub fn polyvec_basemul_acc_montgomery<P: KyberParams>(r: &mut Poly, a: &Polyvec, b: &Polyvec)
 {
   let mut t = Poly::new();
   poly_basemul(r, &a.vec[0], &b.vec[0]);
-  for i in 1..KYBER_K {
+  for i in 1..P::K {
     poly_basemul(&mut t, &a.vec[i], &b.vec[i]);
     poly_add(r, &t);
   }
@@ -267,9 +280,10 @@ pub fn polyvec_basemul_acc_montgomery(r: &mut Poly, a: &Polyvec, b: &Polyvec)
 //              for details of the Barrett reduction see comments in reduce.c
 //
 // Arguments:   - poly *r:       input/output polynomial
-pub fn polyvec_reduce(r: &mut Polyvec)
+#[inline(always)]
+pub fn polyvec_reduce<P: KyberParams>(r: &mut Polyvec)
 {
- for i in 0..KYBER_K {
+ for i in 0..P::K {
   poly_reduce(&mut r.vec[i]);
  } 
 }
@@ -282,9 +296,10 @@ pub fn polyvec_reduce(r: &mut Polyvec)
 // Arguments: - Polyvec r:       output vector of polynomials
 //            - const Polyvec a: first input vector of polynomials
 //            - const Polyvec b: second input vector of polynomials
-pub fn polyvec_add(r: &mut Polyvec, b: &Polyvec)
+#[inline(always)]
+pub fn polyvec_add<P: KyberParams>(r: &mut Polyvec, b: &Polyvec)
 {
-  for i in 0..KYBER_K {
+  for i in 0..P::K {
     poly_add(&mut r.vec[i], &b.vec[i]);
   }
 }
@@ -1,7 +1,7 @@
 use core::arch::x86_64::*;
+use super::consts::*;
 use crate::{
   params::*,
-  consts::*,
   symmetric::*
 };
 
@@ -9,14 +9,17 @@ pub(crate) const REJ_UNIFORM_AVX_NBLOCKS: usize =
   (12*KYBER_N/8*(1 << 12)/KYBER_Q + XOF_BLOCKBYTES)/XOF_BLOCKBYTES;
 const REJ_UNIFORM_AVX_BUFLEN: usize = REJ_UNIFORM_AVX_NBLOCKS*XOF_BLOCKBYTES;
 
+#[inline(always)]
 pub unsafe fn _mm256_cmpge_epu16(a: __m256i, b: __m256i) -> __m256i {
   _mm256_cmpeq_epi16(_mm256_max_epu16(a, b), a)
 }
 
+#[inline(always)]
 pub unsafe fn _mm_cmpge_epu16(a: __m128i, b: __m128i) -> __m128i {
  _mm_cmpeq_epi16(_mm_max_epu16(a, b), a)
 }
 
+#[inline(always)]
 pub unsafe fn rej_uniform_avx(r: &mut[i16], buf: &[u8]) -> usize {
   let mut ctr = 0;
   let mut pos = 0;
@@ -1,5 +1,6 @@
 use core::arch::x86_64::*;
 
+#[inline(always)]
 pub fn verify(a: &[u8], b: &[u8], mut len: usize) -> u8
 {
   let (mut f, mut g);
@@ -22,11 +23,13 @@ pub fn verify(a: &[u8], b: &[u8], mut len: usize) -> u8
   (r.wrapping_neg() >> 63) as u8
 }
 
+// The KEM uses the portable cmov, kept for parity with the C sources
+#[allow(dead_code)]
 pub fn cmov(r: &mut[u8], x: &[u8], mut len: usize, mut b: u8)
 {
   let (mut xvec, mut rvec);
   unsafe {
-    let bvec = _mm256_set1_epi64x(b as i64);
+    let bvec = _mm256_set1_epi64x(-(b as i64));
     for i in 0..(len/32) {
       rvec = _mm256_loadu_si256(r[32*i..].as_ptr() as *const __m256i);
       xvec = _mm256_loadu_si256(x[32*i..].as_ptr() as *const __m256i);
@@ -0,0 +1,110 @@
+use core::sync::atomic::{AtomicU8, Ordering};
+use crate::error::KyberError;
+
+/// Implementations of the IND-CPA primitives compiled into the crate
+///
+/// With the `avx2` feature on x86_64 both backends are built and the
+/// fastest one supported by the running CPU is chosen the first time
+/// it's needed. Builds without `std` can't query the CPU so fall back
+/// to the target features enabled at compile time.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Backend {
+  /// Portable implementation, available on every platform
+  Reference,
+  /// AVX2 optimised implementation, requires the `avx2` feature
+  /// and a CPU supporting AVX2, BMI2 and POPCNT (plus AES-NI in 90s mode)
+  Avx2,
+}
+
+// 0 until the backend has been detected or forced
+const UNSET: u8 = 0;
+const REFERENCE: u8 = 1;
+const AVX2: u8 = 2;
+
+static BACKEND: AtomicU8 = AtomicU8::new(UNSET);
+
+impl Backend {
+  /// Returns true if the backend is compiled in and supported by this CPU
+  ///
+  /// ```
+  /// # use pqc_kyber::*;
+  /// assert!(Backend::Reference.is_available());
+  /// ```
+  pub fn is_available(self) -> bool {
+    match self {
+      Backend::Reference => true,
+      Backend::Avx2 => avx2_detected(),
+    }
+  }
+
+  fn to_u8(self) -> u8 {
+    match self {
+      Backend::Reference => REFERENCE,
+      Backend::Avx2 => AVX2,
+    }
+  }
+}
+
+/// Returns the backend in use, detecting it on the first call
+///
+/// ```
+/// # use pqc_kyber::*;
+/// let active = backend();
+/// assert!(active.is_available());
+/// ```
+pub fn backend() -> Backend {
+  match BACKEND.load(Ordering::Relaxed) {
+    REFERENCE => Backend::Reference,
+    AVX2 => Backend::Avx2,
+    _ => {
+      let detected = if avx2_detected() { Backend::Avx2 } else { Backend::Reference };
+      BACKEND.store(detected.to_u8(), Ordering::Relaxed);
+      detected
+    }
+  }
+}
+
+/// Forces every following operation to use a specific backend
+///
+/// Intended for testing and benchmarking the backends against each other,
+/// all backends produce identical outputs. Returns
+/// `KyberError::UnsupportedBackend` if the backend isn't compiled in
+/// or the CPU lacks the required instructions.
+///
+/// ```
+/// # use pqc_kyber::*;
+/// # fn main() -> Result<(), KyberError> {
+/// set_backend(Backend::Reference)?;
+/// assert_eq!(backend(), Backend::Reference);
+/// # Ok(())}
+/// ```
+pub fn set_backend(backend: Backend) -> Result<(), KyberError> {
+  if !backend.is_available() {
+    return Err(KyberError::UnsupportedBackend)
+  }
+  BACKEND.store(backend.to_u8(), Ordering::Relaxed);
+  Ok(())
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "avx2", feature = "std"))]
+fn avx2_detected() -> bool {
+  std::is_x86_feature_detected!("avx2")
+    && std::is_x86_feature_detected!("bmi2")
+    && std::is_x86_feature_detected!("popcnt")
+    && (!cfg!(feature = "90s") || std::is_x86_feature_detected!("aes"))
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "avx2", not(feature = "std")))]
+fn avx2_detected() -> bool {
+  cfg!(all(
+    target_feature = "avx2",
+    target_feature = "bmi2",
+    target_feature = "popcnt",
+    any(not(feature = "90s"), target_feature = "aes")
+  ))
+}
+
+#[cfg(not(all(target_arch = "x86_64", feature = "avx2")))]
+fn avx2_detected() -> bool {
+  false
+}
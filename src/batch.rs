@@ -0,0 +1,174 @@
+// Batch driver for the KEM, runs four operations at a time through the
+// 4-way functions and spreads large batches over threads with rayon.
+// Randomness is drawn in the same order as one call per operation so a
+// batch gives the same output as the equivalent loop.
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "rayon")]
+use std::vec;
+use crate::{
+  error::KyberError,
+  kem::*,
+  params::*,
+  rng::{randombytes, NoRng},
+  types::*,
+  RngCore, CryptoRng,
+};
+
+// Batches of at least this many operations are split over threads
+#[cfg(feature = "rayon")]
+const PARALLEL_THRESHOLD: usize = 64;
+
+// Operations handed to the 4-way functions at once
+const LANES: usize = 4;
+
+pub(crate) fn keypair_batch<P, R>(pks: &mut [u8], sks: &mut [u8], rng: &mut R) -> Result<(), KyberError>
+  where P: KyberParams, R: RngCore + CryptoRng
+{
+  let n = pks.len() / P::PUBLICKEYBYTES;
+  if pks.len() != n * P::PUBLICKEYBYTES || sks.len() != n * P::SECRETKEYBYTES {
+    return Err(KyberError::InvalidInput)
+  }
+
+  #[cfg(feature = "rayon")]
+  if n >= PARALLEL_THRESHOLD {
+    let mut seeds = vec![0u8; n * KYBER_SEEDBYTES];
+    draw(&mut seeds, rng);
+    pks.par_chunks_mut(LANES * P::PUBLICKEYBYTES)
+      .zip(sks.par_chunks_mut(LANES * P::SECRETKEYBYTES))
+      .zip(seeds.par_chunks(LANES * KYBER_SEEDBYTES))
+      .for_each(|((pk, sk), seed)| keypair_chunk::<P>(pk, sk, seed));
+    wipe(&mut seeds);
+    return Ok(())
+  }
+
+  let mut seeds = [0u8; LANES * KYBER_SEEDBYTES];
+  for (pk, sk) in pks.chunks_mut(LANES * P::PUBLICKEYBYTES).zip(sks.chunks_mut(LANES * P::SECRETKEYBYTES)) {
+    let seed = &mut seeds[..pk.len() / P::PUBLICKEYBYTES * KYBER_SEEDBYTES];
+    draw(seed, rng);
+    keypair_chunk::<P>(pk, sk, seed);
+  }
+  wipe(&mut seeds);
+  Ok(())
+}
+
+pub(crate) fn encapsulate_batch<P, R>(
+  pks: &[PublicKey<P>], cts: &mut [u8], sss: &mut [u8], rng: &mut R
+) -> Result<(), KyberError>
+  where P: KyberParams, R: RngCore + CryptoRng
+{
+  let n = pks.len();
+  if cts.len() != n * P::CIPHERTEXTBYTES || sss.len() != n * KYBER_SSBYTES {
+    return Err(KyberError::InvalidInput)
+  }
+
+  #[cfg(feature = "rayon")]
+  if n >= PARALLEL_THRESHOLD {
+    let mut coins = vec![0u8; n * KYBER_SYMBYTES];
+    draw(&mut coins, rng);
+    pks.par_chunks(LANES)
+      .zip(cts.par_chunks_mut(LANES * P::CIPHERTEXTBYTES))
+      .zip(sss.par_chunks_mut(LANES * KYBER_SSBYTES))
+      .zip(coins.par_chunks(LANES * KYBER_SYMBYTES))
+      .for_each(|(((pk, ct), ss), m)| encapsulate_chunk(pk, ct, ss, m));
+    wipe(&mut coins);
+    return Ok(())
+  }
+
+  let mut coins = [0u8; LANES * KYBER_SYMBYTES];
+  for ((pk, ct), ss) in pks.chunks(LANES)
+    .zip(cts.chunks_mut(LANES * P::CIPHERTEXTBYTES))
+    .zip(sss.chunks_mut(LANES * KYBER_SSBYTES))
+  {
+    let m = &mut coins[..pk.len() * KYBER_SYMBYTES];
+    draw(m, rng);
+    encapsulate_chunk(pk, ct, ss, m);
+  }
+  wipe(&mut coins);
+  Ok(())
+}
+
+pub(crate) fn decapsulate_batch<P: KyberParams>(
+  sk: &SecretKey<P>, cts: &[Ciphertext<P>], sss: &mut [u8]
+) -> Result<(), KyberError>
+{
+  if sss.len() != cts.len() * KYBER_SSBYTES {
+    return Err(KyberError::InvalidInput)
+  }
+
+  #[cfg(feature = "rayon")]
+  if cts.len() >= PARALLEL_THRESHOLD {
+    cts.par_chunks(LANES)
+      .zip(sss.par_chunks_mut(LANES * KYBER_SSBYTES))
+      .for_each(|(ct, ss)| decapsulate_chunk(sk, ct, ss));
+    return Ok(())
+  }
+
+  for (ct, ss) in cts.chunks(LANES).zip(sss.chunks_mut(LANES * KYBER_SSBYTES)) {
+    decapsulate_chunk(sk, ct, ss);
+  }
+  Ok(())
+}
+
+// Draws one KYBER_SYMBYTES value at a time, as the single operations do
+fn draw<R: RngCore + CryptoRng>(out: &mut [u8], rng: &mut R) {
+  for x in out.chunks_mut(KYBER_SYMBYTES) {
+    randombytes(x, KYBER_SYMBYTES, rng);
+  }
+}
+
+// A full chunk goes through the 4-way functions, the remainder one at a time
+fn keypair_chunk<P: KyberParams>(pk: &mut [u8], sk: &mut [u8], seed: &[u8]) {
+  if pk.len() == LANES * P::PUBLICKEYBYTES {
+    return crypto_kem_keypair_x4::<P>(split_mut(pk), split_mut(sk), split(seed))
+  }
+  for ((pk, sk), seed) in pk.chunks_mut(P::PUBLICKEYBYTES)
+    .zip(sk.chunks_mut(P::SECRETKEYBYTES))
+    .zip(seed.chunks(KYBER_SEEDBYTES))
+  {
+    let (d, z) = seed.split_at(KYBER_SYMBYTES);
+    crypto_kem_keypair::<P, _>(pk, sk, &mut NoRng, Some((d, z)));
+  }
+}
+
+fn encapsulate_chunk<P: KyberParams>(pk: &[PublicKey<P>], ct: &mut [u8], ss: &mut [u8], m: &[u8]) {
+  if let [a, b, c, d] = pk {
+    let pk = [a.as_ref(), b.as_ref(), c.as_ref(), d.as_ref()];
+    return crypto_kem_enc_x4::<P>(split_mut(ct), split_mut(ss), pk, split(m))
+  }
+  for (((pk, ct), ss), m) in pk.iter()
+    .zip(ct.chunks_mut(P::CIPHERTEXTBYTES))
+    .zip(ss.chunks_mut(KYBER_SSBYTES))
+    .zip(m.chunks(KYBER_SYMBYTES))
+  {
+    crypto_kem_enc::<P, _>(ct, ss, pk.as_ref(), &mut NoRng, Some(m));
+  }
+}
+
+fn decapsulate_chunk<P: KyberParams>(sk: &SecretKey<P>, ct: &[Ciphertext<P>], ss: &mut [u8]) {
+  let key = sk.as_ref();
+  if let [a, b, c, d] = ct {
+    let ct = [a.as_ref(), b.as_ref(), c.as_ref(), d.as_ref()];
+    return crypto_kem_dec_x4::<P>(split_mut(ss), ct, [key; LANES])
+  }
+  for (ct, ss) in ct.iter().zip(ss.chunks_mut(KYBER_SSBYTES)) {
+    crypto_kem_dec::<P>(ss, ct.as_ref(), key);
+  }
+}
+
+// Splits a buffer holding four equal length values
+fn split(x: &[u8]) -> [&[u8]; LANES] {
+  let len = x.len() / LANES;
+  let (a, rest) = x.split_at(len);
+  let (b, rest) = rest.split_at(len);
+  let (c, d) = rest.split_at(len);
+  [a, b, c, d]
+}
+
+fn split_mut(x: &mut [u8]) -> [&mut [u8]; LANES] {
+  let len = x.len() / LANES;
+  let (a, rest) = x.split_at_mut(len);
+  let (b, rest) = rest.split_at_mut(len);
+  let (c, d) = rest.split_at_mut(len);
+  [a, b, c, d]
+}
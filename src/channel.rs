@@ -0,0 +1,263 @@
+//! Encrypted duplex channel over an established key exchange
+//!
+//! A [`SecureChannel`] derives a ChaCha20Poly1305 key and nonce for each direction
+//! from the shared secret of an [`Established`] exchange with HKDF-SHA256. Every
+//! record carries its sequence number, records have to be opened in the order they
+//! were sealed, so replayed, reordered or dropped records are rejected. Each direction
+//! moves to a fresh key after the number of records or bytes set by its [`RekeyPolicy`],
+//! both peers rekey at the same point without any extra messages.
+//!
+//! Records are built in caller provided buffers with [`SecureChannel::seal_record`] and
+//! [`SecureChannel::open_record`], which work without `std`. With `std` [`SecureChannel::send`]
+//! and [`SecureChannel::recv`] frame records over any `Read + Write` transport.
+//!
+//! ```
+//! # use pqc_kyber::*;
+//! # use pqc_kyber::channel::*;
+//! # fn main() -> Result<(), KyberError> {
+//! # let mut rng = rand::thread_rng();
+//! # let bob_keys = keypair(&mut rng);
+//! # let (alice, client_init) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
+//! # let (bob, server_send) = UakeResponder::new().server_receive(client_init, &bob_keys.secret, &mut rng)?;
+//! # let alice = alice.client_confirm(server_send)?;
+//! let mut alice = SecureChannel::new(&alice, Role::Initiator);
+//! let mut bob = SecureChannel::new(&bob, Role::Responder);
+//!
+//! let mut record = [0u8; 5 + RECORD_OVERHEAD];
+//! alice.seal_record(b"hello", &mut record)?;
+//!
+//! let mut message = [0u8; 5];
+//! let len = bob.open_record(&record, &mut message)?;
+//! assert_eq!(&message[..len], b"hello");
+//! # Ok(()) }
+//! ```
+use chacha20poly1305::{
+  aead::{generic_array::GenericArray, AeadInPlace, KeyInit},
+  ChaCha20Poly1305,
+};
+use hkdf::Hkdf;
+use sha2::Sha256;
+#[cfg(feature = "std")]
+use std::{io::{self, Read, Write}, vec::Vec, vec};
+use crate::{error::KyberError, kex::{Established, Role}, types::wipe};
+
+/// Size in bytes of the sequence number leading every record
+pub const SEQBYTES: usize = 8;
+/// Size in bytes of the authentication tag ending every record
+pub const TAGBYTES: usize = 16;
+/// Bytes a record adds to its plaintext
+pub const RECORD_OVERHEAD: usize = SEQBYTES + TAGBYTES;
+/// Largest plaintext a single record can hold
+pub const MAX_RECORD_PLAINTEXT: usize = 1 << 16;
+
+const KEYBYTES: usize = 32;
+const NONCEBYTES: usize = 12;
+const CHANNEL_SALT: &[u8] = b"pqc_kyber channel v1";
+const INITIATOR_LABEL: &[u8] = b"initiator to responder";
+const RESPONDER_LABEL: &[u8] = b"responder to initiator";
+const REKEY_LABEL: &[u8] = b"rekey";
+#[cfg(feature = "std")]
+const LENGTHBYTES: usize = 4;
+
+/// Limits after which each direction of a channel moves to a fresh key
+///
+/// Both peers need the same policy, records sealed after a rekey won't open
+/// under a different one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct RekeyPolicy {
+  /// Records sealed under one key
+  pub records: u64,
+  /// Plaintext bytes sealed under one key
+  pub bytes: u64,
+}
+
+impl Default for RekeyPolicy {
+  /// Rekeys every 2^20 records or 2^30 bytes, whichever comes first
+  fn default() -> Self {
+    RekeyPolicy { records: 1 << 20, bytes: 1 << 30 }
+  }
+}
+
+// Key, nonce and counters for one direction of the channel
+struct Direction {
+  key: [u8; KEYBYTES],
+  base_nonce: [u8; NONCEBYTES],
+  seq: u64,
+  records: u64,
+  bytes: u64,
+}
+
+impl Direction {
+  fn new(hkdf: &Hkdf<Sha256>, label: &[u8]) -> Self {
+    let mut okm = [0u8; KEYBYTES + NONCEBYTES];
+    hkdf.expand(label, &mut okm).expect("output within HKDF limits");
+    let mut dir = Direction {
+      key: [0u8; KEYBYTES],
+      base_nonce: [0u8; NONCEBYTES],
+      seq: 0,
+      records: 0,
+      bytes: 0,
+    };
+    dir.key.copy_from_slice(&okm[..KEYBYTES]);
+    dir.base_nonce.copy_from_slice(&okm[KEYBYTES..]);
+    wipe(&mut okm);
+    dir
+  }
+
+  // base_nonce XOR the big endian sequence number
+  fn nonce(&self) -> Result<[u8; NONCEBYTES], KyberError> {
+    if self.seq == u64::MAX {
+      return Err(KyberError::MessageLimit)
+    }
+    let mut nonce = self.base_nonce;
+    for (n, s) in nonce[NONCEBYTES - SEQBYTES..].iter_mut().zip(self.seq.to_be_bytes().iter()) {
+      *n ^= s;
+    }
+    Ok(nonce)
+  }
+
+  fn cipher(&self) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(GenericArray::from_slice(&self.key))
+  }
+
+  // Moves past a record, replacing the key once the policy's limits are reached
+  fn advance(&mut self, len: usize, policy: &RekeyPolicy) {
+    self.seq += 1;
+    self.records += 1;
+    self.bytes = self.bytes.saturating_add(len as u64);
+    if self.records >= policy.records || self.bytes >= policy.bytes {
+      let hkdf = Hkdf::<Sha256>::from_prk(&self.key).expect("key is a full length PRK");
+      hkdf.expand(REKEY_LABEL, &mut self.key).expect("output within HKDF limits");
+      self.records = 0;
+      self.bytes = 0;
+    }
+  }
+}
+
+impl Drop for Direction {
+  fn drop(&mut self) {
+    wipe(&mut self.key);
+    wipe(&mut self.base_nonce);
+  }
+}
+
+/// Both directions of an encrypted channel between the parties of a key exchange
+pub struct SecureChannel {
+  send: Direction,
+  recv: Direction,
+  policy: RekeyPolicy,
+}
+
+impl SecureChannel {
+  /// Derives a channel from an established key exchange with the default [`RekeyPolicy`]
+  pub fn new(session: &Established, role: Role) -> Self {
+    Self::with_policy(session, role, RekeyPolicy::default())
+  }
+
+  /// Derives a channel from an established key exchange, rekeying each direction
+  /// after the records or bytes set by `policy`
+  ///
+  /// Limits of zero are treated as one, rekeying after every record.
+  pub fn with_policy(session: &Established, role: Role, policy: RekeyPolicy) -> Self {
+    let hkdf = Hkdf::<Sha256>::new(Some(CHANNEL_SALT), session.shared_secret().as_ref());
+    let initiator = Direction::new(&hkdf, INITIATOR_LABEL);
+    let responder = Direction::new(&hkdf, RESPONDER_LABEL);
+    let (send, recv) = match role {
+      Role::Initiator => (initiator, responder),
+      Role::Responder => (responder, initiator),
+    };
+    SecureChannel { send, recv, policy }
+  }
+
+  /// Seals `plaintext` as the next record into `record`, returning the record length
+  ///
+  /// The record is `plaintext.len() + RECORD_OVERHEAD` bytes, returns `KyberError::InvalidInput`
+  /// if `record` is shorter or `plaintext` is longer than `MAX_RECORD_PLAINTEXT`.
+  pub fn seal_record(&mut self, plaintext: &[u8], record: &mut [u8]) -> Result<usize, KyberError> {
+    let len = plaintext.len() + RECORD_OVERHEAD;
+    if plaintext.len() > MAX_RECORD_PLAINTEXT || record.len() < len {
+      return Err(KyberError::InvalidInput)
+    }
+    let nonce = self.send.nonce()?;
+    let (header, body) = record[..len].split_at_mut(SEQBYTES);
+    header.copy_from_slice(&self.send.seq.to_be_bytes());
+    let (ct, tag) = body.split_at_mut(plaintext.len());
+    ct.copy_from_slice(plaintext);
+    let out = self.send.cipher()
+      .encrypt_in_place_detached(GenericArray::from_slice(&nonce), header, ct)
+      .map_err(|_| KyberError::InvalidInput)?;
+    tag.copy_from_slice(&out);
+    self.send.advance(plaintext.len(), &self.policy);
+    Ok(len)
+  }
+
+  /// Opens the next record into `plaintext`, returning the plaintext length
+  ///
+  /// Returns `KyberError::OutOfOrder` if this isn't the next record, `KyberError::Authentication`
+  /// if it was modified or sealed under another key and `KyberError::InvalidInput` if `plaintext`
+  /// is too short. A failed record leaves the channel unchanged.
+  pub fn open_record(&mut self, record: &[u8], plaintext: &mut [u8]) -> Result<usize, KyberError> {
+    if record.len() < RECORD_OVERHEAD || record.len() > MAX_RECORD_PLAINTEXT + RECORD_OVERHEAD {
+      return Err(KyberError::InvalidInput)
+    }
+    let len = record.len() - RECORD_OVERHEAD;
+    if plaintext.len() < len {
+      return Err(KyberError::InvalidInput)
+    }
+    let (header, body) = record.split_at(SEQBYTES);
+    let mut seq = [0u8; SEQBYTES];
+    seq.copy_from_slice(header);
+    if u64::from_be_bytes(seq) != self.recv.seq {
+      return Err(KyberError::OutOfOrder)
+    }
+    let nonce = self.recv.nonce()?;
+    let (ct, tag) = body.split_at(len);
+    let out = &mut plaintext[..len];
+    out.copy_from_slice(ct);
+    let opened = self.recv.cipher().decrypt_in_place_detached(
+      GenericArray::from_slice(&nonce), header, out, GenericArray::from_slice(tag)
+    );
+    if opened.is_err() {
+      wipe(out);
+      return Err(KyberError::Authentication)
+    }
+    self.recv.advance(len, &self.policy);
+    Ok(len)
+  }
+
+  /// Seals `message` as the next record and writes it to `transport`, prefixed
+  /// by its length as a 4 byte big endian integer
+  #[cfg(feature = "std")]
+  pub fn send<W: Write>(&mut self, transport: &mut W, message: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0u8; LENGTHBYTES + message.len() + RECORD_OVERHEAD];
+    let (length, record) = frame.split_at_mut(LENGTHBYTES);
+    let len = self.seal_record(message, record).map_err(invalid_data)?;
+    length.copy_from_slice(&(len as u32).to_be_bytes());
+    transport.write_all(&frame)?;
+    transport.flush()
+  }
+
+  /// Reads the next record written by the peer's [`send`](Self::send) from `transport`
+  /// and opens it
+  ///
+  /// Channel errors are returned as `io::ErrorKind::InvalidData` wrapping the [`KyberError`].
+  #[cfg(feature = "std")]
+  pub fn recv<R: Read>(&mut self, transport: &mut R) -> io::Result<Vec<u8>> {
+    let mut length = [0u8; LENGTHBYTES];
+    transport.read_exact(&mut length)?;
+    let len = u32::from_be_bytes(length) as usize;
+    if !(RECORD_OVERHEAD..=MAX_RECORD_PLAINTEXT + RECORD_OVERHEAD).contains(&len) {
+      return Err(invalid_data(KyberError::InvalidInput))
+    }
+    let mut record = vec![0u8; len];
+    transport.read_exact(&mut record)?;
+    let mut message = vec![0u8; len - RECORD_OVERHEAD];
+    self.open_record(&record, &mut message).map_err(invalid_data)?;
+    Ok(message)
+  }
+}
+
+#[cfg(feature = "std")]
+fn invalid_data(err: KyberError) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, err)
+}
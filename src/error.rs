@@ -6,7 +6,27 @@ pub enum KyberError {
   InvalidInput,
   /// The ciphertext was unable to be authenticated. 
   /// The shared secret was not decapsulated. 
+  ///
+  /// Only returned by the explicit decapsulation functions behind the
+  /// `hazmat` feature, by default decapsulation uses implicit rejection.
   Decapsulation,
+  /// The public key failed the FIPS 203 modulus check, one or more
+  /// of its coefficients is not reduced mod q.
+  InvalidPublicKey,
+  /// The secret key failed the FIPS 203 hash check, the stored hash
+  /// doesn't match the embedded public key.
+  InvalidSecretKey,
+  /// The requested backend isn't compiled in or isn't supported by the CPU.
+  UnsupportedBackend,
+  /// An AEAD ciphertext or key confirmation tag failed to authenticate, it was
+  /// modified in transit or produced under a different key.
+  Authentication,
+  /// An encryption context has used up its sequence numbers and
+  /// can't seal or open any more messages.
+  MessageLimit,
+  /// A channel record arrived with an unexpected sequence number, it
+  /// was replayed, reordered or an earlier record was lost.
+  OutOfOrder,
 }
 
 impl core::fmt::Display for KyberError {
@@ -14,6 +34,15 @@ impl core::fmt::Display for KyberError {
     match *self {
       KyberError::InvalidInput => write!(f, "Function input is of incorrect length"),
       KyberError::Decapsulation => write!(f, "Decapsulation Failure, unable to obtain shared secret from ciphertext"),
+      KyberError::InvalidPublicKey => write!(f, "Public key failed the modulus check"),
+      KyberError::InvalidSecretKey => write!(f, "Secret key failed the public key hash check"),
+      KyberError::UnsupportedBackend => write!(f, "Backend is not available on this platform"),
+      KyberError::Authentication => write!(f, "Ciphertext or confirmation tag failed to authenticate"),
+      KyberError::MessageLimit => write!(f, "Encryption context message limit reached"),
+      KyberError::OutOfOrder => write!(f, "Record sequence number out of order"),
     }
   }
 }
+
+#[cfg(feature = "std")]
+impl std::error::Error for KyberError {}
@@ -0,0 +1,546 @@
+//! Hybrid Public Key Encryption (RFC 9180) with post-quantum KEMs
+//!
+//! Implements the HPKE KEM interface for [`MlKem512`], [`MlKem768`] and [`MlKem1024`],
+//! plus `XWing` with the `xwing` feature, using the KEM ids from the HPKE post-quantum
+//! drafts. The KEM shared secret is used directly by the standard key schedule.
+//! Base mode contexts seal and open messages with AES-GCM or ChaCha20Poly1305 under
+//! HKDF-SHA256, HKDF-SHA384 or HKDF-SHA512. The `Vec` based [`seal_base`] and [`open_base`]
+//! need `std`, the in place context methods work without it.
+//!
+//! ```
+//! # use pqc_kyber::*;
+//! # use pqc_kyber::hpke::*;
+//! # fn main() -> Result<(), KyberError> {
+//! let mut rng = rand::thread_rng();
+//! let (sk, pk) = MlKem768::generate_key_pair(&mut rng);
+//!
+//! // Alice sets up a sender context with Bob's public key
+//! let (enc, mut sender) = setup_base_sender::<MlKem768, HkdfSha256, Aes128Gcm, _>(
+//!   &pk, b"app info", &mut rng
+//! )?;
+//! let mut message = *b"hello bob";
+//! let tag = sender.seal_in_place_detached(b"header", &mut message)?;
+//!
+//! // Bob opens it with the encapsulated key
+//! let mut receiver = setup_base_recipient::<MlKem768, HkdfSha256, Aes128Gcm>(
+//!   &enc, &sk, b"app info"
+//! )?;
+//! receiver.open_in_place_detached(b"header", &mut message, &tag)?;
+//! assert_eq!(&message, b"hello bob");
+//! # Ok(()) }
+//! ```
+use core::{convert::TryFrom, marker::PhantomData};
+use aes_gcm::aead::{generic_array::GenericArray, AeadInPlace, KeyInit};
+use hkdf::{Hkdf, HkdfExtract};
+use sha2::{Sha256, Sha384, Sha512};
+use crate::{
+  api::KyberKem,
+  error::KyberError,
+  kem::*,
+  params::*,
+  reference::fips202::shake256,
+  types::*,
+  CryptoRng, RngCore,
+};
+#[cfg(feature = "xwing")]
+use crate::xwing;
+
+/// Size in bytes of the authentication tag appended by every AEAD
+pub const HPKE_TAGBYTES: usize = 16;
+/// Size in bytes of the AEAD nonces
+pub const HPKE_NONCEBYTES: usize = 12;
+
+const MODE_BASE: u8 = 0x00;
+const HPKE_VERSION: &[u8] = b"HPKE-v1";
+const SUITE_IDBYTES: usize = 10;
+const MAX_NH: usize = 64;
+const MAX_NK: usize = 32;
+
+/// A KEM usable with HPKE, the interface of RFC 9180 section 4
+pub trait Kem {
+  /// The KEM identifier
+  const KEM_ID: u16;
+  /// Size in bytes of the KEM shared secret
+  const NSECRET: usize;
+  /// Size in bytes of an encapsulated key
+  const NENC: usize;
+  /// Size in bytes of a serialized public key
+  const NPK: usize;
+  /// Size in bytes of a serialized private key
+  const NSK: usize;
+
+  /// Public key type
+  type PublicKey: AsRef<[u8]>;
+  /// Private key type
+  type SecretKey;
+  /// Encapsulated key type, sent alongside the ciphertexts
+  type EncappedKey: AsRef<[u8]> + for<'a> TryFrom<&'a [u8], Error = KyberError>;
+
+  /// Deterministically derives a keypair from input keying material,
+  /// which should hold at least `NSK` bytes of entropy
+  fn derive_key_pair(ikm: &[u8]) -> (Self::SecretKey, Self::PublicKey);
+
+  /// Generates a random keypair
+  fn generate_key_pair<R>(rng: &mut R) -> (Self::SecretKey, Self::PublicKey)
+    where R: RngCore + CryptoRng
+  {
+    let mut ikm = [0u8; MAX_NH];
+    rng.fill_bytes(&mut ikm[..Self::NSK]);
+    let keys = Self::derive_key_pair(&ikm[..Self::NSK]);
+    wipe(&mut ikm);
+    keys
+  }
+
+  /// Encodes a public key
+  fn serialize_public_key(pk: &Self::PublicKey) -> &[u8] {
+    pk.as_ref()
+  }
+
+  /// Decodes and validates a public key
+  fn deserialize_public_key(bytes: &[u8]) -> Result<Self::PublicKey, KyberError>;
+
+  /// Generates a shared secret and its encapsulation to the public key
+  fn encap<R>(pk: &Self::PublicKey, rng: &mut R) -> Result<(SharedSecret, Self::EncappedKey), KyberError>
+    where R: RngCore + CryptoRng;
+
+  /// Recovers the shared secret from an encapsulated key
+  fn decap(enc: &Self::EncappedKey, sk: &Self::SecretKey) -> Result<SharedSecret, KyberError>;
+}
+
+// Implements the HPKE KEM interface for an ML-KEM parameter set,
+// derived keypairs are expanded from SHAKE256(ikm, 64) as d || z
+macro_rules! impl_mlkem {
+  ($name:ident, $id:expr) => {
+    impl Kem for $name {
+      const KEM_ID: u16 = $id;
+      const NSECRET: usize = KYBER_SSBYTES;
+      const NENC: usize = <$name as KyberParams>::CIPHERTEXTBYTES;
+      const NPK: usize = <$name as KyberParams>::PUBLICKEYBYTES;
+      const NSK: usize = KYBER_SEEDBYTES;
+
+      type PublicKey = PublicKey<$name>;
+      type SecretKey = SecretKey<$name>;
+      type EncappedKey = Ciphertext<$name>;
+
+      fn derive_key_pair(ikm: &[u8]) -> (Self::SecretKey, Self::PublicKey) {
+        let mut seed = [0u8; KYBER_SEEDBYTES];
+        shake256(&mut seed, KYBER_SEEDBYTES, ikm, ikm.len());
+        let keys = <$name as KyberKem>::keypair_from_seed(&seed);
+        wipe(&mut seed);
+        (keys.secret, keys.public)
+      }
+
+      fn deserialize_public_key(bytes: &[u8]) -> Result<Self::PublicKey, KyberError> {
+        let pk = PublicKey::<$name>::try_from(bytes)?;
+        if crypto_kem_check_pk::<$name>(pk.as_ref()) != 0 {
+          return Err(KyberError::InvalidPublicKey)
+        }
+        Ok(pk)
+      }
+
+      fn encap<R>(pk: &Self::PublicKey, rng: &mut R)
+        -> Result<(SharedSecret, Self::EncappedKey), KyberError>
+        where R: RngCore + CryptoRng
+      {
+        if crypto_kem_check_pk::<$name>(pk.as_ref()) != 0 {
+          return Err(KyberError::InvalidPublicKey)
+        }
+        let mut ct = Ciphertext::<$name>::zeroed();
+        let mut ss = SharedSecret::zeroed();
+        crypto_kem_enc::<$name, R>(ct.0.as_mut(), &mut ss.0, pk.as_ref(), rng, None);
+        Ok((ss, ct))
+      }
+
+      fn decap(enc: &Self::EncappedKey, sk: &Self::SecretKey) -> Result<SharedSecret, KyberError> {
+        let mut ss = SharedSecret::zeroed();
+        crypto_kem_dec::<$name>(&mut ss.0, enc.as_ref(), sk.as_ref());
+        Ok(ss)
+      }
+    }
+  };
+}
+
+impl_mlkem!(MlKem512, 0x0040);
+impl_mlkem!(MlKem768, 0x0041);
+impl_mlkem!(MlKem1024, 0x0042);
+
+/// The X-Wing KEM for HPKE, derived keypairs use SHAKE256(ikm, 32) as the seed
+#[cfg(feature = "xwing")]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct XWing;
+
+#[cfg(feature = "xwing")]
+impl Kem for XWing {
+  const KEM_ID: u16 = 0x647a;
+  const NSECRET: usize = KYBER_SSBYTES;
+  const NENC: usize = xwing::XWING_CIPHERTEXTBYTES;
+  const NPK: usize = xwing::XWING_PUBLICKEYBYTES;
+  const NSK: usize = xwing::XWING_SECRETKEYBYTES;
+
+  type PublicKey = xwing::PublicKey;
+  type SecretKey = xwing::SecretKey;
+  type EncappedKey = xwing::Ciphertext;
+
+  fn derive_key_pair(ikm: &[u8]) -> (Self::SecretKey, Self::PublicKey) {
+    let mut seed = [0u8; xwing::XWING_SECRETKEYBYTES];
+    shake256(&mut seed, xwing::XWING_SECRETKEYBYTES, ikm, ikm.len());
+    let keys = xwing::keypair_from_seed(&seed);
+    wipe(&mut seed);
+    (keys.secret, keys.public)
+  }
+
+  fn deserialize_public_key(bytes: &[u8]) -> Result<Self::PublicKey, KyberError> {
+    let pk = xwing::PublicKey::try_from(bytes)?;
+    if crypto_kem_check_pk::<MlKem768>(&bytes[..MlKem768::PUBLICKEYBYTES]) != 0 {
+      return Err(KyberError::InvalidPublicKey)
+    }
+    Ok(pk)
+  }
+
+  fn encap<R>(pk: &Self::PublicKey, rng: &mut R)
+    -> Result<(SharedSecret, Self::EncappedKey), KyberError>
+    where R: RngCore + CryptoRng
+  {
+    let (ct, ss) = xwing::encapsulate(pk, rng)?;
+    Ok((ss, ct))
+  }
+
+  fn decap(enc: &Self::EncappedKey, sk: &Self::SecretKey) -> Result<SharedSecret, KyberError> {
+    xwing::decapsulate(enc, sk)
+  }
+}
+
+/// A KDF usable with HPKE, providing the labeled functions of RFC 9180 section 4
+pub trait Kdf {
+  /// The KDF identifier
+  const KDF_ID: u16;
+  /// Output size in bytes of the extract step
+  const NH: usize;
+
+  /// Writes `Extract(salt, "HPKE-v1" || suite_id || label || ikm)` into the first
+  /// `NH` bytes of `prk`
+  fn labeled_extract(suite_id: &[u8], salt: &[u8], label: &[u8], ikm: &[u8], prk: &mut [u8]);
+
+  /// Fills `out` with `Expand(prk, I2OSP(L, 2) || "HPKE-v1" || suite_id || label || info, L)`,
+  /// fails if more than `255 * NH` bytes are requested
+  fn labeled_expand(suite_id: &[u8], prk: &[u8], label: &[u8], info: &[u8], out: &mut [u8])
+    -> Result<(), KyberError>;
+}
+
+// Implements the HPKE KDF interface with HKDF over a SHA2 hash
+macro_rules! impl_hkdf {
+  ($name:ident, $hash:ident, $id:expr, $nh:expr, $doc:expr) => {
+    #[doc = $doc]
+    #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+    pub struct $name;
+
+    impl Kdf for $name {
+      const KDF_ID: u16 = $id;
+      const NH: usize = $nh;
+
+      fn labeled_extract(suite_id: &[u8], salt: &[u8], label: &[u8], ikm: &[u8], prk: &mut [u8]) {
+        let mut extract = HkdfExtract::<$hash>::new(Some(salt));
+        for input in [HPKE_VERSION, suite_id, label, ikm].iter() {
+          extract.input_ikm(input);
+        }
+        let (mut out, _) = extract.finalize();
+        prk[..$nh].copy_from_slice(&out);
+        wipe(&mut out);
+      }
+
+      fn labeled_expand(suite_id: &[u8], prk: &[u8], label: &[u8], info: &[u8], out: &mut [u8])
+        -> Result<(), KyberError>
+      {
+        if out.len() > 255 * $nh {
+          return Err(KyberError::InvalidInput)
+        }
+        let len = (out.len() as u16).to_be_bytes();
+        let hkdf = Hkdf::<$hash>::from_prk(prk).map_err(|_| KyberError::InvalidInput)?;
+        hkdf.expand_multi_info(&[&len, HPKE_VERSION, suite_id, label, info], out)
+          .map_err(|_| KyberError::InvalidInput)
+      }
+    }
+  };
+}
+
+impl_hkdf!(HkdfSha256, Sha256, 0x0001, 32, "HKDF-SHA256");
+impl_hkdf!(HkdfSha384, Sha384, 0x0002, 48, "HKDF-SHA384");
+impl_hkdf!(HkdfSha512, Sha512, 0x0003, 64, "HKDF-SHA512");
+
+/// An AEAD usable with HPKE, encrypting in place with a detached tag
+pub trait Aead {
+  /// The AEAD identifier
+  const AEAD_ID: u16;
+  /// Size in bytes of the key
+  const NK: usize;
+
+  /// Encrypts `buffer` in place and returns the authentication tag
+  fn seal_in_place_detached(key: &[u8], nonce: &[u8], aad: &[u8], buffer: &mut [u8])
+    -> Result<[u8; HPKE_TAGBYTES], KyberError>;
+
+  /// Decrypts `buffer` in place after checking the tag, leaving it
+  /// unchanged on failure
+  fn open_in_place_detached(key: &[u8], nonce: &[u8], aad: &[u8], buffer: &mut [u8], tag: &[u8])
+    -> Result<(), KyberError>;
+}
+
+// Implements the HPKE AEAD interface for a RustCrypto AEAD
+macro_rules! impl_aead {
+  ($name:ident, $cipher:path, $id:expr, $nk:expr, $doc:expr) => {
+    #[doc = $doc]
+    #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+    pub struct $name;
+
+    impl Aead for $name {
+      const AEAD_ID: u16 = $id;
+      const NK: usize = $nk;
+
+      fn seal_in_place_detached(key: &[u8], nonce: &[u8], aad: &[u8], buffer: &mut [u8])
+        -> Result<[u8; HPKE_TAGBYTES], KyberError>
+      {
+        if nonce.len() != HPKE_NONCEBYTES {
+          return Err(KyberError::InvalidInput)
+        }
+        let cipher = <$cipher>::new_from_slice(key).map_err(|_| KyberError::InvalidInput)?;
+        let tag = cipher.encrypt_in_place_detached(GenericArray::from_slice(nonce), aad, buffer)
+          .map_err(|_| KyberError::InvalidInput)?;
+        let mut out = [0u8; HPKE_TAGBYTES];
+        out.copy_from_slice(&tag);
+        Ok(out)
+      }
+
+      fn open_in_place_detached(key: &[u8], nonce: &[u8], aad: &[u8], buffer: &mut [u8], tag: &[u8])
+        -> Result<(), KyberError>
+      {
+        if nonce.len() != HPKE_NONCEBYTES || tag.len() != HPKE_TAGBYTES {
+          return Err(KyberError::InvalidInput)
+        }
+        let cipher = <$cipher>::new_from_slice(key).map_err(|_| KyberError::InvalidInput)?;
+        cipher.decrypt_in_place_detached(
+          GenericArray::from_slice(nonce), aad, buffer, GenericArray::from_slice(tag)
+        ).map_err(|_| KyberError::Authentication)
+      }
+    }
+  };
+}
+
+impl_aead!(Aes128Gcm, aes_gcm::Aes128Gcm, 0x0001, 16, "AES-128-GCM");
+impl_aead!(Aes256Gcm, aes_gcm::Aes256Gcm, 0x0002, 32, "AES-256-GCM");
+impl_aead!(ChaCha20Poly1305, chacha20poly1305::ChaCha20Poly1305, 0x0003, 32, "ChaCha20Poly1305");
+
+// Key, nonce and exporter secret shared by both context roles
+struct Context<F: Kdf, A: Aead> {
+  suite_id: [u8; SUITE_IDBYTES],
+  key: [u8; MAX_NK],
+  base_nonce: [u8; HPKE_NONCEBYTES],
+  exporter_secret: [u8; MAX_NH],
+  seq: u64,
+  suite: PhantomData<(F, A)>,
+}
+
+impl<F: Kdf, A: Aead> Context<F, A> {
+  // base_nonce XOR I2OSP(seq, Nn)
+  fn nonce(&self) -> Result<[u8; HPKE_NONCEBYTES], KyberError> {
+    if self.seq == u64::MAX {
+      return Err(KyberError::MessageLimit)
+    }
+    let mut nonce = self.base_nonce;
+    for (n, s) in nonce[HPKE_NONCEBYTES - 8..].iter_mut().zip(self.seq.to_be_bytes().iter()) {
+      *n ^= s;
+    }
+    Ok(nonce)
+  }
+
+  fn export(&self, exporter_context: &[u8], out: &mut [u8]) -> Result<(), KyberError> {
+    F::labeled_expand(
+      &self.suite_id, &self.exporter_secret[..F::NH], b"sec", exporter_context, out
+    )
+  }
+}
+
+impl<F: Kdf, A: Aead> Drop for Context<F, A> {
+  fn drop(&mut self) {
+    wipe(&mut self.key);
+    wipe(&mut self.base_nonce);
+    wipe(&mut self.exporter_secret);
+  }
+}
+
+/// Sender side of an HPKE context, seals messages in order
+pub struct SenderContext<F: Kdf, A: Aead>(Context<F, A>);
+
+/// Recipient side of an HPKE context, opens messages in the order they were sealed
+pub struct RecipientContext<F: Kdf, A: Aead>(Context<F, A>);
+
+impl<F: Kdf, A: Aead> SenderContext<F, A> {
+  /// Encrypts `buffer` in place with the next sequence number and returns the tag
+  pub fn seal_in_place_detached(&mut self, aad: &[u8], buffer: &mut [u8])
+    -> Result<[u8; HPKE_TAGBYTES], KyberError>
+  {
+    let nonce = self.0.nonce()?;
+    let tag = A::seal_in_place_detached(&self.0.key[..A::NK], &nonce, aad, buffer)?;
+    self.0.seq += 1;
+    Ok(tag)
+  }
+
+  /// Encrypts a message with the next sequence number, returning the
+  /// ciphertext followed by the tag
+  #[cfg(feature = "std")]
+  pub fn seal(&mut self, aad: &[u8], pt: &[u8]) -> Result<Vec<u8>, KyberError> {
+    let mut out = pt.to_vec();
+    let tag = self.seal_in_place_detached(aad, &mut out)?;
+    out.extend_from_slice(&tag);
+    Ok(out)
+  }
+
+  /// Derives a secret of `out.len()` bytes bound to the context
+  pub fn export(&self, exporter_context: &[u8], out: &mut [u8]) -> Result<(), KyberError> {
+    self.0.export(exporter_context, out)
+  }
+}
+
+impl<F: Kdf, A: Aead> RecipientContext<F, A> {
+  /// Decrypts `buffer` in place with the next sequence number, the sequence
+  /// number only advances when the tag is valid
+  pub fn open_in_place_detached(&mut self, aad: &[u8], buffer: &mut [u8], tag: &[u8])
+    -> Result<(), KyberError>
+  {
+    let nonce = self.0.nonce()?;
+    A::open_in_place_detached(&self.0.key[..A::NK], &nonce, aad, buffer, tag)?;
+    self.0.seq += 1;
+    Ok(())
+  }
+
+  /// Decrypts a ciphertext followed by its tag with the next sequence number
+  #[cfg(feature = "std")]
+  pub fn open(&mut self, aad: &[u8], ct: &[u8]) -> Result<Vec<u8>, KyberError> {
+    if ct.len() < HPKE_TAGBYTES {
+      return Err(KyberError::InvalidInput)
+    }
+    let (ct, tag) = ct.split_at(ct.len() - HPKE_TAGBYTES);
+    let mut out = ct.to_vec();
+    self.open_in_place_detached(aad, &mut out, tag)?;
+    Ok(out)
+  }
+
+  /// Derives a secret of `out.len()` bytes bound to the context
+  pub fn export(&self, exporter_context: &[u8], out: &mut [u8]) -> Result<(), KyberError> {
+    self.0.export(exporter_context, out)
+  }
+}
+
+// "HPKE" || I2OSP(kem_id, 2) || I2OSP(kdf_id, 2) || I2OSP(aead_id, 2)
+fn suite_id<K: Kem, F: Kdf, A: Aead>() -> [u8; SUITE_IDBYTES] {
+  let mut id = [0u8; SUITE_IDBYTES];
+  id[..4].copy_from_slice(b"HPKE");
+  id[4..6].copy_from_slice(&K::KEM_ID.to_be_bytes());
+  id[6..8].copy_from_slice(&F::KDF_ID.to_be_bytes());
+  id[8..].copy_from_slice(&A::AEAD_ID.to_be_bytes());
+  id
+}
+
+// RFC 9180 KeySchedule in Base mode, with an empty psk and psk_id
+fn key_schedule<K: Kem, F: Kdf, A: Aead>(shared_secret: &SharedSecret, info: &[u8])
+  -> Result<Context<F, A>, KyberError>
+{
+  let suite_id = suite_id::<K, F, A>();
+  let mut ks_context = [0u8; 1 + 2 * MAX_NH];
+  ks_context[0] = MODE_BASE;
+  F::labeled_extract(&suite_id, b"", b"psk_id_hash", b"", &mut ks_context[1..]);
+  F::labeled_extract(&suite_id, b"", b"info_hash", info, &mut ks_context[1 + F::NH..]);
+  let ks_context = &ks_context[..1 + 2 * F::NH];
+
+  let mut secret = [0u8; MAX_NH];
+  F::labeled_extract(&suite_id, shared_secret.as_ref(), b"secret", b"", &mut secret);
+  let mut ctx = Context {
+    suite_id,
+    key: [0u8; MAX_NK],
+    base_nonce: [0u8; HPKE_NONCEBYTES],
+    exporter_secret: [0u8; MAX_NH],
+    seq: 0,
+    suite: PhantomData,
+  };
+  let secret_ref = &secret[..F::NH];
+  let res = F::labeled_expand(&suite_id, secret_ref, b"key", ks_context, &mut ctx.key[..A::NK])
+    .and_then(|_| F::labeled_expand(&suite_id, secret_ref, b"base_nonce", ks_context, &mut ctx.base_nonce))
+    .and_then(|_| F::labeled_expand(
+      &suite_id, secret_ref, b"exp", ks_context, &mut ctx.exporter_secret[..F::NH]
+    ));
+  wipe(&mut secret);
+  res.map(|_| ctx)
+}
+
+/// Sets up a Base mode sender context, returning the encapsulated key to send
+/// to the recipient with the context
+///
+/// ```
+/// # use pqc_kyber::*;
+/// # use pqc_kyber::hpke::*;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let (sk, pk) = MlKem1024::generate_key_pair(&mut rng);
+/// let (enc, sender) = setup_base_sender::<MlKem1024, HkdfSha512, Aes256Gcm, _>(
+///   &pk, b"", &mut rng
+/// )?;
+/// let receiver = setup_base_recipient::<MlKem1024, HkdfSha512, Aes256Gcm>(&enc, &sk, b"")?;
+///
+/// let (mut a, mut b) = ([0u8; 32], [0u8; 32]);
+/// sender.export(b"exporter context", &mut a)?;
+/// receiver.export(b"exporter context", &mut b)?;
+/// assert_eq!(a, b);
+/// # Ok(()) }
+/// ```
+pub fn setup_base_sender<K, F, A, R>(pk: &K::PublicKey, info: &[u8], rng: &mut R)
+  -> Result<(K::EncappedKey, SenderContext<F, A>), KyberError>
+  where K: Kem, F: Kdf, A: Aead, R: RngCore + CryptoRng
+{
+  let (shared_secret, enc) = K::encap(pk, rng)?;
+  let ctx = key_schedule::<K, F, A>(&shared_secret, info)?;
+  Ok((enc, SenderContext(ctx)))
+}
+
+/// Sets up a Base mode recipient context from the encapsulated key
+pub fn setup_base_recipient<K, F, A>(enc: &K::EncappedKey, sk: &K::SecretKey, info: &[u8])
+  -> Result<RecipientContext<F, A>, KyberError>
+  where K: Kem, F: Kdf, A: Aead
+{
+  let shared_secret = K::decap(enc, sk)?;
+  let ctx = key_schedule::<K, F, A>(&shared_secret, info)?;
+  Ok(RecipientContext(ctx))
+}
+
+/// Single-shot Base mode encryption of one message, returns the encapsulated
+/// key and the ciphertext
+///
+/// ```
+/// # use pqc_kyber::*;
+/// # use pqc_kyber::hpke::*;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let (sk, pk) = MlKem768::generate_key_pair(&mut rng);
+/// let (enc, ct) = seal_base::<MlKem768, HkdfSha256, ChaCha20Poly1305, _>(
+///   &pk, b"info", b"aad", b"secret message", &mut rng
+/// )?;
+/// let pt = open_base::<MlKem768, HkdfSha256, ChaCha20Poly1305>(&enc, &sk, b"info", b"aad", &ct)?;
+/// assert_eq!(pt, b"secret message");
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "std")]
+pub fn seal_base<K, F, A, R>(pk: &K::PublicKey, info: &[u8], aad: &[u8], pt: &[u8], rng: &mut R)
+  -> Result<(K::EncappedKey, Vec<u8>), KyberError>
+  where K: Kem, F: Kdf, A: Aead, R: RngCore + CryptoRng
+{
+  let (enc, mut ctx) = setup_base_sender::<K, F, A, R>(pk, info, rng)?;
+  let ct = ctx.seal(aad, pt)?;
+  Ok((enc, ct))
+}
+
+/// Single-shot Base mode decryption of one message
+#[cfg(feature = "std")]
+pub fn open_base<K, F, A>(enc: &K::EncappedKey, sk: &K::SecretKey, info: &[u8], aad: &[u8], ct: &[u8])
+  -> Result<Vec<u8>, KyberError>
+  where K: Kem, F: Kdf, A: Aead
+{
+  let mut ctx = setup_base_recipient::<K, F, A>(enc, sk, info)?;
+  ctx.open(aad, ct)
+}
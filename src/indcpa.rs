@@ -0,0 +1,275 @@
+// Runtime dispatch of the IND-CPA primitives to the selected backend
+use crate::{
+  backend::*,
+  params::KyberParams,
+  reference,
+  RngCore,
+  CryptoRng,
+};
+#[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+use crate::avx2;
+
+// Name:        indcpa_keypair
+//
+// Description: Generates public and private key for the CPA-secure
+//              public-key encryption scheme underlying Kyber
+//
+// Arguments: - [u8] pk: output public key (length INDCPA_PUBLICKEYBYTES)
+//            - [u8] sk: output private key (length INDCPA_SECRETKEYBYTES)
+pub fn indcpa_keypair<P, R>(
+  pk: &mut[u8],
+  sk: &mut[u8],
+  seed: Option<(&[u8], &[u8])>,
+  rng: &mut R
+)
+  where P: KyberParams, R: CryptoRng + RngCore
+{
+  match backend() {
+    #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+    Backend::Avx2 => unsafe { avx2_keypair::<P, R>(pk, sk, seed, rng) },
+    _ => reference::indcpa::indcpa_keypair::<P, R>(pk, sk, seed, rng)
+  }
+}
+
+// Name:        indcpa_enc
+//
+// Description: Encryption function of the CPA-secure
+//              public-key encryption scheme underlying Kyber.
+//
+// Arguments: - [u8] c:          output ciphertext (length INDCPA_BYTES)
+//            - const [u8] m:    input message (length INDCPA_MSGBYTES)
+//            - const [u8] pk:   input public key (length INDCPA_PUBLICKEYBYTES)
+//            - const [u8] coins: input random coins used as seed (length KYBER_SYMBYTES)
+//                               to deterministically generate all randomness
+pub fn indcpa_enc<P: KyberParams>(c: &mut[u8], m: &[u8], pk: &[u8], coins: &[u8])
+{
+  match backend() {
+    #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+    Backend::Avx2 => unsafe { avx2_enc::<P>(c, m, pk, coins) },
+    _ => reference::indcpa::indcpa_enc::<P>(c, m, pk, coins)
+  }
+}
+
+// A public key expanded by the backend that was active at the time, it
+// stays with that backend as the polynomial layouts differ between them
+#[derive(Clone)]
+pub(crate) enum ExpandedPk {
+  Reference(reference::indcpa::ExpandedPk),
+  #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+  Avx2(avx2::indcpa::ExpandedPk),
+}
+
+// Name:        indcpa_expand_pk
+//
+// Description: Unpacks the public key and expands its matrix once
+//              for use with indcpa_enc_expanded
+//
+// Arguments: - const [u8] pk:   input public key (length INDCPA_PUBLICKEYBYTES)
+pub(crate) fn indcpa_expand_pk<P: KyberParams>(pk: &[u8]) -> ExpandedPk
+{
+  match backend() {
+    #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+    Backend::Avx2 => ExpandedPk::Avx2(unsafe { avx2_expand_pk::<P>(pk) }),
+    _ => ExpandedPk::Reference(reference::indcpa::indcpa_expand_pk::<P>(pk))
+  }
+}
+
+// Name:        indcpa_enc_expanded
+//
+// Description: Encryption with an expanded public key, see indcpa_enc
+//
+// Arguments: - [u8] c:               output ciphertext (length INDCPA_BYTES)
+//            - const [u8] m:         input message (length INDCPA_MSGBYTES)
+//            - const ExpandedPk epk: input expanded public key
+//            - const [u8] coins:     input random coins (length KYBER_SYMBYTES)
+pub(crate) fn indcpa_enc_expanded<P: KyberParams>(c: &mut[u8], m: &[u8], epk: &ExpandedPk, coins: &[u8])
+{
+  match epk {
+    #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+    ExpandedPk::Avx2(epk) => unsafe { avx2_enc_expanded::<P>(c, m, epk, coins) },
+    ExpandedPk::Reference(epk) => reference::indcpa::indcpa_enc_expanded::<P>(c, m, epk, coins)
+  }
+}
+
+// A secret key unpacked by the backend that was active at the time,
+// the coefficients are wiped on drop
+#[derive(Clone)]
+pub(crate) enum ExpandedSk {
+  Reference(reference::indcpa::ExpandedSk),
+  #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+  Avx2(avx2::indcpa::ExpandedSk),
+}
+
+// Name:        indcpa_expand_sk
+//
+// Description: Unpacks the secret key once for use with indcpa_dec_expanded
+//
+// Arguments:   - const [u8] sk: input secret key (of length INDCPA_SECRETKEYBYTES)
+pub(crate) fn indcpa_expand_sk<P: KyberParams>(sk: &[u8]) -> ExpandedSk
+{
+  match backend() {
+    #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+    Backend::Avx2 => ExpandedSk::Avx2(unsafe { avx2_expand_sk::<P>(sk) }),
+    _ => ExpandedSk::Reference(reference::indcpa::indcpa_expand_sk::<P>(sk))
+  }
+}
+
+// Name:        indcpa_dec_expanded
+//
+// Description: Decryption with an expanded secret key, see indcpa_dec
+//
+// Arguments:   - [u8] m:               output decrypted message (of length INDCPA_MSGBYTES)
+//              - const [u8] c:         input ciphertext (of length INDCPA_BYTES)
+//              - const ExpandedSk esk: input expanded secret key
+pub(crate) fn indcpa_dec_expanded<P: KyberParams>(m: &mut[u8], c: &[u8], esk: &ExpandedSk)
+{
+  match esk {
+    #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+    ExpandedSk::Avx2(esk) => unsafe { avx2_dec_expanded::<P>(m, c, esk) },
+    ExpandedSk::Reference(esk) => reference::indcpa::indcpa_dec_expanded::<P>(m, c, esk)
+  }
+}
+
+// Name:        indcpa_enc_x4
+//
+// Description: Four independent encryptions, batching their noise
+//              sampling on the avx2 backend
+//
+// Arguments: - [&mut [u8]; 4] c:      output ciphertexts (length INDCPA_BYTES)
+//            - [&[u8]; 4] m:          input messages (length INDCPA_MSGBYTES)
+//            - [&[u8]; 4] pk:         input public keys (length INDCPA_PUBLICKEYBYTES)
+//            - [&[u8]; 4] coins:      input random coins (length KYBER_SYMBYTES)
+pub fn indcpa_enc_x4<P: KyberParams>(
+  mut c: [&mut[u8]; 4], m: [&[u8]; 4], pk: [&[u8]; 4], coins: [&[u8]; 4]
+)
+{
+  #[cfg(all(target_arch = "x86_64", feature = "avx2", not(feature = "90s")))]
+  if backend() == Backend::Avx2 {
+    return unsafe { avx2_enc_x4::<P>(c, m, pk, coins) }
+  }
+  for (i, c) in c.iter_mut().enumerate() {
+    indcpa_enc::<P>(c, m[i], pk[i], coins[i]);
+  }
+}
+
+// Name:        indcpa_dec
+//
+// Description: Decryption function of the CPA-secure
+//              public-key encryption scheme underlying Kyber.
+//
+// Arguments:   - [u8] m:        output decrypted message (of length INDCPA_MSGBYTES)
+//              - const [u8] c:  input ciphertext (of length INDCPA_BYTES)
+//              - const [u8] sk: input secret key (of length INDCPA_SECRETKEYBYTES)
+pub fn indcpa_dec<P: KyberParams>(m: &mut[u8], c: &[u8], sk: &[u8])
+{
+  match backend() {
+    #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+    Backend::Avx2 => unsafe { avx2_dec::<P>(m, c, sk) },
+    _ => reference::indcpa::indcpa_dec::<P>(m, c, sk)
+  }
+}
+
+// Name:        indcpa_check_pk
+//
+// Description: FIPS 203 modulus check, decodes and re-encodes the
+//              polynomial vector of the public key
+//
+// Arguments:   - const [u8] pk: input public key (length INDCPA_PUBLICKEYBYTES)
+//
+// Returns 0 if the public key is valid, 1 otherwise
+pub fn indcpa_check_pk<P: KyberParams>(pk: &[u8]) -> u8
+{
+  match backend() {
+    #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+    Backend::Avx2 => unsafe { avx2_check_pk::<P>(pk) },
+    _ => reference::indcpa::indcpa_check_pk::<P>(pk)
+  }
+}
+
+// The wrappers below let the compiler use the instructions the avx2
+// backend relies on without requiring them for the whole crate.
+// Safety: only called once `backend()` has confirmed CPU support.
+
+#[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+#[target_feature(enable = "avx2,bmi2,popcnt")]
+#[cfg_attr(feature = "90s", target_feature(enable = "aes"))]
+unsafe fn avx2_keypair<P, R>(
+  pk: &mut[u8],
+  sk: &mut[u8],
+  seed: Option<(&[u8], &[u8])>,
+  rng: &mut R
+)
+  where P: KyberParams, R: CryptoRng + RngCore
+{
+  avx2::indcpa::indcpa_keypair::<P, R>(pk, sk, seed, rng)
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+#[target_feature(enable = "avx2,bmi2,popcnt")]
+#[cfg_attr(feature = "90s", target_feature(enable = "aes"))]
+unsafe fn avx2_enc<P: KyberParams>(c: &mut[u8], m: &[u8], pk: &[u8], coins: &[u8])
+{
+  avx2::indcpa::indcpa_enc::<P>(c, m, pk, coins)
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+#[target_feature(enable = "avx2,bmi2,popcnt")]
+#[cfg_attr(feature = "90s", target_feature(enable = "aes"))]
+unsafe fn avx2_expand_pk<P: KyberParams>(pk: &[u8]) -> avx2::indcpa::ExpandedPk
+{
+  avx2::indcpa::indcpa_expand_pk::<P>(pk)
+}
+
+// Only reachable with a key expanded by the avx2 backend, which
+// implies the CPU supports it
+#[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+#[target_feature(enable = "avx2,bmi2,popcnt")]
+#[cfg_attr(feature = "90s", target_feature(enable = "aes"))]
+unsafe fn avx2_enc_expanded<P: KyberParams>(
+  c: &mut[u8], m: &[u8], epk: &avx2::indcpa::ExpandedPk, coins: &[u8]
+)
+{
+  avx2::indcpa::indcpa_enc_expanded::<P>(c, m, epk, coins)
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+#[target_feature(enable = "avx2,bmi2,popcnt")]
+#[cfg_attr(feature = "90s", target_feature(enable = "aes"))]
+unsafe fn avx2_expand_sk<P: KyberParams>(sk: &[u8]) -> avx2::indcpa::ExpandedSk
+{
+  avx2::indcpa::indcpa_expand_sk::<P>(sk)
+}
+
+// Only reachable with a key expanded by the avx2 backend
+#[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+#[target_feature(enable = "avx2,bmi2,popcnt")]
+#[cfg_attr(feature = "90s", target_feature(enable = "aes"))]
+unsafe fn avx2_dec_expanded<P: KyberParams>(m: &mut[u8], c: &[u8], esk: &avx2::indcpa::ExpandedSk)
+{
+  avx2::indcpa::indcpa_dec_expanded::<P>(m, c, esk)
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "avx2", not(feature = "90s")))]
+#[target_feature(enable = "avx2,bmi2,popcnt")]
+unsafe fn avx2_enc_x4<P: KyberParams>(
+  c: [&mut[u8]; 4], m: [&[u8]; 4], pk: [&[u8]; 4], coins: [&[u8]; 4]
+)
+{
+  avx2::indcpa::indcpa_enc_x4::<P>(c, m, pk, coins)
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+#[target_feature(enable = "avx2,bmi2,popcnt")]
+#[cfg_attr(feature = "90s", target_feature(enable = "aes"))]
+unsafe fn avx2_dec<P: KyberParams>(m: &mut[u8], c: &[u8], sk: &[u8])
+{
+  avx2::indcpa::indcpa_dec::<P>(m, c, sk)
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+#[target_feature(enable = "avx2,bmi2,popcnt")]
+#[cfg_attr(feature = "90s", target_feature(enable = "aes"))]
+unsafe fn avx2_check_pk<P: KyberParams>(pk: &[u8]) -> u8
+{
+  avx2::indcpa::indcpa_check_pk::<P>(pk)
+}
@@ -1,125 +1,407 @@
-use crate::rng::randombytes;
-use rand_core::{RngCore, CryptoRng};
-use crate::{
-  params::*,
-  indcpa::*,
-  symmetric::*,
-  error::KyberError,
-  verify::*
-};
-
-// Name:        crypto_kem_keypair
-//
-// Description: Generates public and private key
-//              for CCA-secure Kyber key encapsulation mechanism
-//
-// Arguments:   - [u8] pk: output public key (an already allocated array of CRYPTO_PUBLICKEYBYTES bytes)
-//              - [u8] sk: output private key (an already allocated array of CRYPTO_SECRETKEYBYTES bytes)
-pub fn crypto_kem_keypair<R>(
-  pk: &mut[u8], sk: &mut[u8], _rng: &mut R, _seed: Option<(&[u8], &[u8])> 
-)
-  where R: RngCore + CryptoRng
-{ 
-  const PK_START: usize = KYBER_SECRETKEYBYTES - (2 * KYBER_SYMBYTES);
-  const SK_START: usize = KYBER_SECRETKEYBYTES-KYBER_SYMBYTES;
-  const END: usize = KYBER_INDCPA_PUBLICKEYBYTES + KYBER_INDCPA_SECRETKEYBYTES;
-  
-  indcpa_keypair(pk, sk, _seed, _rng);
-
-  sk[KYBER_INDCPA_SECRETKEYBYTES..END]
-    .copy_from_slice(&pk[..KYBER_INDCPA_PUBLICKEYBYTES]);
-  hash_h(&mut sk[PK_START..], pk, KYBER_PUBLICKEYBYTES);
-  
-  if let Some(s) = _seed {
-    sk[SK_START..].copy_from_slice(&s.1)
-  } else {
-    randombytes(&mut sk[SK_START..],KYBER_SYMBYTES, _rng);
-  }
-}
-
-// Name:        crypto_kem_enc
-//
-// Description: Generates cipher text and shared
-//              secret for given public key
-//
-// Arguments:   - [u8] ct:       output cipher text (an already allocated array of CRYPTO_CIPHERTEXTBYTES bytes)
-//              - [u8] ss:       output shared secret (an already allocated array of CRYPTO_BYTES bytes)
-//              - const [u8] pk: input public key (an already allocated array of CRYPTO_PUBLICKEYBYTES bytes)
-pub fn crypto_kem_enc<R>(
-  ct: &mut[u8], ss: &mut[u8], pk: &[u8], _rng: &mut R,_seed: Option<&[u8]>
-)
-  where R: RngCore + CryptoRng
-{
-  let mut kr = [0u8; 2*KYBER_SYMBYTES];
-  let mut buf = [0u8; 2*KYBER_SYMBYTES];
-  let mut randbuf = [0u8; 2*KYBER_SYMBYTES];
-
-  // Deterministic randbuf for KAT's
-  if let Some(s) = _seed {
-    randbuf[..KYBER_SYMBYTES].copy_from_slice(&s);
-  } else {
-    randombytes(&mut randbuf, KYBER_SYMBYTES, _rng);
-  }
-
-  // Don't release system RNG output 
-  hash_h(&mut buf, &randbuf, KYBER_SYMBYTES);
-
-  // Multitarget countermeasure for coins + contributory KEM
-  hash_h(&mut buf[KYBER_SYMBYTES..], pk, KYBER_PUBLICKEYBYTES);
-  hash_g(&mut kr, &buf, 2*KYBER_SYMBYTES);
-
-  // coins are in kr[KYBER_SYMBYTES..]
-  indcpa_enc(ct, &buf, pk, &kr[KYBER_SYMBYTES..]);
-
-  // overwrite coins in kr with H(c) 
-  hash_h(&mut kr[KYBER_SYMBYTES..], ct, KYBER_CIPHERTEXTBYTES);
-
-  // hash concatenation of pre-k and H(c) to k
-  kdf(ss, &kr, 2*KYBER_SYMBYTES);
-}
-
-// Name:        crypto_kem_dec
-//
-// Description: Generates shared secret for given
-//              cipher text and private key
-//
-// Arguments:   - [u8] ss:       output shared secret (an already allocated array of CRYPTO_BYTES bytes)
-//              - const [u8] ct: input cipher text (an already allocated array of CRYPTO_CIPHERTEXTBYTES bytes)
-//              - const [u8] sk: input private key (an already allocated array of CRYPTO_SECRETKEYBYTES bytes)
-//
-// On failure, ss will contain a pseudo-random value.
-pub fn crypto_kem_dec(
-  ss: &mut[u8], ct: &[u8], sk: &[u8]
-) 
--> Result<(), KyberError> 
-{
-  let mut buf = [0u8; 2*KYBER_SYMBYTES];
-  let mut kr = [0u8; 2*KYBER_SYMBYTES];
-  let mut cmp = [0u8; KYBER_CIPHERTEXTBYTES];
-  let mut pk = [0u8; KYBER_INDCPA_PUBLICKEYBYTES + 2*KYBER_SYMBYTES];
-
-  pk.copy_from_slice(&sk[KYBER_INDCPA_SECRETKEYBYTES..]);
-  
-  indcpa_dec(&mut buf, ct, sk);
-
-  // Multitarget countermeasure for coins + contributory KEM
-  const START: usize = KYBER_SECRETKEYBYTES-2*KYBER_SYMBYTES;
-  const END: usize = KYBER_SECRETKEYBYTES-KYBER_SYMBYTES; 
-  buf[KYBER_SYMBYTES..].copy_from_slice(&sk[START..END]);
-  hash_g(&mut kr, &buf, 2*KYBER_SYMBYTES);
-  
-  // coins are in kr[KYBER_SYMBYTES..] 
-  indcpa_enc(&mut cmp, &buf, &pk, &kr[KYBER_SYMBYTES..]);
-  let fail = verify(ct, &cmp, KYBER_CIPHERTEXTBYTES);
-  // overwrite coins in kr with H(c)
-  hash_h(&mut kr[KYBER_SYMBYTES..], ct, KYBER_CIPHERTEXTBYTES);
-  // Overwrite pre-k with z on re-encryption failure 
-  cmov(&mut kr, &sk[END..], KYBER_SYMBYTES, fail);
-  // hash concatenation of pre-k and H(c) to k 
-  kdf(ss, &kr, 2*KYBER_SYMBYTES);
-
-  match fail {
-    0 => Ok(()),
-    _ => Err(KyberError::Decapsulation)
-  }
-}
+use core::ops::Range;
+use crate::rng::{randombytes, NoRng};
+use rand_core::{RngCore, CryptoRng};
+use crate::{
+  params::*,
+  indcpa::*,
+  symmetric::*,
+  reference::verify::*
+};
+#[cfg(any(feature = "hazmat", kyber_kat, fuzzing))]
+use crate::error::KyberError;
+
+// Name:        crypto_kem_keypair
+//
+// Description: Generates public and private key
+//              for CCA-secure Kyber key encapsulation mechanism
+//
+// Arguments:   - [u8] pk: output public key (an already allocated array of PUBLICKEYBYTES bytes)
+//              - [u8] sk: output private key (an already allocated array of SECRETKEYBYTES bytes)
+pub fn crypto_kem_keypair<P, R>(
+  pk: &mut[u8], sk: &mut[u8], _rng: &mut R, _seed: Option<(&[u8], &[u8])> 
+)
+  where P: KyberParams, R: RngCore + CryptoRng
+{ 
+  let pk_start = P::SECRETKEYBYTES - (2 * KYBER_SYMBYTES);
+  let sk_start = P::SECRETKEYBYTES-KYBER_SYMBYTES;
+  let end = P::INDCPA_PUBLICKEYBYTES + P::INDCPA_SECRETKEYBYTES;
+  
+  indcpa_keypair::<P, R>(pk, sk, _seed, _rng);
+
+  sk[P::INDCPA_SECRETKEYBYTES..end]
+    .copy_from_slice(&pk[..P::INDCPA_PUBLICKEYBYTES]);
+  hash_h(&mut sk[pk_start..], pk, P::PUBLICKEYBYTES);
+  
+  if let Some(s) = _seed {
+    sk[sk_start..P::SECRETKEYBYTES].copy_from_slice(s.1)
+  } else {
+    randombytes(&mut sk[sk_start..],KYBER_SYMBYTES, _rng);
+  }
+}
+
+// Name:        crypto_kem_enc
+//
+// Description: Generates cipher text and shared
+//              secret for given public key
+//
+// Arguments:   - [u8] ct:       output cipher text (an already allocated array of CIPHERTEXTBYTES bytes)
+//              - [u8] ss:       output shared secret (an already allocated array of KYBER_SSBYTES bytes)
+//              - const [u8] pk: input public key (an already allocated array of PUBLICKEYBYTES bytes)
+pub fn crypto_kem_enc<P, R>(
+  ct: &mut[u8], ss: &mut[u8], pk: &[u8], _rng: &mut R,_seed: Option<&[u8]>
+)
+  where P: KyberParams, R: RngCore + CryptoRng
+{
+  let mut hpk = [0u8; KYBER_SYMBYTES];
+  hash_h(&mut hpk, pk, P::PUBLICKEYBYTES);
+  kem_enc::<P, R, _>(ct, ss, &hpk, _rng, _seed, |ct, m, coins| indcpa_enc::<P>(ct, m, pk, coins));
+}
+
+// Name:        crypto_kem_enc_expanded
+//
+// Description: Encapsulation to an already expanded public key
+//
+// Arguments:   - [u8] ct:              output cipher text (CIPHERTEXTBYTES bytes)
+//              - [u8] ss:              output shared secret (KYBER_SSBYTES bytes)
+//              - const ExpandedPk epk: input expanded public key
+//              - const [u8] hpk:       input hash of the public key (KYBER_SYMBYTES bytes)
+pub(crate) fn crypto_kem_enc_expanded<P, R>(
+  ct: &mut[u8], ss: &mut[u8], epk: &ExpandedPk, hpk: &[u8], _rng: &mut R, _seed: Option<&[u8]>
+)
+  where P: KyberParams, R: RngCore + CryptoRng
+{
+  kem_enc::<P, R, _>(ct, ss, hpk, _rng, _seed, |ct, m, coins| indcpa_enc_expanded::<P>(ct, m, epk, coins));
+}
+
+// Encapsulation given H(pk), enc is the IND-CPA encryption of m with the coins
+fn kem_enc<P, R, F>(
+  ct: &mut[u8], ss: &mut[u8], hpk: &[u8], _rng: &mut R, _seed: Option<&[u8]>, enc: F
+)
+  where P: KyberParams, R: RngCore + CryptoRng, F: FnOnce(&mut[u8], &[u8], &[u8])
+{
+  let mut kr = [0u8; 2*KYBER_SYMBYTES];
+  let mut buf = [0u8; 2*KYBER_SYMBYTES];
+  let mut randbuf = [0u8; 2*KYBER_SYMBYTES];
+
+  // Deterministic randbuf for KAT's
+  if let Some(s) = _seed {
+    randbuf[..KYBER_SYMBYTES].copy_from_slice(s);
+  } else {
+    randombytes(&mut randbuf, KYBER_SYMBYTES, _rng);
+  }
+
+  if P::ML_KEM {
+    buf[..KYBER_SYMBYTES].copy_from_slice(&randbuf[..KYBER_SYMBYTES]);
+  } else {
+    // Don't release system RNG output 
+    hash_h(&mut buf, &randbuf, KYBER_SYMBYTES);
+  }
+
+  // Multitarget countermeasure for coins + contributory KEM
+  buf[KYBER_SYMBYTES..].copy_from_slice(&hpk[..KYBER_SYMBYTES]);
+  hash_g(&mut kr, &buf, 2*KYBER_SYMBYTES);
+
+  // coins are in kr[KYBER_SYMBYTES..]
+  enc(ct, &buf, &kr[KYBER_SYMBYTES..]);
+
+  if P::ML_KEM {
+    // ML-KEM uses the first half of G(m||H(pk)) directly
+    ss[..KYBER_SSBYTES].copy_from_slice(&kr[..KYBER_SSBYTES]);
+    return
+  }
+
+  // overwrite coins in kr with H(c) 
+  hash_h(&mut kr[KYBER_SYMBYTES..], ct, P::CIPHERTEXTBYTES);
+
+  // hash concatenation of pre-k and H(c) to k
+  kdf(ss, &kr, 2*KYBER_SYMBYTES);
+}
+
+// Name:        crypto_kem_dec
+//
+// Description: Generates shared secret for given
+//              cipher text and private key
+//
+// Arguments:   - [u8] ss:       output shared secret (an already allocated array of KYBER_SSBYTES bytes)
+//              - const [u8] ct: input cipher text (an already allocated array of CIPHERTEXTBYTES bytes)
+//              - const [u8] sk: input private key (an already allocated array of SECRETKEYBYTES bytes)
+//
+// Never fails, on an invalid ciphertext ss will contain a pseudo-random value
+// derived from the implicit rejection key.
+pub fn crypto_kem_dec<P: KyberParams>(ss: &mut[u8], ct: &[u8], sk: &[u8])
+{
+  kem_dec::<P>(ss, ct, sk);
+}
+
+// Name:        crypto_kem_dec_explicit
+//
+// Description: Decapsulation that also reports whether re-encryption failed.
+//              This exposes a decryption oracle and is only available with 
+//              the hazmat feature and in test builds.
+//
+// Arguments:   - [u8] ss:       output shared secret (an already allocated array of KYBER_SSBYTES bytes)
+//              - const [u8] ct: input cipher text (an already allocated array of CIPHERTEXTBYTES bytes)
+//              - const [u8] sk: input private key (an already allocated array of SECRETKEYBYTES bytes)
+//
+// On failure, ss will contain the same pseudo-random value as crypto_kem_dec.
+#[cfg(any(feature = "hazmat", kyber_kat, fuzzing))]
+pub fn crypto_kem_dec_explicit<P: KyberParams>(
+  ss: &mut[u8], ct: &[u8], sk: &[u8]
+) 
+-> Result<(), KyberError> 
+{
+  match kem_dec::<P>(ss, ct, sk) {
+    0 => Ok(()),
+    _ => Err(KyberError::Decapsulation)
+  }
+}
+
+// Name:        crypto_kem_dec_expanded
+//
+// Description: Decapsulation with an expanded secret key and the expanded
+//              public key embedded in it, returns 1 if re-encryption failed
+//
+// Arguments:   - [u8] ss:              output shared secret (KYBER_SSBYTES bytes)
+//              - const [u8] ct:        input cipher text (CIPHERTEXTBYTES bytes)
+//              - const [u8] sk:        input private key, for H(pk) and z (SECRETKEYBYTES bytes)
+//              - const ExpandedSk esk: input expanded private key
+//              - const ExpandedPk epk: input expanded public key
+pub(crate) fn crypto_kem_dec_expanded<P: KyberParams>(
+  ss: &mut[u8], ct: &[u8], sk: &[u8], esk: &ExpandedSk, epk: &ExpandedPk
+) -> u8
+{
+  kem_dec_with::<P, _, _>(
+    ss, ct, sk,
+    |m, c| indcpa_dec_expanded::<P>(m, c, esk),
+    |c, m, coins| indcpa_enc_expanded::<P>(c, m, epk, coins)
+  )
+}
+
+// Decapsulation with implicit rejection, returns 1 if re-encryption failed
+fn kem_dec<P: KyberParams>(ss: &mut[u8], ct: &[u8], sk: &[u8]) -> u8
+{
+  let pk = &sk[P::INDCPA_SECRETKEYBYTES..];
+  kem_dec_with::<P, _, _>(
+    ss, ct, sk,
+    |m, c| indcpa_dec::<P>(m, c, sk),
+    |c, m, coins| indcpa_enc::<P>(c, m, pk, coins)
+  )
+}
+
+// Decapsulation given the IND-CPA decryption and re-encryption
+fn kem_dec_with<P, D, E>(ss: &mut[u8], ct: &[u8], sk: &[u8], dec: D, enc: E) -> u8
+  where P: KyberParams, D: FnOnce(&mut[u8], &[u8]), E: FnOnce(&mut[u8], &[u8], &[u8])
+{
+  let mut buf = [0u8; 2*KYBER_SYMBYTES];
+  let mut kr = [0u8; 2*KYBER_SYMBYTES];
+  let mut cmp = [0u8; KYBER_MAX_CIPHERTEXTBYTES];
+  
+  dec(&mut buf, ct);
+
+  // Multitarget countermeasure for coins + contributory KEM
+  let start = P::SECRETKEYBYTES-2*KYBER_SYMBYTES;
+  let end = P::SECRETKEYBYTES-KYBER_SYMBYTES; 
+  buf[KYBER_SYMBYTES..].copy_from_slice(&sk[start..end]);
+  hash_g(&mut kr, &buf, 2*KYBER_SYMBYTES);
+  
+  // coins are in kr[KYBER_SYMBYTES..] 
+  enc(&mut cmp, &buf, &kr[KYBER_SYMBYTES..]);
+  let fail = verify(ct, &cmp, P::CIPHERTEXTBYTES);
+  if P::ML_KEM {
+    // Compute the rejection key J(z||c)
+    rkprf(ss, &sk[end..], &ct[..P::CIPHERTEXTBYTES]);
+    // Overwrite it with the true key on re-encryption success
+    cmov(ss, &kr, KYBER_SSBYTES, 1 - fail);
+  } else {
+    // overwrite coins in kr with H(c)
+    hash_h(&mut kr[KYBER_SYMBYTES..], ct, P::CIPHERTEXTBYTES);
+    // Overwrite pre-k with z on re-encryption failure 
+    cmov(&mut kr, &sk[end..], KYBER_SYMBYTES, fail);
+    // hash concatenation of pre-k and H(c) to k 
+    kdf(ss, &kr, 2*KYBER_SYMBYTES);
+  }
+  fail
+}
+
+// Name:        crypto_kem_keypair_x4
+//
+// Description: Four keypairs from their d||z seeds, the public key hashes
+//              share the 4-way Keccak
+//
+// Arguments:   - [&mut [u8]; 4] pk: output public keys (PUBLICKEYBYTES bytes each)
+//              - [&mut [u8]; 4] sk: output private keys (SECRETKEYBYTES bytes each)
+//              - [&[u8]; 4] seed:   input d||z seeds (KYBER_SEEDBYTES bytes each)
+pub fn crypto_kem_keypair_x4<P: KyberParams>(
+  pk: [&mut[u8]; 4], mut sk: [&mut[u8]; 4], seed: [&[u8]; 4]
+)
+{
+  let pk_start = P::SECRETKEYBYTES - (2 * KYBER_SYMBYTES);
+  let sk_start = P::SECRETKEYBYTES-KYBER_SYMBYTES;
+  let end = P::INDCPA_PUBLICKEYBYTES + P::INDCPA_SECRETKEYBYTES;
+
+  for i in 0..4 {
+    let (d, z) = seed[i].split_at(KYBER_SYMBYTES);
+    indcpa_keypair::<P, _>(pk[i], sk[i], Some((d, z)), &mut NoRng);
+    sk[i][P::INDCPA_SECRETKEYBYTES..end]
+      .copy_from_slice(&pk[i][..P::INDCPA_PUBLICKEYBYTES]);
+    sk[i][sk_start..P::SECRETKEYBYTES].copy_from_slice(&z[..KYBER_SYMBYTES]);
+  }
+  hash_h_x4(
+    lanes_mut(&mut sk, pk_start..sk_start), lanes(&pk, 0..P::PUBLICKEYBYTES), P::PUBLICKEYBYTES
+  );
+}
+
+// Name:        crypto_kem_enc_x4
+//
+// Description: Four encapsulations from their messages, hashing and
+//              sampling noise with the 4-way Keccak
+//
+// Arguments:   - [&mut [u8]; 4] ct: output cipher texts (CIPHERTEXTBYTES bytes each)
+//              - [&mut [u8]; 4] ss: output shared secrets (KYBER_SSBYTES bytes each)
+//              - [&[u8]; 4] pk:     input public keys (PUBLICKEYBYTES bytes each)
+//              - [&[u8]; 4] m:      input messages (KYBER_SYMBYTES bytes each)
+pub fn crypto_kem_enc_x4<P: KyberParams>(
+  mut ct: [&mut[u8]; 4], mut ss: [&mut[u8]; 4], pk: [&[u8]; 4], m: [&[u8]; 4]
+)
+{
+  let mut kr = [[0u8; 2*KYBER_SYMBYTES]; 4];
+  let mut buf = [[0u8; 2*KYBER_SYMBYTES]; 4];
+
+  if P::ML_KEM {
+    for (b, m) in buf.iter_mut().zip(m.iter()) {
+      b[..KYBER_SYMBYTES].copy_from_slice(&m[..KYBER_SYMBYTES]);
+    }
+  } else {
+    // Don't release system RNG output
+    hash_h_x4(lanes_mut(&mut buf, 0..KYBER_SYMBYTES), m, KYBER_SYMBYTES);
+  }
+
+  // Multitarget countermeasure for coins + contributory KEM
+  hash_h_x4(lanes_mut(&mut buf, KYBER_SYMBYTES..2*KYBER_SYMBYTES), pk, P::PUBLICKEYBYTES);
+  hash_g_x4(lanes_mut(&mut kr, 0..2*KYBER_SYMBYTES), lanes(&buf, 0..2*KYBER_SYMBYTES), 2*KYBER_SYMBYTES);
+
+  // coins are in kr[KYBER_SYMBYTES..]
+  indcpa_enc_x4::<P>(
+    lanes_mut(&mut ct, 0..P::CIPHERTEXTBYTES),
+    lanes(&buf, 0..KYBER_SYMBYTES),
+    pk,
+    lanes(&kr, KYBER_SYMBYTES..2*KYBER_SYMBYTES)
+  );
+
+  if P::ML_KEM {
+    for (s, k) in ss.iter_mut().zip(kr.iter()) {
+      s[..KYBER_SSBYTES].copy_from_slice(&k[..KYBER_SSBYTES]);
+    }
+    return
+  }
+
+  // overwrite coins in kr with H(c)
+  hash_h_x4(
+    lanes_mut(&mut kr, KYBER_SYMBYTES..2*KYBER_SYMBYTES),
+    lanes(&ct, 0..P::CIPHERTEXTBYTES),
+    P::CIPHERTEXTBYTES
+  );
+
+  // hash concatenation of pre-k and H(c) to k
+  kdf_x4(lanes_mut(&mut ss, 0..KYBER_SSBYTES), lanes(&kr, 0..2*KYBER_SYMBYTES), 2*KYBER_SYMBYTES);
+}
+
+// Name:        crypto_kem_dec_x4
+//
+// Description: Four decapsulations with implicit rejection, the
+//              re-encryptions and hashes share the 4-way Keccak
+//
+// Arguments:   - [&mut [u8]; 4] ss: output shared secrets (KYBER_SSBYTES bytes each)
+//              - [&[u8]; 4] ct:     input cipher texts (CIPHERTEXTBYTES bytes each)
+//              - [&[u8]; 4] sk:     input private keys (SECRETKEYBYTES bytes each)
+pub fn crypto_kem_dec_x4<P: KyberParams>(
+  mut ss: [&mut[u8]; 4], ct: [&[u8]; 4], sk: [&[u8]; 4]
+)
+{
+  let mut buf = [[0u8; 2*KYBER_SYMBYTES]; 4];
+  let mut kr = [[0u8; 2*KYBER_SYMBYTES]; 4];
+  let mut cmp = [[0u8; KYBER_MAX_CIPHERTEXTBYTES]; 4];
+  let mut fail = [0u8; 4];
+  let start = P::SECRETKEYBYTES-2*KYBER_SYMBYTES;
+  let end = P::SECRETKEYBYTES-KYBER_SYMBYTES;
+
+  for i in 0..4 {
+    indcpa_dec::<P>(&mut buf[i], ct[i], sk[i]);
+    // Multitarget countermeasure for coins + contributory KEM
+    buf[i][KYBER_SYMBYTES..].copy_from_slice(&sk[i][start..end]);
+  }
+  hash_g_x4(lanes_mut(&mut kr, 0..2*KYBER_SYMBYTES), lanes(&buf, 0..2*KYBER_SYMBYTES), 2*KYBER_SYMBYTES);
+
+  // coins are in kr[KYBER_SYMBYTES..]
+  indcpa_enc_x4::<P>(
+    lanes_mut(&mut cmp, 0..P::CIPHERTEXTBYTES),
+    lanes(&buf, 0..KYBER_SYMBYTES),
+    lanes(&sk, P::INDCPA_SECRETKEYBYTES..start),
+    lanes(&kr, KYBER_SYMBYTES..2*KYBER_SYMBYTES)
+  );
+  for i in 0..4 {
+    fail[i] = verify(ct[i], &cmp[i], P::CIPHERTEXTBYTES);
+  }
+
+  if P::ML_KEM {
+    // Compute the rejection keys J(z||c)
+    rkprf_x4(
+      lanes_mut(&mut ss, 0..KYBER_SSBYTES), lanes(&sk, end..P::SECRETKEYBYTES), ct, P::CIPHERTEXTBYTES
+    );
+    // Overwrite them with the true keys on re-encryption success
+    for i in 0..4 {
+      cmov(ss[i], &kr[i], KYBER_SSBYTES, 1 - fail[i]);
+    }
+  } else {
+    // overwrite coins in kr with H(c)
+    hash_h_x4(lanes_mut(&mut kr, KYBER_SYMBYTES..2*KYBER_SYMBYTES), ct, P::CIPHERTEXTBYTES);
+    // Overwrite pre-k with z on re-encryption failure
+    for i in 0..4 {
+      cmov(&mut kr[i], &sk[i][end..], KYBER_SYMBYTES, fail[i]);
+    }
+    // hash concatenation of pre-k and H(c) to k
+    kdf_x4(lanes_mut(&mut ss, 0..KYBER_SSBYTES), lanes(&kr, 0..2*KYBER_SYMBYTES), 2*KYBER_SYMBYTES);
+  }
+}
+
+// The same range of four buffers
+fn lanes<T: AsRef<[u8]>>(x: &[T; 4], r: Range<usize>) -> [&[u8]; 4]
+{
+  let [a, b, c, d] = x;
+  [&a.as_ref()[r.clone()], &b.as_ref()[r.clone()], &c.as_ref()[r.clone()], &d.as_ref()[r]]
+}
+
+fn lanes_mut<T: AsMut<[u8]>>(x: &mut [T; 4], r: Range<usize>) -> [&mut [u8]; 4]
+{
+  let [a, b, c, d] = x;
+  [&mut a.as_mut()[r.clone()], &mut b.as_mut()[r.clone()], &mut c.as_mut()[r.clone()], &mut d.as_mut()[r]]
+}
+
+// Name:        crypto_kem_check_pk
+//
+// Description: FIPS 203 encapsulation key check
+//
+// Arguments:   - const [u8] pk: input public key (an already allocated array of PUBLICKEYBYTES bytes)
+//
+// Returns 0 if the key passes the modulus check, 1 otherwise
+pub fn crypto_kem_check_pk<P: KyberParams>(pk: &[u8]) -> u8
+{
+  indcpa_check_pk::<P>(pk)
+}
+
+// Name:        crypto_kem_check_sk
+//
+// Description: FIPS 203 decapsulation key check, the stored hash of the
+//              public key must match the embedded public key
+//
+// Arguments:   - const [u8] sk: input private key (an already allocated array of SECRETKEYBYTES bytes)
+//
+// Returns 0 if the hashes match, 1 otherwise
+pub fn crypto_kem_check_sk<P: KyberParams>(sk: &[u8]) -> u8
+{
+  let mut h = [0u8; KYBER_SYMBYTES];
+  let start = P::SECRETKEYBYTES-2*KYBER_SYMBYTES;
+  let end = P::SECRETKEYBYTES-KYBER_SYMBYTES;
+  hash_h(&mut h, &sk[P::INDCPA_SECRETKEYBYTES..], P::PUBLICKEYBYTES);
+  verify(&h, &sk[start..end], KYBER_SYMBYTES)
+}
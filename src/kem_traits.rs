@@ -0,0 +1,64 @@
+// The RustCrypto `kem` traits, so keys of every level can stand in for any
+// other KEM in code written against them. Errors are KyberError and the
+// shared secret is the crate's SharedSecret, wiped on drop.
+use ::kem::{Decapsulate, Encapsulate};
+use rand_core::CryptoRngCore;
+use crate::{
+  api::KyberKem,
+  error::KyberError,
+  params::*,
+  precomputed::*,
+  types::*,
+};
+
+impl<P: KyberParams> Encapsulate<Ciphertext<P>, SharedSecret> for PublicKey<P> {
+  type Error = KyberError;
+
+  fn encapsulate(&self, rng: &mut impl CryptoRngCore) -> Encapsulated<P> {
+    P::encapsulate(self, rng)
+  }
+}
+
+impl<P: KyberParams> Encapsulate<Ciphertext<P>, SharedSecret> for EncapsulationKey<P> {
+  type Error = KyberError;
+
+  fn encapsulate(&self, rng: &mut impl CryptoRngCore) -> Encapsulated<P> {
+    EncapsulationKey::encapsulate(self, rng)
+  }
+}
+
+impl<P: KyberParams> Decapsulate<Ciphertext<P>, SharedSecret> for SecretKey<P> {
+  type Error = KyberError;
+
+  fn decapsulate(&self, ct: &Ciphertext<P>) -> Decapsulated {
+    P::decapsulate(ct, self)
+  }
+}
+
+impl<P: KyberParams> Decapsulate<Ciphertext<P>, SharedSecret> for DecapsulationKey<P> {
+  type Error = KyberError;
+
+  fn decapsulate(&self, ct: &Ciphertext<P>) -> Decapsulated {
+    DecapsulationKey::decapsulate(self, ct)
+  }
+}
+
+impl<P: KyberParams> DecapsulationKey<P> {
+  /// Generates a keypair prepared for repeated use, returning the key to
+  /// keep and the key to hand out, as the RustCrypto KEM crates do
+  ///
+  /// ```
+  /// # use pqc_kyber::*;
+  /// use kem::{Decapsulate, Encapsulate};
+  /// # fn main() -> Result<(), KyberError> {
+  /// let mut rng = rand::thread_rng();
+  /// let (dk, ek) = DecapsulationKey::<Kyber768>::generate(&mut rng);
+  /// let (ct, ss) = ek.encapsulate(&mut rng)?;
+  /// assert_eq!(dk.decapsulate(&ct)?, ss);
+  /// # Ok(()) }
+  /// ```
+  pub fn generate(rng: &mut impl CryptoRngCore) -> (Self, EncapsulationKey<P>) {
+    let keys = P::keypair(rng);
+    (DecapsulationKey::new(&keys.secret), EncapsulationKey::new(&keys.public))
+  }
+}
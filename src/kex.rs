@@ -1,10 +1,13 @@
+use core::{convert::TryFrom, fmt};
 use rand_core::{RngCore, CryptoRng};
 #[cfg(feature = "zeroize")]
 use zeroize::{Zeroize, ZeroizeOnDrop};
 use crate::{
   kem::*,
-  symmetric::kdf,
+  symmetric::{hash_h, kdf},
   params::*,
+  types::*,
+  reference::verify::verify,
   KyberError
 };
 
@@ -16,284 +19,646 @@ pub const UAKE_RESPONSE_BYTES: usize = KYBER_CIPHERTEXTBYTES;
 pub const AKE_INIT_BYTES: usize = KYBER_PUBLICKEYBYTES + KYBER_CIPHERTEXTBYTES;
 /// Mutual Key Exchange Response Byte Length
 pub const AKE_RESPONSE_BYTES: usize = 2 * KYBER_CIPHERTEXTBYTES;
+/// Key Confirmation Tag Byte Length
+pub const CONFIRMATION_TAG_BYTES: usize = 32;
 
-/// Result of encapsulating a public key which includes the ciphertext and shared secret
-pub type Encapsulated =  Result<([u8; KYBER_CIPHERTEXTBYTES], [u8; KYBER_SSBYTES]), KyberError>;
-/// The result of  decapsulating a ciphertext which produces a shared secret when confirmed
-pub type Decapsulated = Result<[u8; KYBER_SSBYTES], KyberError>;
-/// Kyber public key
-pub type PublicKey = [u8; KYBER_PUBLICKEYBYTES];
-/// Kyber secret key
-pub type SecretKey = [u8; KYBER_SECRETKEYBYTES];
-/// Kyber Shared Secret
-pub type SharedSecret = [u8; KYBER_SSBYTES]; 
 /// Bytes to send when initiating a unilateral key exchange
-pub type UakeSendInit = [u8; UAKE_INIT_BYTES]; 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UakeSendInit([u8; UAKE_INIT_BYTES]);
 /// Bytes to send when responding to a unilateral key exchange
-pub type UakeSendResponse = [u8; UAKE_RESPONSE_BYTES]; 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UakeSendResponse([u8; UAKE_RESPONSE_BYTES]);
 /// Bytes to send when initiating a mutual key exchange
-pub type AkeSendInit = [u8; AKE_INIT_BYTES]; 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct AkeSendInit([u8; AKE_INIT_BYTES]);
 /// Bytes to send when responding to a mutual key exchange
-pub type AkeSendResponse = [u8; AKE_RESPONSE_BYTES]; 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct AkeSendResponse([u8; AKE_RESPONSE_BYTES]);
+/// Tag proving knowledge of the shared secret, sent alongside the response
+/// by the responder and as a third flight by the initiator
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ConfirmationTag([u8; CONFIRMATION_TAG_BYTES]);
 
-// Ephemeral keys
-type TempKey = [u8; KYBER_SSBYTES];
-type Eska = [u8; KYBER_SECRETKEYBYTES];
+// Implements TryFrom<&[u8]> and AsRef<[u8]> for a handshake message
+macro_rules! impl_message {
+  ($name:ident, $len:ident) => {
+    impl $name {
+      fn zeroed() -> Self {
+        $name([0u8; $len])
+      }
+    }
+
+    impl TryFrom<&[u8]> for $name {
+      type Error = KyberError;
+
+      fn try_from(bytes: &[u8]) -> Result<Self, KyberError> {
+        if bytes.len() != $len {
+          return Err(KyberError::InvalidInput)
+        }
+        let mut out = Self::zeroed();
+        out.0.copy_from_slice(bytes);
+        Ok(out)
+      }
+    }
+
+    impl AsRef<[u8]> for $name {
+      fn as_ref(&self) -> &[u8] {
+        &self.0
+      }
+    }
+
+    #[cfg(feature = "zeroize")]
+    impl Zeroize for $name {
+      fn zeroize(&mut self) {
+        self.0.zeroize();
+      }
+    }
+  };
+}
+
+impl_message!(UakeSendInit, UAKE_INIT_BYTES);
+impl_message!(UakeSendResponse, UAKE_RESPONSE_BYTES);
+impl_message!(AkeSendInit, AKE_INIT_BYTES);
+impl_message!(AkeSendResponse, AKE_RESPONSE_BYTES);
+impl_message!(ConfirmationTag, CONFIRMATION_TAG_BYTES);
+
+// Prefixes the input of the v1 key schedule
+const KEX_V1_LABEL: &[u8; 16] = b"pqc_kyber kex v1";
+const MODE_UAKE: u8 = 0x01;
+const MODE_AKE: u8 = 0x02;
+
+// Prefixes the input of the key confirmation derivations
+const KEX_CONFIRM_LABEL: &[u8; 16] = b"pqc_kyber kex cf";
+const CONFIRM_SESSION: u8 = 0x00;
+const CONFIRM_MAC_KEY: u8 = 0x01;
+const CONFIRM_SERVER: u8 = 0x02;
+const CONFIRM_CLIENT: u8 = 0x03;
+
+/// Key derivation of the shared secret in a key exchange
+///
+/// Both parties must use the same schedule, a mismatch results in
+/// differing shared secrets.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum KeySchedule {
+  /// The derivation of earlier releases, a KDF over the KEM secrets alone. It isn't
+  /// bound to the handshake messages, the parties' identities or a context, only use
+  /// it to interoperate with peers that haven't upgraded.
+  Legacy,
+  /// Binds the hashes of both handshake messages, both static public keys and
+  /// the context label into the shared secret.
+  #[default]
+  V1,
+}
+
+// Hashes of everything bound into a v1 shared secret besides the response,
+// the initiator's key stays zeroed in a unilateral exchange
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Binding {
+  schedule: KeySchedule,
+  context: [u8; KYBER_SYMBYTES],
+  pk_a: [u8; KYBER_SYMBYTES],
+  pk_b: [u8; KYBER_SYMBYTES],
+  send_a: [u8; KYBER_SYMBYTES],
+}
+
+impl Binding {
+  fn new(schedule: KeySchedule, context: &[u8]) -> Self {
+    let mut binding = Binding {
+      schedule,
+      context: [0u8; KYBER_SYMBYTES],
+      pk_a: [0u8; KYBER_SYMBYTES],
+      pk_b: [0u8; KYBER_SYMBYTES],
+      send_a: [0u8; KYBER_SYMBYTES],
+    };
+    hash_h(&mut binding.context, context, context.len());
+    binding
+  }
+}
+
+/// The side of a key exchange a party was on, orients the keys derived
+/// from its shared secret
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Role {
+  /// The party that sent the first handshake message
+  Initiator,
+  /// The party that answered it
+  Responder,
+}
+
+/// A completed key exchange holding the shared secret, returned by the
+/// final transition of either party
+///
+/// The ephemeral keys of the exchange are gone by the time this exists,
+/// the shared secret is wiped when it's dropped.
+#[cfg_attr(feature = "zeroize", derive(Zeroize, ZeroizeOnDrop))]
+#[derive(Debug, Eq, PartialEq)]
+pub struct Established {
+  shared_secret: SharedSecret,
+}
+
+impl Established {
+  /// The shared secret agreed by both parties
+  pub fn shared_secret(&self) -> &SharedSecret {
+    &self.shared_secret
+  }
+}
+
+/// Responder in a handshake with key confirmation, holds the shared secret
+/// until the initiator's tag verifies in `server_confirm()`
+#[cfg_attr(feature = "zeroize", derive(Zeroize, ZeroizeOnDrop))]
+pub struct AwaitingConfirmation {
+  shared_secret: SharedSecret,
+  client_tag: ConfirmationTag,
+}
+
+// The expected tag is what a forger needs, so it's never printed
+impl fmt::Debug for AwaitingConfirmation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("AwaitingConfirmation([REDACTED])")
+  }
+}
+
+impl AwaitingConfirmation {
+  /// Verifies the initiator's tag from `client_confirm_with_tag()`, proving it
+  /// derived the same shared secret
+  ///
+  /// Returns `KyberError::Authentication` if the tag doesn't match.
+  pub fn server_confirm(self, client_tag: ConfirmationTag)
+  -> Result<Established, KyberError>
+  {
+    if verify(self.client_tag.as_ref(), client_tag.as_ref(), CONFIRMATION_TAG_BYTES) != 0 {
+      return Err(KyberError::Authentication)
+    }
+    Ok(Established { shared_secret: self.shared_secret.clone() })
+  }
+}
 
-/// Used for unilaterally authenticated key exchange between two parties.
-/// 
+/// Starting state of the initiating party in a unilaterally authenticated key exchange,
+/// only the responder is authenticated by its long term key.
+///
 /// ```
 /// # use pqc_kyber::*;
 /// # fn main() -> Result<(),KyberError> {
 /// let mut rng = rand::thread_rng();
-/// 
-/// let mut alice = Uake::new();
-/// let mut bob = Uake::new();
 /// let bob_keys = keypair(&mut rng);
-/// 
-/// let client_init = alice.client_init(&bob_keys.public, &mut rng);
-/// let server_send = bob.server_receive(client_init, &bob_keys.secret, &mut rng)?;
-/// let client_confirm = alice.client_confirm(server_send);
-/// 
-/// assert_eq!(alice.shared_secret, bob.shared_secret);
+///
+/// let (alice, client_init) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
+/// let (bob, server_send) = UakeResponder::new().server_receive(client_init, &bob_keys.secret, &mut rng)?;
+/// let alice = alice.client_confirm(server_send)?;
+///
+/// assert_eq!(alice.shared_secret(), bob.shared_secret());
 /// # Ok(()) }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UakeInitiator(Binding);
 
+/// Initiator of a unilaterally authenticated key exchange waiting for the
+/// responder, holds the ephemeral keys until `client_confirm()`
 #[cfg_attr(feature = "zeroize", derive(Zeroize, ZeroizeOnDrop))]
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Uake {
-  /// The resulting shared secret from a key exchange
-  pub shared_secret: SharedSecret,
-  /// Sent when initiating a key exchange
-  send_a: UakeSendInit,
-  /// Response to a key exchange initiation
-  send_b: UakeSendResponse,
-  // Epheremal keys
-  temp_key: TempKey,
-  eska: Eska
+#[derive(Debug)]
+pub struct UakeAwaitingResponse {
+  temp_key: SharedSecret,
+  eska: SecretKey,
+  #[cfg_attr(feature = "zeroize", zeroize(skip))]
+  binding: Binding,
 }
 
-impl Default for Uake {
-  fn default() -> Self {
-    Uake {
-      shared_secret: [0u8; KYBER_SSBYTES],
-      send_a: [0u8; UAKE_INIT_BYTES],
-      send_b: [0u8; UAKE_RESPONSE_BYTES],
-      temp_key: [0u8; KYBER_SSBYTES],
-      eska: [0u8; KYBER_SECRETKEYBYTES],
+/// Starting state of the responding party in a unilaterally authenticated key exchange
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UakeResponder(Binding);
+
+// Constructors shared by the starting states
+macro_rules! impl_start {
+  ($name:ident) => {
+    impl $name {
+      /// Starts a new exchange with the v1 key schedule and an empty context
+      pub fn new() -> Self {
+        Self::with_context(&[])
+      }
+
+      /// Starts a new exchange with the v1 key schedule bound to an application
+      /// context label, both parties must pass the same label
+      /// ```
+      /// # use pqc_kyber::*;
+      #[doc = concat!("let kex = ", stringify!($name), "::with_context(b\"my protocol v2\");")]
+      /// ```
+      pub fn with_context(context: &[u8]) -> Self {
+        $name(Binding::new(KeySchedule::V1, context))
+      }
+
+      /// Starts a new exchange with the legacy key schedule, for peers
+      /// on earlier releases
+      pub fn legacy() -> Self {
+        $name(Binding::new(KeySchedule::Legacy, &[]))
+      }
+
+      /// The key schedule the exchange will use
+      pub fn key_schedule(&self) -> KeySchedule {
+        self.0.schedule
+      }
     }
-  }
+
+    impl Default for $name {
+      fn default() -> Self {
+        Self::new()
+      }
+    }
+  };
 }
 
-impl Uake {
-  /// Builds new UAKE struct
-  /// ```
-  /// # use pqc_kyber::Uake;
-  /// let mut kex = Uake::new();
-  /// ```
-  pub fn new() -> Self {
-    Self::default()
-  }
+impl_start!(UakeInitiator);
+impl_start!(UakeResponder);
+impl_start!(AkeInitiator);
+impl_start!(AkeResponder);
 
-  /// Initiates a Unilaterally Authenticated Key Exchange.
-  /// ``` 
+impl UakeInitiator {
+  /// Initiates the exchange with the responder's public key, returning the next
+  /// state and the message to send.
+  /// ```
   /// # use pqc_kyber::*;
-  /// # fn main() -> Result<(),KyberError> {
   /// let mut rng = rand::thread_rng();
-  /// let mut alice = Uake::new();
   /// let bob_keys = keypair(&mut rng);
-  /// let client_init = alice.client_init(&bob_keys.public, &mut rng);
-  /// # Ok(()) }
+  /// let (alice, client_init) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
   /// ```
-  pub fn client_init<R>(&mut self, pubkey: &PublicKey, rng: &mut R) 
-  -> UakeSendInit
+  pub fn client_init<R>(self, pubkey: &PublicKey, rng: &mut R)
+  -> (UakeAwaitingResponse, UakeSendInit)
     where R: CryptoRng + RngCore
   {
+    let mut send_a = UakeSendInit::zeroed();
+    let mut state = UakeAwaitingResponse {
+      temp_key: SharedSecret::zeroed(),
+      eska: SecretKey::zeroed(),
+      binding: self.0,
+    };
     uake_init_a(
-      &mut self.send_a, &mut self.temp_key, 
-      &mut self.eska, pubkey, rng
+      &mut send_a.0, &mut state.temp_key.0,
+      state.eska.0.as_mut(), pubkey.as_ref(), rng
     );
-    self.send_a
+    hash_h(&mut state.binding.pk_b, pubkey.as_ref(), KYBER_PUBLICKEYBYTES);
+    hash_h(&mut state.binding.send_a, &send_a.0, UAKE_INIT_BYTES);
+    (state, send_a)
+  }
+}
+
+impl UakeAwaitingResponse {
+  /// Decapsulates the shared secret from the output of `server_receive()`,
+  /// the ephemeral keys are wiped as this state is consumed
+  ///
+  /// Decapsulation uses implicit rejection, a tampered or mismatched response
+  /// results in differing shared secrets rather than an error.
+  pub fn client_confirm(self, send_b: UakeSendResponse)
+  -> Result<Established, KyberError>
+  {
+    Ok(Established { shared_secret: self.derive(&send_b) })
   }
 
-  /// Handles the output of a `client_init()` request
+  /// Decapsulates the shared secret from the output of `server_receive_with_tag()`
+  /// and verifies the responder's tag, returning the tag to send back as the third flight
+  ///
+  /// Returns `KyberError::Authentication` if the responder derived a different shared secret.
   /// ```
   /// # use pqc_kyber::*;
   /// # fn main() -> Result<(),KyberError> {
   /// # let mut rng = rand::thread_rng();
-  /// let mut alice = Uake::new();
-  /// let mut bob = Uake::new();
-  /// let mut bob_keys = keypair(&mut rng);
-  /// let client_init = alice.client_init(&bob_keys.public, &mut rng);
-  /// let server_send = bob.server_receive(client_init, &bob_keys.secret, &mut rng)?;
+  /// let bob_keys = keypair(&mut rng);
+  /// let (alice, client_init) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  /// let (bob, server_send, server_tag) = UakeResponder::new().server_receive_with_tag(
+  ///   client_init, &bob_keys.secret, &mut rng
+  /// )?;
+  /// let (alice, client_tag) = alice.client_confirm_with_tag(server_send, server_tag)?;
+  /// let bob = bob.server_confirm(client_tag)?;
+  /// assert_eq!(alice.shared_secret(), bob.shared_secret());
   /// # Ok(()) }
-  pub fn server_receive<R>(
-    &mut self, send_a: UakeSendInit, secretkey: &SecretKey, rng: &mut R
-  ) 
-  -> Result<UakeSendResponse, KyberError> 
-    where R: CryptoRng + RngCore
+  /// ```
+  pub fn client_confirm_with_tag(self, send_b: UakeSendResponse, server_tag: ConfirmationTag)
+  -> Result<(Established, ConfirmationTag), KyberError>
   {
-    uake_shared_b(
-      &mut self.send_b, &mut self.shared_secret,
-      &send_a, secretkey, rng
-    )?;
-    Ok(self.send_b)
+    let mut shared_secret = self.derive(&send_b);
+    let (expected, client_tag) = kex_confirm(&mut shared_secret.0, &self.binding, send_b.as_ref());
+    if verify(expected.as_ref(), server_tag.as_ref(), CONFIRMATION_TAG_BYTES) != 0 {
+      return Err(KyberError::Authentication)
+    }
+    Ok((Established { shared_secret }, client_tag))
+  }
+
+  fn derive(&self, send_b: &UakeSendResponse) -> SharedSecret {
+    let mut keys = [0u8; 2*KYBER_SYMBYTES];
+    uake_shared_a(&mut keys, send_b.as_ref(), self.temp_key.as_ref(), self.eska.as_ref());
+    let mut shared_secret = SharedSecret::zeroed();
+    kex_derive(&mut shared_secret.0, &self.binding, MODE_UAKE, send_b.as_ref(), &keys);
+    wipe(&mut keys);
+    shared_secret
   }
+}
 
-  /// Decapsulates and authenticates the shared secret from the output of 
-  /// `server_receive()`
+impl UakeResponder {
+  /// Handles the output of a `client_init()` request with the responder's
+  /// secret key, returning the established exchange and the message to send back
   /// ```
   /// # use pqc_kyber::*;
   /// # fn main() -> Result<(),KyberError> {
   /// # let mut rng = rand::thread_rng();
-  /// # let mut alice = Uake::new();
-  /// # let mut bob = Uake::new();
-  /// # let bob_keys = keypair(&mut rng);
-  /// let client_init = alice.client_init(&bob_keys.public, &mut rng);
-  /// let server_send = bob.server_receive(client_init, &bob_keys.secret, &mut rng)?;
-  /// let client_confirm = alice.client_confirm(server_send);
-  /// assert_eq!(alice.shared_secret, bob.shared_secret);
+  /// let bob_keys = keypair(&mut rng);
+  /// let (alice, client_init) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  /// let (bob, server_send) = UakeResponder::new().server_receive(client_init, &bob_keys.secret, &mut rng)?;
   /// # Ok(()) }
-  pub fn client_confirm(&mut self, send_b: UakeSendResponse) 
-  -> Result<(), KyberError> 
+  /// ```
+  pub fn server_receive<R>(
+    self, send_a: UakeSendInit, secretkey: &SecretKey, rng: &mut R
+  )
+  -> Result<(Established, UakeSendResponse), KyberError>
+    where R: CryptoRng + RngCore
   {
-    uake_shared_a(
-      &mut self.shared_secret, &send_b, 
-      &self.temp_key, &self.eska
-    )?;
-    Ok(())
+    let (shared_secret, _, send_b) = self.respond(send_a, secretkey, rng);
+    Ok((Established { shared_secret }, send_b))
+  }
+
+  /// Handles the output of a `client_init()` request like `server_receive()`, also
+  /// returning a tag to send with the response. The exchange is established once
+  /// the initiator's tag verifies in `server_confirm()`.
+  pub fn server_receive_with_tag<R>(
+    self, send_a: UakeSendInit, secretkey: &SecretKey, rng: &mut R
+  )
+  -> Result<(AwaitingConfirmation, UakeSendResponse, ConfirmationTag), KyberError>
+    where R: CryptoRng + RngCore
+  {
+    let (mut shared_secret, binding, send_b) = self.respond(send_a, secretkey, rng);
+    let (server_tag, client_tag) = kex_confirm(&mut shared_secret.0, &binding, send_b.as_ref());
+    Ok((AwaitingConfirmation { shared_secret, client_tag }, send_b, server_tag))
+  }
+
+  fn respond<R>(self, send_a: UakeSendInit, secretkey: &SecretKey, rng: &mut R)
+  -> (SharedSecret, Binding, UakeSendResponse)
+    where R: CryptoRng + RngCore
+  {
+    let mut binding = self.0;
+    binding.pk_b.copy_from_slice(public_key_hash(secretkey.as_ref()));
+    hash_h(&mut binding.send_a, send_a.as_ref(), UAKE_INIT_BYTES);
+
+    let mut send_b = UakeSendResponse::zeroed();
+    let mut keys = [0u8; 2*KYBER_SYMBYTES];
+    uake_shared_b(&mut send_b.0, &mut keys, send_a.as_ref(), secretkey.as_ref(), rng);
+    let mut shared_secret = SharedSecret::zeroed();
+    kex_derive(&mut shared_secret.0, &binding, MODE_UAKE, send_b.as_ref(), &keys);
+    wipe(&mut keys);
+    (shared_secret, binding, send_b)
   }
 }
 
-/// Used for mutually authenticated key exchange between two parties.
-/// 
+/// Starting state of the initiating party in a mutually authenticated key exchange,
+/// both parties are authenticated by their long term keys.
+///
 /// # Example:
-/// ``` 
+/// ```
 /// # use pqc_kyber::*;
 /// # fn main() -> Result<(),KyberError> {
 /// let mut rng = rand::thread_rng();
-/// 
-/// let mut alice = Ake::new();
-/// let mut bob = Ake::new();
-/// 
 /// let alice_keys = keypair(&mut rng);
 /// let bob_keys = keypair(&mut rng);
-/// 
-/// let client_init = alice.client_init(&bob_keys.public, &mut rng);
-/// let server_send = bob.server_receive(client_init, &alice_keys.public, &bob_keys.secret, &mut rng)?;
-/// let client_confirm = alice.client_confirm(server_send, &alice_keys.secret);
-/// 
-/// assert_eq!(alice.shared_secret, bob.shared_secret);
+///
+/// let (alice, client_init) = AkeInitiator::new().client_init(&bob_keys.public, &mut rng);
+/// let (bob, server_send) = AkeResponder::new().server_receive(
+///   client_init, &alice_keys.public, &bob_keys.secret, &mut rng
+/// )?;
+/// let alice = alice.client_confirm(server_send, &alice_keys.secret)?;
+///
+/// assert_eq!(alice.shared_secret(), bob.shared_secret());
 /// # Ok(()) }
 /// ```
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AkeInitiator(Binding);
+
+/// Initiator of a mutually authenticated key exchange waiting for the
+/// responder, holds the ephemeral keys until `client_confirm()`
 #[cfg_attr(feature = "zeroize", derive(Zeroize, ZeroizeOnDrop))]
-pub struct Ake {
-  /// The resulting shared secret from a key exchange
-  pub shared_secret: SharedSecret,
-  /// Sent when initiating a key exchange
-  send_a: AkeSendInit,
-  /// Sent back responding to a key exchange initiation
-  send_b: AkeSendResponse,
-  // Epheremal keys
-  temp_key: TempKey,
-  eska: Eska
+#[derive(Debug)]
+pub struct AkeAwaitingResponse {
+  temp_key: SharedSecret,
+  eska: SecretKey,
+  #[cfg_attr(feature = "zeroize", zeroize(skip))]
+  binding: Binding,
 }
 
-impl Default for Ake {
-  fn default() -> Self {
-    Ake {
-      shared_secret: [0u8; KYBER_SSBYTES],
-      send_a: [0u8; AKE_INIT_BYTES],
-      send_b: [0u8; AKE_RESPONSE_BYTES],
-      temp_key: [0u8; KYBER_SSBYTES],
-      eska: [0u8; KYBER_SECRETKEYBYTES],
-    }
-  }
-}
+/// Starting state of the responding party in a mutually authenticated key exchange
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AkeResponder(Binding);
 
-impl Ake {
-  /// Builds a new AKE struct
-  /// ```
-  /// # use pqc_kyber::Ake;
-  /// let mut kex = Ake::new();
+impl AkeInitiator {
+  /// Initiates the exchange with the responder's public key, returning the next
+  /// state and the message to send.
   /// ```
-  pub fn new() -> Self {
-    Self::default()
-  }
-
-  /// Initiates a Mutually Authenticated Key Exchange.
-  /// ``` 
   /// # use pqc_kyber::*;
-  /// # fn main() -> Result<(),KyberError> {
   /// let mut rng = rand::thread_rng();
-  /// let mut alice = Ake::new();
   /// let bob_keys = keypair(&mut rng);
-  /// let client_init = alice.client_init(&bob_keys.public, &mut rng);
-  /// # Ok(()) }
+  /// let (alice, client_init) = AkeInitiator::new().client_init(&bob_keys.public, &mut rng);
   /// ```
-  pub fn client_init<R>(&mut self, pubkey: &PublicKey, rng: &mut R) 
-  -> AkeSendInit
+  pub fn client_init<R>(self, pubkey: &PublicKey, rng: &mut R)
+  -> (AkeAwaitingResponse, AkeSendInit)
     where R: CryptoRng + RngCore
   {
+    let mut send_a = AkeSendInit::zeroed();
+    let mut state = AkeAwaitingResponse {
+      temp_key: SharedSecret::zeroed(),
+      eska: SecretKey::zeroed(),
+      binding: self.0,
+    };
     ake_init_a(
-      &mut self.send_a, &mut self.temp_key, 
-      &mut self.eska, pubkey, rng
+      &mut send_a.0, &mut state.temp_key.0,
+      state.eska.0.as_mut(), pubkey.as_ref(), rng
     );
-    self.send_a
+    hash_h(&mut state.binding.pk_b, pubkey.as_ref(), KYBER_PUBLICKEYBYTES);
+    hash_h(&mut state.binding.send_a, &send_a.0, AKE_INIT_BYTES);
+    (state, send_a)
   }
+}
 
-  /// Handles and authenticates the output of a `client_init()` request
+impl AkeAwaitingResponse {
+  /// Decapsulates and authenticates the shared secret from the output of
+  /// `server_receive()` with the initiator's secret key, the ephemeral keys
+  /// are wiped as this state is consumed
+  ///
+  /// Decapsulation uses implicit rejection, a tampered or mismatched response
+  /// results in differing shared secrets rather than an error.
+  pub fn client_confirm(self, send_b: AkeSendResponse, secretkey: &SecretKey)
+  -> Result<Established, KyberError>
+  {
+    Ok(Established { shared_secret: self.derive(&send_b, secretkey) })
+  }
+
+  /// Decapsulates and authenticates the shared secret from the output of
+  /// `server_receive_with_tag()` and verifies the responder's tag, returning
+  /// the tag to send back as the third flight
+  ///
+  /// Returns `KyberError::Authentication` if the responder derived a different shared secret.
   /// ```
   /// # use pqc_kyber::*;
   /// # fn main() -> Result<(),KyberError> {
   /// # let mut rng = rand::thread_rng();
-  /// let mut alice = Ake::new();
-  /// let mut bob = Ake::new();
   /// let alice_keys = keypair(&mut rng);
   /// let bob_keys = keypair(&mut rng);
-  /// let client_init = alice.client_init(&bob_keys.public, &mut rng);
-  /// let server_send = bob.server_receive(client_init, &alice_keys.public, &bob_keys.secret, &mut rng)?;
+  /// let (alice, client_init) = AkeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  /// let (bob, server_send, server_tag) = AkeResponder::new().server_receive_with_tag(
+  ///   client_init, &alice_keys.public, &bob_keys.secret, &mut rng
+  /// )?;
+  /// let (alice, client_tag) = alice.client_confirm_with_tag(server_send, server_tag, &alice_keys.secret)?;
+  /// let bob = bob.server_confirm(client_tag)?;
+  /// assert_eq!(alice.shared_secret(), bob.shared_secret());
   /// # Ok(()) }
-  pub fn server_receive<R>(
-    &mut self, ake_send_a: AkeSendInit, pubkey: &PublicKey, 
-    secretkey: &SecretKey, rng: &mut R
-  ) 
-  -> Result<AkeSendResponse, KyberError>
-    where R: CryptoRng + RngCore 
+  /// ```
+  pub fn client_confirm_with_tag(
+    self, send_b: AkeSendResponse, server_tag: ConfirmationTag, secretkey: &SecretKey
+  )
+  -> Result<(Established, ConfirmationTag), KyberError>
   {
-    ake_shared_b(
-      &mut self.send_b, &mut self.shared_secret, 
-      &ake_send_a, secretkey, pubkey, rng
-    )?;
-    Ok(self.send_b)
+    let mut shared_secret = self.derive(&send_b, secretkey);
+    let (expected, client_tag) = kex_confirm(&mut shared_secret.0, &self.binding, send_b.as_ref());
+    if verify(expected.as_ref(), server_tag.as_ref(), CONFIRMATION_TAG_BYTES) != 0 {
+      return Err(KyberError::Authentication)
+    }
+    Ok((Established { shared_secret }, client_tag))
+  }
+
+  fn derive(&self, send_b: &AkeSendResponse, secretkey: &SecretKey) -> SharedSecret {
+    let mut binding = self.binding;
+    binding.pk_a.copy_from_slice(public_key_hash(secretkey.as_ref()));
+
+    let mut keys = [0u8; 3*KYBER_SYMBYTES];
+    ake_shared_a(
+      &mut keys, send_b.as_ref(),
+      self.temp_key.as_ref(), self.eska.as_ref(), secretkey.as_ref()
+    );
+    let mut shared_secret = SharedSecret::zeroed();
+    kex_derive(&mut shared_secret.0, &binding, MODE_AKE, send_b.as_ref(), &keys);
+    wipe(&mut keys);
+    shared_secret
   }
+}
 
-  /// Decapsulates and authenticates the shared secret from the output of 
-  /// `server_receive()`
+impl AkeResponder {
+  /// Handles and authenticates the output of a `client_init()` request with the
+  /// initiator's public key and the responder's secret key, returning the
+  /// established exchange and the message to send back
   /// ```
   /// # use pqc_kyber::*;
   /// # fn main() -> Result<(),KyberError> {
   /// # let mut rng = rand::thread_rng();
-  /// # let mut alice = Ake::new();
-  /// # let mut bob = Ake::new();
-  /// # let alice_keys = keypair(&mut rng);
-  /// # let bob_keys = keypair(&mut rng);
-  /// # let client_init = alice.client_init(&bob_keys.public, &mut rng);
-  /// let server_send = bob.server_receive(client_init, &alice_keys.public, &bob_keys.secret, &mut rng)?;
-  /// let client_confirm = alice.client_confirm(server_send, &alice_keys.secret);
-  /// assert_eq!(alice.shared_secret, bob.shared_secret);
+  /// let alice_keys = keypair(&mut rng);
+  /// let bob_keys = keypair(&mut rng);
+  /// let (alice, client_init) = AkeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  /// let (bob, server_send) = AkeResponder::new().server_receive(
+  ///   client_init, &alice_keys.public, &bob_keys.secret, &mut rng
+  /// )?;
   /// # Ok(()) }
-  pub fn client_confirm(&mut self, send_b: AkeSendResponse, secretkey: &SecretKey) 
-  -> Result<(), KyberError> 
+  /// ```
+  pub fn server_receive<R>(
+    self, ake_send_a: AkeSendInit, pubkey: &PublicKey,
+    secretkey: &SecretKey, rng: &mut R
+  )
+  -> Result<(Established, AkeSendResponse), KyberError>
+    where R: CryptoRng + RngCore
   {
-    ake_shared_a(
-      &mut self.shared_secret, &send_b, 
-      &self.temp_key, &self.eska, secretkey
-    )?;
-    Ok(())
+    let (shared_secret, _, send_b) = self.respond(ake_send_a, pubkey, secretkey, rng);
+    Ok((Established { shared_secret }, send_b))
+  }
+
+  /// Handles and authenticates the output of a `client_init()` request like
+  /// `server_receive()`, also returning a tag to send with the response. The
+  /// exchange is established once the initiator's tag verifies in `server_confirm()`,
+  /// proving it holds the secret key matching `pubkey`.
+  pub fn server_receive_with_tag<R>(
+    self, ake_send_a: AkeSendInit, pubkey: &PublicKey,
+    secretkey: &SecretKey, rng: &mut R
+  )
+  -> Result<(AwaitingConfirmation, AkeSendResponse, ConfirmationTag), KyberError>
+    where R: CryptoRng + RngCore
+  {
+    let (mut shared_secret, binding, send_b) = self.respond(ake_send_a, pubkey, secretkey, rng);
+    let (server_tag, client_tag) = kex_confirm(&mut shared_secret.0, &binding, send_b.as_ref());
+    Ok((AwaitingConfirmation { shared_secret, client_tag }, send_b, server_tag))
+  }
+
+  fn respond<R>(
+    self, ake_send_a: AkeSendInit, pubkey: &PublicKey,
+    secretkey: &SecretKey, rng: &mut R
+  )
+  -> (SharedSecret, Binding, AkeSendResponse)
+    where R: CryptoRng + RngCore
+  {
+    let mut binding = self.0;
+    hash_h(&mut binding.pk_a, pubkey.as_ref(), KYBER_PUBLICKEYBYTES);
+    binding.pk_b.copy_from_slice(public_key_hash(secretkey.as_ref()));
+    hash_h(&mut binding.send_a, ake_send_a.as_ref(), AKE_INIT_BYTES);
+
+    let mut send_b = AkeSendResponse::zeroed();
+    let mut keys = [0u8; 3*KYBER_SYMBYTES];
+    ake_shared_b(
+      &mut send_b.0, &mut keys,
+      ake_send_a.as_ref(), secretkey.as_ref(), pubkey.as_ref(), rng
+    );
+    let mut shared_secret = SharedSecret::zeroed();
+    kex_derive(&mut shared_secret.0, &binding, MODE_AKE, send_b.as_ref(), &keys);
+    wipe(&mut keys);
+    (shared_secret, binding, send_b)
   }
 }
 
+// H(pk) as stored in a secret key
+fn public_key_hash(sk: &[u8]) -> &[u8] {
+  &sk[KYBER_SECRETKEYBYTES-2*KYBER_SYMBYTES..KYBER_SECRETKEYBYTES-KYBER_SYMBYTES]
+}
+
+// Derives the shared secret from the KEM secrets. v1 hashes in the label, mode,
+// context, both public keys and both messages ahead of the secrets
+fn kex_derive(k: &mut[u8], binding: &Binding, mode: u8, send_b: &[u8], keys: &[u8])
+{
+  if binding.schedule == KeySchedule::Legacy {
+    kdf(k, keys, keys.len());
+    return
+  }
+  let mut buf = [0u8; KEX_V1_LABEL.len() + 1 + 5*KYBER_SYMBYTES + 3*KYBER_SYMBYTES];
+  let mut send_b_hash = [0u8; KYBER_SYMBYTES];
+  hash_h(&mut send_b_hash, send_b, send_b.len());
+  let inputs = [
+    &KEX_V1_LABEL[..], &[mode], &binding.context, &binding.pk_a, &binding.pk_b,
+    &binding.send_a, &send_b_hash, keys
+  ];
+  let mut len = 0;
+  for input in inputs.iter() {
+    buf[len..len + input.len()].copy_from_slice(input);
+    len += input.len();
+  }
+  kdf(k, &buf, len);
+  wipe(&mut buf);
+}
+
+// Replaces the derived key with the session key and returns the responder's and
+// initiator's tags, each a hash of the transcript under a separately derived MAC key
+fn kex_confirm(k: &mut[u8], binding: &Binding, send_b: &[u8])
+-> (ConfirmationTag, ConfirmationTag)
+{
+  let l = KEX_CONFIRM_LABEL.len();
+  let mut buf = [0u8; KEX_CONFIRM_LABEL.len() + 1 + 3*KYBER_SYMBYTES];
+  let mut mac_key = [0u8; KYBER_SYMBYTES];
+  buf[..l].copy_from_slice(KEX_CONFIRM_LABEL);
+  buf[l+1..l+1+KYBER_SYMBYTES].copy_from_slice(&k[..KYBER_SYMBYTES]);
+  buf[l] = CONFIRM_MAC_KEY;
+  kdf(&mut mac_key, &buf, l+1+KYBER_SYMBYTES);
+  buf[l] = CONFIRM_SESSION;
+  kdf(k, &buf, l+1+KYBER_SYMBYTES);
+
+  buf[l+1..l+1+KYBER_SYMBYTES].copy_from_slice(&mac_key);
+  buf[l+1+KYBER_SYMBYTES..l+1+2*KYBER_SYMBYTES].copy_from_slice(&binding.send_a);
+  hash_h(&mut buf[l+1+2*KYBER_SYMBYTES..], send_b, send_b.len());
+  let mut server_tag = ConfirmationTag::zeroed();
+  let mut client_tag = ConfirmationTag::zeroed();
+  buf[l] = CONFIRM_SERVER;
+  hash_h(&mut server_tag.0, &buf, buf.len());
+  buf[l] = CONFIRM_CLIENT;
+  hash_h(&mut client_tag.0, &buf, buf.len());
+  wipe(&mut buf);
+  wipe(&mut mac_key);
+  (server_tag, client_tag)
+}
 
 // Unilaterally Authenticated Key Exchange initiation
 fn uake_init_a<R>(
@@ -305,40 +670,34 @@ fn uake_init_a<R>(
 )
   where R: CryptoRng + RngCore
 {
-  crypto_kem_keypair(send, sk, rng, None);
-  crypto_kem_enc(&mut send[KYBER_PUBLICKEYBYTES..], tk, pkb, rng, None);
+  crypto_kem_keypair::<KyberLevel, _>(send, sk, rng, None);
+  crypto_kem_enc::<KyberLevel, _>(&mut send[KYBER_PUBLICKEYBYTES..], tk, pkb, rng, None);
 }
 
-// Unilaterally authenticated key exchange computation by Bob 
+// Unilaterally authenticated key exchange KEM secrets for Bob
 fn uake_shared_b<R>(
   send: &mut[u8], 
-  k: &mut[u8], 
+  keys: &mut[u8], 
   recv: &[u8], 
   skb: &[u8],
   rng: &mut R
-) -> Result<(), KyberError>
+)
   where R: CryptoRng + RngCore
 {
-  let mut buf = [0u8; 2*KYBER_SYMBYTES];
-  crypto_kem_enc(send, &mut buf, recv, rng, None);
-  crypto_kem_dec(&mut buf[KYBER_SYMBYTES..], &recv[KYBER_PUBLICKEYBYTES..], skb)?;
-  kdf(k, &buf, 2*KYBER_SYMBYTES);
-  Ok(())
+  crypto_kem_enc::<KyberLevel, _>(send, keys, recv, rng, None);
+  crypto_kem_dec::<KyberLevel>(&mut keys[KYBER_SYMBYTES..], &recv[KYBER_PUBLICKEYBYTES..], skb);
 }
 
-// Unilaterally authenticated key exchange computation by Alice
+// Unilaterally authenticated key exchange KEM secrets for Alice
 fn uake_shared_a(
-  k: &mut[u8], 
+  keys: &mut[u8], 
   recv: &[u8], 
   tk: &[u8], 
   sk: &[u8]
-) -> Result<(), KyberError> 
+)
 {
-  let mut buf = [0u8; 2*KYBER_SYMBYTES];
-  crypto_kem_dec(&mut buf, recv, sk)?;
-  buf[KYBER_SYMBYTES..].copy_from_slice(&tk[..]);
-  kdf(k, &buf, 2*KYBER_SYMBYTES);
-  Ok(())
+  crypto_kem_dec::<KyberLevel>(keys, recv, sk);
+  keys[KYBER_SYMBYTES..].copy_from_slice(tk);
 }
 
 // Authenticated key exchange initiation by Alice
@@ -351,42 +710,36 @@ fn ake_init_a<R>(
 )
   where R: CryptoRng + RngCore
 {
-  crypto_kem_keypair(send, sk, rng, None);
-  crypto_kem_enc(&mut send[KYBER_PUBLICKEYBYTES..], tk, pkb, rng, None);
+  crypto_kem_keypair::<KyberLevel, _>(send, sk, rng, None);
+  crypto_kem_enc::<KyberLevel, _>(&mut send[KYBER_PUBLICKEYBYTES..], tk, pkb, rng, None);
 }
 
-// Mutually authenticated key exchange computation by Bob
+// Mutually authenticated key exchange KEM secrets for Bob
 fn ake_shared_b<R>(
   send: &mut[u8], 
-  k: &mut[u8], 
+  keys: &mut[u8], 
   recv: &[u8], 
   skb: &[u8], 
   pka: &[u8],
   rng: &mut R
-) -> Result<(), KyberError> 
+)
   where R: CryptoRng + RngCore
 {
-  let mut buf = [0u8; 3*KYBER_SYMBYTES];
-  crypto_kem_enc(send, &mut buf, recv, rng, None);
-  crypto_kem_enc(&mut send[KYBER_CIPHERTEXTBYTES..], &mut buf[KYBER_SYMBYTES..], pka, rng, None);
-  crypto_kem_dec(&mut buf[2*KYBER_SYMBYTES..], &recv[KYBER_PUBLICKEYBYTES..], skb)?;
-  kdf(k, &buf, 3*KYBER_SYMBYTES);
-  Ok(())
+  crypto_kem_enc::<KyberLevel, _>(send, keys, recv, rng, None);
+  crypto_kem_enc::<KyberLevel, _>(&mut send[KYBER_CIPHERTEXTBYTES..], &mut keys[KYBER_SYMBYTES..], pka, rng, None);
+  crypto_kem_dec::<KyberLevel>(&mut keys[2*KYBER_SYMBYTES..], &recv[KYBER_PUBLICKEYBYTES..], skb);
 }
 
-// Mutually authenticated key exchange computation by Alice
+// Mutually authenticated key exchange KEM secrets for Alice
 fn ake_shared_a(
-  k: &mut[u8], 
+  keys: &mut[u8], 
   recv: &[u8], 
   tk: &[u8], 
   sk: &[u8], 
   ska: &[u8]
-) -> Result<(), KyberError> 
+)
 {
-  let mut buf = [0u8; 3*KYBER_SYMBYTES];
-  crypto_kem_dec(&mut buf, recv, sk)?;
-  crypto_kem_dec(&mut buf[KYBER_SYMBYTES..], &recv[KYBER_CIPHERTEXTBYTES..], ska)?;
-  buf[2*KYBER_SYMBYTES..].copy_from_slice(&tk[..]);
-  kdf(k, &buf, 3*KYBER_SYMBYTES);
-  Ok(())
+  crypto_kem_dec::<KyberLevel>(keys, recv, sk);
+  crypto_kem_dec::<KyberLevel>(&mut keys[KYBER_SYMBYTES..], &recv[KYBER_CIPHERTEXTBYTES..], ska);
+  keys[2*KYBER_SYMBYTES..].copy_from_slice(tk);
 }
\ No newline at end of file
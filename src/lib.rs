@@ -9,20 +9,36 @@
 //! * Compiles to WASM using wasm-bindgen.
 //! 
 //! ## Features
-//! If no security level is set then kyber768 is used, this is roughly equivalent to AES-196. See below for setting other levels.
-//! A compile-time error is raised if more than one level is specified. Besides that all other features can be mixed as needed:
+//! All three security levels are always compiled in and can be used side by side through the
+//! [`Kyber512`], [`Kyber768`] and [`Kyber1024`] types. The level features only select the default
+//! [`KyberLevel`] used by the non-generic functions, structs and constants. If no security level is
+//! set then kyber768 is used, this is roughly equivalent to AES-192. If both are set `kyber512` takes
+//! precedence. All other features can be mixed as needed:
 //!
 //! | Feature   | Description                                                                                                                                                                |
 //! |-----------|----------------------------------------------------------------------------------------------------------------------------------------------------------------------------|
-//! | kyber512  | Enables kyber512 mode, with a security level roughly equivalent to AES-128.                                                                                                |
-//! | kyber1024 | Enables kyber1024 mode, with a security level roughly equivalent to AES-256.                   |
+//! | kyber512  | Sets kyber512 as the default level, with a security level roughly equivalent to AES-128.                                                                                   |
+//! | kyber1024 | Sets kyber1024 as the default level, with a security level roughly equivalent to AES-256.                   |
 //! | 90s       | 90's mode uses SHA2 and AES-CTR as a replacement for SHAKE. This may provide hardware speedups on certain architectures.                                                           |
-//! | avx2      | On x86_64 platforms enable the optimized version. This flag is will cause a compile error on other architectures. |
+//! | avx2      | On x86_64 platforms compiles in the optimized version, used at runtime when the CPU supports it. Ignored on other architectures. |
 //! | wasm      | For compiling to WASM targets. |
+//! | xwing     | The [`xwing`] hybrid KEM of ML-KEM-768 and X25519. Unavailable in 90s mode. |
+//! | pkcs8     | SubjectPublicKeyInfo and PKCS#8 encoding of ML-KEM keys as DER or PEM through the [`pkcs8`](https://docs.rs/pkcs8) traits, both the seed and expanded private key forms are supported. Requires `alloc`, unavailable in 90s mode. |
+//! | hpke      | [`hpke`] (RFC 9180) Base mode encryption with the ML-KEM and, with `xwing`, X-Wing KEMs. Unavailable in 90s mode. |
+//! | channel   | An encrypted duplex [`channel`] over an established key exchange, with replay protection and automatic rekeying. |
+//! | seal      | Public key encryption of whole messages in a versioned envelope with [`seal`], to one recipient or many. |
+//! | noise     | [`noise`] handshake patterns with ML-KEM in place of Diffie-Hellman, following PQNoise. Unavailable in 90s mode. |
+//! | rustls    | The X25519MLKEM768 and X25519Kyber768Draft00 hybrid key exchange groups for [rustls](https://docs.rs/rustls) in [`tls`]. Requires `std`, unavailable in 90s mode. |
+//! | rayon     | Spreads large batches from [`encapsulate_batch`] and the other batch functions over threads. Requires `std`. |
+//! | kem       | Implements the [`kem`](https://docs.rs/kem) `Encapsulate` and `Decapsulate` traits for public and secret keys of every level and the prepared [`EncapsulationKey`] and [`DecapsulationKey`], with [`DecapsulationKey::generate`] for key generation. |
+//! | serde     | Implements `Serialize` and `Deserialize` for keys, ciphertexts, shared secrets, key exchange messages and ratchet state. Bytes with binary formats, hex strings with human readable ones. |
 //! 
 //! ## Usage 
 //! 
-//! For optimisations on x86 platforms enable the `avx2` feature and the following RUSTFLAGS:
+//! For optimisations on x86 platforms enable the `avx2` feature. Both the portable and AVX2 
+//! backends are compiled in and the AVX2 one is used when the CPU supports it, detected once 
+//! at runtime. [`set_backend`] forces a specific backend, for example to test them against each other.
+//! Without `std` the CPU can't be queried, instead the AVX2 backend is used when the following RUSTFLAGS are set:
 //! 
 //! ```shell
 //! export RUSTFLAGS="-C target-feature=+aes,+avx2,+sse2,+sse4.1,+bmi2,+popcnt"
@@ -32,8 +48,10 @@
 //! use pqc_kyber::*;
 //! ```
 //! 
-//! The higher level structs will be appropriate for most use-cases. 
-//! Both [unilateral](struct.Uake.html) or [mutually](struct.Ake.html) authenticated key exchanges are possible.
+//! The higher level key exchange types will be appropriate for most use-cases. 
+//! Both [unilateral](struct.UakeInitiator.html) or [mutually](struct.AkeInitiator.html) authenticated key exchanges are possible.
+//! Each step consumes the previous state, so a handshake can't be run out of order or twice, and the
+//! shared secret is only reachable once the exchange is [`Established`].
 //! 
 //! #### Unilaterally Authenticated Key Exchange
 //! ```
@@ -41,27 +59,22 @@
 //! # fn main() -> Result<(),KyberError> {
 //! let mut rng = rand::thread_rng();
 //! 
-//! // Initialize the key exchange structs
-//! let mut alice = Uake::new();
-//! let mut bob = Uake::new();
-//! 
-//! // Generate Keypairs
-//! let alice_keys = keypair(&mut rng);
+//! // Generate Bob's Keypair
 //! let bob_keys = keypair(&mut rng);
 //! 
 //! // Alice initiates key exchange
-//! let client_init = alice.client_init(&bob_keys.public, &mut rng);
+//! let (alice, client_init) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
 //! 
 //! // Bob authenticates and responds
-//! let server_send = bob.server_receive(
+//! let (bob, server_send) = UakeResponder::new().server_receive(
 //!   client_init, &bob_keys.secret, &mut rng
 //! )?;
 //! 
 //! // Alice decapsulates the shared secret
-//! alice.client_confirm(server_send)?;
+//! let alice = alice.client_confirm(server_send)?;
 //! 
-//! // Both key exchange structs now have the shared secret
-//! assert_eq!(alice.shared_secret, bob.shared_secret);
+//! // Both key exchanges are now established with the same shared secret
+//! assert_eq!(alice.shared_secret(), bob.shared_secret());
 //! # Ok(()) }
 //! ```
 //! 
@@ -72,24 +85,32 @@
 //! # use pqc_kyber::*;
 //! # fn main() -> Result<(),KyberError> {
 //! # let mut rng = rand::thread_rng();
-//! let mut alice = Ake::new();
-//! let mut bob = Ake::new();
-//! 
 //! let alice_keys = keypair(&mut rng);
 //! let bob_keys = keypair(&mut rng);
 //! 
-//! let client_init = alice.client_init(&bob_keys.public, &mut rng);
+//! let (alice, client_init) = AkeInitiator::new().client_init(&bob_keys.public, &mut rng);
 //! 
-//! let server_send = bob.server_receive(
+//! let (bob, server_send) = AkeResponder::new().server_receive(
 //!   client_init, &alice_keys.public, &bob_keys.secret, &mut rng
 //! )?;
 //! 
-//! alice.client_confirm(server_send, &alice_keys.secret)?;
+//! let alice = alice.client_confirm(server_send, &alice_keys.secret)?;
 //! 
-//! assert_eq!(alice.shared_secret, bob.shared_secret);
+//! assert_eq!(alice.shared_secret(), bob.shared_secret());
 //! # Ok(()) }
 //! ```
 //! 
+//! The shared secret is bound to both handshake messages, the static public keys of both
+//! parties and an optional context label through the [`KeySchedule`]. Peers on earlier
+//! releases derive it from the KEM secrets alone, `legacy()` starts an exchange compatible with them.
+//! 
+//! For explicit key confirmation use `server_receive_with_tag()` and `client_confirm_with_tag()`, the
+//! responder is [`AwaitingConfirmation`] until the initiator's tag arrives in a third flight.
+//! 
+//! Long lived sessions can keep refreshing their keys with a [`Ratchet`], which mixes a fresh
+//! KEM secret into each direction every round trip for post-compromise security. A received
+//! header only changes the ratchet when its step is committed after the message authenticates.
+//! 
 //! ##### Key Encapsulation
 //! Lower level functions for using the Kyber algorithm directly.
 //! ```
@@ -109,49 +130,139 @@
 //! # Ok(()) }
 //! ```
 //! 
+//! ##### Selecting a Security Level
+//! Every level is available at runtime through the [`KyberKem`] trait:
+//! ```
+//! # use pqc_kyber::*;
+//! # fn main() -> Result<(),KyberError> {
+//! # let mut rng = rand::thread_rng();
+//! let keys = Kyber512::keypair(&mut rng);
+//! let (ciphertext, shared_secret_alice) = Kyber512::encapsulate(&keys.public, &mut rng)?;
+//! let shared_secret_bob = Kyber512::decapsulate(&ciphertext, &keys.secret)?;
+//! assert_eq!(shared_secret_alice, shared_secret_bob);
+//! assert_eq!(ciphertext.as_ref().len(), Kyber512::CIPHERTEXTBYTES);
+//! # Ok(()) }
+//! ```
+//! 
+//! ##### ML-KEM
+//! The FIPS 203 standard is available through [`MlKem512`], [`MlKem768`] and [`MlKem1024`].
+//! Key and ciphertext sizes are unchanged but the shared secrets differ from round 3 Kyber, 
+//! so both parties need to agree on the variant. ML-KEM is not available in 90s mode.
+//! ```
+//! # use pqc_kyber::*;
+//! # fn main() -> Result<(),KyberError> {
+//! # #[cfg(not(feature = "90s"))] {
+//! # let mut rng = rand::thread_rng();
+//! let keys = MlKem768::keypair(&mut rng);
+//! let (ciphertext, shared_secret_alice) = MlKem768::encapsulate(&keys.public, &mut rng)?;
+//! let shared_secret_bob = MlKem768::decapsulate(&ciphertext, &keys.secret)?;
+//! assert_eq!(shared_secret_alice, shared_secret_bob);
+//! # }
+//! # Ok(()) }
+//! ```
+//! 
 //! ## Errors
-//! The [KyberError](enum.KyberError.html) enum handles errors. It has two variants:
+//! The [KyberError](enum.KyberError.html) enum handles errors. It has eight variants:
+//! 
+//! * **InvalidInput** - One or more byte inputs to a function are incorrectly sized, such as parsing a 
+//!   [`PublicKey`] from the wrong number of bytes. A likely cause of this is two parties using different 
+//!   security levels while trying to negotiate a key exchange.
+//! 
+//! * **Decapsulation** - The ciphertext was unable to be authenticated. The shared secret was not decapsulated.
+//!   Only returned by `decapsulate_explicit` behind the `hazmat` feature. By default decapsulation uses
+//!   implicit rejection, an invalid ciphertext results in a pseudorandom shared secret instead of an error.
+//! 
+//! * **InvalidPublicKey** - The public key failed the FIPS 203 modulus check in [`validate_public_key`] 
+//!   or [`encapsulate_strict`].
+//! 
+//! * **InvalidSecretKey** - The secret key failed the FIPS 203 hash check in [`validate_secret_key`] 
+//!   or [`decapsulate_strict`].
+//! 
+//! * **UnsupportedBackend** - [`set_backend`] was asked for a backend that isn't compiled in or 
+//!   isn't supported by the CPU.
 //! 
-//! * **InvalidInput** - One or more byte inputs to a function are incorrectly sized. A likely cause of 
-//! this is two parties using different security levels while trying to negotiate a key exchange.
+//! * **Authentication** - An HPKE, channel, sealed or Noise ciphertext or key exchange confirmation tag failed to authenticate,
+//!   it was modified or produced under a different key.
 //! 
-//! * **Decapsulation** - The ciphertext was unable to be authenticated. The shared secret was not decapsulated  
+//! * **MessageLimit** - An HPKE context or secure channel has used up its sequence numbers.
+//! 
+//! * **OutOfOrder** - A secure channel record was replayed, reordered or arrived after a lost record.
 
 #![cfg_attr(not(feature ="std"), no_std)]
 #![allow(clippy::many_single_char_names)]
+// Idioms kept from the C reference implementation
+#![allow(
+  clippy::needless_range_loop,
+  clippy::identity_op,
+  clippy::erasing_op,
+  clippy::precedence,
+  clippy::too_many_arguments,
+  clippy::missing_safety_doc,
+  clippy::new_without_default,
+  clippy::assign_op_pattern,
+  clippy::manual_div_ceil,
+  clippy::explicit_counter_loop,
+  clippy::manual_rotate
+)]
 
-// Prevent usage of mutually exclusive features
-#[cfg(all(feature = "kyber1024", feature = "kyber512"))]
-compile_error!("Only one security level can be specified");
-
-#[cfg(all(target_arch = "x86_64", feature = "avx2"))] 
+#[cfg(all(target_arch = "x86_64", feature = "avx2"))]
 mod avx2;
-#[cfg(all(target_arch = "x86_64", feature = "avx2"))] 
-use avx2::*;
-
-#[cfg(any(not(target_arch = "x86_64"), not(feature = "avx2")))] 
 mod reference;
-#[cfg(any(not(target_arch = "x86_64"), not(feature = "avx2")))] 
-use reference::*;
 
-#[cfg(any(not(target_arch = "x86_64"), not(feature = "avx2")))]
 #[cfg(feature = "hazmat")]
-pub use reference::indcpa;
+pub mod indcpa;
+#[cfg(not(feature = "hazmat"))]
+mod indcpa;
 
 #[cfg(feature = "wasm")]
 mod wasm;
 
+#[cfg(all(feature = "xwing", not(feature = "90s")))]
+pub mod xwing;
+
+#[cfg(all(feature = "hpke", not(feature = "90s")))]
+pub mod hpke;
+
+#[cfg(all(feature = "noise", not(feature = "90s")))]
+pub mod noise;
+
+#[cfg(all(feature = "rustls", not(feature = "90s")))]
+pub mod tls;
+
+#[cfg(feature = "channel")]
+pub mod channel;
+
+#[cfg(feature = "seal")]
+pub mod seal;
+
 mod api;
+mod backend;
+mod batch;
 mod error;
 mod kem;
+#[cfg(feature = "kem")]
+mod kem_traits;
 mod kex;
 mod params;
+mod precomputed;
+mod ratchet;
+#[cfg(all(feature = "pkcs8", not(feature = "90s")))]
+mod pkix;
 mod rng;
+#[cfg(feature = "serde")]
+mod serialize;
 mod symmetric;
+mod types;
 
 pub use api::*;
+pub use backend::{Backend, backend, set_backend};
 pub use kex::*;
 pub use params::*;
+pub use precomputed::*;
+pub use ratchet::*;
+#[cfg(all(feature = "pkcs8", not(feature = "90s")))]
+pub use pkix::PrivateKeyChoice;
+pub use types::*;
 pub use error::KyberError;
 pub use rand_core::{RngCore, CryptoRng};
 
@@ -0,0 +1,692 @@
+//! Noise protocol handshakes with a KEM in place of Diffie-Hellman (PQNoise)
+//!
+//! Follows the KEM based patterns of the PQNoise paper, where each `ee`, `es`, `se`
+//! or `ss` token is replaced by an encapsulation:
+//!
+//! * `e` sends a fresh ephemeral public key in the clear.
+//! * `s` sends the static public key, encrypted once a key has been mixed in.
+//! * `ekem` encapsulates to the peer's ephemeral key and sends the ciphertext in the clear.
+//! * `skem` encapsulates to the peer's static key and sends the ciphertext, encrypted
+//!   once a key has been mixed in.
+//!
+//! Both KEM tokens hash the ciphertext into the transcript then mix the shared secret
+//! into the chaining key. The symmetric layer is the one from the Noise specification with
+//! ChaChaPoly and SHA256, protocol names read `Noise_pqXX_MLKEM768_ChaChaPoly_SHA256`.
+//! [`NN`], [`NK`], [`XX`] and [`IK`] are provided, the crate's unilateral and mutual key
+//! exchanges are the [`UAKE`] and [`AKE`] patterns.
+//!
+//! ```
+//! # use pqc_kyber::*;
+//! # use pqc_kyber::noise::*;
+//! # fn main() -> Result<(), KyberError> {
+//! let mut rng = rand::thread_rng();
+//! let bob_keys = MlKem768::keypair(&mut rng);
+//!
+//! let mut alice = HandshakeState::<MlKem768>::initiator(
+//!   &NK, b"prologue", None, Some(bob_keys.public)
+//! )?;
+//! let mut bob = HandshakeState::<MlKem768>::responder(&NK, b"prologue", Some(bob_keys), None)?;
+//!
+//! let (mut message, mut payload) = ([0u8; MAX_MESSAGE_LEN], [0u8; MAX_MESSAGE_LEN]);
+//! let len = alice.write_message(b"hello", &mut message, &mut rng)?;
+//! let n = bob.read_message(&message[..len], &mut payload)?;
+//! assert_eq!(&payload[..n], b"hello");
+//!
+//! let len = bob.write_message(b"", &mut message, &mut rng)?;
+//! alice.read_message(&message[..len], &mut payload)?;
+//! assert!(alice.is_finished() && bob.is_finished());
+//!
+//! let mut alice = alice.into_transport()?;
+//! let mut bob = bob.into_transport()?;
+//! let len = alice.write_message(b"transport", &mut message)?;
+//! let n = bob.read_message(&message[..len], &mut payload)?;
+//! assert_eq!(&payload[..n], b"transport");
+//! # Ok(()) }
+//! ```
+use core::convert::TryFrom;
+use chacha20poly1305::{
+  aead::{generic_array::GenericArray, AeadInPlace, KeyInit},
+  ChaCha20Poly1305,
+};
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
+use crate::{
+  api::{KyberKem, Keypair},
+  error::KyberError,
+  params::*,
+  types::*,
+  CryptoRng, RngCore,
+};
+
+/// Longest Noise message, handshake or transport
+pub const MAX_MESSAGE_LEN: usize = 65535;
+/// Size in bytes of the authentication tag on every encrypted field
+pub const TAGBYTES: usize = 16;
+/// Size in bytes of the hash output, chaining key and cipher keys
+pub const HASHLEN: usize = 32;
+
+const NONCEBYTES: usize = 12;
+const MAX_PROTOCOL_NAME: usize = 64;
+
+/// A handshake token
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Token {
+  /// Ephemeral public key
+  E,
+  /// Static public key
+  S,
+  /// Encapsulation to the peer's ephemeral key
+  Ekem,
+  /// Encapsulation to the peer's static key
+  Skem,
+}
+
+/// A handshake pattern, the pre-messages of each party and the token
+/// lists of each message, alternating from the initiator
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct HandshakePattern {
+  /// Name used in the protocol name
+  pub name: &'static str,
+  /// Keys of the initiator known to the responder beforehand, only `S`
+  pub initiator_pre: &'static [Token],
+  /// Keys of the responder known to the initiator beforehand, only `S`
+  pub responder_pre: &'static [Token],
+  /// Tokens of each handshake message
+  pub messages: &'static [&'static [Token]],
+}
+
+use Token::*;
+
+/// ```text
+/// pqNN:
+///   -> e
+///   <- ekem
+/// ```
+pub const NN: HandshakePattern = HandshakePattern {
+  name: "pqNN",
+  initiator_pre: &[],
+  responder_pre: &[],
+  messages: &[&[E], &[Ekem]],
+};
+
+/// ```text
+/// pqNK:
+///   <- s
+///   ...
+///   -> e, skem
+///   <- ekem
+/// ```
+pub const NK: HandshakePattern = HandshakePattern {
+  name: "pqNK",
+  initiator_pre: &[],
+  responder_pre: &[S],
+  messages: &[&[E, Skem], &[Ekem]],
+};
+
+/// ```text
+/// pqXX:
+///   -> e
+///   <- ekem, s
+///   -> skem, s
+///   <- skem
+/// ```
+pub const XX: HandshakePattern = HandshakePattern {
+  name: "pqXX",
+  initiator_pre: &[],
+  responder_pre: &[],
+  messages: &[&[E], &[Ekem, S], &[Skem, S], &[Skem]],
+};
+
+/// ```text
+/// pqIK:
+///   <- s
+///   ...
+///   -> e, skem, s
+///   <- ekem, skem
+/// ```
+pub const IK: HandshakePattern = HandshakePattern {
+  name: "pqIK",
+  initiator_pre: &[],
+  responder_pre: &[S],
+  messages: &[&[E, Skem, S], &[Ekem, Skem]],
+};
+
+/// ```text
+/// pqKK:
+///   -> s
+///   <- s
+///   ...
+///   -> e, skem
+///   <- ekem, skem
+/// ```
+pub const KK: HandshakePattern = HandshakePattern {
+  name: "pqKK",
+  initiator_pre: &[S],
+  responder_pre: &[S],
+  messages: &[&[E, Skem], &[Ekem, Skem]],
+};
+
+/// The flow of [`UakeInitiator`](crate::UakeInitiator), an ephemeral key and an
+/// encapsulation to the responder's known static key answered by an encapsulation
+/// to the ephemeral key
+pub const UAKE: HandshakePattern = NK;
+
+/// The flow of [`AkeInitiator`](crate::AkeInitiator), which adds an encapsulation to
+/// the initiator's known static key to the response of [`UAKE`]
+pub const AKE: HandshakePattern = KK;
+
+/// A Noise CipherState, ChaChaPoly under a key and a counter nonce
+pub struct CipherState {
+  k: Option<[u8; HASHLEN]>,
+  n: u64,
+}
+
+impl CipherState {
+  fn new() -> Self {
+    CipherState { k: None, n: 0 }
+  }
+
+  fn initialize_key(&mut self, key: &[u8]) {
+    let mut k = [0u8; HASHLEN];
+    k.copy_from_slice(&key[..HASHLEN]);
+    self.k = Some(k);
+    self.n = 0;
+  }
+
+  /// Whether a key has been set, fields are sent in the clear until it is
+  pub fn has_key(&self) -> bool {
+    self.k.is_some()
+  }
+
+  // 32 bits of zeroes followed by the little endian counter
+  fn nonce(n: u64) -> [u8; NONCEBYTES] {
+    let mut nonce = [0u8; NONCEBYTES];
+    nonce[4..].copy_from_slice(&n.to_le_bytes());
+    nonce
+  }
+
+  /// Encrypts `buffer` in place with the next nonce and returns the tag, or
+  /// leaves it as is without a key
+  pub fn encrypt_with_ad(&mut self, ad: &[u8], buffer: &mut [u8])
+    -> Result<Option<[u8; TAGBYTES]>, KyberError>
+  {
+    let k = match &self.k {
+      Some(k) => k,
+      None => return Ok(None),
+    };
+    if self.n == u64::MAX {
+      return Err(KyberError::MessageLimit)
+    }
+    let tag = ChaCha20Poly1305::new(GenericArray::from_slice(k))
+      .encrypt_in_place_detached(GenericArray::from_slice(&Self::nonce(self.n)), ad, buffer)
+      .map_err(|_| KyberError::InvalidInput)?;
+    self.n += 1;
+    let mut out = [0u8; TAGBYTES];
+    out.copy_from_slice(&tag);
+    Ok(Some(out))
+  }
+
+  /// Decrypts `buffer` in place with the next nonce, the tag is ignored without a key
+  ///
+  /// The nonce only advances when the tag verifies.
+  pub fn decrypt_with_ad(&mut self, ad: &[u8], buffer: &mut [u8], tag: &[u8])
+    -> Result<(), KyberError>
+  {
+    let k = match &self.k {
+      Some(k) => k,
+      None => return Ok(()),
+    };
+    if self.n == u64::MAX {
+      return Err(KyberError::MessageLimit)
+    }
+    if tag.len() != TAGBYTES {
+      return Err(KyberError::InvalidInput)
+    }
+    ChaCha20Poly1305::new(GenericArray::from_slice(k))
+      .decrypt_in_place_detached(
+        GenericArray::from_slice(&Self::nonce(self.n)), ad, buffer, GenericArray::from_slice(tag)
+      )
+      .map_err(|_| KyberError::Authentication)?;
+    self.n += 1;
+    Ok(())
+  }
+
+  /// Replaces the key with the encryption of zeroes under the maximum nonce
+  pub fn rekey(&mut self) {
+    if let Some(k) = &mut self.k {
+      let mut zeroes = [0u8; HASHLEN];
+      let cipher = ChaCha20Poly1305::new(GenericArray::from_slice(&k[..]));
+      // Only fails for inputs longer than the ChaCha20 keystream
+      let _ = cipher.encrypt_in_place_detached(
+        GenericArray::from_slice(&Self::nonce(u64::MAX)), &[], &mut zeroes
+      );
+      k.copy_from_slice(&zeroes);
+      wipe(&mut zeroes);
+    }
+  }
+}
+
+impl Drop for CipherState {
+  fn drop(&mut self) {
+    if let Some(k) = &mut self.k {
+      wipe(k);
+    }
+  }
+}
+
+/// A Noise SymmetricState, the chaining key and handshake hash
+pub struct SymmetricState {
+  cipher: CipherState,
+  ck: [u8; HASHLEN],
+  h: [u8; HASHLEN],
+}
+
+impl SymmetricState {
+  /// Starts from the protocol name, hashed if it's longer than `HASHLEN`
+  pub fn initialize(protocol_name: &[u8]) -> Self {
+    let mut h = [0u8; HASHLEN];
+    if protocol_name.len() <= HASHLEN {
+      h[..protocol_name.len()].copy_from_slice(protocol_name);
+    } else {
+      h.copy_from_slice(&Sha256::digest(protocol_name));
+    }
+    SymmetricState { cipher: CipherState::new(), ck: h, h }
+  }
+
+  /// Mixes input key material into the chaining key and rekeys the cipher
+  pub fn mix_key(&mut self, ikm: &[u8]) {
+    let mut out = hkdf2(&self.ck, ikm);
+    self.ck.copy_from_slice(&out[..HASHLEN]);
+    self.cipher.initialize_key(&out[HASHLEN..]);
+    wipe(&mut out);
+  }
+
+  /// Hashes data into the handshake hash
+  pub fn mix_hash(&mut self, data: &[u8]) {
+    let mut hasher = Sha256::new();
+    hasher.update(self.h);
+    hasher.update(data);
+    self.h.copy_from_slice(&hasher.finalize());
+  }
+
+  /// The handshake hash, identifying the handshake transcript
+  pub fn handshake_hash(&self) -> [u8; HASHLEN] {
+    self.h
+  }
+
+  /// Encrypts `plaintext` into `out` with the handshake hash as associated data, appending
+  /// the tag when there is a key, and hashes the result. Returns the output length.
+  pub fn encrypt_and_hash(&mut self, plaintext: &[u8], out: &mut [u8]) -> Result<usize, KyberError> {
+    let len = plaintext.len();
+    let out_len = len + if self.cipher.has_key() { TAGBYTES } else { 0 };
+    if out.len() < out_len {
+      return Err(KyberError::InvalidInput)
+    }
+    out[..len].copy_from_slice(plaintext);
+    let h = self.h;
+    if let Some(tag) = self.cipher.encrypt_with_ad(&h, &mut out[..len])? {
+      out[len..out_len].copy_from_slice(&tag);
+    }
+    self.mix_hash(&out[..out_len]);
+    Ok(out_len)
+  }
+
+  /// Decrypts `ciphertext`, the output of `encrypt_and_hash()`, into `out` and hashes
+  /// it. Returns the plaintext length.
+  pub fn decrypt_and_hash(&mut self, ciphertext: &[u8], out: &mut [u8]) -> Result<usize, KyberError> {
+    let (ct, tag) = match self.cipher.has_key() {
+      true if ciphertext.len() < TAGBYTES => return Err(KyberError::InvalidInput),
+      true => ciphertext.split_at(ciphertext.len() - TAGBYTES),
+      false => (ciphertext, &[][..]),
+    };
+    if out.len() < ct.len() {
+      return Err(KyberError::InvalidInput)
+    }
+    let out = &mut out[..ct.len()];
+    out.copy_from_slice(ct);
+    let h = self.h;
+    if let Err(e) = self.cipher.decrypt_with_ad(&h, out, tag) {
+      wipe(out);
+      return Err(e)
+    }
+    self.mix_hash(ciphertext);
+    Ok(ct.len())
+  }
+
+  /// Derives the transport keys, initiator to responder then responder to initiator
+  pub fn split(&self) -> (CipherState, CipherState) {
+    let mut out = hkdf2(&self.ck, &[]);
+    let (mut c1, mut c2) = (CipherState::new(), CipherState::new());
+    c1.initialize_key(&out[..HASHLEN]);
+    c2.initialize_key(&out[HASHLEN..]);
+    wipe(&mut out);
+    (c1, c2)
+  }
+}
+
+impl Drop for SymmetricState {
+  fn drop(&mut self) {
+    wipe(&mut self.ck);
+  }
+}
+
+// The two output Noise HKDF, which is RFC 5869 HKDF with an empty info
+fn hkdf2(ck: &[u8], ikm: &[u8]) -> [u8; 2*HASHLEN] {
+  let mut out = [0u8; 2*HASHLEN];
+  Hkdf::<Sha256>::new(Some(ck), ikm)
+    .expand(&[], &mut out)
+    .expect("output within HKDF limits");
+  out
+}
+
+/// Transport keys of a finished handshake, oriented for one party
+pub struct TransportState {
+  send: CipherState,
+  recv: CipherState,
+  h: [u8; HASHLEN],
+}
+
+impl TransportState {
+  /// Encrypts `payload` into `message`, returning the message length
+  pub fn write_message(&mut self, payload: &[u8], message: &mut [u8]) -> Result<usize, KyberError> {
+    let len = payload.len() + TAGBYTES;
+    if len > MAX_MESSAGE_LEN || message.len() < len {
+      return Err(KyberError::InvalidInput)
+    }
+    message[..payload.len()].copy_from_slice(payload);
+    let (ct, rest) = message.split_at_mut(payload.len());
+    let tag = self.send.encrypt_with_ad(&[], ct)?.ok_or(KyberError::InvalidInput)?;
+    rest[..TAGBYTES].copy_from_slice(&tag);
+    Ok(len)
+  }
+
+  /// Decrypts `message` into `payload`, returning the payload length
+  pub fn read_message(&mut self, message: &[u8], payload: &mut [u8]) -> Result<usize, KyberError> {
+    if message.len() < TAGBYTES || message.len() > MAX_MESSAGE_LEN {
+      return Err(KyberError::InvalidInput)
+    }
+    let len = message.len() - TAGBYTES;
+    if payload.len() < len {
+      return Err(KyberError::InvalidInput)
+    }
+    payload[..len].copy_from_slice(&message[..len]);
+    let result = self.recv.decrypt_with_ad(&[], &mut payload[..len], &message[len..]);
+    if result.is_err() {
+      wipe(&mut payload[..len]);
+    }
+    result.map(|_| len)
+  }
+
+  /// Rekeys the sending cipher, the peer has to rekey its receiving cipher at the same point
+  pub fn rekey_outgoing(&mut self) {
+    self.send.rekey();
+  }
+
+  /// Rekeys the receiving cipher
+  pub fn rekey_incoming(&mut self) {
+    self.recv.rekey();
+  }
+
+  /// The handshake hash of the finished handshake, for channel binding
+  pub fn handshake_hash(&self) -> [u8; HASHLEN] {
+    self.h
+  }
+}
+
+/// A Noise HandshakeState running a pattern for one party
+///
+/// Any error aborts the handshake, later calls fail with `KyberError::InvalidInput`.
+pub struct HandshakeState<P: KyberParams = KyberLevel> {
+  symmetric: SymmetricState,
+  pattern: HandshakePattern,
+  initiator: bool,
+  message: usize,
+  failed: bool,
+  s: Option<Keypair<P>>,
+  e: Option<Keypair<P>>,
+  rs: Option<PublicKey<P>>,
+  re: Option<PublicKey<P>>,
+}
+
+impl<P: KyberParams> HandshakeState<P> {
+  /// Starts a handshake as the initiator with its static keys and the responder's
+  /// static public key, as far as the pattern needs them
+  ///
+  /// Returns `KyberError::InvalidInput` if a key the pattern needs is missing.
+  pub fn initiator(
+    pattern: &HandshakePattern, prologue: &[u8],
+    s: Option<Keypair<P>>, rs: Option<PublicKey<P>>
+  ) -> Result<Self, KyberError>
+  {
+    Self::new(pattern, true, prologue, s, rs)
+  }
+
+  /// Starts a handshake as the responder, see [`initiator()`](Self::initiator)
+  pub fn responder(
+    pattern: &HandshakePattern, prologue: &[u8],
+    s: Option<Keypair<P>>, rs: Option<PublicKey<P>>
+  ) -> Result<Self, KyberError>
+  {
+    Self::new(pattern, false, prologue, s, rs)
+  }
+
+  fn new(
+    pattern: &HandshakePattern, initiator: bool, prologue: &[u8],
+    s: Option<Keypair<P>>, rs: Option<PublicKey<P>>
+  ) -> Result<Self, KyberError>
+  {
+    let mut name = [0u8; MAX_PROTOCOL_NAME];
+    let len = protocol_name::<P>(pattern, &mut name)?;
+    let mut symmetric = SymmetricState::initialize(&name[..len]);
+    symmetric.mix_hash(prologue);
+
+    // The static key pair is needed to send it and to decapsulate the peer's skem
+    let own_pre = if initiator { pattern.initiator_pre } else { pattern.responder_pre };
+    let peer_pre = if initiator { pattern.responder_pre } else { pattern.initiator_pre };
+    let needs_s = own_pre.contains(&S) || sends(pattern, initiator, S) || sends(pattern, !initiator, Skem);
+    if needs_s && s.is_none() {
+      return Err(KyberError::InvalidInput)
+    }
+    if peer_pre.contains(&S) && rs.is_none() {
+      return Err(KyberError::InvalidInput)
+    }
+    if pattern.initiator_pre.iter().chain(pattern.responder_pre).any(|t| *t != S) {
+      return Err(KyberError::InvalidInput)
+    }
+
+    let (init_s, resp_s) = match initiator {
+      true => (s.as_ref().map(|k| &k.public), rs.as_ref()),
+      false => (rs.as_ref(), s.as_ref().map(|k| &k.public)),
+    };
+    if pattern.initiator_pre.contains(&S) {
+      symmetric.mix_hash(init_s.ok_or(KyberError::InvalidInput)?.as_ref());
+    }
+    if pattern.responder_pre.contains(&S) {
+      symmetric.mix_hash(resp_s.ok_or(KyberError::InvalidInput)?.as_ref());
+    }
+    let rs = if peer_pre.contains(&S) { rs } else { None };
+    Ok(HandshakeState {
+      symmetric, pattern: *pattern, initiator, message: 0, failed: false, s, e: None, rs, re: None
+    })
+  }
+
+  /// Whether all handshake messages have been written and read
+  pub fn is_finished(&self) -> bool {
+    self.message >= self.pattern.messages.len()
+  }
+
+  /// Whether the next message is ours to write
+  pub fn is_my_turn(&self) -> bool {
+    !self.is_finished() && (self.message & 1 == 0) == self.initiator
+  }
+
+  /// The peer's static public key, once known
+  pub fn remote_static(&self) -> Option<&PublicKey<P>> {
+    self.rs.as_ref()
+  }
+
+  /// The current handshake hash
+  pub fn handshake_hash(&self) -> [u8; HASHLEN] {
+    self.symmetric.handshake_hash()
+  }
+
+  /// Writes the next handshake message carrying `payload` into `message`,
+  /// returning its length
+  pub fn write_message<R>(&mut self, payload: &[u8], message: &mut [u8], rng: &mut R)
+    -> Result<usize, KyberError>
+    where R: CryptoRng + RngCore
+  {
+    if self.failed || !self.is_my_turn() {
+      return Err(KyberError::InvalidInput)
+    }
+    let out = self.write_tokens(payload, message, rng);
+    self.failed = out.is_err();
+    self.message += 1;
+    out
+  }
+
+  /// Reads the next handshake message into `payload`, returning the payload length
+  pub fn read_message(&mut self, message: &[u8], payload: &mut [u8]) -> Result<usize, KyberError> {
+    if self.failed || self.is_finished() || self.is_my_turn() {
+      return Err(KyberError::InvalidInput)
+    }
+    let out = self.read_tokens(message, payload);
+    self.failed = out.is_err();
+    self.message += 1;
+    out
+  }
+
+  /// Finishes the handshake, splitting the chaining key into the transport keys
+  pub fn into_transport(self) -> Result<TransportState, KyberError> {
+    if self.failed || !self.is_finished() {
+      return Err(KyberError::InvalidInput)
+    }
+    let (c1, c2) = self.symmetric.split();
+    let (send, recv) = if self.initiator { (c1, c2) } else { (c2, c1) };
+    Ok(TransportState { send, recv, h: self.symmetric.handshake_hash() })
+  }
+
+  fn write_tokens<R>(&mut self, payload: &[u8], message: &mut [u8], rng: &mut R)
+    -> Result<usize, KyberError>
+    where R: CryptoRng + RngCore
+  {
+    let cap = message.len().min(MAX_MESSAGE_LEN);
+    let message = &mut message[..cap];
+    let mut pos = 0;
+    for token in self.pattern.messages[self.message] {
+      match token {
+        E => {
+          let keys = P::keypair(rng);
+          pos = write(message, pos, keys.public.as_ref())?;
+          self.symmetric.mix_hash(keys.public.as_ref());
+          self.e = Some(keys);
+        },
+        S => {
+          let s = self.s.as_ref().ok_or(KyberError::InvalidInput)?;
+          pos += self.symmetric.encrypt_and_hash(s.public.as_ref(), &mut message[pos..])?;
+        },
+        Ekem => {
+          let re = self.re.as_ref().ok_or(KyberError::InvalidInput)?;
+          let (ct, ss) = P::encapsulate(re, rng)?;
+          pos = write(message, pos, ct.as_ref())?;
+          self.symmetric.mix_hash(ct.as_ref());
+          self.symmetric.mix_key(ss.as_ref());
+        },
+        Skem => {
+          let rs = self.rs.as_ref().ok_or(KyberError::InvalidInput)?;
+          let (ct, ss) = P::encapsulate(rs, rng)?;
+          pos += self.symmetric.encrypt_and_hash(ct.as_ref(), &mut message[pos..])?;
+          self.symmetric.mix_key(ss.as_ref());
+        },
+      }
+    }
+    Ok(pos + self.symmetric.encrypt_and_hash(payload, &mut message[pos..])?)
+  }
+
+  fn read_tokens(&mut self, message: &[u8], payload: &mut [u8]) -> Result<usize, KyberError> {
+    if message.len() > MAX_MESSAGE_LEN {
+      return Err(KyberError::InvalidInput)
+    }
+    let mut rest = message;
+    let mut buf = [0u8; MAX_FIELD];
+    for token in self.pattern.messages[self.message] {
+      match token {
+        E => {
+          let re = PublicKey::<P>::try_from(take(&mut rest, P::PUBLICKEYBYTES)?)?;
+          P::validate_public_key(&re)?;
+          self.symmetric.mix_hash(re.as_ref());
+          self.re = Some(re);
+        },
+        S => {
+          let field = take(&mut rest, P::PUBLICKEYBYTES + self.tag_len())?;
+          let n = self.symmetric.decrypt_and_hash(field, &mut buf)?;
+          let rs = PublicKey::<P>::try_from(&buf[..n])?;
+          P::validate_public_key(&rs)?;
+          self.rs = Some(rs);
+        },
+        Ekem => {
+          let e = self.e.as_ref().ok_or(KyberError::InvalidInput)?;
+          let ct = Ciphertext::<P>::try_from(take(&mut rest, P::CIPHERTEXTBYTES)?)?;
+          self.symmetric.mix_hash(ct.as_ref());
+          let ss = P::decapsulate(&ct, &e.secret)?;
+          self.symmetric.mix_key(ss.as_ref());
+        },
+        Skem => {
+          let s = self.s.as_ref().ok_or(KyberError::InvalidInput)?;
+          let field = take(&mut rest, P::CIPHERTEXTBYTES + self.tag_len())?;
+          let n = self.symmetric.decrypt_and_hash(field, &mut buf)?;
+          let ct = Ciphertext::<P>::try_from(&buf[..n])?;
+          let ss = P::decapsulate(&ct, &s.secret)?;
+          self.symmetric.mix_key(ss.as_ref());
+        },
+      }
+    }
+    self.symmetric.decrypt_and_hash(rest, payload)
+  }
+
+  fn tag_len(&self) -> usize {
+    if self.symmetric.cipher.has_key() { TAGBYTES } else { 0 }
+  }
+}
+
+// Longest public key or ciphertext with a tag
+const MAX_FIELD: usize = Kyber1024::PUBLICKEYBYTES + TAGBYTES;
+
+// Whether either party's messages contain the token
+fn sends(pattern: &HandshakePattern, initiator: bool, token: Token) -> bool {
+  pattern.messages.iter()
+    .skip(if initiator { 0 } else { 1 })
+    .step_by(2)
+    .any(|m| m.contains(&token))
+}
+
+// Copies data into the message at pos, returning the new position
+fn write(message: &mut [u8], pos: usize, data: &[u8]) -> Result<usize, KyberError> {
+  let end = pos + data.len();
+  if message.len() < end {
+    return Err(KyberError::InvalidInput)
+  }
+  message[pos..end].copy_from_slice(data);
+  Ok(end)
+}
+
+// Splits len bytes off the front of the input
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], KyberError> {
+  if input.len() < len {
+    return Err(KyberError::InvalidInput)
+  }
+  let (head, tail) = input.split_at(len);
+  *input = tail;
+  Ok(head)
+}
+
+// Noise_<pattern>_<KEM>_ChaChaPoly_SHA256, with the dashes dropped from the KEM name
+fn protocol_name<P: KyberParams>(pattern: &HandshakePattern, out: &mut [u8]) -> Result<usize, KyberError> {
+  let mut len = 0;
+  let parts = [b"Noise_".as_ref(), pattern.name.as_bytes(), b"_", P::NAME.as_bytes(), b"_ChaChaPoly_SHA256"];
+  for b in parts.iter().flat_map(|p| p.iter()).filter(|b| **b != b'-') {
+    *out.get_mut(len).ok_or(KyberError::InvalidInput)? = *b;
+    len += 1;
+  }
+  Ok(len)
+}
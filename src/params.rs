@@ -1,59 +1,263 @@
-/// The security level of Kyber
-/// 
-/// Defaults to 3 (kyber768), will be 2 or 4 respectively when
-/// kyber512 or kyber1024 are selected with feature flags.
-/// 
-/// * Kyber-512 aims at security roughly equivalent to AES-128
-/// * Kyber-768 aims at security roughly equivalent to AES-192
-/// * Kyber-1024 aims at security roughly equivalent to AES-256
-pub const KYBER_K: usize = if cfg!(feature = "kyber512") {
-    2
-} else if cfg!(feature = "kyber1024") {
-    4
-} else {
-    3
-};
-
-/// A boolean flag for whether 90's mode is activated.
-/// 
-/// If true AES-CTR and SHA2 will be used as cryptographic primitives instead, 
-/// which may have hardware speed-ups on certain platforms.
-/// 
-/// Defaults to false, set`features = ["90s"]` in Cargo.toml to enable. 
-pub const KYBER_90S: bool = cfg!(feature = "90s");
-
-pub(crate) const KYBER_N: usize = 256;
-pub(crate) const KYBER_Q: usize = 3329;
-
-pub(crate) const KYBER_ETA1: usize = if cfg!(feature = "kyber512") { 3 } else { 2 };
-pub(crate) const KYBER_ETA2: usize = 2;
-
-// Size of the hashes and seeds
-pub const KYBER_SYMBYTES: usize = 32;
-
-/// Size of the shared key 
-pub const KYBER_SSBYTES: usize =  32; 
-
-pub(crate) const KYBER_POLYBYTES: usize = 384;
-pub(crate) const KYBER_POLYVECBYTES: usize =  KYBER_K * KYBER_POLYBYTES;
-
-#[cfg(not(feature = "kyber1024"))]
-pub(crate) const KYBER_POLYCOMPRESSEDBYTES: usize =     128;
-#[cfg(not(feature = "kyber1024"))]
-pub(crate) const KYBER_POLYVECCOMPRESSEDBYTES: usize =  KYBER_K * 320;
-
-#[cfg(feature = "kyber1024")]
-pub(crate) const KYBER_POLYCOMPRESSEDBYTES: usize =     160;
-#[cfg(feature = "kyber1024")]
-pub(crate) const KYBER_POLYVECCOMPRESSEDBYTES: usize = KYBER_K * 352;
-
-pub const KYBER_INDCPA_PUBLICKEYBYTES: usize = KYBER_POLYVECBYTES + KYBER_SYMBYTES;
-pub const KYBER_INDCPA_SECRETKEYBYTES: usize = KYBER_POLYVECBYTES;
-pub const KYBER_INDCPA_BYTES: usize = KYBER_POLYVECCOMPRESSEDBYTES + KYBER_POLYCOMPRESSEDBYTES;
-
-/// Size in bytes of the Kyber public key
-pub const KYBER_PUBLICKEYBYTES: usize = KYBER_INDCPA_PUBLICKEYBYTES;
-/// Size in bytes of the Kyber secret key 
-pub const KYBER_SECRETKEYBYTES: usize =  KYBER_INDCPA_SECRETKEYBYTES +  KYBER_INDCPA_PUBLICKEYBYTES + 2*KYBER_SYMBYTES; 
-/// Size in bytes of the Kyber ciphertext
-pub const KYBER_CIPHERTEXTBYTES: usize =  KYBER_INDCPA_BYTES;
+use core::fmt::Debug;
+
+/// A set of Kyber parameters
+///
+/// Every security level is described by a zero-sized type implementing
+/// this trait, all levels can be used side by side in the same binary:
+///
+/// * [`Kyber512`] aims at security roughly equivalent to AES-128
+/// * [`Kyber768`] aims at security roughly equivalent to AES-192
+/// * [`Kyber1024`] aims at security roughly equivalent to AES-256
+///
+/// Each level also comes in a FIPS 203 variant, [`MlKem512`], [`MlKem768`]
+/// and [`MlKem1024`], sharing the same sizes but not wire compatible with
+/// the round 3 submission.
+///
+/// The byte lengths of keys and ciphertexts are exposed as associated
+/// constants and the matching fixed size arrays as associated types.
+pub trait KyberParams:
+  Copy + Clone + Debug + Default + Eq + PartialEq + Send + Sync + 'static
+{
+  /// Name of the parameter set
+  const NAME: &'static str;
+
+  /// The module rank, 2 for Kyber512, 3 for Kyber768 and 4 for Kyber1024
+  const K: usize;
+
+  /// Noise parameter used when sampling the secret and error vectors
+  const ETA1: usize;
+
+  /// Size in bytes of a compressed polynomial
+  const POLYCOMPRESSEDBYTES: usize;
+
+  /// Size in bytes of a compressed vector of polynomials
+  const POLYVECCOMPRESSEDBYTES: usize;
+
+  /// Whether the parameter set follows FIPS 203 ML-KEM rather than the
+  /// round 3 Kyber submission
+  const ML_KEM: bool = false;
+
+  /// Size in bytes of a serialized vector of polynomials
+  const POLYVECBYTES: usize = Self::K * KYBER_POLYBYTES;
+
+  /// Size in bytes of the IND-CPA public key
+  const INDCPA_PUBLICKEYBYTES: usize = Self::POLYVECBYTES + KYBER_SYMBYTES;
+  /// Size in bytes of the IND-CPA secret key
+  const INDCPA_SECRETKEYBYTES: usize = Self::POLYVECBYTES;
+  /// Size in bytes of the IND-CPA ciphertext
+  const INDCPA_BYTES: usize =
+    Self::POLYVECCOMPRESSEDBYTES + Self::POLYCOMPRESSEDBYTES;
+
+  /// Size in bytes of the public key
+  const PUBLICKEYBYTES: usize = Self::INDCPA_PUBLICKEYBYTES;
+  /// Size in bytes of the secret key
+  const SECRETKEYBYTES: usize =
+    Self::INDCPA_SECRETKEYBYTES + Self::INDCPA_PUBLICKEYBYTES + 2*KYBER_SYMBYTES;
+  /// Size in bytes of the ciphertext
+  const CIPHERTEXTBYTES: usize = Self::INDCPA_BYTES;
+
+  /// Public key byte array, `[u8; PUBLICKEYBYTES]`
+  type PublicKey: ByteArray;
+  /// Secret key byte array, `[u8; SECRETKEYBYTES]`
+  type SecretKey: ByteArray;
+  /// Ciphertext byte array, `[u8; CIPHERTEXTBYTES]`
+  type Ciphertext: ByteArray;
+}
+
+/// Fixed size byte arrays used for keys and ciphertexts
+pub trait ByteArray:
+  Copy + Clone + Debug + Eq + PartialEq + AsRef<[u8]> + AsMut<[u8]> + Send + Sync
+{
+  /// Length of the array
+  const LEN: usize;
+
+  /// Returns an array filled with zeroes
+  fn zeroed() -> Self;
+}
+
+impl<const N: usize> ByteArray for [u8; N] {
+  const LEN: usize = N;
+
+  fn zeroed() -> Self {
+    [0u8; N]
+  }
+}
+
+/// Kyber-512, security roughly equivalent to AES-128
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Kyber512;
+
+/// Kyber-768, security roughly equivalent to AES-192
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Kyber768;
+
+/// Kyber-1024, security roughly equivalent to AES-256
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Kyber1024;
+
+impl KyberParams for Kyber512 {
+  const NAME: &'static str = "Kyber512";
+  const K: usize = 2;
+  const ETA1: usize = 3;
+  const POLYCOMPRESSEDBYTES: usize = 128;
+  const POLYVECCOMPRESSEDBYTES: usize = Self::K * 320;
+  type PublicKey = [u8; 800];
+  type SecretKey = [u8; 1632];
+  type Ciphertext = [u8; 768];
+}
+
+impl KyberParams for Kyber768 {
+  const NAME: &'static str = "Kyber768";
+  const K: usize = 3;
+  const ETA1: usize = 2;
+  const POLYCOMPRESSEDBYTES: usize = 128;
+  const POLYVECCOMPRESSEDBYTES: usize = Self::K * 320;
+  type PublicKey = [u8; 1184];
+  type SecretKey = [u8; 2400];
+  type Ciphertext = [u8; 1088];
+}
+
+impl KyberParams for Kyber1024 {
+  const NAME: &'static str = "Kyber1024";
+  const K: usize = 4;
+  const ETA1: usize = 2;
+  const POLYCOMPRESSEDBYTES: usize = 160;
+  const POLYVECCOMPRESSEDBYTES: usize = Self::K * 352;
+  type PublicKey = [u8; 1568];
+  type SecretKey = [u8; 3168];
+  type Ciphertext = [u8; 1568];
+}
+
+/// ML-KEM-512 as standardised in FIPS 203
+///
+/// Not available in 90s mode, which replaces the FIPS 202 primitives
+/// ML-KEM is defined over.
+#[cfg(not(feature = "90s"))]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct MlKem512;
+
+/// ML-KEM-768 as standardised in FIPS 203
+///
+/// Not available in 90s mode, which replaces the FIPS 202 primitives
+/// ML-KEM is defined over.
+#[cfg(not(feature = "90s"))]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct MlKem768;
+
+/// ML-KEM-1024 as standardised in FIPS 203
+///
+/// Not available in 90s mode, which replaces the FIPS 202 primitives
+/// ML-KEM is defined over.
+#[cfg(not(feature = "90s"))]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct MlKem1024;
+
+#[cfg(not(feature = "90s"))]
+impl KyberParams for MlKem512 {
+  const NAME: &'static str = "ML-KEM-512";
+  const K: usize = 2;
+  const ETA1: usize = 3;
+  const POLYCOMPRESSEDBYTES: usize = 128;
+  const POLYVECCOMPRESSEDBYTES: usize = Self::K * 320;
+  const ML_KEM: bool = true;
+  type PublicKey = [u8; 800];
+  type SecretKey = [u8; 1632];
+  type Ciphertext = [u8; 768];
+}
+
+#[cfg(not(feature = "90s"))]
+impl KyberParams for MlKem768 {
+  const NAME: &'static str = "ML-KEM-768";
+  const K: usize = 3;
+  const ETA1: usize = 2;
+  const POLYCOMPRESSEDBYTES: usize = 128;
+  const POLYVECCOMPRESSEDBYTES: usize = Self::K * 320;
+  const ML_KEM: bool = true;
+  type PublicKey = [u8; 1184];
+  type SecretKey = [u8; 2400];
+  type Ciphertext = [u8; 1088];
+}
+
+#[cfg(not(feature = "90s"))]
+impl KyberParams for MlKem1024 {
+  const NAME: &'static str = "ML-KEM-1024";
+  const K: usize = 4;
+  const ETA1: usize = 2;
+  const POLYCOMPRESSEDBYTES: usize = 160;
+  const POLYVECCOMPRESSEDBYTES: usize = Self::K * 352;
+  const ML_KEM: bool = true;
+  type PublicKey = [u8; 1568];
+  type SecretKey = [u8; 3168];
+  type Ciphertext = [u8; 1568];
+}
+
+/// The default security level used by the non-generic functions and constants
+///
+/// Kyber768 unless the `kyber512` or `kyber1024` feature is selected.
+/// If both are enabled `kyber512` takes precedence.
+#[cfg(feature = "kyber512")]
+pub type KyberLevel = Kyber512;
+/// The default security level used by the non-generic functions and constants
+///
+/// Kyber768 unless the `kyber512` or `kyber1024` feature is selected.
+/// If both are enabled `kyber512` takes precedence.
+#[cfg(all(feature = "kyber1024", not(feature = "kyber512")))]
+pub type KyberLevel = Kyber1024;
+/// The default security level used by the non-generic functions and constants
+///
+/// Kyber768 unless the `kyber512` or `kyber1024` feature is selected.
+/// If both are enabled `kyber512` takes precedence.
+#[cfg(not(any(feature = "kyber512", feature = "kyber1024")))]
+pub type KyberLevel = Kyber768;
+
+/// The default security level of Kyber
+///
+/// Defaults to 3 (kyber768), will be 2 or 4 respectively when
+/// kyber512 or kyber1024 are selected with feature flags.
+///
+/// * Kyber-512 aims at security roughly equivalent to AES-128
+/// * Kyber-768 aims at security roughly equivalent to AES-192
+/// * Kyber-1024 aims at security roughly equivalent to AES-256
+pub const KYBER_K: usize = KyberLevel::K;
+
+/// A boolean flag for whether 90's mode is activated.
+///
+/// If true AES-CTR and SHA2 will be used as cryptographic primitives instead,
+/// which may have hardware speed-ups on certain platforms.
+///
+/// Defaults to false, set`features = ["90s"]` in Cargo.toml to enable.
+pub const KYBER_90S: bool = cfg!(feature = "90s");
+
+pub(crate) const KYBER_N: usize = 256;
+pub(crate) const KYBER_Q: usize = 3329;
+
+pub(crate) const KYBER_ETA2: usize = 2;
+
+// Largest values across all parameter sets, used to size stack buffers
+pub(crate) const KYBER_MAX_K: usize = 4;
+pub(crate) const KYBER_MAX_ETA1: usize = 3;
+
+// Size of the hashes and seeds
+pub const KYBER_SYMBYTES: usize = 32;
+
+/// Size of the shared key
+pub const KYBER_SSBYTES: usize =  32;
+
+/// Size of the d||z seed a keypair is expanded from
+pub const KYBER_SEEDBYTES: usize = 2 * KYBER_SYMBYTES;
+
+pub(crate) const KYBER_POLYBYTES: usize = 384;
+
+pub const KYBER_INDCPA_PUBLICKEYBYTES: usize = KyberLevel::INDCPA_PUBLICKEYBYTES;
+pub const KYBER_INDCPA_SECRETKEYBYTES: usize = KyberLevel::INDCPA_SECRETKEYBYTES;
+pub const KYBER_INDCPA_BYTES: usize = KyberLevel::INDCPA_BYTES;
+
+/// Size in bytes of the Kyber public key
+pub const KYBER_PUBLICKEYBYTES: usize = KyberLevel::PUBLICKEYBYTES;
+/// Size in bytes of the Kyber secret key
+pub const KYBER_SECRETKEYBYTES: usize = KyberLevel::SECRETKEYBYTES;
+/// Size in bytes of the Kyber ciphertext
+pub const KYBER_CIPHERTEXTBYTES: usize = KyberLevel::CIPHERTEXTBYTES;
+
+// Largest ciphertext across all parameter sets
+pub(crate) const KYBER_MAX_CIPHERTEXTBYTES: usize = Kyber1024::CIPHERTEXTBYTES;
@@ -0,0 +1,226 @@
+// SubjectPublicKeyInfo and PKCS#8 encodings of ML-KEM keys, following
+// the LAMPS ML-KEM certificates draft. Round 3 Kyber has no OIDs assigned.
+use core::convert::TryFrom;
+use pkcs8::{
+  der::{
+    asn1::{AnyRef, BitStringRef, OctetStringRef},
+    Decode, Encode, SliceWriter, Tag, TagMode, TagNumber, Tagged,
+  },
+  spki, AlgorithmIdentifierRef, AssociatedOid, Document, EncodePrivateKey,
+  EncodePublicKey, ObjectIdentifier, PrivateKeyInfo, SecretDocument,
+  SubjectPublicKeyInfoRef,
+};
+use crate::{
+  api::{Keypair, KyberKem},
+  params::*,
+  types::*,
+};
+
+impl AssociatedOid for MlKem512 {
+  const OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.4.1");
+}
+
+impl AssociatedOid for MlKem768 {
+  const OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.4.2");
+}
+
+impl AssociatedOid for MlKem1024 {
+  const OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.4.3");
+}
+
+/// Which form of the `ML-KEM-PrivateKey` CHOICE to write into a PKCS#8 document
+///
+/// Keys from [`keypair`](crate::keypair) or decoded from a seed remember
+/// their 64 byte seed, keys parsed from expanded bytes only have the
+/// expanded form available.
+///
+/// ```
+/// # use pqc_kyber::*;
+/// # use pkcs8::DecodePrivateKey;
+/// # fn main() -> Result<(), pkcs8::Error> {
+/// let mut rng = rand::thread_rng();
+/// let keys = MlKem768::keypair(&mut rng);
+/// let der = keys.secret.to_pkcs8_der_with(PrivateKeyChoice::Both)?;
+/// let secret = SecretKey::<MlKem768>::from_pkcs8_der(der.as_bytes())?;
+/// assert_eq!(secret, keys.secret);
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PrivateKeyChoice {
+  /// `seed [0] IMPLICIT OCTET STRING`, the 64 byte d||z seed
+  Seed,
+  /// `expandedKey OCTET STRING`, the FIPS 203 decapsulation key
+  ExpandedKey,
+  /// `both SEQUENCE`, the seed followed by the expanded key
+  Both,
+}
+
+fn algorithm<'a, P: AssociatedOid>() -> AlgorithmIdentifierRef<'a> {
+  AlgorithmIdentifierRef { oid: P::OID, parameters: None }
+}
+
+impl<P: KyberParams + AssociatedOid> TryFrom<SubjectPublicKeyInfoRef<'_>> for PublicKey<P> {
+  type Error = spki::Error;
+
+  fn try_from(spki: SubjectPublicKeyInfoRef<'_>) -> spki::Result<Self> {
+    spki.algorithm.assert_algorithm_oid(P::OID)?;
+    if spki.algorithm.parameters.is_some() {
+      return Err(spki::Error::KeyMalformed)
+    }
+    let bytes = spki.subject_public_key.as_bytes().ok_or(spki::Error::KeyMalformed)?;
+    let public = PublicKey::try_from(bytes).map_err(|_| spki::Error::KeyMalformed)?;
+    P::validate_public_key(&public).map_err(|_| spki::Error::KeyMalformed)?;
+    Ok(public)
+  }
+}
+
+impl<P: KyberParams + AssociatedOid> EncodePublicKey for PublicKey<P> {
+  fn to_public_key_der(&self) -> spki::Result<Document> {
+    let spki = SubjectPublicKeyInfoRef {
+      algorithm: algorithm::<P>(),
+      subject_public_key: BitStringRef::from_bytes(self.as_ref())?,
+    };
+    Ok(Document::encode_msg(&spki)?)
+  }
+}
+
+// Expands the seed choice, the seed is copied into the key
+fn from_seed<P: KyberParams>(bytes: &[u8]) -> pkcs8::Result<Keypair<P>> {
+  let mut seed = Seed::try_from(bytes).map_err(|_| pkcs8::Error::KeyMalformed)?;
+  let keys = P::keypair_from_seed(&seed);
+  wipe(&mut seed);
+  Ok(keys)
+}
+
+// Parses the expanded key choice, the public key is embedded after the
+// IND-CPA secret key
+fn from_expanded<P: KyberParams>(bytes: &[u8]) -> pkcs8::Result<Keypair<P>> {
+  let secret = SecretKey::<P>::try_from(bytes).map_err(|_| pkcs8::Error::KeyMalformed)?;
+  P::validate_secret_key(&secret).map_err(|_| pkcs8::Error::KeyMalformed)?;
+  let start = P::INDCPA_SECRETKEYBYTES;
+  let public = PublicKey::<P>::try_from(&bytes[start..start + P::PUBLICKEYBYTES])
+    .map_err(|_| pkcs8::Error::KeyMalformed)?;
+  P::validate_public_key(&public).map_err(|_| pkcs8::Error::KeyMalformed)?;
+  Ok(Keypair { public, secret })
+}
+
+impl<P: KyberParams + AssociatedOid> TryFrom<PrivateKeyInfo<'_>> for Keypair<P> {
+  type Error = pkcs8::Error;
+
+  fn try_from(info: PrivateKeyInfo<'_>) -> pkcs8::Result<Self> {
+    info.algorithm.assert_algorithm_oid(P::OID)?;
+    if info.algorithm.parameters.is_some() {
+      return Err(pkcs8::Error::ParametersMalformed)
+    }
+    let choice = AnyRef::from_der(info.private_key)?;
+    let keys = match choice.tag() {
+      Tag::ContextSpecific { constructed: false, number: TagNumber::N0 } => {
+        from_seed(choice.value())?
+      },
+      Tag::OctetString => from_expanded(choice.value())?,
+      Tag::Sequence => {
+        let (seed, expanded) = choice.sequence(|reader| {
+          let seed = OctetStringRef::decode(reader)?;
+          let expanded = OctetStringRef::decode(reader)?;
+          Ok((seed.as_bytes(), expanded.as_bytes()))
+        })?;
+        let keys = from_seed::<P>(seed)?;
+        // Both forms must describe the same key
+        let expanded = SecretKey::<P>::try_from(expanded)
+          .map_err(|_| pkcs8::Error::KeyMalformed)?;
+        if keys.secret != expanded {
+          return Err(pkcs8::Error::KeyMalformed)
+        }
+        keys
+      },
+      _ => return Err(pkcs8::Error::KeyMalformed)
+    };
+    // OneAsymmetricKey can also carry the public key
+    if let Some(public) = info.public_key {
+      if public != keys.public.as_ref() {
+        return Err(pkcs8::Error::KeyMalformed)
+      }
+    }
+    Ok(keys)
+  }
+}
+
+impl<P: KyberParams + AssociatedOid> TryFrom<PrivateKeyInfo<'_>> for SecretKey<P> {
+  type Error = pkcs8::Error;
+
+  fn try_from(info: PrivateKeyInfo<'_>) -> pkcs8::Result<Self> {
+    Keypair::try_from(info).map(|keys| keys.secret)
+  }
+}
+
+// Writes the ML-KEM-PrivateKey CHOICE into buf
+fn encode_choice<'a>(
+  buf: &'a mut [u8],
+  choice: PrivateKeyChoice,
+  seed: Option<&Seed>,
+  expanded: &[u8]
+) -> pkcs8::Result<&'a [u8]>
+{
+  let mut writer = SliceWriter::new(buf);
+  let expanded = OctetStringRef::new(expanded)?;
+  match (choice, seed) {
+    (PrivateKeyChoice::ExpandedKey, _) => writer.encode(&expanded)?,
+    (PrivateKeyChoice::Seed, Some(seed)) => {
+      let seed = OctetStringRef::new(seed)?;
+      writer.context_specific(TagNumber::N0, TagMode::Implicit, &seed)?
+    },
+    (PrivateKeyChoice::Both, Some(seed)) => {
+      let seed = OctetStringRef::new(seed)?;
+      let len = (seed.encoded_len()? + expanded.encoded_len()?)?;
+      writer.sequence(len, |writer| {
+        writer.encode(&seed)?;
+        writer.encode(&expanded)
+      })?
+    },
+    (_, None) => return Err(pkcs8::Error::KeyMalformed)
+  }
+  Ok(writer.finish()?)
+}
+
+impl<P: KyberParams + AssociatedOid> SecretKey<P> {
+  /// Encodes the key as PKCS#8 with the chosen private key form.
+  ///
+  /// Returns `pkcs8::Error::KeyMalformed` if a form containing the seed
+  /// is requested for a key that doesn't have one.
+  ///
+  /// ```
+  /// # use pqc_kyber::*;
+  /// # use core::convert::TryFrom;
+  /// # fn main() -> Result<(), KyberError> {
+  /// let mut rng = rand::thread_rng();
+  /// let keys = MlKem512::keypair(&mut rng);
+  /// assert!(keys.secret.to_pkcs8_der_with(PrivateKeyChoice::Seed).is_ok());
+  ///
+  /// // Parsed from the expanded bytes so the seed is unknown
+  /// let secret = SecretKey::<MlKem512>::try_from(keys.secret.as_ref())?;
+  /// assert!(secret.to_pkcs8_der_with(PrivateKeyChoice::ExpandedKey).is_ok());
+  /// assert!(secret.to_pkcs8_der_with(PrivateKeyChoice::Seed).is_err());
+  /// # Ok(()) }
+  /// ```
+  pub fn to_pkcs8_der_with(&self, choice: PrivateKeyChoice) -> pkcs8::Result<SecretDocument> {
+    // Room for the largest choice, both forms at the highest level
+    let mut buf = [0u8; 2 * KYBER_SYMBYTES + Kyber1024::SECRETKEYBYTES + 16];
+    let out = encode_choice(&mut buf, choice, self.1.as_ref(), self.as_ref())
+      .and_then(|private_key| {
+        let info = PrivateKeyInfo::new(algorithm::<P>(), private_key);
+        Ok(SecretDocument::encode_msg(&info)?)
+      });
+    wipe(&mut buf);
+    out
+  }
+}
+
+impl<P: KyberParams + AssociatedOid> EncodePrivateKey for SecretKey<P> {
+  // Only the seed when it's known, as the draft recommends
+  fn to_pkcs8_der(&self) -> pkcs8::Result<SecretDocument> {
+    match self.1 {
+      Some(_) => self.to_pkcs8_der_with(PrivateKeyChoice::Seed),
+      None => self.to_pkcs8_der_with(PrivateKeyChoice::ExpandedKey)
+    }
+  }
+}
@@ -0,0 +1,210 @@
+// Keys parsed once for repeated use, caching the work every one-shot
+// call would redo from the key bytes
+use core::fmt;
+use crate::{
+  indcpa::{indcpa_expand_pk, indcpa_expand_sk, ExpandedPk, ExpandedSk},
+  kem::*,
+  params::*,
+  symmetric::hash_h,
+  types::*,
+  RngCore, CryptoRng,
+};
+#[cfg(feature = "hazmat")]
+use crate::{error::KyberError, rng::NoRng};
+#[cfg(feature = "zeroize")]
+use zeroize::ZeroizeOnDrop;
+
+/// A public key prepared for repeated encapsulation
+///
+/// Encapsulating with a [`PublicKey`] unpacks it, expands the matrix A from
+/// its seed with the XOF and hashes it on every call. This does all three
+/// once, keeping the public key vector in NTT form, the expanded matrix and
+/// `H(pk)`, so [`encapsulate`](EncapsulationKey::encapsulate) only samples
+/// noise and multiplies. Outputs are identical to [`KyberKem::encapsulate`](crate::KyberKem::encapsulate)
+/// with the same RNG. Like it the key isn't validated, see
+/// [`validate_public_key`](crate::validate_public_key).
+///
+/// The key is expanded by the backend in use when it's created and keeps
+/// using it.
+///
+/// ### Example
+/// ```
+/// # use pqc_kyber::*;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng);
+/// let server = EncapsulationKey::new(&keys.public);
+/// for _ in 0..3 {
+///   let (ct, ss) = server.encapsulate(&mut rng)?;
+///   assert_eq!(decapsulate(&ct, &keys.secret)?, ss);
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone)]
+pub struct EncapsulationKey<P: KyberParams = KyberLevel> {
+  public: PublicKey<P>,
+  expanded: ExpandedPk,
+  hash: [u8; KYBER_SYMBYTES],
+}
+
+impl<P: KyberParams> EncapsulationKey<P> {
+  /// Unpacks the public key, expands its matrix and hashes it
+  pub fn new(pk: &PublicKey<P>) -> Self {
+    let mut hash = [0u8; KYBER_SYMBYTES];
+    hash_h(&mut hash, pk.as_ref(), P::PUBLICKEYBYTES);
+    EncapsulationKey {
+      public: *pk,
+      expanded: indcpa_expand_pk::<P>(pk.as_ref()),
+      hash,
+    }
+  }
+
+  /// The public key this was created from
+  pub fn public_key(&self) -> &PublicKey<P> {
+    &self.public
+  }
+
+  /// Encapsulates to the public key returning the ciphertext
+  /// to send and the shared secret
+  pub fn encapsulate<R>(&self, rng: &mut R) -> Encapsulated<P>
+    where R: CryptoRng + RngCore
+  {
+    let mut ct = Ciphertext::<P>::zeroed();
+    let mut ss = SharedSecret::zeroed();
+    crypto_kem_enc_expanded::<P, R>(ct.0.as_mut(), &mut ss.0, &self.expanded, &self.hash, rng, None);
+    Ok((ct, ss))
+  }
+
+  /// Deterministically encapsulates with caller supplied coins,
+  /// see [`encapsulate_derand`](crate::encapsulate_derand)
+  ///
+  /// **WARNING**: the coins must be fresh secret randomness for every call.
+  #[cfg(feature = "hazmat")]
+  pub fn encapsulate_derand(&self, coins: &[u8; KYBER_SYMBYTES]) -> Encapsulated<P>
+  {
+    let mut ct = Ciphertext::<P>::zeroed();
+    let mut ss = SharedSecret::zeroed();
+    crypto_kem_enc_expanded::<P, _>(ct.0.as_mut(), &mut ss.0, &self.expanded, &self.hash, &mut NoRng, Some(coins));
+    Ok((ct, ss))
+  }
+}
+
+impl<P: KyberParams> From<&PublicKey<P>> for EncapsulationKey<P> {
+  fn from(pk: &PublicKey<P>) -> Self {
+    Self::new(pk)
+  }
+}
+
+impl<P: KyberParams> PartialEq for EncapsulationKey<P> {
+  fn eq(&self, other: &Self) -> bool {
+    self.public == other.public
+  }
+}
+
+impl<P: KyberParams> Eq for EncapsulationKey<P> {}
+
+impl<P: KyberParams> fmt::Debug for EncapsulationKey<P> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "EncapsulationKey<{}>", P::NAME)
+  }
+}
+
+/// A secret key prepared for repeated decapsulation
+///
+/// Decapsulating with a [`SecretKey`] unpacks its secret vector and, for the
+/// re-encryption check, unpacks the embedded public key and expands its
+/// matrix on every call. This does that once, `H(pk)` and the rejection
+/// value `z` are read from the secret key it holds, so
+/// [`decapsulate`](DecapsulationKey::decapsulate) only decrypts and
+/// re-encrypts. Outputs are identical to [`KyberKem::decapsulate`](crate::KyberKem::decapsulate).
+///
+/// The unpacked secret vector is wiped on drop along with the held secret
+/// key. Like [`EncapsulationKey`] it keeps the backend it was created with.
+///
+/// ### Example
+/// ```
+/// # use pqc_kyber::*;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng);
+/// let server = DecapsulationKey::new(&keys.secret);
+/// for _ in 0..3 {
+///   let (ct, ss) = encapsulate(&keys.public, &mut rng)?;
+///   assert_eq!(server.decapsulate(&ct)?, ss);
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone)]
+pub struct DecapsulationKey<P: KyberParams = KyberLevel> {
+  secret: SecretKey<P>,
+  expanded: ExpandedSk,
+  public: ExpandedPk,
+}
+
+impl<P: KyberParams> DecapsulationKey<P> {
+  /// Unpacks the secret key and expands the matrix of its public key
+  pub fn new(sk: &SecretKey<P>) -> Self {
+    let bytes = sk.as_ref();
+    let pk = &bytes[P::INDCPA_SECRETKEYBYTES..][..P::PUBLICKEYBYTES];
+    DecapsulationKey {
+      secret: sk.clone(),
+      expanded: indcpa_expand_sk::<P>(bytes),
+      public: indcpa_expand_pk::<P>(pk),
+    }
+  }
+
+  /// The secret key this was created from
+  pub fn secret_key(&self) -> &SecretKey<P> {
+    &self.secret
+  }
+
+  /// Decapsulates ciphertext, on re-encryption failure the shared secret
+  /// is the implicit rejection value as with [`KyberKem::decapsulate`](crate::KyberKem::decapsulate)
+  pub fn decapsulate(&self, ct: &Ciphertext<P>) -> Decapsulated {
+    let mut ss = SharedSecret::zeroed();
+    self.dec(&mut ss, ct);
+    Ok(ss)
+  }
+
+  /// Decapsulates ciphertext, returning `KyberError::Decapsulation` if it
+  /// fails re-encryption
+  ///
+  /// **WARNING**: revealing decapsulation failures to a peer turns the KEM 
+  /// into a chosen ciphertext oracle.
+  #[cfg(feature = "hazmat")]
+  pub fn decapsulate_explicit(&self, ct: &Ciphertext<P>) -> Decapsulated {
+    let mut ss = SharedSecret::zeroed();
+    match self.dec(&mut ss, ct) {
+      0 => Ok(ss),
+      _ => Err(KyberError::Decapsulation)
+    }
+  }
+
+  fn dec(&self, ss: &mut SharedSecret, ct: &Ciphertext<P>) -> u8 {
+    crypto_kem_dec_expanded::<P>(&mut ss.0, ct.as_ref(), self.secret.as_ref(), &self.expanded, &self.public)
+  }
+}
+
+impl<P: KyberParams> From<&SecretKey<P>> for DecapsulationKey<P> {
+  fn from(sk: &SecretKey<P>) -> Self {
+    Self::new(sk)
+  }
+}
+
+impl<P: KyberParams> PartialEq for DecapsulationKey<P> {
+  fn eq(&self, other: &Self) -> bool {
+    self.secret == other.secret
+  }
+}
+
+impl<P: KyberParams> Eq for DecapsulationKey<P> {}
+
+impl<P: KyberParams> fmt::Debug for DecapsulationKey<P> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "DecapsulationKey<{}>", P::NAME)
+  }
+}
+
+// Every secret field wipes itself on drop
+#[cfg(feature = "zeroize")]
+impl<P: KyberParams> ZeroizeOnDrop for DecapsulationKey<P> {}
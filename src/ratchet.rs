@@ -0,0 +1,324 @@
+use core::convert::TryFrom;
+use rand_core::{RngCore, CryptoRng};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use crate::{
+  api::*,
+  kex::{Established, Role},
+  params::*,
+  symmetric::kdf,
+  types::*,
+  KyberError
+};
+
+/// Longest encoding of a [`RatchetHeader`], carrying both a public key and a ciphertext
+pub const RATCHET_HEADER_MAX_BYTES: usize = 9 + KYBER_PUBLICKEYBYTES + KYBER_CIPHERTEXTBYTES;
+
+const FLAG_PUBLIC_KEY: u8 = 0x01;
+const FLAG_CIPHERTEXT: u8 = 0x02;
+
+// Prefix the inputs of each ratchet derivation
+const INIT_LABEL: &[u8; 22] = b"pqc_kyber ratchet init";
+const MIX_LABEL: &[u8; 21] = b"pqc_kyber ratchet mix";
+const MESSAGE_LABEL: &[u8; 25] = b"pqc_kyber ratchet message";
+const CHAIN_LABEL: &[u8; 23] = b"pqc_kyber ratchet chain";
+
+/// Sent alongside every ratchet message, moves the peer's receiving chain
+/// along with the sender's
+///
+/// Encodes as a flags byte, the big endian message number, then the public
+/// key and ciphertext when present.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RatchetHeader {
+  n: u64,
+  public_key: Option<PublicKey>,
+  ciphertext: Option<Ciphertext>,
+}
+
+impl RatchetHeader {
+  /// Position of the message in the sender's chain
+  pub fn message_number(&self) -> u64 {
+    self.n
+  }
+
+  /// The sender's fresh public key, if it offered one
+  pub fn public_key(&self) -> Option<&PublicKey> {
+    self.public_key.as_ref()
+  }
+
+  /// Encapsulation to the last public key the sender received, if it had one to answer
+  pub fn ciphertext(&self) -> Option<&Ciphertext> {
+    self.ciphertext.as_ref()
+  }
+
+  /// Length of the encoded header
+  pub fn encoded_len(&self) -> usize {
+    let mut len = 9;
+    if self.public_key.is_some() {
+      len += KYBER_PUBLICKEYBYTES;
+    }
+    if self.ciphertext.is_some() {
+      len += KYBER_CIPHERTEXTBYTES;
+    }
+    len
+  }
+
+  /// Writes the encoded header to `out`, returning its length
+  ///
+  /// Returns `KyberError::InvalidInput` if `out` is shorter than `encoded_len()`.
+  pub fn encode(&self, out: &mut [u8]) -> Result<usize, KyberError> {
+    let len = self.encoded_len();
+    if out.len() < len {
+      return Err(KyberError::InvalidInput)
+    }
+    let mut flags = 0;
+    let mut pos = 9;
+    if let Some(pk) = &self.public_key {
+      flags |= FLAG_PUBLIC_KEY;
+      out[pos..pos + KYBER_PUBLICKEYBYTES].copy_from_slice(pk.as_ref());
+      pos += KYBER_PUBLICKEYBYTES;
+    }
+    if let Some(ct) = &self.ciphertext {
+      flags |= FLAG_CIPHERTEXT;
+      out[pos..pos + KYBER_CIPHERTEXTBYTES].copy_from_slice(ct.as_ref());
+    }
+    out[0] = flags;
+    out[1..9].copy_from_slice(&self.n.to_be_bytes());
+    Ok(len)
+  }
+}
+
+impl TryFrom<&[u8]> for RatchetHeader {
+  type Error = KyberError;
+
+  // Rejects unknown flags and trailing bytes
+  fn try_from(bytes: &[u8]) -> Result<Self, KyberError> {
+    if bytes.len() < 9 || bytes[0] & !(FLAG_PUBLIC_KEY | FLAG_CIPHERTEXT) != 0 {
+      return Err(KyberError::InvalidInput)
+    }
+    let mut n = [0u8; 8];
+    n.copy_from_slice(&bytes[1..9]);
+    let mut header = RatchetHeader { n: u64::from_be_bytes(n), public_key: None, ciphertext: None };
+    let mut rest = &bytes[9..];
+    if bytes[0] & FLAG_PUBLIC_KEY != 0 {
+      if rest.len() < KYBER_PUBLICKEYBYTES {
+        return Err(KyberError::InvalidInput)
+      }
+      header.public_key = Some(PublicKey::try_from(&rest[..KYBER_PUBLICKEYBYTES])?);
+      rest = &rest[KYBER_PUBLICKEYBYTES..];
+    }
+    if bytes[0] & FLAG_CIPHERTEXT != 0 {
+      header.ciphertext = Some(Ciphertext::try_from(rest)?);
+      rest = &[];
+    }
+    if !rest.is_empty() {
+      return Err(KyberError::InvalidInput)
+    }
+    Ok(header)
+  }
+}
+
+/// A KEM ratchet refreshing the keys of a long lived session
+///
+/// Each party offers a fresh public key in its next message once the previous
+/// one has been answered. The peer encapsulates to it in its next message and
+/// both mix the new secret into the chain for that direction, so a session recovers
+/// once a round trip completes after a compromise. Every message also steps its chain
+/// forward, a message key can't be recomputed from a later state.
+///
+/// Headers aren't authenticated by the ratchet. Seal each message under its key with
+/// the encoded header as associated data, and on receipt only [`commit`](Ratchet::commit)
+/// the step from [`receive`](Ratchet::receive) once the message opens. A forged header
+/// is then dropped without touching the session.
+///
+/// Messages have to be received in the order they were sent. The state serializes
+/// with the `serde` feature for sessions outliving the process, never restore the
+/// same state twice.
+///
+/// ```
+/// # use pqc_kyber::*;
+/// use chacha20poly1305::{aead::{Aead, KeyInit, Payload}, ChaCha20Poly1305};
+/// # fn main() -> Result<(),KyberError> {
+/// # let mut rng = rand::thread_rng();
+/// # let bob_keys = keypair(&mut rng);
+/// # let (alice, client_init) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
+/// # let (bob, server_send) = UakeResponder::new().server_receive(client_init, &bob_keys.secret, &mut rng)?;
+/// # let alice = alice.client_confirm(server_send)?;
+/// let mut alice = Ratchet::new(&alice, Role::Initiator);
+/// let mut bob = Ratchet::new(&bob, Role::Responder);
+///
+/// // Every key is used for a single message, so a zero nonce is fine
+/// let nonce = [0u8; 12];
+/// let (header, key) = alice.send(&mut rng)?;
+/// let mut buf = [0u8; RATCHET_HEADER_MAX_BYTES];
+/// let len = header.encode(&mut buf)?;
+/// let aad = &buf[..len];
+/// let sealed = ChaCha20Poly1305::new(key.as_ref().into())
+///   .encrypt(&nonce.into(), Payload { msg: b"hello", aad })
+///   .unwrap();
+///
+/// let (step, key) = bob.receive(&header)?;
+/// let msg = ChaCha20Poly1305::new(key.as_ref().into())
+///   .decrypt(&nonce.into(), Payload { msg: &sealed, aad })
+///   .map_err(|_| KyberError::Authentication)?;
+/// bob.commit(step)?;
+/// assert_eq!(msg, b"hello");
+///
+/// // Bob's reply encapsulates to the public key Alice offered
+/// let (header, bob_key) = bob.send(&mut rng)?;
+/// assert!(header.ciphertext().is_some());
+/// let (step, alice_key) = alice.receive(&header)?;
+/// assert_eq!(alice_key, bob_key);
+/// alice.commit(step)?;
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ratchet {
+  send_chain: SharedSecret,
+  recv_chain: SharedSecret,
+  send_n: u64,
+  recv_n: u64,
+  own_keys: Option<Keypair>,
+  peer_key: Option<PublicKey>,
+}
+
+impl Ratchet {
+  /// Starts a ratchet from an established key exchange, each party passing
+  /// the role it had in the exchange
+  pub fn new(session: &Established, role: Role) -> Self {
+    let initiator = init_chain(session.shared_secret(), Role::Initiator);
+    let responder = init_chain(session.shared_secret(), Role::Responder);
+    let (send_chain, recv_chain) = match role {
+      Role::Initiator => (initiator, responder),
+      Role::Responder => (responder, initiator),
+    };
+    Ratchet { send_chain, recv_chain, send_n: 0, recv_n: 0, own_keys: None, peer_key: None }
+  }
+
+  /// Derives the key for the next outgoing message and the header to send with it
+  ///
+  /// Encapsulates to the peer's latest public key if it hasn't been answered
+  /// and offers a fresh public key if the last one was.
+  pub fn send<R>(&mut self, rng: &mut R) -> Result<(RatchetHeader, SharedSecret), KyberError>
+    where R: CryptoRng + RngCore
+  {
+    if self.send_n == u64::MAX {
+      return Err(KyberError::MessageLimit)
+    }
+    let mut header = RatchetHeader { n: self.send_n, public_key: None, ciphertext: None };
+    if let Some(pk) = &self.peer_key {
+      let (ct, ss) = encapsulate(pk, rng)?;
+      mix(&mut self.send_chain, &ss);
+      header.ciphertext = Some(ct);
+      self.peer_key = None;
+    }
+    if self.own_keys.is_none() {
+      let keys = keypair(rng);
+      header.public_key = Some(keys.public);
+      self.own_keys = Some(keys);
+    }
+    let key = step(&mut self.send_chain);
+    self.send_n += 1;
+    Ok((header, key))
+  }
+
+  /// Derives the key for the next incoming message from its header, returning
+  /// it with the step to [`commit`](Ratchet::commit) once the message is authenticated
+  ///
+  /// The ratchet is left unchanged. Returns `KyberError::OutOfOrder` if it isn't the next
+  /// message, `KyberError::InvalidPublicKey` if the offered key fails validation and
+  /// `KyberError::InvalidInput` if it answers a public key that wasn't offered. Decapsulation
+  /// uses implicit rejection, a tampered ciphertext yields a different key rather than an error.
+  pub fn receive(&self, header: &RatchetHeader) -> Result<(RatchetStep, SharedSecret), KyberError> {
+    if header.n != self.recv_n {
+      return Err(KyberError::OutOfOrder)
+    }
+    if let Some(pk) = &header.public_key {
+      validate_public_key(pk)?;
+    }
+    let mut chain = self.recv_chain.clone();
+    if let Some(ct) = &header.ciphertext {
+      let keys = self.own_keys.as_ref().ok_or(KyberError::InvalidInput)?;
+      let ss = decapsulate(ct, &keys.secret)?;
+      mix(&mut chain, &ss);
+    }
+    let key = step(&mut chain);
+    let step = RatchetStep {
+      n: header.n,
+      recv_chain: chain,
+      answered: header.ciphertext.is_some(),
+      peer_key: header.public_key,
+    };
+    Ok((step, key))
+  }
+
+  /// Applies a step from [`receive`](Ratchet::receive) after its message authenticated
+  ///
+  /// Returns `KyberError::OutOfOrder` if another message was committed since the step
+  /// was derived.
+  pub fn commit(&mut self, step: RatchetStep) -> Result<(), KyberError> {
+    if step.n != self.recv_n {
+      return Err(KyberError::OutOfOrder)
+    }
+    if step.answered {
+      self.own_keys = None;
+    }
+    if step.peer_key.is_some() {
+      self.peer_key = step.peer_key;
+    }
+    self.recv_chain = step.recv_chain;
+    self.recv_n += 1;
+    Ok(())
+  }
+}
+
+/// The changes a received message makes to a [`Ratchet`], held back until
+/// [`Ratchet::commit`] so an unauthenticated header can't alter the session
+#[derive(Debug)]
+pub struct RatchetStep {
+  n: u64,
+  recv_chain: SharedSecret,
+  answered: bool,
+  peer_key: Option<PublicKey>,
+}
+
+// Initial chain key for the messages sent by one role
+fn init_chain(ss: &SharedSecret, role: Role) -> SharedSecret {
+  let mut buf = [0u8; INIT_LABEL.len() + 1 + KYBER_SSBYTES];
+  buf[..INIT_LABEL.len()].copy_from_slice(INIT_LABEL);
+  buf[INIT_LABEL.len()] = match role {
+    Role::Initiator => 0x01,
+    Role::Responder => 0x02,
+  };
+  buf[INIT_LABEL.len() + 1..].copy_from_slice(ss.as_ref());
+  let mut chain = SharedSecret::zeroed();
+  kdf(&mut chain.0, &buf, buf.len());
+  wipe(&mut buf);
+  chain
+}
+
+// Mixes a fresh KEM secret into a chain key
+fn mix(chain: &mut SharedSecret, ss: &SharedSecret) {
+  let mut buf = [0u8; MIX_LABEL.len() + 2*KYBER_SSBYTES];
+  buf[..MIX_LABEL.len()].copy_from_slice(MIX_LABEL);
+  buf[MIX_LABEL.len()..MIX_LABEL.len() + KYBER_SSBYTES].copy_from_slice(chain.as_ref());
+  buf[MIX_LABEL.len() + KYBER_SSBYTES..].copy_from_slice(ss.as_ref());
+  kdf(&mut chain.0, &buf, buf.len());
+  wipe(&mut buf);
+}
+
+// Splits a chain key into the next message key and chain key
+fn step(chain: &mut SharedSecret) -> SharedSecret {
+  let mut key = SharedSecret::zeroed();
+  let mut buf = [0u8; MESSAGE_LABEL.len() + KYBER_SSBYTES];
+  buf[..MESSAGE_LABEL.len()].copy_from_slice(MESSAGE_LABEL);
+  buf[MESSAGE_LABEL.len()..].copy_from_slice(chain.as_ref());
+  kdf(&mut key.0, &buf, buf.len());
+  buf[..CHAIN_LABEL.len()].copy_from_slice(CHAIN_LABEL);
+  buf[CHAIN_LABEL.len()..CHAIN_LABEL.len() + KYBER_SSBYTES].copy_from_slice(chain.as_ref());
+  kdf(&mut chain.0, &buf, CHAIN_LABEL.len() + KYBER_SSBYTES);
+  wipe(&mut buf);
+  key
+}
@@ -364,7 +364,7 @@ fn br_aes_ct64_keysched(comp_skey: &mut[u64], key: &[u8])
 
   let nk = key_len >> 2;
   let nkf = (14 + 1) << 2;
-  br_range_dec32le(&mut skey, (key_len >> 2) as usize, key);
+  br_range_dec32le(&mut skey, key_len >> 2, key);
   let mut tmp = skey[(key_len >> 2) - 1];
   for i in nk..nkf {
     if j == 0 {
@@ -508,7 +508,7 @@ fn inc4_be(x: u32) -> u32
 fn aes_ctr4x(out: &mut [u8], ivw: &mut [u32], sk_exp: &[u64])
 {
   let mut w = [0u32; 16];
-  w.copy_from_slice(&ivw);
+  w.copy_from_slice(ivw);
   let mut q = [0u64; 8];
   let (q0, q1) = q.split_at_mut(4);
   for i in 0..4 {
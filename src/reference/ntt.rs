@@ -1,4 +1,4 @@
-use crate::reduce::*;
+use super::reduce::*;
 
 // Code to generate zetas used in the number-theoretic transform:
 //
@@ -1,9 +1,33 @@
-use rand_core::*;
-
-// Fills buffer x with len bytes, RNG must satisfy the 
-// RngCore trait and CryptoRng marker trait requirements
-pub fn randombytes<R>(x: &mut [u8], len: usize, rng: &mut R)
-  where R: RngCore + CryptoRng,
-{
-  rng.fill_bytes(&mut x[..len]);
-}
+use rand_core::*;
+
+// Fills buffer x with len bytes, RNG must satisfy the 
+// RngCore trait and CryptoRng marker trait requirements
+pub fn randombytes<R>(x: &mut [u8], len: usize, rng: &mut R)
+  where R: RngCore + CryptoRng,
+{
+  rng.fill_bytes(&mut x[..len]);
+}
+
+// Stands in for the RNG when a seed is supplied instead,
+// every call with a seed is deterministic and never draws from it
+pub struct NoRng;
+
+impl RngCore for NoRng {
+  fn next_u32(&mut self) -> u32 {
+    unreachable!("seeded call drew randomness")
+  }
+
+  fn next_u64(&mut self) -> u64 {
+    unreachable!("seeded call drew randomness")
+  }
+
+  fn fill_bytes(&mut self, _dest: &mut [u8]) {
+    unreachable!("seeded call drew randomness")
+  }
+
+  fn try_fill_bytes(&mut self, _dest: &mut [u8]) -> Result<(), Error> {
+    unreachable!("seeded call drew randomness")
+  }
+}
+
+impl CryptoRng for NoRng {}
@@ -0,0 +1,507 @@
+//! Public key encryption of whole messages, a KEM-DEM over the Kyber KEM
+//!
+//! [`seal`] encapsulates to the recipient's public key, derives a ChaCha20Poly1305 key
+//! from the shared secret with HKDF-SHA256 and encrypts the message under it. The envelope
+//! is self-describing, a header records the format version, the KEM mode and the security
+//! level, followed by the KEM ciphertext then the AEAD ciphertext and tag:
+//!
+//! ```text
+//! version (1) | mode (1) | k (1) | KEM ciphertext | AEAD ciphertext | tag (16)
+//! ```
+//!
+//! The header and KEM ciphertext are bound into the key derivation, so a fresh key is used
+//! for every envelope. [`Envelope::parse`] rejects unknown versions, modes and levels and any
+//! length that doesn't fit the level, [`open`] also rejects envelopes sealed for another
+//! level or mode before decapsulating. The derived key is wiped once used.
+//!
+//! [`seal_multi`] encrypts one message to many public keys. A random content key encrypts
+//! the message and is wrapped for each recipient under a key derived from an encapsulation
+//! to their public key, each entry holds the KEM ciphertext and the wrapped key:
+//!
+//! ```text
+//! version (1) | mode (1) | k (1) | count (2) | entries | AEAD ciphertext | tag (16)
+//! ```
+//!
+//! Entries carry no key identifiers and a KEM ciphertext doesn't reveal the key it was made
+//! for, so a recipient learns how many others there are but not who they are. [`open_multi`]
+//! tries each entry until one unwraps the content key. Every entry is bound into the message
+//! key, an envelope with entries added, dropped or modified opens for no one.
+//!
+//! The `Vec` based functions need `std`, [`seal_into`], [`open_into`] and their multi-recipient
+//! counterparts work on caller provided buffers without it.
+//!
+//! ```
+//! # use pqc_kyber::*;
+//! # use pqc_kyber::seal::*;
+//! # fn main() -> Result<(), KyberError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = keypair(&mut rng);
+//!
+//! let mut envelope = [0u8; HEADERBYTES + KYBER_CIPHERTEXTBYTES + 9 + TAGBYTES];
+//! seal_into(&keys.public, b"hello bob", b"header", &mut envelope, &mut rng)?;
+//! assert_eq!(Envelope::parse(&envelope)?.level(), KYBER_K);
+//!
+//! let mut message = [0u8; 9];
+//! open_into(&keys.secret, &envelope, b"header", &mut message)?;
+//! assert_eq!(&message, b"hello bob");
+//! # Ok(()) }
+//! ```
+use core::convert::TryFrom;
+use chacha20poly1305::{
+  aead::{generic_array::GenericArray, AeadInPlace, KeyInit},
+  ChaCha20Poly1305, Nonce,
+};
+use hkdf::Hkdf;
+use sha2::Sha256;
+#[cfg(feature = "std")]
+use std::{vec::Vec, vec};
+use crate::{
+  api::KyberKem,
+  error::KyberError,
+  params::*,
+  types::*,
+  CryptoRng, RngCore,
+};
+
+/// Version of the envelope format written by [`seal`]
+pub const SEAL_VERSION: u8 = 1;
+/// Size in bytes of the envelope header
+pub const HEADERBYTES: usize = 3;
+/// Size in bytes of the authentication tag ending every envelope
+pub const TAGBYTES: usize = 16;
+
+/// Version of the multi-recipient envelope format written by [`seal_multi`], distinct
+/// from [`SEAL_VERSION`] so neither format parses as the other
+pub const MULTI_SEAL_VERSION: u8 = 2;
+/// Size in bytes of the multi-recipient envelope header, the single recipient
+/// header followed by a big endian recipient count
+pub const MULTI_HEADERBYTES: usize = HEADERBYTES + 2;
+/// Size in bytes of a wrapped content key, following the KEM ciphertext in each
+/// multi-recipient entry
+pub const WRAPPED_KEYBYTES: usize = KEYBYTES + TAGBYTES;
+
+const KEYBYTES: usize = 32;
+const SEAL_SALT: &[u8] = b"pqc_kyber seal v1";
+const MULTI_SALT: &[u8] = b"pqc_kyber seal multi v1";
+const WRAP_LABEL: &[u8] = b"wrap";
+const PAYLOAD_LABEL: &[u8] = b"payload";
+
+/// The KEM variant an envelope was sealed with
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum KemMode {
+  /// FIPS 203 ML-KEM
+  MlKem,
+  /// Round 3 Kyber
+  Kyber,
+  /// Round 3 Kyber in 90s mode
+  Kyber90s,
+}
+
+impl KemMode {
+  /// The mode of a parameter set in this build
+  pub fn of<P: KyberParams>() -> Self {
+    match (KYBER_90S, P::ML_KEM) {
+      (true, _) => KemMode::Kyber90s,
+      (false, true) => KemMode::MlKem,
+      (false, false) => KemMode::Kyber,
+    }
+  }
+
+  fn to_byte(self) -> u8 {
+    match self {
+      KemMode::MlKem => 0x01,
+      KemMode::Kyber => 0x02,
+      KemMode::Kyber90s => 0x03,
+    }
+  }
+}
+
+impl TryFrom<u8> for KemMode {
+  type Error = KyberError;
+
+  fn try_from(byte: u8) -> Result<Self, KyberError> {
+    match byte {
+      0x01 => Ok(KemMode::MlKem),
+      0x02 => Ok(KemMode::Kyber),
+      0x03 => Ok(KemMode::Kyber90s),
+      _ => Err(KyberError::InvalidInput),
+    }
+  }
+}
+
+/// A parsed envelope, borrowing its sections from the encoded bytes
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Envelope<'a> {
+  mode: KemMode,
+  k: usize,
+  header: &'a [u8],
+  kem_ciphertext: &'a [u8],
+  ciphertext: &'a [u8],
+  tag: &'a [u8],
+}
+
+impl<'a> Envelope<'a> {
+  /// Splits an envelope into its sections
+  ///
+  /// Returns `KyberError::InvalidInput` for an unknown version, mode or level, or if
+  /// the envelope is too short to hold the KEM ciphertext of its level and a tag.
+  pub fn parse(bytes: &'a [u8]) -> Result<Self, KyberError> {
+    let (mode, k, ct_len) = parse_header(bytes, SEAL_VERSION)?;
+    if bytes.len() < HEADERBYTES + ct_len + TAGBYTES {
+      return Err(KyberError::InvalidInput)
+    }
+    let (header, rest) = bytes.split_at(HEADERBYTES);
+    let (kem_ciphertext, rest) = rest.split_at(ct_len);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAGBYTES);
+    Ok(Envelope { mode, k, header, kem_ciphertext, ciphertext, tag })
+  }
+
+  /// The KEM variant the envelope was sealed with
+  pub fn mode(&self) -> KemMode {
+    self.mode
+  }
+
+  /// The module rank of the security level, 2, 3 or 4 for the 512, 768 and 1024 sets
+  pub fn level(&self) -> usize {
+    self.k
+  }
+
+  /// The KEM ciphertext
+  pub fn kem_ciphertext(&self) -> &'a [u8] {
+    self.kem_ciphertext
+  }
+
+  /// Length of the sealed message
+  pub fn plaintext_len(&self) -> usize {
+    self.ciphertext.len()
+  }
+}
+
+/// Length of the envelope sealing a message of `plaintext_len` bytes for the parameter set `P`
+pub fn sealed_len<P: KyberParams>(plaintext_len: usize) -> usize {
+  HEADERBYTES + P::CIPHERTEXTBYTES + plaintext_len + TAGBYTES
+}
+
+/// Seals `plaintext` to the public key into `out`, returning the envelope length
+///
+/// `aad` is authenticated but not stored, it has to be passed to [`open_into`] again.
+/// Returns `KyberError::InvalidInput` if `out` is shorter than [`sealed_len`] and
+/// `KyberError::InvalidPublicKey` if the key fails validation.
+pub fn seal_into<P, R>(pk: &PublicKey<P>, plaintext: &[u8], aad: &[u8], out: &mut [u8], rng: &mut R)
+  -> Result<usize, KyberError>
+  where P: KyberKem, R: CryptoRng + RngCore
+{
+  let len = sealed_len::<P>(plaintext.len());
+  if out.len() < len {
+    return Err(KyberError::InvalidInput)
+  }
+  let (ct, ss) = P::encapsulate_strict(pk, rng)?;
+  let (header, rest) = out[..len].split_at_mut(HEADERBYTES);
+  header[0] = SEAL_VERSION;
+  header[1] = KemMode::of::<P>().to_byte();
+  header[2] = P::K as u8;
+  let (kem_ciphertext, rest) = rest.split_at_mut(P::CIPHERTEXTBYTES);
+  kem_ciphertext.copy_from_slice(ct.as_ref());
+  let (body, tag) = rest.split_at_mut(plaintext.len());
+  body.copy_from_slice(plaintext);
+
+  let mut key = derive_key(SEAL_SALT, ss.as_ref(), &[header, kem_ciphertext]);
+  let sealed = cipher(&key).encrypt_in_place_detached(&nonce(), aad, body);
+  wipe(&mut key);
+  tag.copy_from_slice(&sealed.map_err(|_| KyberError::InvalidInput)?);
+  Ok(len)
+}
+
+/// Opens an envelope with the secret key into `out`, returning the message length
+///
+/// Returns `KyberError::InvalidInput` if the envelope fails to parse, was sealed for another
+/// level or mode, or `out` is too short, and `KyberError::Authentication` if the envelope or
+/// `aad` was modified or it was sealed to another key.
+pub fn open_into<P>(sk: &SecretKey<P>, envelope: &[u8], aad: &[u8], out: &mut [u8])
+  -> Result<usize, KyberError>
+  where P: KyberKem
+{
+  let envelope = Envelope::parse(envelope)?;
+  if envelope.mode != KemMode::of::<P>() || envelope.k != P::K {
+    return Err(KyberError::InvalidInput)
+  }
+  let len = envelope.plaintext_len();
+  if out.len() < len {
+    return Err(KyberError::InvalidInput)
+  }
+  let ct = Ciphertext::<P>::try_from(envelope.kem_ciphertext)?;
+  let ss = P::decapsulate(&ct, sk)?;
+
+  let mut key = derive_key(SEAL_SALT, ss.as_ref(), &[envelope.header, envelope.kem_ciphertext]);
+  let body = &mut out[..len];
+  body.copy_from_slice(envelope.ciphertext);
+  let opened = cipher(&key).decrypt_in_place_detached(
+    &nonce(), aad, body, GenericArray::from_slice(envelope.tag)
+  );
+  wipe(&mut key);
+  if opened.is_err() {
+    wipe(body);
+    return Err(KyberError::Authentication)
+  }
+  Ok(len)
+}
+
+/// Seals `plaintext` to the public key, see [`seal_into`]
+#[cfg(feature = "std")]
+pub fn seal<P, R>(pk: &PublicKey<P>, plaintext: &[u8], aad: &[u8], rng: &mut R)
+  -> Result<Vec<u8>, KyberError>
+  where P: KyberKem, R: CryptoRng + RngCore
+{
+  let mut out = vec![0u8; sealed_len::<P>(plaintext.len())];
+  seal_into(pk, plaintext, aad, &mut out, rng)?;
+  Ok(out)
+}
+
+/// Opens an envelope with the secret key, see [`open_into`]
+#[cfg(feature = "std")]
+pub fn open<P>(sk: &SecretKey<P>, envelope: &[u8], aad: &[u8]) -> Result<Vec<u8>, KyberError>
+  where P: KyberKem
+{
+  let len = Envelope::parse(envelope)?.plaintext_len();
+  let mut out = vec![0u8; len];
+  open_into(sk, envelope, aad, &mut out)?;
+  Ok(out)
+}
+
+/// A parsed multi-recipient envelope, borrowing its sections from the encoded bytes
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MultiEnvelope<'a> {
+  mode: KemMode,
+  k: usize,
+  ct_len: usize,
+  header: &'a [u8],
+  entries: &'a [u8],
+  ciphertext: &'a [u8],
+  tag: &'a [u8],
+}
+
+impl<'a> MultiEnvelope<'a> {
+  /// Splits a multi-recipient envelope into its sections
+  ///
+  /// Returns `KyberError::InvalidInput` for an unknown version, mode or level, no
+  /// recipients, or if the envelope is too short to hold every entry and a tag.
+  pub fn parse(bytes: &'a [u8]) -> Result<Self, KyberError> {
+    let (mode, k, ct_len) = parse_header(bytes, MULTI_SEAL_VERSION)?;
+    if bytes.len() < MULTI_HEADERBYTES {
+      return Err(KyberError::InvalidInput)
+    }
+    let count = u16::from_be_bytes([bytes[3], bytes[4]]) as usize;
+    let entries_len = count * (ct_len + WRAPPED_KEYBYTES);
+    if count == 0 || bytes.len() < MULTI_HEADERBYTES + entries_len + TAGBYTES {
+      return Err(KyberError::InvalidInput)
+    }
+    let (header, rest) = bytes.split_at(MULTI_HEADERBYTES);
+    let (entries, rest) = rest.split_at(entries_len);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAGBYTES);
+    Ok(MultiEnvelope { mode, k, ct_len, header, entries, ciphertext, tag })
+  }
+
+  /// The KEM variant the envelope was sealed with
+  pub fn mode(&self) -> KemMode {
+    self.mode
+  }
+
+  /// The module rank of the security level, 2, 3 or 4 for the 512, 768 and 1024 sets
+  pub fn level(&self) -> usize {
+    self.k
+  }
+
+  /// Number of key-wrap entries
+  pub fn recipients(&self) -> usize {
+    self.entries.len() / (self.ct_len + WRAPPED_KEYBYTES)
+  }
+
+  /// Length of the sealed message
+  pub fn plaintext_len(&self) -> usize {
+    self.ciphertext.len()
+  }
+}
+
+/// Length of the envelope sealing a message of `plaintext_len` bytes to `recipients`
+/// public keys of the parameter set `P`
+pub fn multi_sealed_len<P: KyberParams>(recipients: usize, plaintext_len: usize) -> usize {
+  MULTI_HEADERBYTES + recipients * (P::CIPHERTEXTBYTES + WRAPPED_KEYBYTES) + plaintext_len + TAGBYTES
+}
+
+/// Seals `plaintext` once to every public key in `pks` into `out`, returning the envelope length
+///
+/// A random content key encrypts the message and is wrapped for each recipient under a key
+/// derived from an encapsulation to their public key. Entries carry no key identifiers,
+/// a recipient finds theirs by trying each one, see [`open_multi_into`].
+///
+/// Returns `KyberError::InvalidInput` if there are no recipients or more than 65535, or `out` is
+/// shorter than [`multi_sealed_len`], and `KyberError::InvalidPublicKey` if a key fails validation.
+pub fn seal_multi_into<P, R>(
+  pks: &[PublicKey<P>], plaintext: &[u8], aad: &[u8], out: &mut [u8], rng: &mut R
+) -> Result<usize, KyberError>
+  where P: KyberKem, R: CryptoRng + RngCore
+{
+  let len = multi_sealed_len::<P>(pks.len(), plaintext.len());
+  if pks.is_empty() || pks.len() > u16::MAX as usize || out.len() < len {
+    return Err(KyberError::InvalidInput)
+  }
+  let mut cek = [0u8; KEYBYTES];
+  rng.fill_bytes(&mut cek);
+  let sealed = seal_multi_with(pks, &cek, plaintext, aad, &mut out[..len], rng);
+  wipe(&mut cek);
+  sealed.map(|_| len)
+}
+
+/// Opens a multi-recipient envelope with one recipient's secret key into `out`, returning
+/// the message length
+///
+/// Decapsulates each entry in turn until one unwraps the content key, opening costs grow
+/// with the number of recipients. Returns `KyberError::InvalidInput` if the envelope fails
+/// to parse, was sealed for another level or mode, or `out` is too short, and
+/// `KyberError::Authentication` if no entry is for this key or the envelope or `aad` was modified.
+pub fn open_multi_into<P>(sk: &SecretKey<P>, envelope: &[u8], aad: &[u8], out: &mut [u8])
+  -> Result<usize, KyberError>
+  where P: KyberKem
+{
+  let envelope = MultiEnvelope::parse(envelope)?;
+  if envelope.mode != KemMode::of::<P>() || envelope.k != P::K {
+    return Err(KyberError::InvalidInput)
+  }
+  let len = envelope.plaintext_len();
+  if out.len() < len {
+    return Err(KyberError::InvalidInput)
+  }
+  let mut cek = [0u8; KEYBYTES];
+  let mut found = false;
+  for entry in envelope.entries.chunks_exact(P::CIPHERTEXTBYTES + WRAPPED_KEYBYTES) {
+    if unwrap_key(sk, envelope.header, entry, &mut cek)? {
+      found = true;
+      break
+    }
+  }
+  if !found {
+    return Err(KyberError::Authentication)
+  }
+
+  let mut key = derive_key(MULTI_SALT, &cek, &[PAYLOAD_LABEL, envelope.header, envelope.entries]);
+  wipe(&mut cek);
+  let body = &mut out[..len];
+  body.copy_from_slice(envelope.ciphertext);
+  let opened = cipher(&key).decrypt_in_place_detached(
+    &nonce(), aad, body, GenericArray::from_slice(envelope.tag)
+  );
+  wipe(&mut key);
+  if opened.is_err() {
+    wipe(body);
+    return Err(KyberError::Authentication)
+  }
+  Ok(len)
+}
+
+/// Seals `plaintext` to every public key in `pks`, see [`seal_multi_into`]
+#[cfg(feature = "std")]
+pub fn seal_multi<P, R>(pks: &[PublicKey<P>], plaintext: &[u8], aad: &[u8], rng: &mut R)
+  -> Result<Vec<u8>, KyberError>
+  where P: KyberKem, R: CryptoRng + RngCore
+{
+  let mut out = vec![0u8; multi_sealed_len::<P>(pks.len(), plaintext.len())];
+  seal_multi_into(pks, plaintext, aad, &mut out, rng)?;
+  Ok(out)
+}
+
+/// Opens a multi-recipient envelope with one recipient's secret key, see [`open_multi_into`]
+#[cfg(feature = "std")]
+pub fn open_multi<P>(sk: &SecretKey<P>, envelope: &[u8], aad: &[u8]) -> Result<Vec<u8>, KyberError>
+  where P: KyberKem
+{
+  let len = MultiEnvelope::parse(envelope)?.plaintext_len();
+  let mut out = vec![0u8; len];
+  open_multi_into(sk, envelope, aad, &mut out)?;
+  Ok(out)
+}
+
+// Writes the header, an entry wrapping the content key for each recipient, then the message
+fn seal_multi_with<P, R>(
+  pks: &[PublicKey<P>], cek: &[u8; KEYBYTES], plaintext: &[u8], aad: &[u8], out: &mut [u8], rng: &mut R
+) -> Result<(), KyberError>
+  where P: KyberKem, R: CryptoRng + RngCore
+{
+  let (header, rest) = out.split_at_mut(MULTI_HEADERBYTES);
+  header[0] = MULTI_SEAL_VERSION;
+  header[1] = KemMode::of::<P>().to_byte();
+  header[2] = P::K as u8;
+  header[3..].copy_from_slice(&(pks.len() as u16).to_be_bytes());
+  let (entries, rest) = rest.split_at_mut(pks.len() * (P::CIPHERTEXTBYTES + WRAPPED_KEYBYTES));
+  for (pk, entry) in pks.iter().zip(entries.chunks_exact_mut(P::CIPHERTEXTBYTES + WRAPPED_KEYBYTES)) {
+    let (ct, ss) = P::encapsulate_strict(pk, rng)?;
+    let (kem_ciphertext, wrapped) = entry.split_at_mut(P::CIPHERTEXTBYTES);
+    kem_ciphertext.copy_from_slice(ct.as_ref());
+    let (wrapped_key, tag) = wrapped.split_at_mut(KEYBYTES);
+    wrapped_key.copy_from_slice(cek);
+    let mut key = derive_key(MULTI_SALT, ss.as_ref(), &[WRAP_LABEL, header, kem_ciphertext]);
+    let sealed = cipher(&key).encrypt_in_place_detached(&nonce(), &[], wrapped_key);
+    wipe(&mut key);
+    tag.copy_from_slice(&sealed.map_err(|_| KyberError::InvalidInput)?);
+  }
+
+  let (body, tag) = rest.split_at_mut(plaintext.len());
+  body.copy_from_slice(plaintext);
+  let mut key = derive_key(MULTI_SALT, cek, &[PAYLOAD_LABEL, header, entries]);
+  let sealed = cipher(&key).encrypt_in_place_detached(&nonce(), aad, body);
+  wipe(&mut key);
+  tag.copy_from_slice(&sealed.map_err(|_| KyberError::InvalidInput)?);
+  Ok(())
+}
+
+// Tries to unwrap the content key from one entry, implicit rejection turns
+// entries for other recipients into an authentication failure
+fn unwrap_key<P: KyberKem>(sk: &SecretKey<P>, header: &[u8], entry: &[u8], cek: &mut [u8; KEYBYTES])
+  -> Result<bool, KyberError>
+{
+  let (kem_ciphertext, wrapped) = entry.split_at(P::CIPHERTEXTBYTES);
+  let ct = Ciphertext::<P>::try_from(kem_ciphertext)?;
+  let ss = P::decapsulate(&ct, sk)?;
+  let mut key = derive_key(MULTI_SALT, ss.as_ref(), &[WRAP_LABEL, header, kem_ciphertext]);
+  cek.copy_from_slice(&wrapped[..KEYBYTES]);
+  let opened = cipher(&key).decrypt_in_place_detached(
+    &nonce(), &[], cek, GenericArray::from_slice(&wrapped[KEYBYTES..])
+  );
+  wipe(&mut key);
+  if opened.is_err() {
+    wipe(cek);
+  }
+  Ok(opened.is_ok())
+}
+
+// Mode, level and KEM ciphertext length from the header shared by both formats
+fn parse_header(bytes: &[u8], version: u8) -> Result<(KemMode, usize, usize), KyberError> {
+  if bytes.len() < HEADERBYTES || bytes[0] != version {
+    return Err(KyberError::InvalidInput)
+  }
+  let mode = KemMode::try_from(bytes[1])?;
+  let k = bytes[2] as usize;
+  let ct_len = match k {
+    2 => Kyber512::CIPHERTEXTBYTES,
+    3 => Kyber768::CIPHERTEXTBYTES,
+    4 => Kyber1024::CIPHERTEXTBYTES,
+    _ => return Err(KyberError::InvalidInput),
+  };
+  Ok((mode, k, ct_len))
+}
+
+// AEAD key bound to the envelope sections in info
+fn derive_key(salt: &[u8], ikm: &[u8], info: &[&[u8]]) -> [u8; KEYBYTES] {
+  let hkdf = Hkdf::<Sha256>::new(Some(salt), ikm);
+  let mut key = [0u8; KEYBYTES];
+  hkdf.expand_multi_info(info, &mut key).expect("output within HKDF limits");
+  key
+}
+
+fn cipher(key: &[u8; KEYBYTES]) -> ChaCha20Poly1305 {
+  ChaCha20Poly1305::new(GenericArray::from_slice(key))
+}
+
+// Every key seals a single message, the nonce can stay fixed
+fn nonce() -> Nonce {
+  Nonce::default()
+}
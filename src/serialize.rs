@@ -0,0 +1,187 @@
+// Serde support for the byte array types.
+//
+// Human readable formats get lowercase hex strings, binary formats get the
+// raw bytes. Deserializing checks the length for the type's security level.
+use core::{
+  convert::TryFrom,
+  fmt,
+  marker::PhantomData,
+};
+use serde::{
+  de::{self, SeqAccess, Unexpected, Visitor},
+  Deserialize, Deserializer, Serialize, Serializer,
+};
+use crate::{
+  kex::*,
+  params::*,
+  types::*,
+};
+#[cfg(all(feature = "xwing", not(feature = "90s")))]
+use crate::xwing::{
+  Ciphertext as XWingCiphertext, PublicKey as XWingPublicKey,
+  SecretKey as XWingSecretKey, XWING_CIPHERTEXTBYTES, XWING_PUBLICKEYBYTES,
+  XWING_SECRETKEYBYTES,
+};
+
+// Longest encoding of any type, decoding goes through a stack buffer
+const MAX_BYTES: usize = Kyber1024::SECRETKEYBYTES;
+
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for b in self.0 {
+      write!(f, "{:02x}", b)?;
+    }
+    Ok(())
+  }
+}
+
+fn serialize_bytes<S: Serializer>(bytes: &[u8], serializer: S)
+  -> Result<S::Ok, S::Error>
+{
+  if serializer.is_human_readable() {
+    serializer.collect_str(&Hex(bytes))
+  } else {
+    serializer.serialize_bytes(bytes)
+  }
+}
+
+fn deserialize_bytes<'de, D, T>(deserializer: D, len: usize) -> Result<T, D::Error>
+  where D: Deserializer<'de>, T: for<'a> TryFrom<&'a [u8]>
+{
+  let visitor = BytesVisitor { len, marker: PhantomData };
+  if deserializer.is_human_readable() {
+    deserializer.deserialize_str(visitor)
+  } else {
+    deserializer.deserialize_bytes(visitor)
+  }
+}
+
+fn hex_value(c: u8) -> Option<u8> {
+  match c {
+    b'0'..=b'9' => Some(c - b'0'),
+    b'a'..=b'f' => Some(c - b'a' + 10),
+    b'A'..=b'F' => Some(c - b'A' + 10),
+    _ => None
+  }
+}
+
+// Decodes hex into out, which must be half the length of the input
+fn decode_hex(hex: &[u8], out: &mut [u8]) -> bool {
+  for (pair, byte) in hex.chunks(2).zip(out.iter_mut()) {
+    match (hex_value(pair[0]), hex_value(pair[1])) {
+      (Some(hi), Some(lo)) => *byte = hi << 4 | lo,
+      _ => return false
+    }
+  }
+  true
+}
+
+struct BytesVisitor<T> {
+  len: usize,
+  marker: PhantomData<T>
+}
+
+impl<T> BytesVisitor<T>
+  where T: for<'a> TryFrom<&'a [u8]>
+{
+  fn parse<E: de::Error>(&self, bytes: &[u8]) -> Result<T, E> {
+    T::try_from(bytes).map_err(|_| E::invalid_length(bytes.len(), self))
+  }
+}
+
+impl<'de, T> Visitor<'de> for BytesVisitor<T>
+  where T: for<'a> TryFrom<&'a [u8]>
+{
+  type Value = T;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} bytes or a hex string of {} characters", self.len, 2 * self.len)
+  }
+
+  fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+    if v.len() != 2 * self.len {
+      return Err(E::invalid_length(v.len(), &self))
+    }
+    // The input may be a secret, don't echo it back in the error
+    let mut buf = [0u8; MAX_BYTES];
+    let out = if decode_hex(v.as_bytes(), &mut buf[..self.len]) {
+      self.parse(&buf[..self.len])
+    } else {
+      Err(E::invalid_value(Unexpected::Other("non-hex characters"), &self))
+    };
+    wipe(&mut buf);
+    out
+  }
+
+  fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<T, E> {
+    self.parse(v)
+  }
+
+  // For formats that encode bytes as a sequence
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<T, A::Error> {
+    let mut buf = [0u8; MAX_BYTES];
+    let mut n = 0;
+    let out = loop {
+      match seq.next_element::<u8>() {
+        Ok(Some(b)) if n < self.len => {
+          buf[n] = b;
+          n += 1;
+        },
+        Ok(Some(_)) => break Err(de::Error::invalid_length(n + 1, &self)),
+        Ok(None) => break self.parse(&buf[..n]),
+        Err(e) => break Err(e)
+      }
+    };
+    wipe(&mut buf);
+    out
+  }
+}
+
+// Implements Serialize and Deserialize for a byte array newtype,
+// with the length taken from the security level for generic types
+macro_rules! impl_serde {
+  ($name:ident<P>, $len:ident) => {
+    impl<P: KyberParams> Serialize for $name<P> {
+      fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self.as_ref(), serializer)
+      }
+    }
+
+    impl<'de, P: KyberParams> Deserialize<'de> for $name<P> {
+      fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer, P::$len)
+      }
+    }
+  };
+  ($name:ident, $len:ident) => {
+    impl Serialize for $name {
+      fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self.as_ref(), serializer)
+      }
+    }
+
+    impl<'de> Deserialize<'de> for $name {
+      fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer, $len)
+      }
+    }
+  };
+}
+
+impl_serde!(PublicKey<P>, PUBLICKEYBYTES);
+impl_serde!(SecretKey<P>, SECRETKEYBYTES);
+impl_serde!(Ciphertext<P>, CIPHERTEXTBYTES);
+impl_serde!(SharedSecret, KYBER_SSBYTES);
+impl_serde!(UakeSendInit, UAKE_INIT_BYTES);
+impl_serde!(UakeSendResponse, UAKE_RESPONSE_BYTES);
+impl_serde!(AkeSendInit, AKE_INIT_BYTES);
+impl_serde!(AkeSendResponse, AKE_RESPONSE_BYTES);
+impl_serde!(ConfirmationTag, CONFIRMATION_TAG_BYTES);
+#[cfg(all(feature = "xwing", not(feature = "90s")))]
+impl_serde!(XWingPublicKey, XWING_PUBLICKEYBYTES);
+#[cfg(all(feature = "xwing", not(feature = "90s")))]
+impl_serde!(XWingSecretKey, XWING_SECRETKEYBYTES);
+#[cfg(all(feature = "xwing", not(feature = "90s")))]
+impl_serde!(XWingCiphertext, XWING_CIPHERTEXTBYTES);
@@ -0,0 +1,213 @@
+//! Post-quantum hybrid key exchange groups for rustls
+//!
+//! [`X25519MLKEM768`] is the hybrid of ML-KEM-768 and X25519 from the TLS ECDHE-MLKEM
+//! draft, [`X25519KYBER768DRAFT00`] the earlier pairing with round 3 Kyber768 still
+//! offered by some peers. Both only negotiate in TLS 1.3. Add them ahead of the
+//! classical groups of a crypto provider:
+//!
+//! ```
+//! let mut provider = rustls::crypto::ring::default_provider();
+//! provider.kx_groups.insert(0, pqc_kyber::tls::X25519MLKEM768);
+//! ```
+//!
+//! The client share also carries a plain X25519 key that rustls can reuse when
+//! the server picks X25519 instead.
+use std::{boxed::Box, vec::Vec, convert::TryFrom};
+use rand::rngs::OsRng;
+use rustls::{
+  crypto::{ActiveKeyExchange, CompletedKeyExchange, SharedSecret, SupportedKxGroup},
+  Error, NamedGroup, PeerMisbehaved, ProtocolVersion,
+};
+use x25519_dalek::{x25519, X25519_BASEPOINT_BYTES};
+use crate::{
+  api::{KyberKem, Keypair},
+  params::*,
+  rng::randombytes,
+  types::*,
+};
+
+const X25519_BYTES: usize = 32;
+const INVALID_KEY_SHARE: Error = Error::PeerMisbehaved(PeerMisbehaved::InvalidKeyShare);
+
+/// X25519MLKEM768, key shares and secrets hold the ML-KEM-768 part first
+pub static X25519MLKEM768: &dyn SupportedKxGroup = &X25519MlKem768;
+
+/// X25519Kyber768Draft00, key shares and secrets hold the X25519 part first
+pub static X25519KYBER768DRAFT00: &dyn SupportedKxGroup = &X25519Kyber768Draft00;
+
+/// Both hybrid groups in order of preference
+pub static ALL_KX_GROUPS: &[&dyn SupportedKxGroup] = &[X25519MLKEM768, X25519KYBER768DRAFT00];
+
+/// The X25519MLKEM768 group (0x11ec) built on [`MlKem768`]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct X25519MlKem768;
+
+/// The X25519Kyber768Draft00 group (0x6399) built on round 3 [`Kyber768`]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct X25519Kyber768Draft00;
+
+// Wire layout of a hybrid group
+trait Hybrid: Send + Sync + 'static {
+  type Kem: KyberKem;
+  const NAME: NamedGroup;
+  const KEM_FIRST: bool;
+}
+
+impl Hybrid for X25519MlKem768 {
+  type Kem = MlKem768;
+  const NAME: NamedGroup = NamedGroup::X25519MLKEM768;
+  const KEM_FIRST: bool = true;
+}
+
+impl Hybrid for X25519Kyber768Draft00 {
+  type Kem = Kyber768;
+  const NAME: NamedGroup = NamedGroup::Unknown(0x6399);
+  const KEM_FIRST: bool = false;
+}
+
+// Implements the rustls group trait for a hybrid, the server
+// encapsulates to the client share instead of starting its own
+macro_rules! impl_group {
+  ($name:ident) => {
+    impl SupportedKxGroup for $name {
+      fn start(&self) -> Result<Box<dyn ActiveKeyExchange>, Error> {
+        Ok(Box::new(Active::<$name>::new()))
+      }
+
+      fn start_and_complete(&self, client_share: &[u8]) -> Result<CompletedKeyExchange, Error> {
+        encapsulate::<$name>(client_share)
+      }
+
+      fn ffdhe_group(&self) -> Option<rustls::ffdhe_groups::FfdheGroup<'static>> {
+        None
+      }
+
+      fn name(&self) -> NamedGroup {
+        <$name as Hybrid>::NAME
+      }
+
+      fn usable_for_version(&self, version: ProtocolVersion) -> bool {
+        version == ProtocolVersion::TLSv1_3
+      }
+    }
+  };
+}
+
+impl_group!(X25519MlKem768);
+impl_group!(X25519Kyber768Draft00);
+
+// Client side state between sending its share and receiving the server's
+struct Active<H: Hybrid> {
+  keys: Keypair<H::Kem>,
+  sk_x: [u8; X25519_BYTES],
+  pk_x: [u8; X25519_BYTES],
+  pub_key: Vec<u8>,
+}
+
+impl<H: Hybrid> Active<H> {
+  fn new() -> Self {
+    let keys = H::Kem::keypair(&mut OsRng);
+    let mut sk_x = [0u8; X25519_BYTES];
+    randombytes(&mut sk_x, X25519_BYTES, &mut OsRng);
+    let pk_x = x25519(sk_x, X25519_BASEPOINT_BYTES);
+    let pub_key = concat::<H>(keys.public.as_ref(), &pk_x);
+    Active { keys, sk_x, pk_x, pub_key }
+  }
+}
+
+impl<H: Hybrid> ActiveKeyExchange for Active<H> {
+  fn complete(self: Box<Self>, peer_pub_key: &[u8]) -> Result<SharedSecret, Error> {
+    let (ct, pk_x) = split::<H>(peer_pub_key, H::Kem::CIPHERTEXTBYTES)?;
+    let ct = Ciphertext::<H::Kem>::try_from(ct).map_err(|_| INVALID_KEY_SHARE)?;
+    let ss_m = H::Kem::decapsulate(&ct, &self.keys.secret).map_err(|_| INVALID_KEY_SHARE)?;
+    let mut ss_x = diffie_hellman(&self.sk_x, pk_x)?;
+    let secret = concat::<H>(ss_m.as_ref(), &ss_x);
+    wipe(&mut ss_x);
+    Ok(SharedSecret::from(secret))
+  }
+
+  fn hybrid_component(&self) -> Option<(NamedGroup, &[u8])> {
+    Some((NamedGroup::X25519, &self.pk_x))
+  }
+
+  fn complete_hybrid_component(self: Box<Self>, peer_pub_key: &[u8]) -> Result<SharedSecret, Error> {
+    let mut ss_x = diffie_hellman(&self.sk_x, peer_pub_key)?;
+    let secret = SharedSecret::from(&ss_x[..]);
+    wipe(&mut ss_x);
+    Ok(secret)
+  }
+
+  fn pub_key(&self) -> &[u8] {
+    &self.pub_key
+  }
+
+  fn ffdhe_group(&self) -> Option<rustls::ffdhe_groups::FfdheGroup<'static>> {
+    None
+  }
+
+  fn group(&self) -> NamedGroup {
+    H::NAME
+  }
+}
+
+impl<H: Hybrid> Drop for Active<H> {
+  fn drop(&mut self) {
+    wipe(&mut self.sk_x);
+  }
+}
+
+// Server side, encapsulates to the client's KEM key and answers its X25519 key
+fn encapsulate<H: Hybrid>(client_share: &[u8]) -> Result<CompletedKeyExchange, Error> {
+  let (pk, pk_x) = split::<H>(client_share, H::Kem::PUBLICKEYBYTES)?;
+  let pk = PublicKey::<H::Kem>::try_from(pk).map_err(|_| INVALID_KEY_SHARE)?;
+  H::Kem::validate_public_key(&pk).map_err(|_| INVALID_KEY_SHARE)?;
+  let (ct, ss_m) = H::Kem::encapsulate(&pk, &mut OsRng).map_err(|_| INVALID_KEY_SHARE)?;
+
+  let mut ek_x = [0u8; X25519_BYTES];
+  randombytes(&mut ek_x, X25519_BYTES, &mut OsRng);
+  let ct_x = x25519(ek_x, X25519_BASEPOINT_BYTES);
+  let ss_x = diffie_hellman(&ek_x, pk_x);
+  wipe(&mut ek_x);
+  let mut ss_x = ss_x?;
+
+  let secret = concat::<H>(ss_m.as_ref(), &ss_x);
+  wipe(&mut ss_x);
+  Ok(CompletedKeyExchange {
+    group: H::NAME,
+    pub_key: concat::<H>(ct.as_ref(), &ct_x),
+    secret: SharedSecret::from(secret),
+  })
+}
+
+// X25519 with the peer's key, rejecting the all zero output of small order points
+fn diffie_hellman(sk: &[u8; X25519_BYTES], peer: &[u8]) -> Result<[u8; X25519_BYTES], Error> {
+  let peer = <[u8; X25519_BYTES]>::try_from(peer).map_err(|_| INVALID_KEY_SHARE)?;
+  let mut ss = x25519(*sk, peer);
+  if ss.iter().fold(0u8, |acc, b| acc | b) == 0 {
+    wipe(&mut ss);
+    return Err(INVALID_KEY_SHARE)
+  }
+  Ok(ss)
+}
+
+// Joins the KEM and X25519 parts in the group's order
+fn concat<H: Hybrid>(kem: &[u8], x: &[u8]) -> Vec<u8> {
+  let (first, second) = if H::KEM_FIRST { (kem, x) } else { (x, kem) };
+  let mut out = Vec::with_capacity(first.len() + second.len());
+  out.extend_from_slice(first);
+  out.extend_from_slice(second);
+  out
+}
+
+// Splits a received share into its KEM and X25519 parts
+fn split<H: Hybrid>(share: &[u8], kem_len: usize) -> Result<(&[u8], &[u8]), Error> {
+  if share.len() != kem_len + X25519_BYTES {
+    return Err(INVALID_KEY_SHARE)
+  }
+  if H::KEM_FIRST {
+    Ok(share.split_at(kem_len))
+  } else {
+    let (x, kem) = share.split_at(X25519_BYTES);
+    Ok((kem, x))
+  }
+}
@@ -0,0 +1,300 @@
+use core::{
+  convert::TryFrom,
+  fmt,
+  hash::{Hash, Hasher},
+  sync::atomic::{compiler_fence, Ordering},
+};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+use crate::{
+  api::KyberKem,
+  error::KyberError,
+  params::*,
+  reference::verify::verify,
+};
+
+/// Result of encapsulating a public key which includes the ciphertext and shared secret
+pub type Encapsulated<P = KyberLevel> = Result<(Ciphertext<P>, SharedSecret), KyberError>;
+/// The result of  decapsulating a ciphertext which produces a shared secret when confirmed
+pub type Decapsulated = Result<SharedSecret, KyberError>;
+
+/// Kyber public key, the encapsulation key in FIPS 203
+///
+/// Created by [`keypair`](crate::keypair) or parsed from bytes, which
+/// only checks the length, see [`validate_public_key`](crate::validate_public_key)
+/// for the FIPS 203 modulus check.
+///
+/// ```
+/// # use pqc_kyber::*;
+/// # use core::convert::TryFrom;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng);
+/// let bytes = keys.public.as_ref();
+/// let public = PublicKey::try_from(bytes)?;
+/// assert_eq!(public, keys.public);
+/// assert_eq!(PublicKey::<KyberLevel>::try_from(&bytes[1..]), Err(KyberError::InvalidInput));
+/// # Ok(()) }
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct PublicKey<P: KyberParams = KyberLevel>(pub(crate) P::PublicKey);
+
+/// Kyber secret key, the decapsulation key in FIPS 203
+///
+/// Overwritten with zeroes when dropped, compared in constant time and
+/// never printed by its `Debug` implementation. Keys from [`keypair`](crate::keypair)
+/// also keep the 64 byte seed they were expanded from, parsing the expanded
+/// bytes gives a key without one.
+///
+/// ```
+/// # use pqc_kyber::*;
+/// # use core::convert::TryFrom;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng);
+/// let secret = SecretKey::try_from(keys.secret.as_ref())?;
+/// assert_eq!(secret, keys.secret);
+/// assert_eq!(format!("{:?}", secret), format!("SecretKey<{}>([REDACTED])", KyberLevel::NAME));
+/// # Ok(()) }
+/// ```
+#[derive(Clone)]
+pub struct SecretKey<P: KyberParams = KyberLevel>(pub(crate) P::SecretKey, pub(crate) Option<Seed>);
+
+// The d||z seed of FIPS 203 key generation
+pub(crate) type Seed = [u8; KYBER_SEEDBYTES];
+
+/// Kyber ciphertext, sent to the holder of the secret key
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct Ciphertext<P: KyberParams = KyberLevel>(pub(crate) P::Ciphertext);
+
+/// Kyber shared secret, the same length at every security level
+///
+/// Overwritten with zeroes when dropped, compared in constant time and
+/// never printed by its `Debug` implementation.
+///
+/// ```
+/// # use pqc_kyber::*;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = keypair(&mut rng);
+/// let (ct, shared_secret) = encapsulate(&keys.public, &mut rng)?;
+/// assert_eq!(shared_secret.as_ref().len(), KYBER_SSBYTES);
+/// assert_eq!(format!("{:?}", shared_secret), "SharedSecret([REDACTED])");
+/// # Ok(()) }
+/// ```
+#[derive(Clone)]
+pub struct SharedSecret(pub(crate) [u8; KYBER_SSBYTES]);
+
+impl<P: KyberParams> PublicKey<P> {
+  pub(crate) fn zeroed() -> Self {
+    PublicKey(P::PublicKey::zeroed())
+  }
+}
+
+impl<P: KyberParams> SecretKey<P> {
+  pub(crate) fn zeroed() -> Self {
+    SecretKey(P::SecretKey::zeroed(), None)
+  }
+
+  /// Expands a 64 byte seed into the secret key, see [`keypair_from_seed`](crate::keypair_from_seed)
+  ///
+  /// ```
+  /// # use pqc_kyber::*;
+  /// let seed = [42u8; KYBER_SEEDBYTES];
+  /// let secret = SecretKey::<Kyber768>::from_seed(&seed);
+  /// assert_eq!(secret, Kyber768::keypair_from_seed(&seed).secret);
+  /// assert_eq!(secret.to_seed(), Some(seed));
+  /// ```
+  pub fn from_seed(seed: &[u8; KYBER_SEEDBYTES]) -> Self {
+    P::keypair_from_seed(seed).secret
+  }
+
+  /// Returns the seed the key was expanded from.
+  ///
+  /// Keys from [`keypair`](crate::keypair) or a seed remember it, keys
+  /// parsed from their expanded bytes return `None`. The copy isn't wiped
+  /// on drop, that is left to the caller.
+  ///
+  /// ```
+  /// # use pqc_kyber::*;
+  /// # use core::convert::TryFrom;
+  /// # fn main() -> Result<(), KyberError> {
+  /// let mut rng = rand::thread_rng();
+  /// let keys = keypair(&mut rng);
+  /// let seed = keys.secret.to_seed().unwrap();
+  /// assert_eq!(keypair_from_seed(&seed), keys);
+  ///
+  /// let parsed = SecretKey::<KyberLevel>::try_from(keys.secret.as_ref())?;
+  /// assert_eq!(parsed.to_seed(), None);
+  /// # Ok(()) }
+  /// ```
+  pub fn to_seed(&self) -> Option<[u8; KYBER_SEEDBYTES]> {
+    self.1
+  }
+}
+
+impl<P: KyberParams> Ciphertext<P> {
+  pub(crate) fn zeroed() -> Self {
+    Ciphertext(P::Ciphertext::zeroed())
+  }
+}
+
+impl SharedSecret {
+  pub(crate) fn zeroed() -> Self {
+    SharedSecret([0u8; KYBER_SSBYTES])
+  }
+}
+
+// Implements TryFrom<&[u8]> and AsRef<[u8]> for a byte array newtype
+macro_rules! impl_bytes {
+  ($name:ident, $array:ident) => {
+    impl<P: KyberParams> TryFrom<&[u8]> for $name<P> {
+      type Error = KyberError;
+
+      fn try_from(bytes: &[u8]) -> Result<Self, KyberError> {
+        if bytes.len() != P::$array::LEN {
+          return Err(KyberError::InvalidInput)
+        }
+        let mut out = Self::zeroed();
+        out.0.as_mut().copy_from_slice(bytes);
+        Ok(out)
+      }
+    }
+
+    impl<P: KyberParams> AsRef<[u8]> for $name<P> {
+      fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+      }
+    }
+  };
+}
+
+impl_bytes!(PublicKey, PublicKey);
+impl_bytes!(SecretKey, SecretKey);
+impl_bytes!(Ciphertext, Ciphertext);
+
+impl TryFrom<&[u8]> for SharedSecret {
+  type Error = KyberError;
+
+  fn try_from(bytes: &[u8]) -> Result<Self, KyberError> {
+    if bytes.len() != KYBER_SSBYTES {
+      return Err(KyberError::InvalidInput)
+    }
+    let mut out = Self::zeroed();
+    out.0.copy_from_slice(bytes);
+    Ok(out)
+  }
+}
+
+impl AsRef<[u8]> for SharedSecret {
+  fn as_ref(&self) -> &[u8] {
+    &self.0
+  }
+}
+
+impl<P: KyberParams> Hash for PublicKey<P> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.as_ref().hash(state)
+  }
+}
+
+impl<P: KyberParams> Hash for Ciphertext<P> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.as_ref().hash(state)
+  }
+}
+
+impl<P: KyberParams> fmt::Debug for PublicKey<P> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "PublicKey<{}>({} bytes)", P::NAME, P::PUBLICKEYBYTES)
+  }
+}
+
+impl<P: KyberParams> fmt::Debug for Ciphertext<P> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Ciphertext<{}>({} bytes)", P::NAME, P::CIPHERTEXTBYTES)
+  }
+}
+
+impl<P: KyberParams> fmt::Debug for SecretKey<P> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "SecretKey<{}>([REDACTED])", P::NAME)
+  }
+}
+
+impl fmt::Debug for SharedSecret {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("SharedSecret([REDACTED])")
+  }
+}
+
+// Secrets are compared in constant time, lengths are fixed by the type.
+// The seed is determined by the expanded key so it isn't compared.
+impl<P: KyberParams> PartialEq for SecretKey<P> {
+  fn eq(&self, other: &Self) -> bool {
+    verify(self.as_ref(), other.as_ref(), P::SECRETKEYBYTES) == 0
+  }
+}
+
+impl<P: KyberParams> Eq for SecretKey<P> {}
+
+impl PartialEq for SharedSecret {
+  fn eq(&self, other: &Self) -> bool {
+    verify(&self.0, &other.0, KYBER_SSBYTES) == 0
+  }
+}
+
+impl Eq for SharedSecret {}
+
+impl<P: KyberParams> Drop for SecretKey<P> {
+  fn drop(&mut self) {
+    wipe(self.0.as_mut());
+    if let Some(seed) = self.1.as_mut() {
+      wipe(seed);
+    }
+  }
+}
+
+impl Drop for SharedSecret {
+  fn drop(&mut self) {
+    wipe(&mut self.0);
+  }
+}
+
+#[cfg(feature = "zeroize")]
+impl<P: KyberParams> Zeroize for SecretKey<P> {
+  fn zeroize(&mut self) {
+    self.0.as_mut().zeroize();
+    self.1.zeroize();
+  }
+}
+
+#[cfg(feature = "zeroize")]
+impl<P: KyberParams> ZeroizeOnDrop for SecretKey<P> {}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for SharedSecret {
+  fn zeroize(&mut self) {
+    self.0.zeroize();
+  }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for SharedSecret {}
+
+// Overwrites secret material with zeroes, the volatile writes and fence
+// stop the compiler from removing the stores as dead
+pub(crate) fn wipe(bytes: &mut [u8]) {
+  for b in bytes.iter_mut() {
+    unsafe { core::ptr::write_volatile(b, 0) };
+  }
+  compiler_fence(Ordering::SeqCst);
+}
+
+// Overwrites the coefficients of secret polynomials, see wipe
+pub(crate) fn wipe_coeffs(coeffs: &mut [i16]) {
+  for c in coeffs.iter_mut() {
+    unsafe { core::ptr::write_volatile(c, 0) };
+  }
+  compiler_fence(Ordering::SeqCst);
+}
@@ -1,9 +1,12 @@
 #![allow(non_snake_case)]
+// wasm-bindgen passes byte arrays from JS as boxed slices
+#![allow(clippy::boxed_local)]
 extern crate alloc;
 
 use super::*;
 use crate::params::*;
 use alloc::boxed::Box;
+use core::convert::TryFrom;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -11,22 +14,20 @@ pub fn keypair() -> Keys {
   let mut rng = rand::rngs::OsRng{};
   let keys = api::keypair(&mut rng);
   Keys{
-    pubkey: Box::new(keys.public),
-    secret: Box::new(keys.secret)
+    pubkey: keys.public.as_ref().into(),
+    secret: keys.secret.as_ref().into()
   }
 }
 
 #[wasm_bindgen]
 pub fn encapsulate(pk: Box<[u8]>) -> Result<Kex, JsValue> {
-  if pk.len() != KYBER_PUBLICKEYBYTES {
-    return Err(JsValue::null())
-  }
+  let pk = PublicKey::try_from(&pk[..]).map_err(|_| JsValue::null())?;
 
   let mut rng = rand::rngs::OsRng{};
   match api::encapsulate(&pk, &mut rng) {
     Ok(kex) => Ok(Kex {
-      ciphertext: Box::new(kex.0),
-      sharedSecret: Box::new(kex.1)
+      ciphertext: kex.0.as_ref().into(),
+      sharedSecret: kex.1.as_ref().into()
     }),
     Err(_) => Err(JsValue::null())
   }
@@ -34,12 +35,11 @@ pub fn encapsulate(pk: Box<[u8]>) -> Result<Kex, JsValue> {
 
 #[wasm_bindgen]
 pub fn decapsulate(ct: Box<[u8]>, sk: Box<[u8]>) -> Result<Box<[u8]>, JsValue> {
-  if ct.len() != KYBER_CIPHERTEXTBYTES || sk.len() != KYBER_SECRETKEYBYTES {
-    return Err(JsValue::null())
-  }
+  let ct = Ciphertext::try_from(&ct[..]).map_err(|_| JsValue::null())?;
+  let sk = SecretKey::try_from(&sk[..]).map_err(|_| JsValue::null())?;
 
   match api::decapsulate(&ct, &sk) {
-    Ok(ss) => Ok(Box::new(ss)),
+    Ok(ss) => Ok(ss.as_ref().into()),
     Err(_) => Err(JsValue::null())
   }
 }
@@ -0,0 +1,335 @@
+//! X-Wing hybrid KEM combining ML-KEM-768 with X25519
+//!
+//! Follows the X-Wing general purpose hybrid KEM draft (draft-connolly-cfrg-xwing-kem).
+//! The shared secret stays secure as long as either ML-KEM-768 or X25519 is unbroken.
+//! Secret keys are a 32 byte seed, both component keys are expanded from it with
+//! SHAKE256 when needed.
+//!
+//! ```
+//! # use pqc_kyber::*;
+//! # fn main() -> Result<(), KyberError> {
+//! let mut rng = rand::thread_rng();
+//! let keys = xwing::keypair(&mut rng);
+//! let (ct, ss1) = xwing::encapsulate(&keys.public, &mut rng)?;
+//! let ss2 = xwing::decapsulate(&ct, &keys.secret)?;
+//! assert_eq!(ss1, ss2);
+//! # Ok(()) }
+//! ```
+use core::{
+  convert::TryFrom,
+  fmt,
+  hash::{Hash, Hasher},
+};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use x25519_dalek::{x25519, X25519_BASEPOINT_BYTES};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+use crate::{
+  api::KyberKem,
+  error::KyberError,
+  kem::*,
+  params::*,
+  reference::{fips202::{sha3_256, shake256}, verify::verify},
+  rng::randombytes,
+  types::{wipe, SharedSecret},
+  CryptoRng, RngCore,
+};
+
+const X25519_BYTES: usize = 32;
+const MLKEM_PUBLICKEYBYTES: usize = MlKem768::PUBLICKEYBYTES;
+const MLKEM_CIPHERTEXTBYTES: usize = MlKem768::CIPHERTEXTBYTES;
+
+/// Size in bytes of the X-Wing public key, the ML-KEM-768 key followed by the X25519 key
+pub const XWING_PUBLICKEYBYTES: usize = MLKEM_PUBLICKEYBYTES + X25519_BYTES;
+/// Size in bytes of the X-Wing secret key seed
+pub const XWING_SECRETKEYBYTES: usize = 32;
+/// Size in bytes of the X-Wing ciphertext, the ML-KEM-768 ciphertext followed by the
+/// ephemeral X25519 key
+pub const XWING_CIPHERTEXTBYTES: usize = MLKEM_CIPHERTEXTBYTES + X25519_BYTES;
+/// Size in bytes of the randomness used by encapsulation
+pub const XWING_ENCAPSSEEDBYTES: usize = 64;
+
+// Domain separator appended to the combiner input
+const XWING_LABEL: &[u8; 6] = br"\.//^\";
+
+/// X-Wing public key
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct PublicKey([u8; XWING_PUBLICKEYBYTES]);
+
+/// X-Wing secret key, the 32 byte seed both component keys are expanded from
+///
+/// Overwritten with zeroes when dropped, compared in constant time and
+/// never printed by its `Debug` implementation.
+#[derive(Clone)]
+pub struct SecretKey([u8; XWING_SECRETKEYBYTES]);
+
+/// X-Wing ciphertext
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct Ciphertext([u8; XWING_CIPHERTEXTBYTES]);
+
+/// An X-Wing public/secret keypair
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Keypair {
+  pub public: PublicKey,
+  pub secret: SecretKey
+}
+
+// Implements TryFrom<&[u8]> and AsRef<[u8]> for a byte array newtype
+macro_rules! impl_bytes {
+  ($name:ident, $len:ident) => {
+    impl TryFrom<&[u8]> for $name {
+      type Error = KyberError;
+
+      fn try_from(bytes: &[u8]) -> Result<Self, KyberError> {
+        if bytes.len() != $len {
+          return Err(KyberError::InvalidInput)
+        }
+        let mut out = $name([0u8; $len]);
+        out.0.copy_from_slice(bytes);
+        Ok(out)
+      }
+    }
+
+    impl AsRef<[u8]> for $name {
+      fn as_ref(&self) -> &[u8] {
+        &self.0
+      }
+    }
+  };
+}
+
+impl_bytes!(PublicKey, XWING_PUBLICKEYBYTES);
+impl_bytes!(SecretKey, XWING_SECRETKEYBYTES);
+impl_bytes!(Ciphertext, XWING_CIPHERTEXTBYTES);
+
+impl Hash for PublicKey {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.0.hash(state)
+  }
+}
+
+impl Hash for Ciphertext {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.0.hash(state)
+  }
+}
+
+impl fmt::Debug for PublicKey {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "PublicKey<X-Wing>({} bytes)", XWING_PUBLICKEYBYTES)
+  }
+}
+
+impl fmt::Debug for Ciphertext {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Ciphertext<X-Wing>({} bytes)", XWING_CIPHERTEXTBYTES)
+  }
+}
+
+impl fmt::Debug for SecretKey {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("SecretKey<X-Wing>([REDACTED])")
+  }
+}
+
+impl PartialEq for SecretKey {
+  fn eq(&self, other: &Self) -> bool {
+    verify(&self.0, &other.0, XWING_SECRETKEYBYTES) == 0
+  }
+}
+
+impl Eq for SecretKey {}
+
+impl Drop for SecretKey {
+  fn drop(&mut self) {
+    wipe(&mut self.0);
+  }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for SecretKey {
+  fn zeroize(&mut self) {
+    self.0.zeroize();
+  }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for SecretKey {}
+
+/// Generates an X-Wing keypair
+///
+/// ```
+/// # use pqc_kyber::*;
+/// let mut rng = rand::thread_rng();
+/// let keys = xwing::keypair(&mut rng);
+/// ```
+pub fn keypair<R>(rng: &mut R) -> Keypair
+  where R: RngCore + CryptoRng
+{
+  let mut seed = [0u8; XWING_SECRETKEYBYTES];
+  randombytes(&mut seed, XWING_SECRETKEYBYTES, rng);
+  let keys = keypair_from_seed(&seed);
+  wipe(&mut seed);
+  keys
+}
+
+/// Deterministically derives an X-Wing keypair from a 32 byte seed,
+/// the seed is the secret key
+///
+/// ```
+/// # use pqc_kyber::*;
+/// let keys = xwing::keypair_from_seed(&[7u8; xwing::XWING_SECRETKEYBYTES]);
+/// assert_eq!(keys, xwing::keypair_from_seed(&[7u8; xwing::XWING_SECRETKEYBYTES]));
+/// ```
+pub fn keypair_from_seed(seed: &[u8; XWING_SECRETKEYBYTES]) -> Keypair
+{
+  let secret = SecretKey(*seed);
+  let mut public = PublicKey([0u8; XWING_PUBLICKEYBYTES]);
+  let (mlkem, mut sk_x) = expand(&secret);
+  public.0[..MLKEM_PUBLICKEYBYTES].copy_from_slice(mlkem.public.as_ref());
+  public.0[MLKEM_PUBLICKEYBYTES..].copy_from_slice(&x25519(sk_x, X25519_BASEPOINT_BYTES));
+  wipe(&mut sk_x);
+  Keypair { public, secret }
+}
+
+/// Encapsulates an X-Wing public key returning the ciphertext to send
+/// and the shared secret
+///
+/// Returns `KyberError::InvalidPublicKey` if the ML-KEM-768 key fails the
+/// FIPS 203 modulus check.
+///
+/// ```
+/// # use pqc_kyber::*;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = xwing::keypair(&mut rng);
+/// let (ciphertext, shared_secret) = xwing::encapsulate(&keys.public, &mut rng)?;
+/// # Ok(()) }
+/// ```
+pub fn encapsulate<R>(pk: &PublicKey, rng: &mut R) -> Result<(Ciphertext, SharedSecret), KyberError>
+  where R: RngCore + CryptoRng
+{
+  let mut eseed = [0u8; XWING_ENCAPSSEEDBYTES];
+  randombytes(&mut eseed, XWING_ENCAPSSEEDBYTES, rng);
+  let out = encaps(pk, &eseed);
+  wipe(&mut eseed);
+  out
+}
+
+/// Deterministically encapsulates an X-Wing public key, the first half of the
+/// seed is the ML-KEM message and the second half the ephemeral X25519 key
+///
+/// **WARNING**: the seed must be fresh secret randomness for every call.
+///
+/// ```
+/// # use pqc_kyber::*;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = xwing::keypair(&mut rng);
+/// let eseed = [3u8; xwing::XWING_ENCAPSSEEDBYTES];
+/// let (ct, ss) = xwing::encapsulate_derand(&keys.public, &eseed)?;
+/// assert_eq!(xwing::encapsulate_derand(&keys.public, &eseed)?, (ct, ss));
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "hazmat")]
+pub fn encapsulate_derand(pk: &PublicKey, eseed: &[u8; XWING_ENCAPSSEEDBYTES])
+  -> Result<(Ciphertext, SharedSecret), KyberError>
+{
+  encaps(pk, eseed)
+}
+
+/// Decapsulates an X-Wing ciphertext with a secret key
+///
+/// Like ML-KEM an invalid ciphertext results in a pseudorandom shared
+/// secret rather than an error.
+///
+/// ```
+/// # use pqc_kyber::*;
+/// # fn main() -> Result<(), KyberError> {
+/// let mut rng = rand::thread_rng();
+/// let keys = xwing::keypair(&mut rng);
+/// let (ct, ss1) = xwing::encapsulate(&keys.public, &mut rng)?;
+/// let ss2 = xwing::decapsulate(&ct, &keys.secret)?;
+/// assert_eq!(ss1, ss2);
+/// # Ok(()) }
+/// ```
+pub fn decapsulate(ct: &Ciphertext, sk: &SecretKey) -> Result<SharedSecret, KyberError>
+{
+  let (mlkem, mut sk_x) = expand(sk);
+  let (ct_m, ct_x) = ct.0.split_at(MLKEM_CIPHERTEXTBYTES);
+  let mut ct_x_bytes = [0u8; X25519_BYTES];
+  ct_x_bytes.copy_from_slice(ct_x);
+
+  let mut ss_m = [0u8; KYBER_SSBYTES];
+  crypto_kem_dec::<MlKem768>(&mut ss_m, ct_m, mlkem.secret.as_ref());
+  let mut ss_x = x25519(sk_x, ct_x_bytes);
+  let mut pk_x = x25519(sk_x, X25519_BASEPOINT_BYTES);
+
+  let mut ss = SharedSecret::zeroed();
+  combiner(&mut ss.0, &ss_m, &ss_x, ct_x, &pk_x);
+  wipe(&mut ss_m);
+  wipe(&mut ss_x);
+  wipe(&mut sk_x);
+  wipe(&mut pk_x);
+  Ok(ss)
+}
+
+// Expands the secret key seed into the ML-KEM-768 keypair and X25519 secret key
+fn expand(sk: &SecretKey) -> (crate::Keypair<MlKem768>, [u8; X25519_BYTES])
+{
+  let mut expanded = [0u8; KYBER_SEEDBYTES + X25519_BYTES];
+  shake256(&mut expanded, KYBER_SEEDBYTES + X25519_BYTES, &sk.0, XWING_SECRETKEYBYTES);
+  let mut seed = [0u8; KYBER_SEEDBYTES];
+  let mut sk_x = [0u8; X25519_BYTES];
+  seed.copy_from_slice(&expanded[..KYBER_SEEDBYTES]);
+  sk_x.copy_from_slice(&expanded[KYBER_SEEDBYTES..]);
+  let mlkem = MlKem768::keypair_from_seed(&seed);
+  wipe(&mut expanded);
+  wipe(&mut seed);
+  (mlkem, sk_x)
+}
+
+fn encaps(pk: &PublicKey, eseed: &[u8; XWING_ENCAPSSEEDBYTES])
+  -> Result<(Ciphertext, SharedSecret), KyberError>
+{
+  let (pk_m, pk_x) = pk.0.split_at(MLKEM_PUBLICKEYBYTES);
+  if crypto_kem_check_pk::<MlKem768>(pk_m) != 0 {
+    return Err(KyberError::InvalidPublicKey)
+  }
+  let mut pk_x_bytes = [0u8; X25519_BYTES];
+  pk_x_bytes.copy_from_slice(pk_x);
+  let mut ek_x = [0u8; X25519_BYTES];
+  ek_x.copy_from_slice(&eseed[KYBER_SYMBYTES..]);
+
+  let mut ct = Ciphertext([0u8; XWING_CIPHERTEXTBYTES]);
+  let mut ss_m = [0u8; KYBER_SSBYTES];
+  let (ct_m, ct_x) = ct.0.split_at_mut(MLKEM_CIPHERTEXTBYTES);
+  crypto_kem_enc::<MlKem768, _>(
+    ct_m, &mut ss_m, pk_m, &mut crate::rng::NoRng, Some(&eseed[..KYBER_SYMBYTES])
+  );
+  ct_x.copy_from_slice(&x25519(ek_x, X25519_BASEPOINT_BYTES));
+  let mut ss_x = x25519(ek_x, pk_x_bytes);
+
+  let mut ss = SharedSecret::zeroed();
+  combiner(&mut ss.0, &ss_m, &ss_x, ct_x, pk_x);
+  wipe(&mut ss_m);
+  wipe(&mut ss_x);
+  wipe(&mut ek_x);
+  Ok((ct, ss))
+}
+
+// SHA3-256(ss_M || ss_X || ct_X || pk_X || XWingLabel)
+fn combiner(ss: &mut [u8], ss_m: &[u8], ss_x: &[u8], ct_x: &[u8], pk_x: &[u8])
+{
+  let mut buf = [0u8; KYBER_SSBYTES + 3 * X25519_BYTES + 6];
+  let inputs = [ss_m, ss_x, ct_x, pk_x, &XWING_LABEL[..]];
+  let mut pos = 0;
+  for input in inputs.iter() {
+    buf[pos..pos + input.len()].copy_from_slice(input);
+    pos += input.len();
+  }
+  sha3_256(ss, &buf, buf.len());
+  wipe(&mut buf);
+}
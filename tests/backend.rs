@@ -0,0 +1,44 @@
+use pqc_kyber::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+// Runs a seeded keypair/encapsulate/decapsulate on the given backend
+fn transcript<P: KyberKem>(backend: Backend) -> (Keypair<P>, Keypair<P>, Ciphertext<P>, SharedSecret) {
+  set_backend(backend).unwrap();
+  let mut rng = StdRng::seed_from_u64(42);
+  let keys = P::keypair(&mut rng);
+  let (ct, ss) = P::encapsulate(&keys.public, &mut rng).unwrap();
+  assert_eq!(P::decapsulate(&ct, &keys.secret).unwrap(), ss);
+  P::validate_public_key(&keys.public).unwrap();
+  let seeded = P::keypair_from_seed(&[7u8; KYBER_SEEDBYTES]);
+  (keys, seeded, ct, ss)
+}
+
+fn compare<P: KyberKem>() {
+  let reference = transcript::<P>(Backend::Reference);
+  if Backend::Avx2.is_available() {
+    assert_eq!(transcript::<P>(Backend::Avx2), reference, "{} backends differ", P::NAME);
+  }
+}
+
+// Forcing changes global state, so everything runs in a single test
+#[test]
+fn backends_agree() {
+  assert!(Backend::Reference.is_available());
+  assert_eq!(set_backend(Backend::Avx2).is_ok(), Backend::Avx2.is_available());
+  if !Backend::Avx2.is_available() {
+    assert_eq!(set_backend(Backend::Avx2), Err(KyberError::UnsupportedBackend));
+  }
+
+  compare::<Kyber512>();
+  compare::<Kyber768>();
+  compare::<Kyber1024>();
+  #[cfg(not(feature = "90s"))]
+  {
+    compare::<MlKem512>();
+    compare::<MlKem768>();
+    compare::<MlKem1024>();
+  }
+
+  set_backend(Backend::Reference).unwrap();
+  assert_eq!(backend(), Backend::Reference);
+}
@@ -0,0 +1,103 @@
+use pqc_kyber::*;
+use rand::{rngs::StdRng, SeedableRng};
+use std::convert::TryFrom;
+
+// Batches of every remainder, one over the rayon threshold
+const SIZES: [usize; 6] = [0, 1, 3, 4, 9, 70];
+
+// Batch output must match one call per operation with the same RNG
+fn matches_loop<P: KyberKem>(n: usize) {
+  let mut rng = StdRng::seed_from_u64(n as u64);
+  let mut pks = vec![0u8; n * P::PUBLICKEYBYTES];
+  let mut sks = vec![0u8; n * P::SECRETKEYBYTES];
+  P::keypair_batch(&mut pks, &mut sks, &mut rng).unwrap();
+  let mut cts = vec![0u8; n * P::CIPHERTEXTBYTES];
+  let mut sss = vec![0u8; n * KYBER_SSBYTES];
+  let public: Vec<_> = pks.chunks(P::PUBLICKEYBYTES)
+    .map(|pk| PublicKey::<P>::try_from(pk).unwrap())
+    .collect();
+  P::encapsulate_batch(&public, &mut cts, &mut sss, &mut rng).unwrap();
+
+  let mut rng = StdRng::seed_from_u64(n as u64);
+  let keys: Vec<_> = (0..n).map(|_| P::keypair(&mut rng)).collect();
+  for (i, keys) in keys.iter().enumerate() {
+    assert_eq!(&pks[i * P::PUBLICKEYBYTES..][..P::PUBLICKEYBYTES], keys.public.as_ref());
+    assert_eq!(&sks[i * P::SECRETKEYBYTES..][..P::SECRETKEYBYTES], keys.secret.as_ref());
+  }
+  for (i, keys) in keys.iter().enumerate() {
+    let (ct, ss) = P::encapsulate(&keys.public, &mut rng).unwrap();
+    assert_eq!(&cts[i * P::CIPHERTEXTBYTES..][..P::CIPHERTEXTBYTES], ct.as_ref(), "{}", P::NAME);
+    assert_eq!(&sss[i * KYBER_SSBYTES..][..KYBER_SSBYTES], ss.as_ref(), "{}", P::NAME);
+  }
+}
+
+// One key for every ciphertext, with every other one corrupted
+// to exercise implicit rejection within a 4-way group
+fn decapsulates<P: KyberKem>(n: usize) {
+  let mut rng = StdRng::seed_from_u64(n as u64);
+  let keys = P::keypair(&mut rng);
+  let cts: Vec<_> = (0..n).map(|i| {
+    let (ct, _) = P::encapsulate(&keys.public, &mut rng).unwrap();
+    let mut bytes = ct.as_ref().to_vec();
+    bytes[0] ^= (i % 2) as u8;
+    Ciphertext::<P>::try_from(&bytes[..]).unwrap()
+  }).collect();
+  let mut sss = vec![0u8; n * KYBER_SSBYTES];
+  P::decapsulate_batch(&keys.secret, &cts, &mut sss).unwrap();
+  for (ct, ss) in cts.iter().zip(sss.chunks(KYBER_SSBYTES)) {
+    assert_eq!(P::decapsulate(ct, &keys.secret).unwrap().as_ref(), ss, "{}", P::NAME);
+  }
+}
+
+fn every_size<P: KyberKem>() {
+  for n in SIZES.iter() {
+    matches_loop::<P>(*n);
+    decapsulates::<P>(*n);
+  }
+}
+
+fn every_level() {
+  every_size::<Kyber512>();
+  every_size::<Kyber768>();
+  every_size::<Kyber1024>();
+  #[cfg(not(feature = "90s"))]
+  {
+    every_size::<MlKem512>();
+    every_size::<MlKem768>();
+    every_size::<MlKem1024>();
+  }
+}
+
+// Forcing changes global state, so both backends run in a single test
+#[test]
+fn batches_match_single_operations() {
+  set_backend(Backend::Reference).unwrap();
+  every_level();
+  if Backend::Avx2.is_available() {
+    set_backend(Backend::Avx2).unwrap();
+    every_level();
+  }
+}
+
+#[test]
+fn buffer_lengths() {
+  let mut rng = rand::thread_rng();
+  let keys = keypair(&mut rng);
+  let mut pks = [0u8; 2 * KYBER_PUBLICKEYBYTES];
+  let mut sks = [0u8; 2 * KYBER_SECRETKEYBYTES];
+  assert_eq!(keypair_batch(&mut pks[1..], &mut sks, &mut rng), Err(KyberError::InvalidInput));
+  assert_eq!(keypair_batch(&mut pks, &mut sks[1..], &mut rng), Err(KyberError::InvalidInput));
+
+  let mut cts = [0u8; 2 * KYBER_CIPHERTEXTBYTES];
+  let mut sss = [0u8; 2 * KYBER_SSBYTES];
+  let pks = [keys.public, keys.public];
+  assert_eq!(encapsulate_batch(&pks[..1], &mut cts, &mut sss, &mut rng), Err(KyberError::InvalidInput));
+  assert_eq!(encapsulate_batch(&pks, &mut cts, &mut sss[1..], &mut rng), Err(KyberError::InvalidInput));
+  encapsulate_batch(&pks, &mut cts, &mut sss, &mut rng).unwrap();
+
+  let cts: Vec<_> = cts.chunks(KYBER_CIPHERTEXTBYTES).map(|c| Ciphertext::try_from(c).unwrap()).collect();
+  let mut out = [0u8; 2 * KYBER_SSBYTES];
+  assert_eq!(decapsulate_batch(&keys.secret, &cts[..1], &mut out), Err(KyberError::InvalidInput));
+  decapsulate_batch(&keys.secret, &cts, &mut out).unwrap();
+  assert_eq!(out, sss);
+}
@@ -0,0 +1,167 @@
+#![cfg(all(feature = "channel", feature = "std"))]
+use pqc_kyber::*;
+use pqc_kyber::channel::*;
+use std::{
+  collections::VecDeque,
+  io::{self, Read, Write},
+};
+
+fn sessions() -> (Established, Established) {
+  let mut rng = rand::thread_rng();
+  let alice_keys = keypair(&mut rng);
+  let bob_keys = keypair(&mut rng);
+  let (alice, client_init) = AkeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  let (bob, server_send) = AkeResponder::new().server_receive(
+    client_init, &alice_keys.public, &bob_keys.secret, &mut rng
+  ).unwrap();
+  let alice = alice.client_confirm(server_send, &alice_keys.secret).unwrap();
+  (alice, bob)
+}
+
+fn channels(policy: RekeyPolicy) -> (SecureChannel, SecureChannel) {
+  let (alice, bob) = sessions();
+  (
+    SecureChannel::with_policy(&alice, Role::Initiator, policy),
+    SecureChannel::with_policy(&bob, Role::Responder, policy),
+  )
+}
+
+fn seal(channel: &mut SecureChannel, msg: &[u8]) -> Vec<u8> {
+  let mut record = vec![0u8; msg.len() + RECORD_OVERHEAD];
+  assert_eq!(channel.seal_record(msg, &mut record).unwrap(), record.len());
+  record
+}
+
+fn open(channel: &mut SecureChannel, record: &[u8]) -> Result<Vec<u8>, KyberError> {
+  let mut msg = vec![0u8; record.len() - RECORD_OVERHEAD];
+  let len = channel.open_record(record, &mut msg)?;
+  msg.truncate(len);
+  Ok(msg)
+}
+
+// Loopback transport, bytes written are read back in order
+#[derive(Default)]
+struct Pipe(VecDeque<u8>);
+
+impl Read for Pipe {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    self.0.read(buf)
+  }
+}
+
+impl Write for Pipe {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.0.extend(buf);
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+#[test]
+fn duplex() {
+  let (mut alice, mut bob) = channels(RekeyPolicy::default());
+  for i in 0..4u8 {
+    let record = seal(&mut alice, &[i; 100]);
+    assert_eq!(open(&mut bob, &record).unwrap(), vec![i; 100]);
+    let record = seal(&mut bob, &[i]);
+    assert_eq!(open(&mut alice, &record).unwrap(), vec![i]);
+  }
+  let record = seal(&mut alice, b"");
+  assert_eq!(open(&mut bob, &record).unwrap(), b"");
+
+  // Each direction has its own key
+  let (mut alice, _) = channels(RekeyPolicy::default());
+  let record = seal(&mut alice, b"to bob");
+  assert_eq!(open(&mut alice, &record), Err(KyberError::Authentication));
+}
+
+#[test]
+fn replay_and_reorder() {
+  let (mut alice, mut bob) = channels(RekeyPolicy::default());
+  let first = seal(&mut alice, b"first");
+  let second = seal(&mut alice, b"second");
+  let third = seal(&mut alice, b"third");
+
+  assert_eq!(open(&mut bob, &second), Err(KyberError::OutOfOrder));
+  assert_eq!(open(&mut bob, &first).unwrap(), b"first");
+  assert_eq!(open(&mut bob, &first), Err(KyberError::OutOfOrder));
+
+  // Tampered records and rewritten sequence numbers fail without advancing
+  let mut bad = second.clone();
+  bad[SEQBYTES] ^= 1;
+  assert_eq!(open(&mut bob, &bad), Err(KyberError::Authentication));
+  let mut moved = third.clone();
+  moved[..SEQBYTES].copy_from_slice(&1u64.to_be_bytes());
+  assert_eq!(open(&mut bob, &moved), Err(KyberError::Authentication));
+  assert_eq!(open(&mut bob, &second).unwrap(), b"second");
+  assert_eq!(open(&mut bob, &third).unwrap(), b"third");
+}
+
+#[test]
+fn rekeying() {
+  let policy = RekeyPolicy { records: 2, bytes: 64 };
+  let (mut alice, mut bob) = channels(policy);
+  for len in [1, 1, 10, 100, 3, 0, 64] {
+    let record = seal(&mut alice, &vec![7u8; len]);
+    assert_eq!(open(&mut bob, &record).unwrap(), vec![7u8; len]);
+  }
+
+  // Peers with different policies part ways at the first rekey
+  let (alice, bob) = sessions();
+  let mut alice = SecureChannel::with_policy(&alice, Role::Initiator, policy);
+  let mut bob = SecureChannel::with_policy(&bob, Role::Responder, RekeyPolicy::default());
+  for _ in 0..2 {
+    let record = seal(&mut alice, b"ok");
+    assert_eq!(open(&mut bob, &record).unwrap(), b"ok");
+  }
+  let record = seal(&mut alice, b"rekeyed");
+  assert_eq!(open(&mut bob, &record), Err(KyberError::Authentication));
+}
+
+#[test]
+fn buffers() {
+  let (mut alice, mut bob) = channels(RekeyPolicy::default());
+  let mut short = [0u8; RECORD_OVERHEAD + 4];
+  assert_eq!(alice.seal_record(b"hello", &mut short), Err(KyberError::InvalidInput));
+  let too_long = vec![0u8; MAX_RECORD_PLAINTEXT + 1];
+  let mut record = vec![0u8; too_long.len() + RECORD_OVERHEAD];
+  assert_eq!(alice.seal_record(&too_long, &mut record), Err(KyberError::InvalidInput));
+
+  // Failed seals and opens leave the sequence numbers alone
+  let record = seal(&mut alice, b"hello");
+  let mut out = [0u8; 4];
+  assert_eq!(bob.open_record(&record, &mut out), Err(KyberError::InvalidInput));
+  assert_eq!(bob.open_record(&record[..RECORD_OVERHEAD - 1], &mut out), Err(KyberError::InvalidInput));
+  assert_eq!(open(&mut bob, &record).unwrap(), b"hello");
+}
+
+#[test]
+fn transport() {
+  let (mut alice, mut bob) = channels(RekeyPolicy::default());
+  let mut pipe = Pipe::default();
+  alice.send(&mut pipe, b"hello bob").unwrap();
+  alice.send(&mut pipe, &[1u8; 5000]).unwrap();
+  assert_eq!(bob.recv(&mut pipe).unwrap(), b"hello bob");
+  assert_eq!(bob.recv(&mut pipe).unwrap(), vec![1u8; 5000]);
+
+  bob.send(&mut pipe, b"hello alice").unwrap();
+  assert_eq!(alice.recv(&mut pipe).unwrap(), b"hello alice");
+
+  // Replayed frames surface as invalid data
+  alice.send(&mut pipe, b"once").unwrap();
+  let frame: Vec<u8> = pipe.0.iter().copied().collect();
+  assert_eq!(bob.recv(&mut pipe).unwrap(), b"once");
+  pipe.write_all(&frame).unwrap();
+  let err = bob.recv(&mut pipe).unwrap_err();
+  assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+  assert_eq!(err.get_ref().unwrap().downcast_ref::<KyberError>(), Some(&KyberError::OutOfOrder));
+
+  // Truncated frames are an io error
+  pipe.write_all(&[0, 0, 0, 40, 1, 2]).unwrap();
+  assert_eq!(bob.recv(&mut pipe).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+  pipe.write_all(&[0, 0, 0, 1]).unwrap();
+  assert_eq!(bob.recv(&mut pipe).unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,163 @@
+#![cfg(all(feature = "hpke", feature = "std", not(feature = "90s")))]
+
+// HPKE vectors generated independently with pyca/cryptography, see tests/vectors
+mod load;
+
+use pqc_kyber::*;
+use pqc_kyber::hpke::*;
+use load::*;
+use std::convert::TryFrom;
+
+struct Vector {
+  kem: String,
+  kdf: String,
+  aead: String,
+  seed: Vec<u8>,
+  info: Vec<u8>,
+  pt: Vec<u8>,
+  ct: Vec<u8>,
+}
+
+fn vectors() -> Vec<Vector> {
+  parse_vectors(include_str!("vectors/hpke.txt")).iter()
+    .map(|v| Vector {
+      kem: v.str("kem").to_string(), kdf: v.str("kdf").to_string(), aead: v.str("aead").to_string(),
+      seed: v.hex("seed"), info: v.hex("info"), pt: v.hex("pt"), ct: v.hex("ct"),
+    })
+    .collect()
+}
+
+// The vectors hold the encapsulated key followed by the sealed message
+fn open_with<K: Kem, F: Kdf, A: Aead>(sk: &K::SecretKey, v: &Vector) -> Vec<u8> {
+  let (enc, ct) = v.ct.split_at(K::NENC);
+  let enc = K::EncappedKey::try_from(enc).unwrap();
+  open_base::<K, F, A>(&enc, sk, &v.info, b"", ct).unwrap()
+}
+
+fn open_suite<K: Kem>(sk: &K::SecretKey, v: &Vector) -> Vec<u8> {
+  match (v.kdf.as_str(), v.aead.as_str()) {
+    ("HKDF-SHA256", "AES-128-GCM") => open_with::<K, HkdfSha256, Aes128Gcm>(sk, v),
+    ("HKDF-SHA384", "AES-256-GCM") => open_with::<K, HkdfSha384, Aes256Gcm>(sk, v),
+    ("HKDF-SHA512", "ChaCha20Poly1305") => open_with::<K, HkdfSha512, ChaCha20Poly1305>(sk, v),
+    suite => panic!("unknown suite {:?}", suite),
+  }
+}
+
+#[test]
+fn open_vectors() {
+  let mut count = 0;
+  for v in vectors() {
+    let pt = match v.kem.as_str() {
+      "ML-KEM-512" => {
+        let sk = SecretKey::<MlKem512>::from_seed(&<[u8; 64]>::try_from(&v.seed[..]).unwrap());
+        open_suite::<MlKem512>(&sk, &v)
+      },
+      "ML-KEM-768" => {
+        let sk = SecretKey::<MlKem768>::from_seed(&<[u8; 64]>::try_from(&v.seed[..]).unwrap());
+        open_suite::<MlKem768>(&sk, &v)
+      },
+      "ML-KEM-1024" => {
+        let sk = SecretKey::<MlKem1024>::from_seed(&<[u8; 64]>::try_from(&v.seed[..]).unwrap());
+        open_suite::<MlKem1024>(&sk, &v)
+      },
+      #[cfg(feature = "xwing")]
+      "X-Wing" => {
+        let sk = xwing::SecretKey::try_from(&v.seed[..]).unwrap();
+        open_suite::<XWing>(&sk, &v)
+      },
+      _ => continue,
+    };
+    assert_eq!(pt, v.pt);
+    count += 1;
+  }
+  assert!(count >= 9);
+}
+
+fn roundtrip<K: Kem, F: Kdf, A: Aead>() {
+  let mut rng = rand::thread_rng();
+  let (sk, pk) = K::generate_key_pair(&mut rng);
+  let pk = K::deserialize_public_key(K::serialize_public_key(&pk)).unwrap();
+  assert_eq!(K::serialize_public_key(&pk).len(), K::NPK);
+
+  let (enc, mut sender) = setup_base_sender::<K, F, A, _>(&pk, b"info", &mut rng).unwrap();
+  assert_eq!(enc.as_ref().len(), K::NENC);
+  let mut receiver = setup_base_recipient::<K, F, A>(&enc, &sk, b"info").unwrap();
+  for i in 0..3u8 {
+    let ct = sender.seal(&[i], &[i; 40]).unwrap();
+    assert_eq!(receiver.open(&[i], &ct).unwrap(), vec![i; 40]);
+  }
+
+  let (mut a, mut b) = ([0u8; 80], [0u8; 80]);
+  sender.export(b"ctx", &mut a).unwrap();
+  receiver.export(b"ctx", &mut b).unwrap();
+  assert_eq!(a, b);
+
+  // A different info string gives an unrelated context
+  let other = setup_base_recipient::<K, F, A>(&enc, &sk, b"other").unwrap();
+  other.export(b"ctx", &mut b).unwrap();
+  assert_ne!(a, b);
+}
+
+#[test]
+fn roundtrips() {
+  roundtrip::<MlKem512, HkdfSha256, Aes128Gcm>();
+  roundtrip::<MlKem768, HkdfSha384, ChaCha20Poly1305>();
+  roundtrip::<MlKem1024, HkdfSha512, Aes256Gcm>();
+  #[cfg(feature = "xwing")]
+  roundtrip::<XWing, HkdfSha256, ChaCha20Poly1305>();
+}
+
+#[test]
+fn derive_key_pair() {
+  let (sk1, pk1) = MlKem768::derive_key_pair(&[1u8; 64]);
+  let (sk2, pk2) = MlKem768::derive_key_pair(&[1u8; 64]);
+  assert_eq!((sk1, pk1), (sk2, pk2));
+  let (_, pk3) = MlKem768::derive_key_pair(&[2u8; 64]);
+  assert_ne!(pk2, pk3);
+  #[cfg(feature = "xwing")]
+  assert_eq!(XWing::derive_key_pair(b"ikm").1, XWing::derive_key_pair(b"ikm").1);
+}
+
+#[test]
+fn kem_ids() {
+  assert_eq!(MlKem512::KEM_ID, 0x0040);
+  assert_eq!(MlKem768::KEM_ID, 0x0041);
+  assert_eq!(MlKem1024::KEM_ID, 0x0042);
+  #[cfg(feature = "xwing")]
+  assert_eq!(XWing::KEM_ID, 0x647a);
+}
+
+#[test]
+fn tampering() {
+  let mut rng = rand::thread_rng();
+  let (sk, pk) = MlKem768::generate_key_pair(&mut rng);
+  let (enc, mut sender) = setup_base_sender::<MlKem768, HkdfSha256, Aes128Gcm, _>(
+    &pk, b"", &mut rng
+  ).unwrap();
+  let mut receiver = setup_base_recipient::<MlKem768, HkdfSha256, Aes128Gcm>(&enc, &sk, b"").unwrap();
+
+  let first = sender.seal(b"", b"first").unwrap();
+  let second = sender.seal(b"", b"second").unwrap();
+
+  // Modified ciphertext, wrong aad and out of order messages fail
+  let mut bad = first.clone();
+  bad[0] ^= 1;
+  assert_eq!(receiver.open(b"", &bad), Err(KyberError::Authentication));
+  assert_eq!(receiver.open(b"aad", &first), Err(KyberError::Authentication));
+  assert_eq!(receiver.open(b"", &second), Err(KyberError::Authentication));
+
+  // Failures don't advance the sequence number
+  assert_eq!(receiver.open(b"", &first).unwrap(), b"first");
+  assert_eq!(receiver.open(b"", &second).unwrap(), b"second");
+  assert_eq!(receiver.open(b"", &[0u8; 4]), Err(KyberError::InvalidInput));
+}
+
+#[test]
+fn invalid_public_key() {
+  let bytes = [0xffu8; MlKem768::PUBLICKEYBYTES];
+  assert_eq!(MlKem768::deserialize_public_key(&bytes).unwrap_err(), KyberError::InvalidPublicKey);
+  assert_eq!(MlKem768::deserialize_public_key(&bytes[1..]).unwrap_err(), KyberError::InvalidInput);
+  let mut rng = rand::thread_rng();
+  let pk = PublicKey::<MlKem768>::try_from(&bytes[..]).unwrap();
+  assert!(setup_base_sender::<MlKem768, HkdfSha256, Aes128Gcm, _>(&pk, b"", &mut rng).is_err());
+}
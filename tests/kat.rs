@@ -4,6 +4,7 @@ mod load;
 
 use pqc_kyber::*;
 use load::*;
+use std::convert::TryFrom;
 
 // Generate KAT keypairs from seeds.
 #[test]
@@ -18,7 +19,7 @@ fn keypairs() {
     let bufs = Some((&buf1[..], &buf2[..]));
     let mut pk = [0u8; KYBER_PUBLICKEYBYTES];
     let mut sk = [0u8; KYBER_SECRETKEYBYTES];
-    crypto_kem_keypair(&mut pk, &mut sk, &mut _rng, bufs);
+    crypto_kem_keypair::<KyberLevel, _>(&mut pk, &mut sk, &mut _rng, bufs);
     assert_eq!(&pk[..], &known_pk[..], "Public key KAT mismatch");
     assert_eq!(&sk[..], &known_sk[..], "Secret key KAT mismatch");
   }
@@ -36,7 +37,7 @@ fn encaps() {
     let encap_buf = Some(&buf1[..]);
     let mut ct = [0u8; KYBER_CIPHERTEXTBYTES];
     let mut ss = [0u8; KYBER_SSBYTES];
-    crypto_kem_enc(&mut ct, &mut ss, &pk, &mut _rng, encap_buf);
+    crypto_kem_enc::<KyberLevel, _>(&mut ct, &mut ss, &pk, &mut _rng, encap_buf);
     assert_eq!(&ss[..], &known_ss[..], "Shared secret KAT mismatch");
   }
 }
@@ -46,12 +47,12 @@ fn encaps() {
 fn decaps() {
   let kats = build_kats();
   for kat in kats {
-    let sk = decode_hex(&kat.sk);
-    let ct = decode_hex(&kat.ct);
+    let sk = SecretKey::try_from(&decode_hex(&kat.sk)[..]).unwrap();
+    let ct = Ciphertext::try_from(&decode_hex(&kat.ct)[..]).unwrap();
     let known_ss = decode_hex(&kat.ss);
     let decap_result = decapsulate(&ct, &sk);
     assert!(decap_result.is_ok(), "KEM decapsulation failure");
-    assert_eq!(&decap_result.unwrap()[..], &known_ss[..], "Shared secret KAT doesn't match")
+    assert_eq!(decap_result.unwrap().as_ref(), &known_ss[..], "Shared secret KAT doesn't match")
   }
 }
 
@@ -64,11 +65,3 @@ pub fn encode_hex(bytes: &[u8]) -> String {
     }
   output
 }
-
-// Decodes hex string into a vector of bytes
-pub fn decode_hex(s: &str) -> Vec<u8> {
-  (0..s.len())
-      .step_by(2)
-      .map(|i| u8::from_str_radix(&s[i..i + 2], 16).expect("Hex string decoding"))
-      .collect::<Vec<u8>>()
-}
\ No newline at end of file
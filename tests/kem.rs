@@ -1,4 +1,11 @@
 use pqc_kyber::*;
+use std::convert::TryFrom;
+
+fn flip_bit(ct: &Ciphertext) -> Ciphertext {
+  let mut bytes = ct.as_ref().to_vec();
+  bytes[0] ^= 1;
+  Ciphertext::try_from(&bytes[..]).unwrap()
+}
 
 #[test]
 fn keypair_encap_decap_kem() {
@@ -9,3 +16,65 @@ fn keypair_encap_decap_kem() {
   assert_eq!(ss1, ss2);
 }
 
+
+#[test]
+fn implicit_rejection() {
+  let mut rng = rand::thread_rng();
+  let keys = keypair(&mut rng);
+  let (ct, ss1) = encapsulate(&keys.public, &mut rng).unwrap();
+  let ct = flip_bit(&ct);
+  let ss2 = decapsulate(&ct, &keys.secret).unwrap();
+  assert_ne!(ss1, ss2);
+  // The rejection secret is deterministic
+  assert_eq!(ss2, decapsulate(&ct, &keys.secret).unwrap());
+}
+
+#[cfg(feature = "hazmat")]
+#[test]
+fn derandomized_encapsulation() {
+  let mut rng = rand::thread_rng();
+  let keys = keypair(&mut rng);
+  let (ct, ss1) = encapsulate_derand(&keys.public, &[1u8; KYBER_SYMBYTES]).unwrap();
+  assert_eq!(decapsulate(&ct, &keys.secret).unwrap(), ss1);
+  let (ct2, ss2) = encapsulate_derand(&keys.public, &[1u8; KYBER_SYMBYTES]).unwrap();
+  assert_eq!((ct, &ss1), (ct2, &ss2));
+  let (ct3, ss3) = encapsulate_derand(&keys.public, &[2u8; KYBER_SYMBYTES]).unwrap();
+  assert_ne!(ct, ct3);
+  assert_ne!(ss1, ss3);
+}
+
+#[cfg(feature = "hazmat")]
+#[test]
+fn explicit_rejection() {
+  let mut rng = rand::thread_rng();
+  let keys = keypair(&mut rng);
+  let (ct, ss1) = encapsulate(&keys.public, &mut rng).unwrap();
+  assert_eq!(decapsulate_explicit(&ct, &keys.secret), Ok(ss1));
+  let ct = flip_bit(&ct);
+  assert_eq!(decapsulate_explicit(&ct, &keys.secret), Err(KyberError::Decapsulation));
+}
+
+#[test]
+fn typed_bytes() {
+  let mut rng = rand::thread_rng();
+  let keys = keypair(&mut rng);
+  let (ct, ss) = encapsulate(&keys.public, &mut rng).unwrap();
+  assert_eq!(PublicKey::try_from(keys.public.as_ref()), Ok(keys.public));
+  assert_eq!(SecretKey::try_from(keys.secret.as_ref()).unwrap(), keys.secret);
+  assert_eq!(Ciphertext::try_from(ct.as_ref()), Ok(ct));
+  assert_eq!(SharedSecret::try_from(ss.as_ref()).unwrap(), ss);
+  assert_eq!(SharedSecret::try_from(&ss.as_ref()[1..]), Err(KyberError::InvalidInput));
+  assert_eq!(SecretKey::<KyberLevel>::try_from(&[0u8; 3][..]), Err(KyberError::InvalidInput));
+}
+
+#[test]
+fn redacted_debug() {
+  let mut rng = rand::thread_rng();
+  let keys = keypair(&mut rng);
+  let (_, ss) = encapsulate(&keys.public, &mut rng).unwrap();
+  let secret_hex: String = keys.secret.as_ref()[..8].iter().map(|b| format!("{:02x}", b)).collect();
+  let printed = format!("{:?} {:?}", keys, ss);
+  assert!(printed.contains("REDACTED"));
+  assert!(!printed.contains(&secret_hex));
+  assert!(!printed.contains(&format!("{:?}", &ss.as_ref()[..4])));
+}
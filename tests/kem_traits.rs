@@ -0,0 +1,45 @@
+#![cfg(feature = "kem")]
+use pqc_kyber::*;
+use kem::{Decapsulate, Encapsulate};
+use rand::{rngs::StdRng, SeedableRng};
+
+// Generic code that only knows the traits
+fn round_trip<E, D, EK, SS>(ek: &E, dk: &D) -> (EK, SS)
+  where E: Encapsulate<EK, SS>, D: Decapsulate<EK, SS>, SS: PartialEq + std::fmt::Debug
+{
+  let mut rng = rand::thread_rng();
+  let (ct, ss) = ek.encapsulate(&mut rng).unwrap();
+  assert_eq!(dk.decapsulate(&ct).unwrap(), ss);
+  (ct, ss)
+}
+
+// Plain and prepared keys are interchangeable and match the KyberKem functions
+fn level<P: KyberKem>() {
+  let mut rng = StdRng::seed_from_u64(11);
+  let keys = P::keypair(&mut rng);
+  let (dk, ek) = (DecapsulationKey::new(&keys.secret), EncapsulationKey::new(&keys.public));
+  round_trip(&keys.public, &keys.secret);
+  round_trip(&keys.public, &dk);
+  let (ct, ss) = round_trip(&ek, &keys.secret);
+  assert_eq!(P::decapsulate(&ct, &keys.secret).unwrap(), ss, "{}", P::NAME);
+
+  let mut seeded = StdRng::seed_from_u64(12);
+  let (generated, _) = DecapsulationKey::<P>::generate(&mut seeded);
+  let mut seeded = StdRng::seed_from_u64(12);
+  assert_eq!(generated.secret_key(), &P::keypair(&mut seeded).secret);
+  let (dk, ek) = DecapsulationKey::<P>::generate(&mut rng);
+  round_trip(&ek, &dk);
+}
+
+#[test]
+fn traits_round_trip() {
+  level::<Kyber512>();
+  level::<Kyber768>();
+  level::<Kyber1024>();
+  #[cfg(not(feature = "90s"))]
+  {
+    level::<MlKem512>();
+    level::<MlKem768>();
+    level::<MlKem1024>();
+  }
+}
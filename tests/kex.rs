@@ -1,27 +1,128 @@
 use pqc_kyber::*;
+use std::convert::TryFrom;
 
-// Kyber struct uake and ake functions
+// Kyber typestate uake and ake handshakes
 #[test]
 fn kyber_uake() {
   let mut rng = rand::thread_rng();
-  let mut alice = Uake::new();
-  let mut bob = Uake::new();
   let bob_keys = keypair(&mut rng);
-  let client_init = alice.client_init(&bob_keys.public, &mut rng);
-  let server_send = bob.server_receive(client_init, &bob_keys.secret, &mut rng).unwrap();
-  let _client_confirm = alice.client_confirm(server_send).unwrap();
-  assert_eq!(alice.shared_secret, bob.shared_secret);
+  let (alice, client_init) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  let (bob, server_send) = UakeResponder::new().server_receive(client_init, &bob_keys.secret, &mut rng).unwrap();
+  let alice = alice.client_confirm(server_send).unwrap();
+  assert_eq!(alice.shared_secret(), bob.shared_secret());
 }
 
 #[test]
 fn kyber_ake() {
   let mut rng = rand::thread_rng();
-  let mut alice = Ake::new();
-  let mut bob = Ake::new();
   let alice_keys = keypair(&mut rng);
   let bob_keys = keypair(&mut rng);
-  let client_init = alice.client_init(&bob_keys.public, &mut rng);
-  let server_send = bob.server_receive(client_init, &alice_keys.public, &bob_keys.secret, &mut rng).unwrap();
-  let _client_confirm = alice.client_confirm(server_send, &alice_keys.secret).unwrap();
-  assert_eq!(alice.shared_secret, bob.shared_secret);
+  let (alice, client_init) = AkeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  let (bob, server_send) = AkeResponder::new().server_receive(
+    client_init, &alice_keys.public, &bob_keys.secret, &mut rng
+  ).unwrap();
+  let alice = alice.client_confirm(server_send, &alice_keys.secret).unwrap();
+  assert_eq!(alice.shared_secret(), bob.shared_secret());
+}
+
+#[test]
+fn fresh_sessions() {
+  let mut rng = rand::thread_rng();
+  let bob_keys = keypair(&mut rng);
+  let (_, init1) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  let (_, init2) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  assert_ne!(init1, init2);
+  let (bob1, _) = UakeResponder::new().server_receive(init1, &bob_keys.secret, &mut rng).unwrap();
+  let (bob2, _) = UakeResponder::new().server_receive(init1, &bob_keys.secret, &mut rng).unwrap();
+  assert_ne!(bob1.shared_secret(), bob2.shared_secret());
+}
+
+#[test]
+fn wrong_keys() {
+  let mut rng = rand::thread_rng();
+  let alice_keys = keypair(&mut rng);
+  let bob_keys = keypair(&mut rng);
+  let eve_keys = keypair(&mut rng);
+
+  // Bob thinks he is talking to Eve
+  let (alice, client_init) = AkeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  let (bob, server_send) = AkeResponder::new().server_receive(
+    client_init, &eve_keys.public, &bob_keys.secret, &mut rng
+  ).unwrap();
+  let alice = alice.client_confirm(server_send, &alice_keys.secret).unwrap();
+  assert_ne!(alice.shared_secret(), bob.shared_secret());
+
+  // Alice encapsulated to Eve instead of Bob
+  let (alice, client_init) = UakeInitiator::new().client_init(&eve_keys.public, &mut rng);
+  let (bob, server_send) = UakeResponder::new().server_receive(client_init, &bob_keys.secret, &mut rng).unwrap();
+  let alice = alice.client_confirm(server_send).unwrap();
+  assert_ne!(alice.shared_secret(), bob.shared_secret());
+}
+
+#[test]
+fn key_schedules() {
+  let mut rng = rand::thread_rng();
+  let alice_keys = keypair(&mut rng);
+  let bob_keys = keypair(&mut rng);
+  assert_eq!(UakeInitiator::default().key_schedule(), KeySchedule::V1);
+  assert_eq!(AkeResponder::legacy().key_schedule(), KeySchedule::Legacy);
+
+  // Each schedule agrees with itself
+  for (a, b) in [
+    (AkeInitiator::legacy(), AkeResponder::legacy()),
+    (AkeInitiator::with_context(b"ctx"), AkeResponder::with_context(b"ctx")),
+  ] {
+    let (alice, client_init) = a.client_init(&bob_keys.public, &mut rng);
+    let (bob, server_send) = b.server_receive(
+      client_init, &alice_keys.public, &bob_keys.secret, &mut rng
+    ).unwrap();
+    let alice = alice.client_confirm(server_send, &alice_keys.secret).unwrap();
+    assert_eq!(alice.shared_secret(), bob.shared_secret());
+  }
+
+  // Mismatched schedules or contexts don't
+  for (a, b) in [
+    (UakeInitiator::legacy(), UakeResponder::new()),
+    (UakeInitiator::with_context(b"ctx"), UakeResponder::new()),
+    (UakeInitiator::with_context(b"ctx"), UakeResponder::with_context(b"other")),
+  ] {
+    let (alice, client_init) = a.client_init(&bob_keys.public, &mut rng);
+    let (bob, server_send) = b.server_receive(client_init, &bob_keys.secret, &mut rng).unwrap();
+    let alice = alice.client_confirm(server_send).unwrap();
+    assert_ne!(alice.shared_secret(), bob.shared_secret());
+  }
+}
+
+#[test]
+fn key_confirmation() {
+  let mut rng = rand::thread_rng();
+  let alice_keys = keypair(&mut rng);
+  let bob_keys = keypair(&mut rng);
+  let eve_keys = keypair(&mut rng);
+
+  let (alice, client_init) = AkeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  let (bob, server_send, server_tag) = AkeResponder::new().server_receive_with_tag(
+    client_init, &alice_keys.public, &bob_keys.secret, &mut rng
+  ).unwrap();
+  let (alice, client_tag) = alice.client_confirm_with_tag(server_send, server_tag, &alice_keys.secret).unwrap();
+  assert_ne!(server_tag, client_tag);
+  assert_eq!(format!("{:?}", bob), "AwaitingConfirmation([REDACTED])");
+  let bob = bob.server_confirm(client_tag).unwrap();
+  assert_eq!(alice.shared_secret(), bob.shared_secret());
+
+  // Without Alice's key neither side confirms
+  let (alice, client_init) = AkeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  let (bob, server_send, server_tag) = AkeResponder::new().server_receive_with_tag(
+    client_init, &alice_keys.public, &bob_keys.secret, &mut rng
+  ).unwrap();
+  let err = alice.client_confirm_with_tag(server_send, server_tag, &eve_keys.secret).unwrap_err();
+  assert_eq!(err, KyberError::Authentication);
+  let forged = ConfirmationTag::try_from(&[0u8; CONFIRMATION_TAG_BYTES][..]).unwrap();
+  assert_eq!(bob.server_confirm(forged).unwrap_err(), KyberError::Authentication);
+
+  // The initiator rejects a tag for a different response
+  let (alice, client_init) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  let (_, server_send, _) = UakeResponder::new().server_receive_with_tag(client_init, &bob_keys.secret, &mut rng).unwrap();
+  let (_, _, other_tag) = UakeResponder::new().server_receive_with_tag(client_init, &bob_keys.secret, &mut rng).unwrap();
+  assert_eq!(alice.client_confirm_with_tag(server_send, other_tag).unwrap_err(), KyberError::Authentication);
 }
@@ -0,0 +1,39 @@
+use pqc_kyber::*;
+use std::convert::TryFrom;
+
+fn roundtrip<P: KyberKem>() {
+  let mut rng = rand::thread_rng();
+  let keys = P::keypair(&mut rng);
+  let (ct, ss1) = P::encapsulate(&keys.public, &mut rng).unwrap();
+  let ss2 = P::decapsulate(&ct, &keys.secret).unwrap();
+  assert_eq!(ss1, ss2);
+  assert_eq!(keys.public.as_ref().len(), P::PUBLICKEYBYTES);
+  assert_eq!(keys.secret.as_ref().len(), P::SECRETKEYBYTES);
+  assert_eq!(ct.as_ref().len(), P::CIPHERTEXTBYTES);
+}
+
+#[test]
+fn keypair_encap_decap_all_levels() {
+  roundtrip::<Kyber512>();
+  roundtrip::<Kyber768>();
+  roundtrip::<Kyber1024>();
+}
+
+#[test]
+fn level_sizes() {
+  assert_eq!((Kyber512::PUBLICKEYBYTES, Kyber512::SECRETKEYBYTES, Kyber512::CIPHERTEXTBYTES), (800, 1632, 768));
+  assert_eq!((Kyber768::PUBLICKEYBYTES, Kyber768::SECRETKEYBYTES, Kyber768::CIPHERTEXTBYTES), (1184, 2400, 1088));
+  assert_eq!((Kyber1024::PUBLICKEYBYTES, Kyber1024::SECRETKEYBYTES, Kyber1024::CIPHERTEXTBYTES), (1568, 3168, 1568));
+  assert_eq!(KYBER_PUBLICKEYBYTES, KyberLevel::PUBLICKEYBYTES);
+}
+
+#[test]
+fn mismatched_levels() {
+  let mut rng = rand::thread_rng();
+  let keys = Kyber768::keypair(&mut rng);
+  assert_eq!(PublicKey::<Kyber512>::try_from(keys.public.as_ref()), Err(KyberError::InvalidInput));
+  assert!(SecretKey::<Kyber1024>::try_from(keys.secret.as_ref()).is_err());
+  let (ct, _) = Kyber768::encapsulate(&keys.public, &mut rng).unwrap();
+  assert_eq!(Ciphertext::<Kyber1024>::try_from(ct.as_ref()), Err(KyberError::InvalidInput));
+  assert!(Ciphertext::<Kyber768>::try_from(ct.as_ref()).is_ok());
+}
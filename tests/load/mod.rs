@@ -1,3 +1,6 @@
+// Shared by every test crate that declares `mod load`, each only uses some
+#![allow(dead_code)]
+
 use std::fs::File;
 use std::path::PathBuf;
 use std::io::{prelude::*, BufReader};
@@ -97,4 +100,47 @@ pub fn build_kats() -> Vec<Kat> {
     |c| {c.into()}
   )
   .collect::<Vec<Kat>>()
-}
\ No newline at end of file
+}
+
+// Decodes hex string into a vector of bytes
+pub fn decode_hex(s: &str) -> Vec<u8> {
+  (0..s.len())
+    .step_by(2)
+    .map(|i| u8::from_str_radix(&s[i..i + 2], 16).expect("Hex string decoding"))
+    .collect::<Vec<u8>>()
+}
+
+// One test vector of `key: value` lines, keys may repeat
+pub struct Entry(Vec<(String, String)>);
+
+impl Entry {
+  // Every value of a key in order
+  pub fn values(&self, key: &str) -> Vec<&str> {
+    self.0.iter().filter(|(k, _)| k == key).map(|(_, v)| v.as_str()).collect()
+  }
+
+  pub fn str(&self, key: &str) -> &str {
+    self.values(key).first().unwrap_or_else(|| panic!("Missing vector field {}", key))
+  }
+
+  pub fn hex(&self, key: &str) -> Vec<u8> {
+    decode_hex(self.str(key))
+  }
+
+  pub fn hex_all(&self, key: &str) -> Vec<Vec<u8>> {
+    self.values(key).into_iter().map(decode_hex).collect()
+  }
+}
+
+// Splits a vector file into entries separated by blank lines
+pub fn parse_vectors(file: &str) -> Vec<Entry> {
+  file.split("\n\n")
+    .filter(|entry| !entry.trim().is_empty())
+    .map(|entry| Entry(
+      entry.lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect()
+    ))
+    .collect()
+}
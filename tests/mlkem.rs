@@ -0,0 +1,148 @@
+#![cfg(not(feature = "90s"))]
+
+// FIPS 203 test vectors generated with OpenSSL 3.5, see tests/vectors
+mod load;
+
+use pqc_kyber::*;
+use load::*;
+use std::convert::TryFrom;
+
+// Seeds and the encapsulation message are only used by the KAT build
+#[cfg_attr(not(kyber_kat), allow(dead_code))]
+struct Vector {
+  d: Vec<u8>,
+  z: Vec<u8>,
+  ek: Vec<u8>,
+  dk: Vec<u8>,
+  m: Vec<u8>,
+  c: Vec<u8>,
+  k: Vec<u8>,
+  c_bad: Vec<u8>,
+  k_bad: Vec<u8>,
+}
+
+fn parse(file: &str) -> Vec<Vector> {
+  parse_vectors(file).iter()
+    .map(|v| Vector {
+      d: v.hex("d"), z: v.hex("z"), ek: v.hex("ek"), dk: v.hex("dk"),
+      m: v.hex("m"), c: v.hex("c"), k: v.hex("K"),
+      c_bad: v.hex("c_bad"), k_bad: v.hex("K_bad"),
+    })
+    .collect()
+}
+
+fn vectors<P: KyberParams>() -> Vec<Vector> {
+  match P::K {
+    2 => parse(include_str!("vectors/mlkem512.txt")),
+    3 => parse(include_str!("vectors/mlkem768.txt")),
+    _ => parse(include_str!("vectors/mlkem1024.txt")),
+  }
+}
+
+fn decaps<P: KyberKem>() {
+  for v in vectors::<P>() {
+    assert_eq!(v.ek.len(), P::PUBLICKEYBYTES);
+    assert_eq!(v.dk.len(), P::SECRETKEYBYTES);
+    let dk = SecretKey::<P>::try_from(&v.dk[..]).unwrap();
+    let ct = Ciphertext::<P>::try_from(&v.c[..]).unwrap();
+    let ss = P::decapsulate(&ct, &dk).unwrap();
+    assert_eq!(ss.as_ref(), &v.k[..], "{} shared secret mismatch", P::NAME);
+    let ct = Ciphertext::<P>::try_from(&v.c_bad[..]).unwrap();
+    let ss = P::decapsulate(&ct, &dk).unwrap();
+    assert_eq!(ss.as_ref(), &v.k_bad[..], "{} implicit rejection mismatch", P::NAME);
+  }
+}
+
+#[test]
+fn decapsulate_vectors() {
+  decaps::<MlKem512>();
+  decaps::<MlKem768>();
+  decaps::<MlKem1024>();
+}
+
+fn keygen<P: KyberKem>() {
+  for v in vectors::<P>() {
+    let mut seed = [0u8; KYBER_SEEDBYTES];
+    seed[..KYBER_SYMBYTES].copy_from_slice(&v.d);
+    seed[KYBER_SYMBYTES..].copy_from_slice(&v.z);
+    let keys = P::keypair_from_seed(&seed);
+    assert_eq!(keys.public.as_ref(), &v.ek[..], "{} encapsulation key mismatch", P::NAME);
+    assert_eq!(keys.secret.as_ref(), &v.dk[..], "{} decapsulation key mismatch", P::NAME);
+    assert_eq!(SecretKey::<P>::from_seed(&seed), keys.secret);
+    assert_eq!(keys.secret.to_seed(), Some(seed));
+  }
+}
+
+#[test]
+fn keygen_vectors() {
+  keygen::<MlKem512>();
+  keygen::<MlKem768>();
+  keygen::<MlKem1024>();
+}
+
+#[cfg(feature = "hazmat")]
+fn encaps<P: KyberKem>() {
+  for v in vectors::<P>() {
+    let ek = PublicKey::<P>::try_from(&v.ek[..]).unwrap();
+    let coins = <[u8; KYBER_SYMBYTES]>::try_from(&v.m[..]).unwrap();
+    let (ct, ss) = P::encapsulate_derand(&ek, &coins).unwrap();
+    assert_eq!(ct.as_ref(), &v.c[..], "{} ciphertext mismatch", P::NAME);
+    assert_eq!(ss.as_ref(), &v.k[..], "{} shared secret mismatch", P::NAME);
+  }
+}
+
+#[cfg(feature = "hazmat")]
+#[test]
+fn encapsulate_vectors() {
+  encaps::<MlKem512>();
+  encaps::<MlKem768>();
+  encaps::<MlKem1024>();
+}
+
+#[test]
+fn round3_is_not_ml_kem() {
+  let v = &vectors::<MlKem768>()[0];
+  let dk = SecretKey::<Kyber768>::try_from(&v.dk[..]).unwrap();
+  let ct = Ciphertext::<Kyber768>::try_from(&v.c[..]).unwrap();
+  let ss = Kyber768::decapsulate(&ct, &dk).unwrap();
+  assert_ne!(ss.as_ref(), &v.k[..]);
+}
+
+#[test]
+fn roundtrip() {
+  let mut rng = rand::thread_rng();
+  let keys = MlKem1024::keypair(&mut rng);
+  let (ct, ss1) = MlKem1024::encapsulate(&keys.public, &mut rng).unwrap();
+  let ss2 = MlKem1024::decapsulate(&ct, &keys.secret).unwrap();
+  assert_eq!(ss1, ss2);
+}
+
+// Deterministic key generation and encapsulation
+#[cfg(kyber_kat)]
+fn deterministic<P: KyberParams>() {
+  let mut rng = rand::thread_rng(); // placeholder
+  for v in vectors::<P>() {
+    let mut pk = vec![0u8; P::PUBLICKEYBYTES];
+    let mut sk = vec![0u8; P::SECRETKEYBYTES];
+    crypto_kem_keypair::<P, _>(&mut pk, &mut sk, &mut rng, Some((&v.d, &v.z)));
+    assert_eq!(pk, v.ek, "{} encapsulation key mismatch", P::NAME);
+    assert_eq!(sk, v.dk, "{} decapsulation key mismatch", P::NAME);
+
+    let mut ct = vec![0u8; P::CIPHERTEXTBYTES];
+    let mut ss = [0u8; KYBER_SSBYTES];
+    crypto_kem_enc::<P, _>(&mut ct, &mut ss, &pk, &mut rng, Some(&v.m));
+    assert_eq!(ct, v.c, "{} ciphertext mismatch", P::NAME);
+    assert_eq!(&ss[..], &v.k[..], "{} shared secret mismatch", P::NAME);
+
+    assert!(crypto_kem_dec_explicit::<P>(&mut ss, &v.c_bad, &sk).is_err());
+    assert_eq!(&ss[..], &v.k_bad[..], "{} implicit rejection mismatch", P::NAME);
+  }
+}
+
+#[cfg(kyber_kat)]
+#[test]
+fn deterministic_vectors() {
+  deterministic::<MlKem512>();
+  deterministic::<MlKem768>();
+  deterministic::<MlKem1024>();
+}
@@ -0,0 +1,243 @@
+#![cfg(all(feature = "noise", not(feature = "90s")))]
+
+// PQNoise vectors generated independently with OpenSSL 3.5 and pyca/cryptography, see tests/vectors
+mod load;
+
+use pqc_kyber::*;
+use pqc_kyber::noise::*;
+use load::*;
+use rand_core::{CryptoRng, RngCore};
+
+struct Vector {
+  pattern: String,
+  prologue: Vec<u8>,
+  init_static: Vec<u8>,
+  resp_static: Vec<u8>,
+  init_rng: Vec<u8>,
+  resp_rng: Vec<u8>,
+  handshake_hash: Vec<u8>,
+  messages: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+fn vectors() -> Vec<Vector> {
+  parse_vectors(include_str!("vectors/noise.txt")).iter()
+    .map(|v| Vector {
+      pattern: v.str("pattern").to_string(), prologue: v.hex("prologue"),
+      init_static: v.hex("init_static"), resp_static: v.hex("resp_static"),
+      init_rng: v.hex("init_rng"), resp_rng: v.hex("resp_rng"),
+      handshake_hash: v.hex("handshake_hash"),
+      messages: v.hex_all("payload").into_iter().zip(v.hex_all("message")).collect(),
+    })
+    .collect()
+}
+
+// Replays the randomness the generator drew for one party
+struct Replay(Vec<u8>);
+
+impl RngCore for Replay {
+  fn next_u32(&mut self) -> u32 {
+    unimplemented!()
+  }
+  fn next_u64(&mut self) -> u64 {
+    unimplemented!()
+  }
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    assert!(self.0.len() >= dest.len(), "ran out of recorded randomness");
+    let rest = self.0.split_off(dest.len());
+    dest.copy_from_slice(&self.0);
+    self.0 = rest;
+  }
+  fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+    self.fill_bytes(dest);
+    Ok(())
+  }
+}
+
+impl CryptoRng for Replay {}
+
+fn pattern(name: &str) -> HandshakePattern {
+  *[NN, NK, XX, IK, KK].iter().find(|p| p.name == name).unwrap()
+}
+
+fn static_keys(seed: &[u8]) -> Option<Keypair<MlKem768>> {
+  let mut rng = Replay(seed.to_vec());
+  (!seed.is_empty()).then(|| MlKem768::keypair(&mut rng))
+}
+
+// Static keys for both parties of a pattern
+fn parties(pattern: &HandshakePattern) -> (HandshakeState<MlKem768>, HandshakeState<MlKem768>) {
+  let mut rng = rand::thread_rng();
+  let alice = MlKem768::keypair(&mut rng);
+  let bob = MlKem768::keypair(&mut rng);
+  let alice_pk = pattern.initiator_pre.contains(&Token::S).then_some(alice.public);
+  let bob_pk = pattern.responder_pre.contains(&Token::S).then_some(bob.public);
+  (
+    HandshakeState::initiator(pattern, b"test", Some(alice), bob_pk).unwrap(),
+    HandshakeState::responder(pattern, b"test", Some(bob), alice_pk).unwrap(),
+  )
+}
+
+// Runs a handshake to completion, checking every payload arrives
+fn handshake(
+  mut alice: HandshakeState<MlKem768>, mut bob: HandshakeState<MlKem768>
+) -> (TransportState, TransportState) {
+  let mut rng = rand::thread_rng();
+  let (mut message, mut payload) = (vec![0u8; MAX_MESSAGE_LEN], vec![0u8; MAX_MESSAGE_LEN]);
+  while !alice.is_finished() {
+    let (writer, reader) = if alice.is_my_turn() { (&mut alice, &mut bob) } else { (&mut bob, &mut alice) };
+    let len = writer.write_message(b"payload", &mut message, &mut rng).unwrap();
+    let n = reader.read_message(&message[..len], &mut payload).unwrap();
+    assert_eq!(&payload[..n], b"payload");
+  }
+  assert!(bob.is_finished());
+  assert_eq!(alice.handshake_hash(), bob.handshake_hash());
+  (alice.into_transport().unwrap(), bob.into_transport().unwrap())
+}
+
+#[test]
+fn handshake_vectors() {
+  for v in vectors() {
+    let pattern = pattern(&v.pattern);
+    let init_s = static_keys(&v.init_static);
+    let resp_s = static_keys(&v.resp_static);
+    let init_rs = pattern.responder_pre.contains(&Token::S).then(|| resp_s.as_ref().unwrap().public);
+    let resp_rs = pattern.initiator_pre.contains(&Token::S).then(|| init_s.as_ref().unwrap().public);
+    let mut alice = HandshakeState::<MlKem768>::initiator(&pattern, &v.prologue, init_s, init_rs).unwrap();
+    let mut bob = HandshakeState::<MlKem768>::responder(&pattern, &v.prologue, resp_s, resp_rs).unwrap();
+    let mut alice_rng = Replay(v.init_rng.clone());
+    let mut bob_rng = Replay(v.resp_rng.clone());
+
+    let (mut message, mut payload) = (vec![0u8; MAX_MESSAGE_LEN], vec![0u8; MAX_MESSAGE_LEN]);
+    let handshake_len = pattern.messages.len();
+    for (i, (expected_payload, expected)) in v.messages[..handshake_len].iter().enumerate() {
+      let (writer, reader, rng) = match i % 2 {
+        0 => (&mut alice, &mut bob, &mut alice_rng),
+        _ => (&mut bob, &mut alice, &mut bob_rng),
+      };
+      let len = writer.write_message(expected_payload, &mut message, rng).unwrap();
+      assert_eq!(&message[..len], &expected[..], "{} message {} mismatch", v.pattern, i);
+      let n = reader.read_message(expected, &mut payload).unwrap();
+      assert_eq!(&payload[..n], &expected_payload[..]);
+    }
+    assert!(alice_rng.0.is_empty() && bob_rng.0.is_empty());
+    assert_eq!(&alice.handshake_hash()[..], &v.handshake_hash[..], "{} handshake hash mismatch", v.pattern);
+
+    let mut alice = alice.into_transport().unwrap();
+    let mut bob = bob.into_transport().unwrap();
+    let transport = &v.messages[handshake_len..];
+    let len = alice.write_message(&transport[0].0, &mut message).unwrap();
+    assert_eq!(&message[..len], &transport[0].1[..]);
+    let len = bob.write_message(&transport[1].0, &mut message).unwrap();
+    assert_eq!(&message[..len], &transport[1].1[..]);
+  }
+}
+
+#[test]
+fn every_pattern() {
+  let (mut message, mut payload) = ([0u8; 64], [0u8; 64]);
+  for pattern in [NN, NK, XX, IK, KK].iter() {
+    let (alice, bob) = parties(pattern);
+    let (mut alice, mut bob) = handshake(alice, bob);
+    assert_eq!(alice.handshake_hash(), bob.handshake_hash());
+    let len = alice.write_message(b"ping", &mut message).unwrap();
+    let n = bob.read_message(&message[..len], &mut payload).unwrap();
+    assert_eq!(&payload[..n], b"ping");
+
+    // Both ends rekey the same direction
+    bob.rekey_outgoing();
+    alice.rekey_incoming();
+    let len = bob.write_message(b"pong", &mut message).unwrap();
+    let n = alice.read_message(&message[..len], &mut payload).unwrap();
+    assert_eq!(&payload[..n], b"pong");
+  }
+  assert_eq!(UAKE, NK);
+  assert_eq!(AKE, KK);
+}
+
+#[test]
+fn learns_remote_static() {
+  let (mut alice, mut bob) = parties(&XX);
+  let mut rng = rand::thread_rng();
+  let (mut message, mut payload) = (vec![0u8; MAX_MESSAGE_LEN], vec![0u8; MAX_MESSAGE_LEN]);
+  assert!(alice.remote_static().is_none());
+  let len = alice.write_message(b"", &mut message, &mut rng).unwrap();
+  bob.read_message(&message[..len], &mut payload).unwrap();
+  let len = bob.write_message(b"", &mut message, &mut rng).unwrap();
+  alice.read_message(&message[..len], &mut payload).unwrap();
+  assert!(alice.remote_static().is_some());
+  assert!(bob.remote_static().is_none());
+}
+
+#[test]
+fn tampered_message() {
+  let mut rng = rand::thread_rng();
+  let (mut message, mut payload) = (vec![0u8; MAX_MESSAGE_LEN], vec![0u8; MAX_MESSAGE_LEN]);
+  // Patterns encapsulating to a known static key encrypt the first payload
+  for pattern in [NK, IK, KK].iter() {
+    let (mut alice, mut bob) = parties(pattern);
+    let len = alice.write_message(b"payload", &mut message, &mut rng).unwrap();
+    message[len - 1] ^= 1;
+    assert_eq!(bob.read_message(&message[..len], &mut payload), Err(KyberError::Authentication));
+    // The failed handshake can't be resumed
+    message[len - 1] ^= 1;
+    assert_eq!(bob.read_message(&message[..len], &mut payload), Err(KyberError::InvalidInput));
+    assert!(bob.into_transport().is_err());
+  }
+
+  // Tampering with a ciphertext changes the decapsulated key and fails the payload
+  let (mut alice, mut bob) = parties(&NN);
+  let len = alice.write_message(b"", &mut message, &mut rng).unwrap();
+  bob.read_message(&message[..len], &mut payload).unwrap();
+  let len = bob.write_message(b"", &mut message, &mut rng).unwrap();
+  message[0] ^= 1;
+  assert_eq!(alice.read_message(&message[..len], &mut payload), Err(KyberError::Authentication));
+  assert!(alice.read_message(&message[..len - 1], &mut payload).is_err());
+}
+
+#[test]
+fn turns_and_lengths() {
+  let mut rng = rand::thread_rng();
+  let (mut message, mut payload) = (vec![0u8; MAX_MESSAGE_LEN], vec![0u8; MAX_MESSAGE_LEN]);
+  let (mut alice, mut bob) = parties(&NK);
+  assert!(alice.is_my_turn() && !bob.is_my_turn());
+  assert_eq!(bob.write_message(b"", &mut message, &mut rng), Err(KyberError::InvalidInput));
+  assert_eq!(alice.read_message(&message[..10], &mut payload), Err(KyberError::InvalidInput));
+
+  // A message or payload that doesn't fit fails
+  let long = vec![0u8; MAX_MESSAGE_LEN];
+  assert!(alice.write_message(&long, &mut message, &mut rng).is_err());
+  let (mut alice, mut bob) = parties(&NK);
+  let len = alice.write_message(b"payload", &mut message, &mut rng).unwrap();
+  assert!(bob.read_message(&message[..len], &mut payload[..3]).is_err());
+  let (mut alice, mut bob) = parties(&NK);
+  let len = alice.write_message(b"payload", &mut message, &mut rng).unwrap();
+  assert!(bob.read_message(&message[..100], &mut payload).is_err());
+  assert!(alice.write_message(b"", &mut message[..len], &mut rng).is_err());
+  assert!(alice.into_transport().is_err());
+}
+
+#[test]
+fn missing_keys() {
+  let mut rng = rand::thread_rng();
+  let keys = MlKem768::keypair(&mut rng);
+  type Handshake = HandshakeState<MlKem768>;
+  assert!(Handshake::initiator(&NK, b"", None, None).is_err());
+  assert!(Handshake::responder(&NK, b"", None, None).is_err());
+  assert!(Handshake::initiator(&XX, b"", None, None).is_err());
+  assert!(Handshake::initiator(&KK, b"", None, Some(keys.public)).is_err());
+  assert!(Handshake::responder(&IK, b"", Some(keys), None).is_ok());
+  assert!(Handshake::initiator(&NN, b"", None, None).is_ok());
+}
+
+#[test]
+fn prologue_mismatch() {
+  let mut rng = rand::thread_rng();
+  let (mut message, mut payload) = (vec![0u8; MAX_MESSAGE_LEN], vec![0u8; MAX_MESSAGE_LEN]);
+  let mut alice = HandshakeState::<MlKem768>::initiator(&NN, b"one", None, None).unwrap();
+  let mut bob = HandshakeState::<MlKem768>::responder(&NN, b"two", None, None).unwrap();
+  // The first message carries no key yet, the mismatch surfaces in the reply
+  let len = alice.write_message(b"", &mut message, &mut rng).unwrap();
+  bob.read_message(&message[..len], &mut payload).unwrap();
+  let len = bob.write_message(b"", &mut message, &mut rng).unwrap();
+  assert_eq!(alice.read_message(&message[..len], &mut payload), Err(KyberError::Authentication));
+}
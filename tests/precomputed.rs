@@ -0,0 +1,95 @@
+use pqc_kyber::*;
+use rand::{rngs::StdRng, SeedableRng};
+use std::convert::TryFrom;
+
+// A prepared key must give the same output as the one-shot functions
+fn encapsulation_matches<P: KyberKem>() {
+  let mut rng = StdRng::seed_from_u64(7);
+  let keys = P::keypair(&mut rng);
+  let prepared = EncapsulationKey::new(&keys.public);
+  assert_eq!(prepared.public_key(), &keys.public);
+
+  let mut one_shot = StdRng::seed_from_u64(8);
+  let mut cached = StdRng::seed_from_u64(8);
+  for _ in 0..3 {
+    let (ct, ss) = prepared.encapsulate(&mut cached).unwrap();
+    assert_eq!((ct, ss.clone()), P::encapsulate(&keys.public, &mut one_shot).unwrap(), "{}", P::NAME);
+    assert_eq!(P::decapsulate(&ct, &keys.secret).unwrap(), ss);
+  }
+}
+
+// Including corrupted ciphertexts for implicit rejection
+fn decapsulation_matches<P: KyberKem>() {
+  let mut rng = StdRng::seed_from_u64(10);
+  let keys = P::keypair(&mut rng);
+  let prepared = DecapsulationKey::new(&keys.secret);
+  assert_eq!(prepared.secret_key(), &keys.secret);
+  for i in 0..4 {
+    let (ct, _) = P::encapsulate(&keys.public, &mut rng).unwrap();
+    let mut bytes = ct.as_ref().to_vec();
+    bytes[i] ^= (i % 2) as u8;
+    let ct = Ciphertext::<P>::try_from(&bytes[..]).unwrap();
+    assert_eq!(prepared.decapsulate(&ct).unwrap(), P::decapsulate(&ct, &keys.secret).unwrap(), "{}", P::NAME);
+  }
+}
+
+fn level<P: KyberKem>() {
+  encapsulation_matches::<P>();
+  decapsulation_matches::<P>();
+}
+
+fn every_level() {
+  level::<Kyber512>();
+  level::<Kyber768>();
+  level::<Kyber1024>();
+  #[cfg(not(feature = "90s"))]
+  {
+    level::<MlKem512>();
+    level::<MlKem768>();
+    level::<MlKem1024>();
+  }
+}
+
+// Forcing changes global state, so both backends run in a single test
+#[test]
+fn prepared_keys_match_one_shot() {
+  set_backend(Backend::Reference).unwrap();
+  every_level();
+  if Backend::Avx2.is_available() {
+    set_backend(Backend::Avx2).unwrap();
+    every_level();
+
+    // A key keeps the backend it was expanded with
+    let mut rng = StdRng::seed_from_u64(9);
+    let keys = keypair(&mut rng);
+    let prepared = EncapsulationKey::from(&keys.public);
+    let server = DecapsulationKey::from(&keys.secret);
+    set_backend(Backend::Reference).unwrap();
+    let (ct, ss) = prepared.encapsulate(&mut rng).unwrap();
+    assert_eq!(server.decapsulate(&ct).unwrap(), ss);
+  }
+}
+
+#[cfg(feature = "hazmat")]
+#[test]
+fn derandomized() {
+  let mut rng = rand::thread_rng();
+  let keys = keypair(&mut rng);
+  let coins = [3u8; KYBER_SYMBYTES];
+  let prepared = EncapsulationKey::new(&keys.public);
+  assert_eq!(prepared.encapsulate_derand(&coins).unwrap(), encapsulate_derand(&keys.public, &coins).unwrap());
+}
+
+#[cfg(feature = "hazmat")]
+#[test]
+fn explicit_rejection() {
+  let mut rng = rand::thread_rng();
+  let keys = keypair(&mut rng);
+  let server = DecapsulationKey::new(&keys.secret);
+  let (ct, ss) = encapsulate(&keys.public, &mut rng).unwrap();
+  assert_eq!(server.decapsulate_explicit(&ct), Ok(ss));
+  let mut bytes = ct.as_ref().to_vec();
+  bytes[0] ^= 1;
+  let ct = Ciphertext::try_from(&bytes[..]).unwrap();
+  assert_eq!(server.decapsulate_explicit(&ct), Err(KyberError::Decapsulation));
+}
@@ -0,0 +1,180 @@
+use pqc_kyber::*;
+use chacha20poly1305::{aead::{Aead, KeyInit, Payload}, ChaCha20Poly1305};
+use std::convert::TryFrom;
+
+fn ratchets() -> (Ratchet, Ratchet) {
+  let mut rng = rand::thread_rng();
+  let bob_keys = keypair(&mut rng);
+  let (alice, client_init) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  let (bob, server_send) = UakeResponder::new().server_receive(client_init, &bob_keys.secret, &mut rng).unwrap();
+  let alice = alice.client_confirm(server_send).unwrap();
+  (Ratchet::new(&alice, Role::Initiator), Ratchet::new(&bob, Role::Responder))
+}
+
+fn encode(header: &RatchetHeader) -> Vec<u8> {
+  let mut bytes = vec![0u8; header.encoded_len()];
+  header.encode(&mut bytes).unwrap();
+  bytes
+}
+
+// Seals a message under its key with the header as associated data
+fn seal(ratchet: &mut Ratchet, msg: &[u8]) -> (RatchetHeader, Vec<u8>) {
+  let (header, key) = ratchet.send(&mut rand::thread_rng()).unwrap();
+  let payload = Payload { msg, aad: &encode(&header) };
+  let sealed = ChaCha20Poly1305::new(key.as_ref().into()).encrypt(&[0u8; 12].into(), payload).unwrap();
+  (header, sealed)
+}
+
+// Only commits the step once the message opens
+fn open(ratchet: &mut Ratchet, header: &RatchetHeader, sealed: &[u8]) -> Result<Vec<u8>, KyberError> {
+  let (step, key) = ratchet.receive(header)?;
+  let payload = Payload { msg: sealed, aad: &encode(header) };
+  let msg = ChaCha20Poly1305::new(key.as_ref().into())
+    .decrypt(&[0u8; 12].into(), payload)
+    .map_err(|_| KyberError::Authentication)?;
+  ratchet.commit(step)?;
+  Ok(msg)
+}
+
+// Receives without a message, for tests of the chains alone
+fn accept(ratchet: &mut Ratchet, header: &RatchetHeader) -> SharedSecret {
+  let (step, key) = ratchet.receive(header).unwrap();
+  ratchet.commit(step).unwrap();
+  key
+}
+
+// Sends a message each way
+fn round_trip(alice: &mut Ratchet, bob: &mut Ratchet) -> (RatchetHeader, RatchetHeader) {
+  let (to_bob, sealed) = seal(alice, b"ping");
+  assert_eq!(open(bob, &to_bob, &sealed).unwrap(), b"ping");
+  let (to_alice, sealed) = seal(bob, b"pong");
+  assert_eq!(open(alice, &to_alice, &sealed).unwrap(), b"pong");
+  (to_bob, to_alice)
+}
+
+#[test]
+fn conversation() {
+  let mut rng = rand::thread_rng();
+  let (mut alice, mut bob) = ratchets();
+
+  // Every round trip answers the last key and offers a new one
+  let (to_bob, to_alice) = round_trip(&mut alice, &mut bob);
+  assert!(to_bob.public_key().is_some() && to_bob.ciphertext().is_none());
+  assert!(to_alice.public_key().is_some() && to_alice.ciphertext().is_some());
+  for _ in 0..3 {
+    let (to_bob, to_alice) = round_trip(&mut alice, &mut bob);
+    assert!(to_bob.public_key().is_some() && to_bob.ciphertext().is_some());
+    assert!(to_alice.public_key().is_some() && to_alice.ciphertext().is_some());
+  }
+
+  // One sided bursts wait for an answer before offering another key
+  let mut keys = Vec::new();
+  for i in 0..4 {
+    let (header, key) = alice.send(&mut rng).unwrap();
+    assert_eq!(header.ciphertext().is_some(), i == 0);
+    assert_eq!(header.public_key().is_some(), i == 0);
+    assert_eq!(accept(&mut bob, &header), key);
+    keys.push(key);
+  }
+  keys.dedup();
+  assert_eq!(keys.len(), 4);
+}
+
+#[test]
+fn out_of_order() {
+  let mut rng = rand::thread_rng();
+  let (mut alice, mut bob) = ratchets();
+  let (first, key) = alice.send(&mut rng).unwrap();
+  let (second, _) = alice.send(&mut rng).unwrap();
+  assert_eq!(bob.receive(&second).unwrap_err(), KyberError::OutOfOrder);
+  let (step, bob_key) = bob.receive(&first).unwrap();
+  assert_eq!(bob_key, key);
+  // Only one step per message can be committed
+  let (stale, _) = bob.receive(&first).unwrap();
+  bob.commit(step).unwrap();
+  assert_eq!(bob.commit(stale), Err(KyberError::OutOfOrder));
+  assert_eq!(bob.receive(&first).unwrap_err(), KyberError::OutOfOrder);
+
+  // Bob answers Alice's key, which another ratchet never offered
+  let (header, _) = bob.send(&mut rng).unwrap();
+  assert!(header.ciphertext().is_some());
+  let (other, _) = ratchets();
+  assert_eq!(other.receive(&header).unwrap_err(), KyberError::InvalidInput);
+}
+
+#[test]
+fn tampered_ciphertext() {
+  let (mut alice, mut bob) = ratchets();
+  let (header, sealed) = seal(&mut alice, b"ping");
+  open(&mut bob, &header, &sealed).unwrap();
+  let (header, sealed) = seal(&mut bob, b"pong");
+
+  let mut bytes = encode(&header);
+  let last = bytes.len() - 1;
+  bytes[last] ^= 1;
+  let tampered = RatchetHeader::try_from(&bytes[..]).unwrap();
+  assert_eq!(open(&mut alice, &tampered, &sealed), Err(KyberError::Authentication));
+  assert_eq!(open(&mut alice, &header, &sealed).unwrap(), b"pong");
+  round_trip(&mut alice, &mut bob);
+}
+
+// A header offering an attacker's key fails authentication and leaves the
+// session as it was, the real message still opens and the reply goes to
+// the real key
+#[test]
+fn tampered_header() {
+  let mut rng = rand::thread_rng();
+  let (mut alice, mut bob) = ratchets();
+  round_trip(&mut alice, &mut bob);
+  let (header, sealed) = seal(&mut alice, b"ping");
+
+  let eve = keypair(&mut rng);
+  let mut bytes = encode(&header);
+  bytes[9..9 + KYBER_PUBLICKEYBYTES].copy_from_slice(eve.public.as_ref());
+  let forged = RatchetHeader::try_from(&bytes[..]).unwrap();
+  assert_eq!(forged.public_key(), Some(&eve.public));
+  assert_eq!(open(&mut bob, &forged, &sealed), Err(KyberError::Authentication));
+
+  assert_eq!(open(&mut bob, &header, &sealed).unwrap(), b"ping");
+  let (reply, sealed) = seal(&mut bob, b"pong");
+  assert_ne!(reply.ciphertext(), None);
+  assert_eq!(open(&mut alice, &reply, &sealed).unwrap(), b"pong");
+}
+
+#[test]
+fn header_encoding() {
+  let mut rng = rand::thread_rng();
+  let (mut alice, mut bob) = ratchets();
+  round_trip(&mut alice, &mut bob);
+  let (header, _) = alice.send(&mut rng).unwrap();
+  assert_eq!(header.encoded_len(), RATCHET_HEADER_MAX_BYTES);
+
+  let mut bytes = [0u8; RATCHET_HEADER_MAX_BYTES + 1];
+  let len = header.encode(&mut bytes).unwrap();
+  assert_eq!(RatchetHeader::try_from(&bytes[..len]).unwrap(), header);
+  assert!(header.encode(&mut bytes[..len - 1]).is_err());
+  assert!(RatchetHeader::try_from(&bytes[..len + 1]).is_err());
+  assert!(RatchetHeader::try_from(&bytes[..len - 1]).is_err());
+  bytes[0] |= 0x04;
+  assert!(RatchetHeader::try_from(&bytes[..len]).is_err());
+
+  let (header, _) = alice.send(&mut rng).unwrap();
+  let len = header.encode(&mut bytes).unwrap();
+  assert_eq!(len, 9);
+  assert_eq!(RatchetHeader::try_from(&bytes[..len]).unwrap().message_number(), 2);
+}
+
+#[test]
+fn invalid_public_key() {
+  let mut rng = rand::thread_rng();
+  let (mut alice, mut bob) = ratchets();
+  let (header, key) = alice.send(&mut rng).unwrap();
+  let mut bytes = vec![0u8; header.encoded_len()];
+  header.encode(&mut bytes).unwrap();
+  for b in bytes[9..].iter_mut() {
+    *b = 0xff;
+  }
+  let bad = RatchetHeader::try_from(&bytes[..]).unwrap();
+  assert_eq!(bob.receive(&bad).unwrap_err(), KyberError::InvalidPublicKey);
+  assert_eq!(accept(&mut bob, &header), key);
+}
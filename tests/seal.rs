@@ -0,0 +1,182 @@
+#![cfg(all(feature = "seal", feature = "std"))]
+use pqc_kyber::*;
+use pqc_kyber::seal::*;
+use std::convert::TryFrom;
+
+fn roundtrip<P: KyberKem>() {
+  let mut rng = rand::thread_rng();
+  let keys = P::keypair(&mut rng);
+  for len in [0, 1, 100, 4096].iter() {
+    let message = vec![0x42u8; *len];
+    let envelope = seal(&keys.public, &message, b"aad", &mut rng).unwrap();
+    assert_eq!(envelope.len(), sealed_len::<P>(*len));
+
+    let parsed = Envelope::parse(&envelope).unwrap();
+    assert_eq!(parsed.mode(), KemMode::of::<P>());
+    assert_eq!(parsed.level(), P::K);
+    assert_eq!(parsed.plaintext_len(), *len);
+    assert_eq!(open(&keys.secret, &envelope, b"aad").unwrap(), message);
+  }
+}
+
+#[test]
+fn every_level() {
+  roundtrip::<Kyber512>();
+  roundtrip::<Kyber768>();
+  roundtrip::<Kyber1024>();
+  #[cfg(not(feature = "90s"))]
+  {
+    roundtrip::<MlKem512>();
+    roundtrip::<MlKem768>();
+    roundtrip::<MlKem1024>();
+  }
+}
+
+#[test]
+fn buffers() {
+  let mut rng = rand::thread_rng();
+  let keys = keypair(&mut rng);
+  let mut envelope = [0u8; HEADERBYTES + KYBER_CIPHERTEXTBYTES + 5 + TAGBYTES + 1];
+  let mut message = [0u8; 6];
+  assert_eq!(seal_into(&keys.public, b"hello", b"", &mut envelope[..5], &mut rng), Err(KyberError::InvalidInput));
+  let len = seal_into(&keys.public, b"hello", b"", &mut envelope, &mut rng).unwrap();
+  assert_eq!(len, envelope.len() - 1);
+  assert_eq!(open_into(&keys.secret, &envelope[..len], b"", &mut message[..4]), Err(KyberError::InvalidInput));
+  let n = open_into(&keys.secret, &envelope[..len], b"", &mut message).unwrap();
+  assert_eq!(&message[..n], b"hello");
+}
+
+#[test]
+fn tampering() {
+  let mut rng = rand::thread_rng();
+  let keys = keypair(&mut rng);
+  let envelope = seal(&keys.public, b"hello bob", b"aad", &mut rng).unwrap();
+  assert_eq!(open(&keys.secret, &envelope, b"other"), Err(KyberError::Authentication));
+
+  // Flipping a bit in the KEM ciphertext, the AEAD ciphertext or the tag
+  for i in [HEADERBYTES, HEADERBYTES + KYBER_CIPHERTEXTBYTES, envelope.len() - 1].iter() {
+    let mut tampered = envelope.clone();
+    tampered[*i] ^= 1;
+    assert_eq!(open(&keys.secret, &tampered, b"aad"), Err(KyberError::Authentication));
+  }
+
+  // Sealed to someone else
+  let other = keypair(&mut rng);
+  assert_eq!(open(&other.secret, &envelope, b"aad"), Err(KyberError::Authentication));
+}
+
+#[test]
+fn strict_parsing() {
+  let mut rng = rand::thread_rng();
+  let keys = keypair(&mut rng);
+  let envelope = seal(&keys.public, b"hello bob", b"", &mut rng).unwrap();
+
+  // Truncated below the KEM ciphertext and tag, or an unknown version, mode or level
+  let min = HEADERBYTES + KYBER_CIPHERTEXTBYTES + TAGBYTES;
+  assert!(Envelope::parse(&envelope[..min]).is_ok());
+  assert_eq!(Envelope::parse(&envelope[..min - 1]), Err(KyberError::InvalidInput));
+  assert_eq!(Envelope::parse(&envelope[..2]), Err(KyberError::InvalidInput));
+  for (i, value) in [(0, 0), (0, 2), (1, 0), (1, 4), (2, 1), (2, 5)].iter() {
+    let mut bad = envelope.clone();
+    bad[*i] = *value;
+    assert_eq!(Envelope::parse(&bad), Err(KyberError::InvalidInput));
+  }
+
+  // Extra or missing bytes in the AEAD ciphertext fail authentication
+  let mut longer = envelope.clone();
+  longer.push(0);
+  assert_eq!(open(&keys.secret, &longer, b""), Err(KyberError::Authentication));
+  assert_eq!(open(&keys.secret, &envelope[..envelope.len() - 1], b""), Err(KyberError::Authentication));
+}
+
+#[test]
+fn level_and_mode_mismatch() {
+  let mut rng = rand::thread_rng();
+  let keys = Kyber512::keypair(&mut rng);
+  let envelope = seal(&keys.public, b"hello bob", b"", &mut rng).unwrap();
+  let other = Kyber1024::keypair(&mut rng);
+  assert_eq!(open(&other.secret, &envelope, b""), Err(KyberError::InvalidInput));
+
+  // A header rewritten to another mode is refused before decapsulating
+  let mut relabelled = envelope.clone();
+  relabelled[1] = if KemMode::of::<Kyber512>() == KemMode::Kyber { 0x01 } else { 0x02 };
+  assert!(Envelope::parse(&relabelled).is_ok());
+  assert_eq!(open(&keys.secret, &relabelled, b""), Err(KyberError::InvalidInput));
+}
+
+#[test]
+fn invalid_public_key() {
+  let mut rng = rand::thread_rng();
+  let pk = PublicKey::<Kyber768>::try_from(&[0xffu8; Kyber768::PUBLICKEYBYTES][..]).unwrap();
+  assert_eq!(seal(&pk, b"hello", b"", &mut rng), Err(KyberError::InvalidPublicKey));
+}
+
+#[test]
+fn multi_recipient() {
+  let mut rng = rand::thread_rng();
+  let devices: Vec<_> = (0..5).map(|_| keypair(&mut rng)).collect();
+  let pks: Vec<_> = devices.iter().map(|k| k.public).collect();
+  let envelope = seal_multi(&pks, b"group message", b"aad", &mut rng).unwrap();
+  assert_eq!(envelope.len(), multi_sealed_len::<KyberLevel>(5, 13));
+
+  let parsed = MultiEnvelope::parse(&envelope).unwrap();
+  assert_eq!(parsed.recipients(), 5);
+  assert_eq!(parsed.level(), KYBER_K);
+  assert_eq!(parsed.plaintext_len(), 13);
+  for device in devices.iter() {
+    assert_eq!(open_multi(&device.secret, &envelope, b"aad").unwrap(), b"group message");
+  }
+  let outsider = keypair(&mut rng);
+  assert_eq!(open_multi(&outsider.secret, &envelope, b"aad"), Err(KyberError::Authentication));
+  assert_eq!(open_multi(&devices[0].secret, &envelope, b"other"), Err(KyberError::Authentication));
+
+  // Neither format parses as the other
+  assert!(Envelope::parse(&envelope).is_err());
+  let single = seal(&pks[0], b"group message", b"aad", &mut rng).unwrap();
+  assert!(MultiEnvelope::parse(&single).is_err());
+}
+
+#[test]
+fn multi_recipient_tampering() {
+  let mut rng = rand::thread_rng();
+  let alice = keypair(&mut rng);
+  let bob = keypair(&mut rng);
+  let envelope = seal_multi(&[alice.public, bob.public], b"hello", b"", &mut rng).unwrap();
+  let entry = KYBER_CIPHERTEXTBYTES + WRAPPED_KEYBYTES;
+
+  // A modified entry fails for its recipient and, bound into the message key, for everyone else
+  let mut tampered = envelope.clone();
+  tampered[MULTI_HEADERBYTES + entry - 1] ^= 1;
+  assert_eq!(open_multi(&alice.secret, &tampered, b""), Err(KyberError::Authentication));
+  assert_eq!(open_multi(&bob.secret, &tampered, b""), Err(KyberError::Authentication));
+
+  // Dropping Bob's entry and fixing the count up
+  let mut dropped = envelope[..MULTI_HEADERBYTES + entry].to_vec();
+  dropped.extend_from_slice(&envelope[MULTI_HEADERBYTES + 2 * entry..]);
+  dropped[4] = 1;
+  assert_eq!(MultiEnvelope::parse(&dropped).unwrap().recipients(), 1);
+  assert_eq!(open_multi(&alice.secret, &dropped, b""), Err(KyberError::Authentication));
+
+  // No recipients or a count larger than the envelope
+  let mut bad = envelope.clone();
+  bad[4] = 0;
+  assert_eq!(MultiEnvelope::parse(&bad), Err(KyberError::InvalidInput));
+  bad[4] = 3;
+  assert_eq!(MultiEnvelope::parse(&bad), Err(KyberError::InvalidInput));
+  assert_eq!(seal_multi::<KyberLevel, _>(&[], b"hello", b"", &mut rng), Err(KyberError::InvalidInput));
+}
+
+#[test]
+fn multi_recipient_buffers() {
+  let mut rng = rand::thread_rng();
+  let keys = Kyber1024::keypair(&mut rng);
+  let mut envelope = vec![0u8; multi_sealed_len::<Kyber1024>(1, 5)];
+  let mut message = [0u8; 5];
+  assert!(seal_multi_into(&[keys.public], b"hello", b"", &mut envelope[..100], &mut rng).is_err());
+  let len = seal_multi_into(&[keys.public], b"hello", b"", &mut envelope, &mut rng).unwrap();
+  assert_eq!(open_multi_into(&keys.secret, &envelope[..len], b"", &mut message[..4]), Err(KyberError::InvalidInput));
+  open_multi_into(&keys.secret, &envelope[..len], b"", &mut message).unwrap();
+  assert_eq!(&message, b"hello");
+  let other = Kyber512::keypair(&mut rng);
+  assert_eq!(open_multi(&other.secret, &envelope, b""), Err(KyberError::InvalidInput));
+}
@@ -0,0 +1,158 @@
+#![cfg(feature = "serde")]
+use pqc_kyber::*;
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn json_hex() {
+  let mut rng = rand::thread_rng();
+  let keys = keypair(&mut rng);
+  let (ct, ss) = encapsulate(&keys.public, &mut rng).unwrap();
+
+  let json = serde_json::to_string(&keys.public).unwrap();
+  assert_eq!(json, format!("\"{}\"", hex(keys.public.as_ref())));
+  assert_eq!(serde_json::from_str::<PublicKey>(&json).unwrap(), keys.public);
+
+  let json = serde_json::to_string(&keys).unwrap();
+  assert_eq!(serde_json::from_str::<Keypair>(&json).unwrap(), keys);
+
+  let json = serde_json::to_string(&ct).unwrap();
+  assert_eq!(serde_json::from_str::<Ciphertext>(&json).unwrap(), ct);
+
+  let json = serde_json::to_string(&ss).unwrap();
+  assert_eq!(serde_json::from_str::<SharedSecret>(&json).unwrap(), ss);
+
+  // Uppercase hex is accepted
+  let upper = format!("\"{}\"", hex(ss.as_ref()).to_uppercase());
+  assert_eq!(serde_json::from_str::<SharedSecret>(&upper).unwrap(), ss);
+}
+
+#[test]
+fn bincode_bytes() {
+  let mut rng = rand::thread_rng();
+  let keys = Kyber1024::keypair(&mut rng);
+  let (ct, ss) = Kyber1024::encapsulate(&keys.public, &mut rng).unwrap();
+
+  // Raw bytes after bincode's u64 length prefix
+  let bytes = bincode::serialize(&keys.public).unwrap();
+  assert_eq!(bytes.len(), 8 + Kyber1024::PUBLICKEYBYTES);
+  assert_eq!(&bytes[8..], keys.public.as_ref());
+  assert_eq!(bincode::deserialize::<PublicKey<Kyber1024>>(&bytes).unwrap(), keys.public);
+
+  let bytes = bincode::serialize(&keys).unwrap();
+  assert_eq!(bytes.len(), 16 + Kyber1024::PUBLICKEYBYTES + Kyber1024::SECRETKEYBYTES);
+  assert_eq!(bincode::deserialize::<Keypair<Kyber1024>>(&bytes).unwrap(), keys);
+
+  let bytes = bincode::serialize(&ct).unwrap();
+  assert_eq!(bincode::deserialize::<Ciphertext<Kyber1024>>(&bytes).unwrap(), ct);
+
+  let bytes = bincode::serialize(&ss).unwrap();
+  assert_eq!(bincode::deserialize::<SharedSecret>(&bytes).unwrap(), ss);
+}
+
+#[test]
+fn wrong_length() {
+  let mut rng = rand::thread_rng();
+  let keys = Kyber512::keypair(&mut rng);
+
+  let bytes = bincode::serialize(&keys.public).unwrap();
+  assert!(bincode::deserialize::<PublicKey<Kyber768>>(&bytes).is_err());
+  let json = serde_json::to_string(&keys.secret).unwrap();
+  assert!(serde_json::from_str::<SecretKey<Kyber1024>>(&json).is_err());
+
+  let short = format!("\"{}\"", hex(&[0u8; KYBER_SSBYTES - 1]));
+  assert!(serde_json::from_str::<SharedSecret>(&short).is_err());
+  let long = bincode::serialize(&[0u8; KYBER_SSBYTES + 1][..]).unwrap();
+  assert!(bincode::deserialize::<SharedSecret>(&long).is_err());
+}
+
+#[test]
+fn invalid_hex() {
+  let json = format!("\"{}\"", "zz".repeat(KYBER_SSBYTES));
+  let err = serde_json::from_str::<SharedSecret>(&json).unwrap_err();
+  // Secrets aren't echoed back in errors
+  assert!(!err.to_string().contains("zz"));
+}
+
+#[test]
+fn kex_messages() {
+  let mut rng = rand::thread_rng();
+  let alice_keys = keypair(&mut rng);
+  let bob_keys = keypair(&mut rng);
+
+  let (alice, client_init) = AkeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  let bytes = bincode::serialize(&client_init).unwrap();
+  let client_init: AkeSendInit = bincode::deserialize(&bytes).unwrap();
+
+  let (bob, server_send) = AkeResponder::new().server_receive(
+    client_init, &alice_keys.public, &bob_keys.secret, &mut rng
+  ).unwrap();
+  let json = serde_json::to_string(&server_send).unwrap();
+  let server_send: AkeSendResponse = serde_json::from_str(&json).unwrap();
+
+  let alice = alice.client_confirm(server_send, &alice_keys.secret).unwrap();
+  assert_eq!(alice.shared_secret(), bob.shared_secret());
+  assert!(serde_json::from_str::<UakeSendResponse>(&json).is_err());
+
+  let (_, client_init) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  let (_, _, server_tag) = UakeResponder::new().server_receive_with_tag(
+    client_init, &bob_keys.secret, &mut rng
+  ).unwrap();
+  let bytes = bincode::serialize(&server_tag).unwrap();
+  assert_eq!(bincode::deserialize::<ConfirmationTag>(&bytes).unwrap(), server_tag);
+}
+
+#[test]
+fn ratchet_state() {
+  let mut rng = rand::thread_rng();
+  let bob_keys = keypair(&mut rng);
+  let (alice, client_init) = UakeInitiator::new().client_init(&bob_keys.public, &mut rng);
+  let (bob, server_send) = UakeResponder::new().server_receive(client_init, &bob_keys.secret, &mut rng).unwrap();
+  let alice = alice.client_confirm(server_send).unwrap();
+  let mut alice = Ratchet::new(&alice, Role::Initiator);
+  let mut bob = Ratchet::new(&bob, Role::Responder);
+
+  let (header, _) = alice.send(&mut rng).unwrap();
+  accept(&mut bob, &header);
+
+  // Restored states carry on where they left off
+  let json = serde_json::to_string(&bob).unwrap();
+  let mut bob: Ratchet = serde_json::from_str(&json).unwrap();
+  let bytes = bincode::serialize(&alice).unwrap();
+  let mut alice: Ratchet = bincode::deserialize(&bytes).unwrap();
+  let (header, key) = bob.send(&mut rng).unwrap();
+  let header: RatchetHeader = bincode::deserialize(&bincode::serialize(&header).unwrap()).unwrap();
+  assert_eq!(accept(&mut alice, &header), key);
+
+  // A copy of Bob's state taken by an attacker stops tracking the session
+  // once a round trip mixes in a key it never saw
+  let mut stolen: Ratchet = serde_json::from_str(&serde_json::to_string(&bob).unwrap()).unwrap();
+  let (header, key) = alice.send(&mut rng).unwrap();
+  assert_eq!(accept(&mut stolen, &header), key);
+  assert_eq!(accept(&mut bob, &header), key);
+  let (header, key) = bob.send(&mut rng).unwrap();
+  stolen.send(&mut rng).unwrap();
+  assert_eq!(accept(&mut alice, &header), key);
+  let (header, key) = alice.send(&mut rng).unwrap();
+  assert_eq!(accept(&mut bob, &header), key);
+  assert_ne!(accept(&mut stolen, &header), key);
+}
+
+fn accept(ratchet: &mut Ratchet, header: &RatchetHeader) -> SharedSecret {
+  let (step, key) = ratchet.receive(header).unwrap();
+  ratchet.commit(step).unwrap();
+  key
+}
+
+#[cfg(all(feature = "xwing", not(feature = "90s")))]
+#[test]
+fn xwing_keys() {
+  let mut rng = rand::thread_rng();
+  let keys = xwing::keypair(&mut rng);
+  let json = serde_json::to_string(&keys).unwrap();
+  assert_eq!(serde_json::from_str::<xwing::Keypair>(&json).unwrap(), keys);
+  let bytes = bincode::serialize(&keys.public).unwrap();
+  assert_eq!(bytes.len(), 8 + xwing::XWING_PUBLICKEYBYTES);
+}
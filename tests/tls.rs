@@ -0,0 +1,164 @@
+#![cfg(all(feature = "rustls", not(feature = "90s")))]
+use pqc_kyber::*;
+use rustls::{
+  crypto::{ring, CryptoProvider, SupportedKxGroup},
+  pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName},
+  ClientConfig, ClientConnection, ConnectionCommon, NamedGroup, RootCertStore, ServerConfig,
+  ServerConnection,
+};
+use std::{
+  convert::TryFrom,
+  io::{Read, Write},
+  sync::Arc,
+};
+use x25519_dalek::{x25519, X25519_BASEPOINT_BYTES};
+
+fn provider(kx_groups: Vec<&'static dyn SupportedKxGroup>) -> Arc<CryptoProvider> {
+  Arc::new(CryptoProvider { kx_groups, ..ring::default_provider() })
+}
+
+fn configs(
+  client_groups: Vec<&'static dyn SupportedKxGroup>,
+  server_groups: Vec<&'static dyn SupportedKxGroup>,
+) -> (ClientConfig, ServerConfig) {
+  let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+  let cert_der = CertificateDer::from(cert.cert.der().to_vec());
+  let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(cert.key_pair.serialize_der()));
+  let mut roots = RootCertStore::empty();
+  roots.add(cert_der.clone()).unwrap();
+
+  let client = ClientConfig::builder_with_provider(provider(client_groups))
+    .with_protocol_versions(&[&rustls::version::TLS13]).unwrap()
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+  let server = ServerConfig::builder_with_provider(provider(server_groups))
+    .with_protocol_versions(&[&rustls::version::TLS13]).unwrap()
+    .with_no_client_auth()
+    .with_single_cert(vec![cert_der], key).unwrap();
+  (client, server)
+}
+
+fn transfer<A, B>(from: &mut ConnectionCommon<A>, to: &mut ConnectionCommon<B>) -> Result<(), rustls::Error> {
+  let mut buf = Vec::new();
+  while from.wants_write() {
+    from.write_tls(&mut buf).unwrap();
+  }
+  let mut records = &buf[..];
+  while !records.is_empty() {
+    to.read_tls(&mut records).unwrap();
+  }
+  to.process_new_packets().map(|_| ())
+}
+
+// Runs a handshake in memory and echoes some application data,
+// returning the negotiated group
+fn handshake(client: ClientConfig, server: ServerConfig) -> Result<NamedGroup, rustls::Error> {
+  let name = ServerName::try_from("localhost").unwrap();
+  let mut client = ClientConnection::new(Arc::new(client), name)?;
+  let mut server = ServerConnection::new(Arc::new(server))?;
+  while client.is_handshaking() || server.is_handshaking() {
+    transfer(&mut client, &mut server)?;
+    transfer(&mut server, &mut client)?;
+  }
+
+  client.writer().write_all(b"ping").unwrap();
+  transfer(&mut client, &mut server)?;
+  let mut buf = [0u8; 4];
+  server.reader().read_exact(&mut buf).unwrap();
+  assert_eq!(&buf, b"ping");
+
+  let group = client.negotiated_key_exchange_group().unwrap().name();
+  assert_eq!(server.negotiated_key_exchange_group().unwrap().name(), group);
+  Ok(group)
+}
+
+#[test]
+fn mlkem_handshake() {
+  let (client, server) = configs(vec![tls::X25519MLKEM768], vec![tls::X25519MLKEM768]);
+  assert_eq!(handshake(client, server).unwrap(), NamedGroup::X25519MLKEM768);
+}
+
+#[test]
+fn kyber_draft_handshake() {
+  let (client, server) = configs(vec![tls::X25519KYBER768DRAFT00], vec![tls::X25519KYBER768DRAFT00]);
+  assert_eq!(handshake(client, server).unwrap(), NamedGroup::Unknown(0x6399));
+}
+
+#[test]
+fn negotiation() {
+  // The server's preference wins among the offered hybrids
+  let (client, server) = configs(tls::ALL_KX_GROUPS.to_vec(), vec![tls::X25519KYBER768DRAFT00]);
+  assert_eq!(handshake(client, server).unwrap(), NamedGroup::Unknown(0x6399));
+
+  // A classical server uses the X25519 key inside the hybrid share
+  let (client, server) = configs(
+    vec![tls::X25519MLKEM768, ring::kx_group::X25519], vec![ring::kx_group::X25519]
+  );
+  assert_eq!(handshake(client, server).unwrap(), NamedGroup::X25519);
+
+  // Classical clients still reach a hybrid server that also offers X25519
+  let (client, server) = configs(
+    vec![ring::kx_group::X25519], vec![tls::X25519MLKEM768, ring::kx_group::X25519]
+  );
+  assert_eq!(handshake(client, server).unwrap(), NamedGroup::X25519);
+}
+
+#[test]
+fn wire_layout() {
+  let mut rng = rand::thread_rng();
+  let keys = MlKem768::keypair(&mut rng);
+  let sk_x = [9u8; 32];
+  let pk_x = x25519(sk_x, X25519_BASEPOINT_BYTES);
+
+  // X25519MLKEM768 client share is ek || x25519, server share ct || x25519
+  let share = [keys.public.as_ref(), &pk_x[..]].concat();
+  let server = tls::X25519MLKEM768.start_and_complete(&share).unwrap();
+  assert_eq!(server.pub_key.len(), MlKem768::CIPHERTEXTBYTES + 32);
+  let (ct, ct_x) = server.pub_key.split_at(MlKem768::CIPHERTEXTBYTES);
+  let ct = Ciphertext::<MlKem768>::try_from(ct).unwrap();
+  let ss_m = MlKem768::decapsulate(&ct, &keys.secret).unwrap();
+  let ss_x = x25519(sk_x, <[u8; 32]>::try_from(ct_x).unwrap());
+  assert_eq!(server.secret.secret_bytes(), &[ss_m.as_ref(), &ss_x[..]].concat()[..]);
+
+  // X25519Kyber768Draft00 puts X25519 first
+  let keys = Kyber768::keypair(&mut rng);
+  let share = [&pk_x[..], keys.public.as_ref()].concat();
+  let server = tls::X25519KYBER768DRAFT00.start_and_complete(&share).unwrap();
+  let (ct_x, ct) = server.pub_key.split_at(32);
+  let ct = Ciphertext::<Kyber768>::try_from(ct).unwrap();
+  let ss_m = Kyber768::decapsulate(&ct, &keys.secret).unwrap();
+  let ss_x = x25519(sk_x, <[u8; 32]>::try_from(ct_x).unwrap());
+  assert_eq!(server.secret.secret_bytes(), &[&ss_x[..], ss_m.as_ref()].concat()[..]);
+
+  // Client side agrees and exposes its X25519 component
+  for group in tls::ALL_KX_GROUPS {
+    let client = group.start().unwrap();
+    let (name, x) = client.hybrid_component().unwrap();
+    assert_eq!(name, NamedGroup::X25519);
+    assert!(client.pub_key().ends_with(x) || client.pub_key().starts_with(x));
+    let server = group.start_and_complete(client.pub_key()).unwrap();
+    assert_eq!(client.complete(&server.pub_key).unwrap().secret_bytes(), server.secret.secret_bytes());
+  }
+}
+
+#[test]
+fn invalid_shares() {
+  let mut rng = rand::thread_rng();
+  let keys = MlKem768::keypair(&mut rng);
+  let pk_x = x25519([9u8; 32], X25519_BASEPOINT_BYTES);
+  let group = tls::X25519MLKEM768;
+
+  let share = [keys.public.as_ref(), &pk_x[..]].concat();
+  assert!(group.start_and_complete(&share[1..]).is_err());
+
+  // Small order X25519 point
+  let share = [keys.public.as_ref(), &[0u8; 32][..]].concat();
+  assert!(group.start_and_complete(&share).is_err());
+
+  // ML-KEM key failing the modulus check
+  let share = [&[0xffu8; MlKem768::PUBLICKEYBYTES][..], &pk_x[..]].concat();
+  assert!(group.start_and_complete(&share).is_err());
+
+  let client = group.start().unwrap();
+  assert!(client.complete(&[0u8; 16]).is_err());
+}
@@ -0,0 +1,75 @@
+use pqc_kyber::*;
+use std::convert::TryFrom;
+
+// Sets the first coefficient of the public key's polynomial vector
+fn set_coeff(pk: &mut [u8], val: u16) {
+  pk[0] = val as u8;
+  pk[1] = (pk[1] & 0xf0) | (val >> 8) as u8;
+}
+
+fn public_key_check<P: KyberKem>() {
+  let mut rng = rand::thread_rng();
+  let keys = P::keypair(&mut rng);
+  let mut pk = keys.public.as_ref().to_vec();
+  let parse = |bytes: &[u8]| PublicKey::<P>::try_from(bytes).unwrap();
+  assert_eq!(P::validate_public_key(&parse(&pk)), Ok(()));
+
+  set_coeff(&mut pk, 3328);
+  assert_eq!(P::validate_public_key(&parse(&pk)), Ok(()));
+
+  for val in [3329, 3330, 4095] {
+    set_coeff(&mut pk, val);
+    assert_eq!(P::validate_public_key(&parse(&pk)), Err(KyberError::InvalidPublicKey));
+    assert_eq!(P::encapsulate_strict(&parse(&pk), &mut rng), Err(KyberError::InvalidPublicKey));
+  }
+
+  // The last coefficient of the last polynomial
+  let mut pk = keys.public.as_ref().to_vec();
+  pk[P::POLYVECBYTES - 1] = 0xff;
+  assert_eq!(P::validate_public_key(&parse(&pk)), Err(KyberError::InvalidPublicKey));
+  assert_eq!(PublicKey::<P>::try_from(&pk[1..]), Err(KyberError::InvalidInput));
+}
+
+fn secret_key_check<P: KyberKem>() {
+  let mut rng = rand::thread_rng();
+  let keys = P::keypair(&mut rng);
+  let (ct, ss1) = P::encapsulate_strict(&keys.public, &mut rng).unwrap();
+  let ss2 = P::decapsulate_strict(&ct, &keys.secret).unwrap();
+  assert_eq!(ss1, ss2);
+
+  // Corrupt the stored hash, then the embedded public key
+  for idx in [P::SECRETKEYBYTES - 2 * KYBER_SYMBYTES, P::INDCPA_SECRETKEYBYTES + 5] {
+    let mut bytes = keys.secret.as_ref().to_vec();
+    bytes[idx] ^= 1;
+    let sk = SecretKey::<P>::try_from(&bytes[..]).unwrap();
+    assert_eq!(P::validate_secret_key(&sk), Err(KyberError::InvalidSecretKey));
+    assert_eq!(P::decapsulate_strict(&ct, &sk), Err(KyberError::InvalidSecretKey));
+  }
+
+  // The implicit rejection key isn't covered by the hash
+  let mut bytes = keys.secret.as_ref().to_vec();
+  bytes[P::SECRETKEYBYTES - 1] ^= 1;
+  let sk = SecretKey::<P>::try_from(&bytes[..]).unwrap();
+  assert_eq!(P::validate_secret_key(&sk), Ok(()));
+}
+
+#[test]
+fn validate_keys() {
+  public_key_check::<Kyber512>();
+  public_key_check::<Kyber768>();
+  public_key_check::<Kyber1024>();
+  secret_key_check::<Kyber512>();
+  secret_key_check::<Kyber768>();
+  secret_key_check::<Kyber1024>();
+}
+
+#[cfg(not(feature = "90s"))]
+#[test]
+fn validate_ml_kem_keys() {
+  public_key_check::<MlKem512>();
+  public_key_check::<MlKem768>();
+  public_key_check::<MlKem1024>();
+  secret_key_check::<MlKem512>();
+  secret_key_check::<MlKem768>();
+  secret_key_check::<MlKem1024>();
+}
@@ -0,0 +1,107 @@
+# Generates HPKE Base mode vectors independently of the crate with pyca/cryptography's
+# HPKE implementation, usage: generate_hpke.py <count>
+#
+# pyca has no ML-KEM-512, for it the KEM runs in the openssl 3.5 cli and the RFC 9180
+# key schedule below uses pyca's HMAC and AEADs. That path is checked by opening
+# pyca's own ML-KEM-768 output with it.
+import hashlib, hmac, os, subprocess, sys, tempfile
+from cryptography.hazmat.primitives import hpke
+from cryptography.hazmat.primitives.asymmetric import mlkem, x25519
+from cryptography.hazmat.primitives.ciphers.aead import AESGCM, ChaCha20Poly1305
+
+KEMS = [
+    ('ML-KEM-512', None),
+    ('ML-KEM-768', hpke.KEM.MLKEM768),
+    ('ML-KEM-1024', hpke.KEM.MLKEM1024),
+    ('X-Wing', hpke.KEM.MLKEM768_X25519),
+]
+KDFS = [('HKDF-SHA256', hpke.KDF.HKDF_SHA256), ('HKDF-SHA384', hpke.KDF.HKDF_SHA384),
+        ('HKDF-SHA512', hpke.KDF.HKDF_SHA512)]
+AEADS = [('AES-128-GCM', hpke.AEAD.AES_128_GCM), ('AES-256-GCM', hpke.AEAD.AES_256_GCM),
+         ('ChaCha20Poly1305', hpke.AEAD.CHACHA20_POLY1305)]
+
+KEM_IDS = {'ML-KEM-512': 0x0040, 'ML-KEM-768': 0x0041}
+KDF_IDS = {'HKDF-SHA256': (1, hashlib.sha256), 'HKDF-SHA384': (2, hashlib.sha384),
+           'HKDF-SHA512': (3, hashlib.sha512)}
+AEAD_IDS = {'AES-128-GCM': (1, 16, AESGCM), 'AES-256-GCM': (2, 32, AESGCM),
+            'ChaCha20Poly1305': (3, 32, ChaCha20Poly1305)}
+NCT = {'ML-KEM-512': 768, 'ML-KEM-768': 1088}
+
+def sh(*a):
+    return subprocess.run(a, check=True, capture_output=True).stdout
+
+# RFC 9180 Base mode key schedule, returning the AEAD and base nonce
+def key_schedule(kem_name, kdf_name, aead_name, shared_secret, info):
+    kdf_id, h = KDF_IDS[kdf_name]
+    aead_id, nk, cipher = AEAD_IDS[aead_name]
+    suite = b'HPKE' + KEM_IDS[kem_name].to_bytes(2, 'big') + kdf_id.to_bytes(2, 'big') + aead_id.to_bytes(2, 'big')
+    def extract(salt, label, ikm):
+        return hmac.new(salt or b'\0' * h().digest_size, b'HPKE-v1' + suite + label + ikm, h).digest()
+    def expand(prk, label, info, n):
+        info = n.to_bytes(2, 'big') + b'HPKE-v1' + suite + label + info
+        out, t, i = b'', b'', 1
+        while len(out) < n:
+            t = hmac.new(prk, t + info + bytes([i]), h).digest(); out += t; i += 1
+        return out[:n]
+    context = b'\0' + extract(b'', b'psk_id_hash', b'') + extract(b'', b'info_hash', info)
+    secret = extract(shared_secret, b'secret', b'')
+    return cipher(expand(secret, b'key', context, nk)), expand(secret, b'base_nonce', context, 12)
+
+# ML-KEM encapsulation in the openssl cli with a fixed message, then the key schedule
+def seal_openssl(kem_name, kdf_name, aead_name, seed, m, info, pt):
+    d = tempfile.mkdtemp()
+    open(d + '/sk.pem', 'wb').write(sh('openssl', 'genpkey', '-algorithm', kem_name, '-pkeyopt', 'hexseed:' + seed.hex()))
+    sh('openssl', 'pkey', '-in', d + '/sk.pem', '-pubout', '-out', d + '/pk.pem')
+    sh('openssl', 'pkeyutl', '-encap', '-inkey', d + '/pk.pem', '-pubin', '-pkeyopt', 'hexikme:' + m.hex(),
+       '-out', d + '/ct', '-secret', d + '/ss')
+    enc, ss = open(d + '/ct', 'rb').read(), open(d + '/ss', 'rb').read()
+    aead, nonce = key_schedule(kem_name, kdf_name, aead_name, ss, info)
+    return enc + aead.encrypt(nonce, pt, b'')
+
+def open_openssl(kem_name, kdf_name, aead_name, seed, info, out):
+    d = tempfile.mkdtemp()
+    open(d + '/sk.pem', 'wb').write(sh('openssl', 'genpkey', '-algorithm', kem_name, '-pkeyopt', 'hexseed:' + seed.hex()))
+    enc, ct = out[:NCT[kem_name]], out[NCT[kem_name]:]
+    open(d + '/ct', 'wb').write(enc)
+    sh('openssl', 'pkeyutl', '-decap', '-inkey', d + '/sk.pem', '-in', d + '/ct', '-secret', d + '/ss')
+    aead, nonce = key_schedule(kem_name, kdf_name, aead_name, open(d + '/ss', 'rb').read(), info)
+    return aead.decrypt(nonce, ct, b'')
+
+def private_key(name, seed):
+    if name == 'ML-KEM-768':
+        return mlkem.MLKEM768PrivateKey.from_seed_bytes(seed)
+    if name == 'ML-KEM-1024':
+        return mlkem.MLKEM1024PrivateKey.from_seed_bytes(seed)
+    # X-Wing expands its 32 byte seed into both component keys
+    expanded = hashlib.shake_256(seed).digest(96)
+    return hpke.MLKEM768X25519PrivateKey(
+        mlkem.MLKEM768PrivateKey.from_seed_bytes(expanded[:64]),
+        x25519.X25519PrivateKey.from_private_bytes(expanded[64:]))
+
+count = int(sys.argv[1])
+for kem_name, kem in KEMS:
+    for i in range(count):
+        kdf_name, kdf = KDFS[i % len(KDFS)]
+        aead_name, aead = AEADS[i % len(AEADS)]
+        seedlen = 32 if kem_name == 'X-Wing' else 64
+        seed = hashlib.shake_256(b'pqc_kyber hpke seed %d' % i).digest(seedlen)
+        info = b'pqc_kyber hpke info %d' % i
+        pt = b'Beauty is truth, truth beauty %d' % i
+        if kem is None:
+            m = hashlib.sha3_256(b'pqc_kyber hpke m %d' % i).digest()
+            out = seal_openssl(kem_name, kdf_name, aead_name, seed, m, info, pt)
+            assert open_openssl(kem_name, kdf_name, aead_name, seed, info, out) == pt
+        else:
+            sk = private_key(kem_name, seed)
+            out = hpke.Suite(kem, kdf, aead).encrypt(pt, sk.public_key(), info=info)
+            assert hpke.Suite(kem, kdf, aead).decrypt(out, sk, info=info) == pt
+            if kem_name == 'ML-KEM-768':
+                assert open_openssl(kem_name, kdf_name, aead_name, seed, info, out) == pt
+        print('kem: ' + kem_name)
+        print('kdf: ' + kdf_name)
+        print('aead: ' + aead_name)
+        print('seed: ' + seed.hex())
+        print('info: ' + info.hex())
+        print('pt: ' + pt.hex())
+        print('ct: ' + out.hex())
+        print()
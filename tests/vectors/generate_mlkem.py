@@ -0,0 +1,37 @@
+# Generates FIPS 203 vectors with the openssl cli, usage: generate_mlkem.py <512|768|1024> <count>
+import subprocess, hashlib, os, re, sys, tempfile
+def sh(*a, inp=None):
+    return subprocess.run(a, check=True, capture_output=True, input=inp).stdout
+def fields(pem):
+    t = sh('openssl','pkey','-text','-noout', inp=pem).decode()
+    out = {}; cur=None
+    for line in t.splitlines():
+        if re.match(r'^[a-z]+:$', line): cur=line[:-1]; out[cur]=''
+        elif cur and line.startswith('    '): out[cur]+=line.strip().replace(':','')
+    return out
+n = int(sys.argv[1]); count = int(sys.argv[2])
+d = tempfile.mkdtemp()
+for i in range(count):
+    seed = hashlib.sha3_512(b'pqc_kyber ml-kem %d %d' % (n, i)).digest()
+    m = hashlib.sha3_256(b'pqc_kyber ml-kem m %d %d' % (n, i)).digest()
+    skpem = sh('openssl','genpkey','-algorithm','ML-KEM-%d'%n,'-pkeyopt','hexseed:'+seed.hex())
+    open(d+'/sk.pem','wb').write(skpem)
+    f = fields(skpem)
+    sh('openssl','pkey','-in',d+'/sk.pem','-pubout','-out',d+'/pk.pem')
+    sh('openssl','pkeyutl','-encap','-inkey',d+'/pk.pem','-pubin','-pkeyopt','hexikme:'+m.hex(),'-out',d+'/ct','-secret',d+'/ss')
+    ct = open(d+'/ct','rb').read(); ss = open(d+'/ss','rb').read()
+    bad = bytearray(ct); bad[i % len(bad)] ^= 1 << (i % 8); bad = bytes(bad)
+    open(d+'/bad','wb').write(bad)
+    sh('openssl','pkeyutl','-decap','-inkey',d+'/sk.pem','-in',d+'/bad','-secret',d+'/ssbad')
+    ssbad = open(d+'/ssbad','rb').read()
+    assert f['seed'] == seed.hex()
+    print('d: ' + seed[:32].hex())
+    print('z: ' + seed[32:].hex())
+    print('ek: ' + f['ek'])
+    print('dk: ' + f['dk'])
+    print('m: ' + m.hex())
+    print('c: ' + ct.hex())
+    print('K: ' + ss.hex())
+    print('c_bad: ' + bad.hex())
+    print('K_bad: ' + ssbad.hex())
+    print()
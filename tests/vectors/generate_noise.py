@@ -0,0 +1,159 @@
+# Generates PQNoise handshake vectors independently of the crate, ML-KEM-768 from the openssl
+# cli, ChaChaPoly from pyca/cryptography and SHA256 from hashlib, usage: generate_noise.py
+import subprocess, hashlib, hmac, re, struct, tempfile
+from cryptography.hazmat.primitives.ciphers.aead import ChaCha20Poly1305
+
+PATTERNS = [
+    ('pqNN', [], [], [['e'], ['ekem']]),
+    ('pqNK', [], ['s'], [['e', 'skem'], ['ekem']]),
+    ('pqXX', [], [], [['e'], ['ekem', 's'], ['skem', 's'], ['skem']]),
+    ('pqIK', [], ['s'], [['e', 'skem', 's'], ['ekem', 'skem']]),
+    ('pqKK', ['s'], ['s'], [['e', 'skem'], ['ekem', 'skem']]),
+]
+TMP = tempfile.mkdtemp()
+
+def sh(*a, inp=None):
+    return subprocess.run(a, check=True, capture_output=True, input=inp).stdout
+def fields(pem):
+    t = sh('openssl','pkey','-text','-noout', inp=pem).decode()
+    out = {}; cur=None
+    for line in t.splitlines():
+        if re.match(r'^[a-z]+:$', line): cur=line[:-1]; out[cur]=''
+        elif cur and line.startswith('    '): out[cur]+=line.strip().replace(':','')
+    return out
+
+# ML-KEM-768 key pair from the d||z seed, keeping the pem files for the openssl cli
+class Key:
+    count = 0
+    def __init__(self, seed):
+        Key.count += 1
+        self.sk = '%s/sk%d.pem' % (TMP, Key.count)
+        self.pkfile = '%s/pk%d.pem' % (TMP, Key.count)
+        pem = sh('openssl','genpkey','-algorithm','ML-KEM-768','-pkeyopt','hexseed:'+seed.hex())
+        open(self.sk, 'wb').write(pem)
+        sh('openssl','pkey','-in',self.sk,'-pubout','-out',self.pkfile)
+        self.pk = bytes.fromhex(fields(pem)['ek'])
+
+def encap(key, m):
+    sh('openssl','pkeyutl','-encap','-inkey',key.pkfile,'-pubin','-pkeyopt','hexikme:'+m.hex(),
+       '-out',TMP+'/ct','-secret',TMP+'/ss')
+    return open(TMP+'/ct','rb').read(), open(TMP+'/ss','rb').read()
+def decap(key, ct):
+    open(TMP+'/ct','wb').write(ct)
+    sh('openssl','pkeyutl','-decap','-inkey',key.sk,'-in',TMP+'/ct','-secret',TMP+'/ss')
+    return open(TMP+'/ss','rb').read()
+
+def hkdf(ck, ikm):
+    t = hmac.new(ck, ikm, hashlib.sha256).digest()
+    o1 = hmac.new(t, b'\x01', hashlib.sha256).digest()
+    return o1, hmac.new(t, o1 + b'\x02', hashlib.sha256).digest()
+
+class CipherState:
+    def __init__(self, k=None):
+        self.k = k; self.n = 0
+    def nonce(self):
+        return b'\0' * 4 + struct.pack('<Q', self.n)
+    def encrypt(self, ad, pt):
+        if self.k is None: return pt
+        ct = ChaCha20Poly1305(self.k).encrypt(self.nonce(), pt, ad); self.n += 1
+        return ct
+    def decrypt(self, ad, ct):
+        if self.k is None: return ct
+        pt = ChaCha20Poly1305(self.k).decrypt(self.nonce(), ct, ad); self.n += 1
+        return pt
+
+class Party:
+    def __init__(self, pattern, initiator, s, rs, label):
+        name, ipre, rpre, self.messages = pattern
+        proto = b'Noise_%s_MLKEM768_ChaChaPoly_SHA256' % name.encode()
+        self.h = proto.ljust(32, b'\0') if len(proto) <= 32 else hashlib.sha256(proto).digest()
+        self.ck = self.h; self.cs = CipherState()
+        self.initiator = initiator; self.s = s; self.rs = rs; self.e = None; self.re = None
+        self.label = label; self.drawn = b''
+        self.mix_hash(PROLOGUE)
+        init_s, resp_s = (s, rs) if initiator else (rs, s)
+        if 's' in ipre: self.mix_hash(init_s.pk)
+        if 's' in rpre: self.mix_hash(resp_s.pk)
+    def draw(self, n):
+        out = hashlib.sha3_512(b'pqc_kyber noise %s %d' % (self.label, len(self.drawn))).digest()[:n]
+        self.drawn += out
+        return out
+    def mix_hash(self, data):
+        self.h = hashlib.sha256(self.h + data).digest()
+    def mix_key(self, ikm):
+        self.ck, k = hkdf(self.ck, ikm); self.cs = CipherState(k)
+    def encrypt_and_hash(self, pt):
+        ct = self.cs.encrypt(self.h, pt); self.mix_hash(ct); return ct
+    def decrypt_and_hash(self, ct):
+        pt = self.cs.decrypt(self.h, ct); self.mix_hash(ct); return pt
+    def write(self, i, payload):
+        out = b''
+        for t in self.messages[i]:
+            if t == 'e':
+                self.e = Key(self.draw(64)); out += self.e.pk; self.mix_hash(self.e.pk)
+            elif t == 's':
+                out += self.encrypt_and_hash(self.s.pk)
+            elif t == 'ekem':
+                ct, ss = encap(self.re, self.draw(32)); out += ct; self.mix_hash(ct); self.mix_key(ss)
+            elif t == 'skem':
+                ct, ss = encap(self.rs, self.draw(32)); out += self.encrypt_and_hash(ct); self.mix_key(ss)
+        return out + self.encrypt_and_hash(payload)
+    def read(self, i, msg, keys):
+        tag = lambda: 16 if self.cs.k else 0
+        for t in self.messages[i]:
+            if t == 'e':
+                self.re = keys[msg[:1184]]; msg = msg[1184:]; self.mix_hash(self.re.pk)
+            elif t == 's':
+                n = 1184 + tag(); self.rs = keys[self.decrypt_and_hash(msg[:n])]; msg = msg[n:]
+            elif t == 'ekem':
+                ct = msg[:1088]; msg = msg[1088:]; self.mix_hash(ct); self.mix_key(decap(self.e, ct))
+            elif t == 'skem':
+                n = 1088 + tag(); ct = self.decrypt_and_hash(msg[:n]); msg = msg[n:]
+                self.mix_key(decap(self.s, ct))
+        return self.decrypt_and_hash(msg)
+    def split(self):
+        c1, c2 = hkdf(self.ck, b'')
+        c1, c2 = CipherState(c1), CipherState(c2)
+        return (c1, c2) if self.initiator else (c2, c1)
+
+PROLOGUE = b'pqc_kyber noise vectors'
+for pattern in PATTERNS:
+    name, ipre, rpre, messages = pattern
+    seed_i = hashlib.sha3_512(b'pqc_kyber noise %s static initiator' % name.encode()).digest()
+    seed_r = hashlib.sha3_512(b'pqc_kyber noise %s static responder' % name.encode()).digest()
+    static_i, static_r = Key(seed_i), Key(seed_r)
+    # The reader looks up received public keys to find their pem files
+    keys = {static_i.pk: static_i, static_r.pk: static_r}
+    uses_s = lambda first: any(t == 's' for m in messages[first::2] for t in m) \
+        or any(t == 'skem' for m in messages[1 - first::2] for t in m)
+    si = static_i if 's' in ipre or uses_s(0) else None
+    sr = static_r if 's' in rpre or uses_s(1) else None
+    init = Party(pattern, True, si, static_r if 's' in rpre else None, b'%s initiator' % name.encode())
+    resp = Party(pattern, False, sr, static_i if 's' in ipre else None, b'%s responder' % name.encode())
+    print('pattern: ' + name)
+    print('prologue: ' + PROLOGUE.hex())
+    print('init_static: ' + (seed_i.hex() if si else ''))
+    print('resp_static: ' + (seed_r.hex() if sr else ''))
+    out = []
+    for i in range(len(messages)):
+        writer, reader = (init, resp) if i % 2 == 0 else (resp, init)
+        payload = b'handshake payload %d' % i if i != 1 else b''
+        msg = writer.write(i, payload)
+        if writer.e: keys[writer.e.pk] = writer.e
+        assert reader.read(i, msg, keys) == payload
+        out.append((payload, msg))
+    assert init.h == resp.h
+    init_send, init_recv = init.split()
+    resp_send, resp_recv = resp.split()
+    for sender, receiver, payload in [(init_send, resp_recv, b'initiator transport'),
+                                      (resp_send, init_recv, b'responder transport')]:
+        msg = sender.encrypt(b'', payload)
+        assert receiver.decrypt(b'', msg) == payload
+        out.append((payload, msg))
+    print('init_rng: ' + init.drawn.hex())
+    print('resp_rng: ' + resp.drawn.hex())
+    print('handshake_hash: ' + init.h.hex())
+    for payload, msg in out:
+        print('payload: ' + payload.hex())
+        print('message: ' + msg.hex())
+    print()
@@ -0,0 +1,50 @@
+# Generates X-Wing vectors independently of the crate, ML-KEM-768 from the openssl cli,
+# X25519 from pyca/cryptography and SHA3 from hashlib, usage: generate_xwing.py <count>
+import subprocess, hashlib, re, sys, tempfile
+from cryptography.hazmat.primitives.asymmetric.x25519 import X25519PrivateKey, X25519PublicKey
+from cryptography.hazmat.primitives.serialization import Encoding, PublicFormat
+LABEL = b'\\.//^\\'
+def sh(*a, inp=None):
+    return subprocess.run(a, check=True, capture_output=True, input=inp).stdout
+def fields(pem):
+    t = sh('openssl','pkey','-text','-noout', inp=pem).decode()
+    out = {}; cur=None
+    for line in t.splitlines():
+        if re.match(r'^[a-z]+:$', line): cur=line[:-1]; out[cur]=''
+        elif cur and line.startswith('    '): out[cur]+=line.strip().replace(':','')
+    return out
+def x25519_base(k):
+    return X25519PrivateKey.from_private_bytes(k).public_key().public_bytes(Encoding.Raw, PublicFormat.Raw)
+def x25519(k, u):
+    return X25519PrivateKey.from_private_bytes(k).exchange(X25519PublicKey.from_public_bytes(u))
+count = int(sys.argv[1])
+d = tempfile.mkdtemp()
+for i in range(count):
+    seed = hashlib.sha3_256(b'pqc_kyber x-wing seed %d' % i).digest()
+    eseed = hashlib.sha3_512(b'pqc_kyber x-wing eseed %d' % i).digest()
+    # Key generation expands the 32 byte seed with SHAKE256
+    expanded = hashlib.shake_256(seed).digest(96)
+    skpem = sh('openssl','genpkey','-algorithm','ML-KEM-768','-pkeyopt','hexseed:'+expanded[:64].hex())
+    open(d+'/sk.pem','wb').write(skpem)
+    pk_m = bytes.fromhex(fields(skpem)['ek'])
+    sk_x = expanded[64:]
+    pk_x = x25519_base(sk_x)
+    pk = pk_m + pk_x
+    # Encapsulation, m then the ephemeral X25519 key
+    sh('openssl','pkey','-in',d+'/sk.pem','-pubout','-out',d+'/pk.pem')
+    sh('openssl','pkeyutl','-encap','-inkey',d+'/pk.pem','-pubin','-pkeyopt','hexikme:'+eseed[:32].hex(),'-out',d+'/ct','-secret',d+'/ss')
+    ct_m = open(d+'/ct','rb').read(); ss_m = open(d+'/ss','rb').read()
+    ek_x = eseed[32:]
+    ct_x = x25519_base(ek_x)
+    ss_x = x25519(ek_x, pk_x)
+    ss = hashlib.sha3_256(ss_m + ss_x + ct_x + pk_x + LABEL).digest()
+    # Decapsulation side agrees
+    sh('openssl','pkeyutl','-decap','-inkey',d+'/sk.pem','-in',d+'/ct','-secret',d+'/ss2')
+    assert open(d+'/ss2','rb').read() == ss_m
+    assert x25519(sk_x, ct_x) == ss_x
+    print('seed: ' + seed.hex())
+    print('pk: ' + pk.hex())
+    print('eseed: ' + eseed.hex())
+    print('ct: ' + (ct_m + ct_x).hex())
+    print('ss: ' + ss.hex())
+    print()
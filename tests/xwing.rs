@@ -0,0 +1,84 @@
+#![cfg(all(feature = "xwing", not(feature = "90s")))]
+
+// The test vectors published with the X-Wing draft, and more generated independently
+// with OpenSSL 3.5 and pyca/cryptography, see tests/vectors
+mod load;
+
+use pqc_kyber::*;
+use load::*;
+use std::convert::TryFrom;
+
+// The encapsulation seed is only used with hazmat
+#[cfg_attr(not(feature = "hazmat"), allow(dead_code))]
+struct Vector {
+  seed: Vec<u8>,
+  pk: Vec<u8>,
+  eseed: Vec<u8>,
+  ct: Vec<u8>,
+  ss: Vec<u8>,
+}
+
+fn vectors() -> Vec<Vector> {
+  let draft = parse_vectors(include_str!("vectors/xwing-draft.txt"));
+  assert_eq!(draft.len(), 3);
+  draft.iter().chain(&parse_vectors(include_str!("vectors/xwing.txt")))
+    .map(|v| Vector {
+      seed: v.hex("seed"), pk: v.hex("pk"), eseed: v.hex("eseed"),
+      ct: v.hex("ct"), ss: v.hex("ss"),
+    })
+    .collect()
+}
+
+#[test]
+fn keygen_vectors() {
+  for v in vectors() {
+    let seed = <[u8; xwing::XWING_SECRETKEYBYTES]>::try_from(&v.seed[..]).unwrap();
+    let keys = xwing::keypair_from_seed(&seed);
+    assert_eq!(keys.public.as_ref(), &v.pk[..]);
+    assert_eq!(keys.secret.as_ref(), &v.seed[..]);
+  }
+}
+
+#[test]
+fn decapsulate_vectors() {
+  for v in vectors() {
+    let sk = xwing::SecretKey::try_from(&v.seed[..]).unwrap();
+    let ct = xwing::Ciphertext::try_from(&v.ct[..]).unwrap();
+    let ss = xwing::decapsulate(&ct, &sk).unwrap();
+    assert_eq!(ss.as_ref(), &v.ss[..]);
+  }
+}
+
+#[cfg(feature = "hazmat")]
+#[test]
+fn encapsulate_vectors() {
+  for v in vectors() {
+    let pk = xwing::PublicKey::try_from(&v.pk[..]).unwrap();
+    let eseed = <[u8; xwing::XWING_ENCAPSSEEDBYTES]>::try_from(&v.eseed[..]).unwrap();
+    let (ct, ss) = xwing::encapsulate_derand(&pk, &eseed).unwrap();
+    assert_eq!(ct.as_ref(), &v.ct[..]);
+    assert_eq!(ss.as_ref(), &v.ss[..]);
+  }
+}
+
+#[test]
+fn implicit_rejection() {
+  let mut rng = rand::thread_rng();
+  let keys = xwing::keypair(&mut rng);
+  let (ct, ss1) = xwing::encapsulate(&keys.public, &mut rng).unwrap();
+  // Tampering with either component changes the secret
+  for i in [0, xwing::XWING_CIPHERTEXTBYTES - 1] {
+    let mut bytes = ct.as_ref().to_vec();
+    bytes[i] ^= 1;
+    let ct = xwing::Ciphertext::try_from(&bytes[..]).unwrap();
+    assert_ne!(xwing::decapsulate(&ct, &keys.secret).unwrap(), ss1);
+  }
+}
+
+#[test]
+fn invalid_public_key() {
+  let mut rng = rand::thread_rng();
+  let pk = xwing::PublicKey::try_from(&[0xff; xwing::XWING_PUBLICKEYBYTES][..]).unwrap();
+  assert_eq!(xwing::encapsulate(&pk, &mut rng).unwrap_err(), KyberError::InvalidPublicKey);
+  assert!(xwing::PublicKey::try_from(&[0u8; xwing::XWING_PUBLICKEYBYTES - 1][..]).is_err());
+}